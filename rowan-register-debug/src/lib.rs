@@ -1,12 +1,47 @@
-use std::ffi::CString;
+use std::fs::File;
+use std::io::Write;
+use std::sync::Mutex;
 
 #[cfg(windows)]
 mod windows;
+#[cfg(target_os = "linux")]
+mod linux;
 
-#[cfg(windows)]
-pub fn register_name(name: &str, address: *const (), size: usize) {
-    let c_string = CString::new(format!("jitted::{name}")).unwrap();
-    let address = address as usize;
+/// The source position of the code from `address` up to the next line
+pub struct SourceLine<'a> {
+    pub address: usize,
+    pub file: &'a str,
+    pub line: u64,
+    pub column: u64,
+}
+
+static PERF_MAP: Mutex<Option<File>> = Mutex::new(None);
+
+/// Starts writing every registered name to `/tmp/perf-<pid>.map`, which is where `perf` looks up symbols for JIT code
+pub fn enable_perf_map() {
+    let path = format!("/tmp/perf-{}.map", std::process::id());
+    let file = File::create(&path).unwrap_or_else(|error| panic!("Failed to create {path}: {error}"));
+    *PERF_MAP.lock().expect("Lock poisoned") = Some(file);
+}
+
+/// Tells debuggers and profilers the name of the JIT code at `address` and which source lines it came from
+pub fn register_name(name: &str, address: *const (), size: usize, lines: &[SourceLine]) {
+    let name = format!("jitted::{name}");
+
+    if let Some(file) = PERF_MAP.lock().expect("Lock poisoned").as_mut() {
+        writeln!(file, "{:x} {:x} {name}", address as usize, size).expect("Failed to write to the perf map");
+    }
+
+    #[cfg(windows)]
+    {
+        let _ = lines;
+        let c_string = std::ffi::CString::new(name).unwrap();
+        windows::register_name(c_string.as_ptr(), address as usize, size);
+    }
+
+    #[cfg(target_os = "linux")]
+    linux::register_name(&name, address as usize, size, lines);
 
-    windows::register_name(c_string.as_ptr(), address, size);
-}
\ No newline at end of file
+    #[cfg(not(any(windows, target_os = "linux")))]
+    let _ = lines;
+}
@@ -0,0 +1,342 @@
+//! Registers JIT code with GDB through its JIT interface.
+//! Every function is handed to GDB as a small ELF file in memory that only holds a symbol for the function
+//! and, when there are source lines, the DWARF needed to map addresses back to them.
+//! See https://sourceware.org/gdb/current/onlinedocs/gdb.html/JIT-Interface.html
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+use gimli::write::{Address, AttributeValue, DwarfUnit, EndianVec, LineProgram, LineString, Sections};
+use gimli::{Encoding, Format, LineEncoding, LittleEndian};
+use crate::SourceLine;
+
+const JIT_NOACTION: u32 = 0;
+const JIT_REGISTER_FN: u32 = 1;
+
+#[repr(C)]
+pub struct JitCodeEntry {
+    next_entry: *mut JitCodeEntry,
+    prev_entry: *mut JitCodeEntry,
+    symfile_addr: *const u8,
+    symfile_size: u64,
+}
+
+#[repr(C)]
+pub struct JitDescriptor {
+    version: u32,
+    action_flag: u32,
+    relevant_entry: *mut JitCodeEntry,
+    first_entry: *mut JitCodeEntry,
+}
+
+/// GDB puts a breakpoint on this function to find out when code gets registered
+#[unsafe(no_mangle)]
+#[inline(never)]
+pub extern "C" fn __jit_debug_register_code() {
+    // Keeps the function from being merged with another empty function
+    std::hint::black_box(());
+}
+
+/// GDB looks up this descriptor by name to read the registered code
+#[allow(non_upper_case_globals)]
+#[unsafe(no_mangle)]
+pub static mut __jit_debug_descriptor: JitDescriptor = JitDescriptor {
+    version: 1,
+    action_flag: JIT_NOACTION,
+    relevant_entry: std::ptr::null_mut(),
+    first_entry: std::ptr::null_mut(),
+};
+
+/// Only one thread may change the descriptor at a time
+static REGISTRATION: Mutex<()> = Mutex::new(());
+
+const MACHINE: u16 = if cfg!(target_arch = "x86_64") {
+    62
+} else if cfg!(target_arch = "aarch64") {
+    183
+} else if cfg!(target_arch = "riscv64") {
+    243
+} else {
+    0
+};
+
+pub fn register_name(name: &str, address: usize, size: usize, lines: &[SourceLine]) {
+    if MACHINE == 0 {
+        return;
+    }
+
+    // GDB keeps pointing at the file and the entry for as long as the code exists, which is until the program exits
+    let symfile: &'static [u8] = Vec::leak(elf_file(name, address, size, lines));
+    let entry = Box::into_raw(Box::new(JitCodeEntry {
+        next_entry: std::ptr::null_mut(),
+        prev_entry: std::ptr::null_mut(),
+        symfile_addr: symfile.as_ptr(),
+        symfile_size: symfile.len() as u64,
+    }));
+
+    let _guard = REGISTRATION.lock().expect("Lock poisoned");
+    unsafe {
+        let descriptor = &raw mut __jit_debug_descriptor;
+        let first_entry = (*descriptor).first_entry;
+        (*entry).next_entry = first_entry;
+        if let Some(first_entry) = first_entry.as_mut() {
+            first_entry.prev_entry = entry;
+        }
+        (*descriptor).first_entry = entry;
+        (*descriptor).relevant_entry = entry;
+        (*descriptor).action_flag = JIT_REGISTER_FN;
+        __jit_debug_register_code();
+        (*descriptor).action_flag = JIT_NOACTION;
+    }
+}
+
+/// Splits a path into the directory and file name that DWARF wants
+fn split_path(path: &str) -> (Vec<u8>, Vec<u8>) {
+    let path = Path::new(path);
+    let directory = match path.parent().and_then(Path::to_str) {
+        Some(directory) if !directory.is_empty() => directory,
+        _ => ".",
+    };
+    let file = path.file_name().and_then(|file| file.to_str()).unwrap_or("<unknown>");
+    (directory.as_bytes().to_vec(), file.as_bytes().to_vec())
+}
+
+/// Creates the DWARF sections for a function that has source lines
+fn debug_sections(name: &str, address: usize, size: usize, lines: &[SourceLine]) -> Vec<(&'static str, Vec<u8>)> {
+    let encoding = Encoding {
+        format: Format::Dwarf32,
+        version: 4,
+        address_size: 8,
+    };
+    let (directory, file) = split_path(lines[0].file);
+    let mut dwarf = DwarfUnit::new(encoding);
+
+    let mut program = LineProgram::new(
+        encoding,
+        LineEncoding::default(),
+        LineString::String(directory.clone()),
+        LineString::String(file.clone()),
+        None,
+    );
+    let mut files = HashMap::new();
+    program.begin_sequence(Some(Address::Constant(address as u64)));
+    for line in lines {
+        let file_id = *files.entry(line.file).or_insert_with(|| {
+            let (directory, file) = split_path(line.file);
+            let directory = program.add_directory(LineString::String(directory));
+            program.add_file(LineString::String(file), directory, None)
+        });
+        let row = program.row();
+        row.address_offset = (line.address - address) as u64;
+        row.file = file_id;
+        row.line = line.line;
+        row.column = line.column;
+        program.generate_row();
+    }
+    program.end_sequence(size as u64);
+    dwarf.unit.line_program = program;
+
+    let root = dwarf.unit.root();
+    let unit = dwarf.unit.get_mut(root);
+    unit.set(gimli::DW_AT_producer, AttributeValue::String(b"rowan".to_vec()));
+    unit.set(gimli::DW_AT_name, AttributeValue::String(file));
+    unit.set(gimli::DW_AT_comp_dir, AttributeValue::String(directory));
+    unit.set(gimli::DW_AT_low_pc, AttributeValue::Address(Address::Constant(address as u64)));
+    unit.set(gimli::DW_AT_high_pc, AttributeValue::Udata(size as u64));
+
+    let subprogram = dwarf.unit.add(root, gimli::DW_TAG_subprogram);
+    let subprogram = dwarf.unit.get_mut(subprogram);
+    subprogram.set(gimli::DW_AT_name, AttributeValue::String(name.as_bytes().to_vec()));
+    subprogram.set(gimli::DW_AT_external, AttributeValue::Flag(true));
+    subprogram.set(gimli::DW_AT_low_pc, AttributeValue::Address(Address::Constant(address as u64)));
+    subprogram.set(gimli::DW_AT_high_pc, AttributeValue::Udata(size as u64));
+
+    let mut sections = Sections::new(EndianVec::new(LittleEndian));
+    dwarf.write(&mut sections).expect("Failed to write DWARF");
+    let mut result = Vec::new();
+    sections.for_each(|id, section| {
+        if !section.slice().is_empty() {
+            result.push((id.name(), section.slice().to_vec()));
+        }
+        Ok::<(), ()>(())
+    }).unwrap();
+    result
+}
+
+struct ElfSection {
+    name: &'static str,
+    kind: u32,
+    flags: u64,
+    address: u64,
+    size: u64,
+    data: Vec<u8>,
+    link: u32,
+    info: u32,
+    entry_size: u64,
+}
+
+impl ElfSection {
+    fn new(name: &'static str, kind: u32, data: Vec<u8>) -> Self {
+        ElfSection {
+            name,
+            kind,
+            flags: 0,
+            address: 0,
+            size: data.len() as u64,
+            data,
+            link: 0,
+            info: 0,
+            entry_size: 0,
+        }
+    }
+}
+
+const SHT_PROGBITS: u32 = 1;
+const SHT_SYMTAB: u32 = 2;
+const SHT_STRTAB: u32 = 3;
+const SHT_NOBITS: u32 = 8;
+const SHF_ALLOC: u64 = 2;
+const SHF_EXECINSTR: u64 = 4;
+const TEXT_INDEX: u16 = 1;
+const STRTAB_INDEX: u32 = 3;
+
+/// Creates a relocatable ELF file whose `.text` section is placed where the code already is.
+/// The code itself isn't copied, GDB reads it out of memory like it does for any other loaded file.
+fn elf_file(name: &str, address: usize, size: usize, lines: &[SourceLine]) -> Vec<u8> {
+    let mut text = ElfSection::new(".text", SHT_NOBITS, Vec::new());
+    text.flags = SHF_ALLOC | SHF_EXECINSTR;
+    text.address = address as u64;
+    text.size = size as u64;
+
+    let mut strtab = vec![0];
+    let mut symtab = vec![0; 24];
+    symtab.extend_from_slice(&(strtab.len() as u32).to_le_bytes());
+    strtab.extend_from_slice(name.as_bytes());
+    strtab.push(0);
+    // A global function
+    symtab.push(0x12);
+    symtab.push(0);
+    symtab.extend_from_slice(&TEXT_INDEX.to_le_bytes());
+    symtab.extend_from_slice(&0u64.to_le_bytes());
+    symtab.extend_from_slice(&(size as u64).to_le_bytes());
+    let mut symtab = ElfSection::new(".symtab", SHT_SYMTAB, symtab);
+    symtab.link = STRTAB_INDEX;
+    symtab.info = 1;
+    symtab.entry_size = 24;
+
+    let mut sections = vec![
+        ElfSection::new("", 0, Vec::new()),
+        text,
+        symtab,
+        ElfSection::new(".strtab", SHT_STRTAB, strtab),
+    ];
+    if !lines.is_empty() {
+        for (name, data) in debug_sections(name, address, size, lines) {
+            sections.push(ElfSection::new(name, SHT_PROGBITS, data));
+        }
+    }
+
+    let mut shstrtab = vec![0];
+    let mut name_offsets = Vec::new();
+    for section in sections.iter() {
+        if section.name.is_empty() {
+            name_offsets.push(0);
+            continue;
+        }
+        name_offsets.push(shstrtab.len() as u32);
+        shstrtab.extend_from_slice(section.name.as_bytes());
+        shstrtab.push(0);
+    }
+    name_offsets.push(shstrtab.len() as u32);
+    shstrtab.extend_from_slice(b".shstrtab\0");
+    sections.push(ElfSection::new(".shstrtab", SHT_STRTAB, shstrtab));
+
+    let mut file = vec![0; 64];
+    let mut offsets = Vec::new();
+    for section in sections.iter() {
+        file.resize(file.len().next_multiple_of(8), 0);
+        offsets.push(file.len() as u64);
+        file.extend_from_slice(&section.data);
+    }
+    file.resize(file.len().next_multiple_of(8), 0);
+    let section_headers = file.len() as u64;
+    for ((section, offset), name) in sections.iter().zip(offsets).zip(name_offsets) {
+        file.extend_from_slice(&name.to_le_bytes());
+        file.extend_from_slice(&section.kind.to_le_bytes());
+        file.extend_from_slice(&section.flags.to_le_bytes());
+        file.extend_from_slice(&section.address.to_le_bytes());
+        file.extend_from_slice(&offset.to_le_bytes());
+        file.extend_from_slice(&section.size.to_le_bytes());
+        file.extend_from_slice(&section.link.to_le_bytes());
+        file.extend_from_slice(&section.info.to_le_bytes());
+        let alignment: u64 = if section.kind == 0 { 0 } else { 8 };
+        file.extend_from_slice(&alignment.to_le_bytes());
+        file.extend_from_slice(&section.entry_size.to_le_bytes());
+    }
+
+    let mut header = Vec::with_capacity(64);
+    // 64 bit, little endian, version 1
+    header.extend_from_slice(&[0x7f, b'E', b'L', b'F', 2, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+    // A relocatable file
+    header.extend_from_slice(&1u16.to_le_bytes());
+    header.extend_from_slice(&MACHINE.to_le_bytes());
+    header.extend_from_slice(&1u32.to_le_bytes());
+    // No entry point or program headers
+    header.extend_from_slice(&0u64.to_le_bytes());
+    header.extend_from_slice(&0u64.to_le_bytes());
+    header.extend_from_slice(&section_headers.to_le_bytes());
+    header.extend_from_slice(&0u32.to_le_bytes());
+    header.extend_from_slice(&64u16.to_le_bytes());
+    header.extend_from_slice(&0u16.to_le_bytes());
+    header.extend_from_slice(&0u16.to_le_bytes());
+    header.extend_from_slice(&64u16.to_le_bytes());
+    header.extend_from_slice(&(sections.len() as u16).to_le_bytes());
+    header.extend_from_slice(&(sections.len() as u16 - 1).to_le_bytes());
+    file[..64].copy_from_slice(&header);
+
+    file
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_u16(file: &[u8], offset: usize) -> u16 {
+        u16::from_le_bytes(file[offset..offset + 2].try_into().unwrap())
+    }
+
+    fn read_u64(file: &[u8], offset: usize) -> u64 {
+        u64::from_le_bytes(file[offset..offset + 8].try_into().unwrap())
+    }
+
+    #[test]
+    fn test_split_path() {
+        assert_eq!(split_path("src/main.rowan"), (b"src".to_vec(), b"main.rowan".to_vec()));
+        assert_eq!(split_path("main.rowan"), (b".".to_vec(), b"main.rowan".to_vec()));
+    }
+
+    #[test]
+    fn test_elf_file_without_lines() {
+        let file = elf_file("main", 0x1000, 32, &[]);
+        assert_eq!(&file[..4], b"\x7fELF");
+        assert_eq!(read_u16(&file, 18), MACHINE);
+        // The null section, .text, .symtab, .strtab and .shstrtab
+        assert_eq!(read_u16(&file, 60), 5);
+        assert_eq!(read_u16(&file, 62), 4);
+
+        let text = read_u64(&file, 40) as usize + 64;
+        assert_eq!(read_u64(&file, text + 16), 0x1000);
+        assert_eq!(read_u64(&file, text + 32), 32);
+    }
+
+    #[test]
+    fn test_elf_file_with_lines_has_debug_sections() {
+        let lines = [
+            SourceLine { address: 0x1000, file: "main.rowan", line: 1, column: 1 },
+            SourceLine { address: 0x1010, file: "main.rowan", line: 2, column: 5 },
+        ];
+        let file = elf_file("main", 0x1000, 32, &lines);
+        assert!(read_u16(&file, 60) > 5);
+    }
+}
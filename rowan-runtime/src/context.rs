@@ -33,6 +33,16 @@ pub enum MethodName {
     }
 }
 
+impl MethodName {
+    pub fn method_symbol(&self) -> Symbol {
+        match self {
+            MethodName::StaticMethod { method_name, .. } => *method_name,
+            MethodName::VirtualMethod { method_name, .. } => *method_name,
+            MethodName::InterfaceMethod { method_name, .. } => *method_name,
+        }
+    }
+}
+
 const CALLING_CONVENTION: ffi_abi = libffi::raw::ffi_abi_FFI_DEFAULT_ABI;
 
 #[inline(never)]
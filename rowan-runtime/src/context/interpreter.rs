@@ -8,13 +8,16 @@ use rowan_shared::bytecode::linked::Bytecode;
 use rowan_shared::TypeTag;
 use crate::runtime;
 use crate::context::{call_function_pointer, MethodName, WrappedReference};
-use crate::runtime::{FunctionDetails, Reference, Runtime, DO_GARBAGE_COLLECTION};
+use crate::runtime::{Function, FunctionDetails, Reference, Runtime, SourceLocation, DO_GARBAGE_COLLECTION};
 use crate::runtime::object::Object;
 use paste::paste;
 use crate::context::interpreter::frame_vars::FrameVars;
 use crate::context::interpreter::stackframe::{StackFrame};
 use crate::runtime::core::interned_string_init;
 use crate::runtime::garbage_collection::GarbageCollection;
+use crate::runtime::inline_cache::{InlineCache, InlineCaches, NO_INLINE_CACHES};
+use crate::runtime::osr::{LocalTypes, Loops, NO_LOOPS};
+use crate::runtime::jit::{jit_threshold, request_to_jit_method, Tier};
 
 #[derive(Debug, Copy, Clone)]
 pub enum CallContinueState {
@@ -28,6 +31,14 @@ pub enum CallContinueState {
     Error,
 }
 
+/// A call that compiled code ended with, left for the runtime to make once the code has returned
+#[derive(Clone, Copy)]
+enum TailCall {
+    Virtual(runtime::Symbol, runtime::Symbol, Option<&'static InlineCache>),
+    Static(runtime::Symbol, runtime::Symbol),
+    Interface(runtime::Symbol, runtime::Symbol, Option<&'static InlineCache>),
+}
+
 #[derive(Clone, Copy, Debug)]
 pub enum StackValue {
     Int8(u8),
@@ -211,10 +222,21 @@ pub struct BytecodeContext {
     active_frames: Vec<StackFrame>,
     current_exception: Reference,
     call_args: [StackValue; 256],
+    /// Set by compiled code that returns to make a tail call, so that the call doesn't need a native stack frame
+    tail_call: Option<TailCall>,
     vars: FrameVars,
+    /// The objects that references have been stored in since the last collection
+    remembered_set: HashSet<WrappedReference>,
 }
 
 
+impl Drop for BytecodeContext {
+    fn drop(&mut self) {
+        // The objects written to by this thread still need to be looked at by the next collection
+        GarbageCollection::remember(std::mem::take(&mut self.remembered_set));
+    }
+}
+
 impl BytecodeContext {
     pub fn new() -> Self {
         BytecodeContext {
@@ -223,7 +245,9 @@ impl BytecodeContext {
             active_frames: Vec::new(),
             current_exception: std::ptr::null_mut(),
             call_args: [StackValue::Blank; 256],
+            tail_call: None,
             vars: FrameVars::new(),
+            remembered_set: HashSet::new(),
         }
     }
     
@@ -290,7 +314,25 @@ impl BytecodeContext {
         !self.current_exception.is_null()
     }
 
-    pub fn push(&mut self, bytecode: &'static [Bytecode], is_for_bytecode: bool, method_name: MethodName, block_positions: &'static FxHashMap<usize, usize>) {
+    pub fn set_exception(&mut self, exception: Reference) {
+        self.current_exception = exception;
+        runtime::core::exception_capture_stack_trace(self, exception);
+    }
+
+    /// Takes the exception that is being thrown, leaving none set
+    pub fn take_exception(&mut self) -> Reference {
+        std::mem::replace(&mut self.current_exception, std::ptr::null_mut())
+    }
+
+    pub fn push(
+        &mut self,
+        bytecode: &'static [Bytecode],
+        is_for_bytecode: bool,
+        method_name: MethodName,
+        block_positions: &'static FxHashMap<usize, usize>,
+        inline_caches: &'static InlineCaches,
+        loops: &'static Loops,
+    ) {
         self.active_bytecodes.push(bytecode);
         self.vars.push();
         for (i, arg) in self.call_args.iter().enumerate() {
@@ -300,7 +342,7 @@ impl BytecodeContext {
             self.vars[i] = *arg;
         }
         if is_for_bytecode {
-            self.active_frames.push(StackFrame::new(method_name, block_positions));
+            self.active_frames.push(StackFrame::new(method_name, block_positions, inline_caches, loops));
         } else {
             self.active_frames.push(StackFrame::new_light(method_name));
         }
@@ -323,9 +365,51 @@ impl BytecodeContext {
         &mut self.active_frames[len - 1]
     }
 
-    /// This function will unwind the stack if needed when an exception is thrown.
+    /// Returns `Error` if an exception was thrown so that the main loop can unwind the stack.
     pub fn handle_exception(&mut self) -> CallContinueState {
-        CallContinueState::Success
+        if self.is_current_exception_set() {
+            CallContinueState::Error
+        } else {
+            CallContinueState::Success
+        }
+    }
+
+    /// Jumps to the handler of the thrown exception, unwinding the frames that started at or after `starting_depth`.
+    /// Returns false if none of those frames could handle the exception, in which case they have all been popped.
+    fn catch_exception(&mut self, starting_depth: usize, starting_stack_height: usize) -> bool {
+        let classes = Self::class_hierarchy(self.current_exception);
+        // A tail call may have already popped the frame that started the loop
+        while self.active_frames.len() >= starting_depth {
+            if let Some(stack_height) = self.current_frame_mut().catch_exception(&classes) {
+                self.operand_stack.truncate(stack_height);
+                let exception = self.take_exception();
+                self.push_value(StackValue::Reference(exception));
+                return true;
+            }
+            self.pop();
+        }
+        self.operand_stack.truncate(starting_stack_height);
+        false
+    }
+
+    /// Gets the class of an object followed by the classes it inherits from
+    pub(crate) fn class_hierarchy(mut object: Reference) -> Vec<runtime::Symbol> {
+        let mut classes = Vec::new();
+        while let Some(current) = unsafe { object.as_ref() } {
+            classes.push(current.class);
+            object = current.parent_object;
+        }
+        classes
+    }
+
+    /// Prints an exception that no handler caught and exits
+    fn report_uncaught_exception(&mut self) {
+        let exception = self.take_exception();
+        let class = unsafe { exception.as_ref().expect("exception was null") }.class;
+        let message = runtime::core::exception_message(exception);
+        eprintln!("Uncaught exception {}: {}", Runtime::get_class_name(class), message);
+        runtime::core::exception_print_stack_trace(self, exception);
+        std::process::exit(1);
     }
 
 
@@ -333,8 +417,20 @@ impl BytecodeContext {
         &mut self,
         specified: runtime::Symbol,
         method_name: runtime::Symbol,
+        inline_cache: Option<&InlineCache>,
         return_slot: Option<&mut StackValue>,
     ) -> CallContinueState {
+        let (details, method_name) = self.virtual_target(specified, method_name, inline_cache);
+        self.call_function(details, method_name, return_slot)
+    }
+
+    /// Looks up the method that a virtual call resolves to for the receiver in the call arguments
+    fn virtual_target(
+        &self,
+        specified: runtime::Symbol,
+        method_name: runtime::Symbol,
+        inline_cache: Option<&InlineCache>,
+    ) -> (FunctionDetails, MethodName) {
         let object = self.call_args[0];
         let object = match object {
             StackValue::Reference(object) => object,
@@ -344,11 +440,11 @@ impl BytecodeContext {
             object.as_ref().expect("report null pointer")
         };
 
-        let details = Runtime::get_virtual_method_details(
-            object.class,
-            specified,
-            method_name,
-        );
+        let lookup = || Runtime::get_virtual_method(object.class, specified, method_name);
+        let function = match inline_cache {
+            Some(inline_cache) => inline_cache.get_or_insert(object.class, lookup),
+            None => lookup(),
+        };
         //println!("bytecode: {:#?}", details.bytecode);
 
         let method_name = MethodName::VirtualMethod {
@@ -356,8 +452,7 @@ impl BytecodeContext {
             class_symbol: specified,
             method_name,
         };
-
-        self.call_function(details, method_name, return_slot)
+        (function.create_details(method_name), method_name)
     }
 
 
@@ -367,6 +462,11 @@ impl BytecodeContext {
         method_name: runtime::Symbol,
         return_slot: Option<&mut StackValue>,
     ) -> CallContinueState {
+        let (details, method_name) = Self::static_target(class_name, method_name);
+        self.call_function(details, method_name, return_slot)
+    }
+
+    fn static_target(class_name: runtime::Symbol, method_name: runtime::Symbol) -> (FunctionDetails, MethodName) {
         let details = Runtime::get_static_method_details(
             class_name,
             method_name,
@@ -377,16 +477,27 @@ impl BytecodeContext {
             class_symbol: class_name,
             method_name
         };
-
-        self.call_function(details, method_name, return_slot)
+        (details, method_name)
     }
 
     pub fn invoke_interface(
         &mut self,
         interface_name: runtime::Symbol,
         method_name: runtime::Symbol,
+        inline_cache: Option<&InlineCache>,
         return_slot: Option<&mut StackValue>,
     ) -> CallContinueState {
+        let (details, method_name) = self.interface_target(interface_name, method_name, inline_cache);
+        self.call_function(details, method_name, return_slot)
+    }
+
+    /// Looks up the method that an interface call resolves to for the receiver in the call arguments
+    fn interface_target(
+        &self,
+        interface_name: runtime::Symbol,
+        method_name: runtime::Symbol,
+        inline_cache: Option<&InlineCache>,
+    ) -> (FunctionDetails, MethodName) {
         let object = self.call_args[0];
         let object = match object {
             StackValue::Reference(object) => object,
@@ -395,11 +506,11 @@ impl BytecodeContext {
         let object = unsafe {
             object.as_ref().expect("report null pointer")
         };
-        let details = Runtime::get_interface_method_details(
-            object.class,
-            interface_name,
-            method_name,
-        );
+        let lookup = || Runtime::get_interface_method(object.class, interface_name, method_name);
+        let function = match inline_cache {
+            Some(inline_cache) => inline_cache.get_or_insert(object.class, lookup),
+            None => lookup(),
+        };
         //println!("bytecode: {:#?}", details.bytecode);
 
         let method_name = MethodName::InterfaceMethod {
@@ -407,76 +518,96 @@ impl BytecodeContext {
             interface_symbol: interface_name,
             method_name
         };
+        (function.create_details(method_name), method_name)
+    }
 
-        self.call_function(details, method_name, return_slot)
+    /// Looks up the method that a tail call left by compiled code resolves to
+    fn tail_call_target(&self, tail_call: TailCall) -> (FunctionDetails, MethodName) {
+        match tail_call {
+            TailCall::Virtual(specified, method_name, inline_cache) => self.virtual_target(specified, method_name, inline_cache),
+            TailCall::Static(class_name, method_name) => Self::static_target(class_name, method_name),
+            TailCall::Interface(interface_name, method_name, inline_cache) => self.interface_target(interface_name, method_name, inline_cache),
+        }
     }
 
     pub fn call_function(
         &mut self,
-        details: FunctionDetails,
-        method_name: MethodName,
+        mut details: FunctionDetails,
+        mut method_name: MethodName,
         return_slot: Option<&mut StackValue>
     ) -> CallContinueState {
-        for pair in self.call_args.iter().zip(details.arguments.iter()) {
-            match pair {
-                (StackValue::Int8(_), runtime::class::TypeTag::U8) |
-                (StackValue::Int8(_), runtime::class::TypeTag::I8) => {}
-                (StackValue::Int16(_), runtime::class::TypeTag::U16) |
-                (StackValue::Int16(_), runtime::class::TypeTag::I16) => {}
-                (StackValue::Int32(_), runtime::class::TypeTag::U32) |
-                (StackValue::Int32(_), runtime::class::TypeTag::I32) => {}
-                (StackValue::Int64(_), runtime::class::TypeTag::U64) |
-                (StackValue::Int64(_), runtime::class::TypeTag::I64) => {}
-                (StackValue::Float32(_), runtime::class::TypeTag::F32) => {}
-                (StackValue::Float64(_), runtime::class::TypeTag::F64) => {}
-                (StackValue::Reference(_), runtime::class::TypeTag::Object) => {}
-                (value, type_tag) => {
-                    todo!("report type error in typing for tag: {:?} and type_tag: {:?}", value, type_tag);
+        // Compiled code that ends with a tail call returns first, and the call is made here in a loop
+        // so that a chain of tail calls runs in constant native stack space
+        loop {
+            for pair in self.call_args.iter().zip(details.arguments.iter()) {
+                match pair {
+                    (StackValue::Int8(_), runtime::class::TypeTag::U8) |
+                    (StackValue::Int8(_), runtime::class::TypeTag::I8) => {}
+                    (StackValue::Int16(_), runtime::class::TypeTag::U16) |
+                    (StackValue::Int16(_), runtime::class::TypeTag::I16) => {}
+                    (StackValue::Int32(_), runtime::class::TypeTag::U32) |
+                    (StackValue::Int32(_), runtime::class::TypeTag::I32) => {}
+                    (StackValue::Int64(_), runtime::class::TypeTag::U64) |
+                    (StackValue::Int64(_), runtime::class::TypeTag::I64) => {}
+                    (StackValue::Float32(_), runtime::class::TypeTag::F32) => {}
+                    (StackValue::Float64(_), runtime::class::TypeTag::F64) => {}
+                    (StackValue::Reference(_), runtime::class::TypeTag::Object) => {}
+                    (value, type_tag) => {
+                        todo!("report type error in typing for tag: {:?} and type_tag: {:?}", value, type_tag);
+                    }
                 }
             }
-        }
 
-        self.push(details.bytecode, details.fn_ptr.is_none(), method_name, details.block_positions);
-
-        let state = match details.fn_ptr {
-            Some(fn_ptr) => {
-                //println!("calling function pointer");
-                let var_len = self.args_len();
-                let mut variables = self.call_args[0..var_len].to_vec();
-                let mut return_value = StackValue::Blank;
-                call_function_pointer(
-                    self,
-                    &mut variables,
-                    fn_ptr.as_ptr(),
-                    details.return_type,
-                    &mut return_value
-                );
-                self.pop();
-                if let Some(return_slot) = return_slot {
-                    *return_slot = return_value;
-                } else {
-                    if !return_value.is_blank() {
-                        self.push_value(return_value);
-                    }
+            self.push(details.bytecode, details.fn_ptr.is_none(), method_name, details.block_positions, details.inline_caches, details.loops);
+
+            let Some(fn_ptr) = details.fn_ptr else {
+                self.clear_args();
+                return CallContinueState::ExecuteFunction;
+            };
+            //println!("calling function pointer");
+            let var_len = self.args_len();
+            let mut variables = self.call_args[0..var_len].to_vec();
+            // The arguments of a tail call the function ends with are stored after it has started
+            self.clear_args();
+            let mut return_value = StackValue::Blank;
+            call_function_pointer(
+                self,
+                &mut variables,
+                fn_ptr.as_ptr(),
+                details.return_type,
+                &mut return_value
+            );
+            self.pop();
+            if let Some(tail_call) = self.tail_call.take() {
+                (details, method_name) = self.tail_call_target(tail_call);
+                continue;
+            }
+            if let Some(return_slot) = return_slot {
+                *return_slot = return_value;
+            } else {
+                if !return_value.is_blank() {
+                    self.push_value(return_value);
                 }
-                //self.handle_exception()
-                CallContinueState::Return
-            }
-            _ => {
-                CallContinueState::ExecuteFunction
             }
-        };
+            self.clear_args();
+            return match self.handle_exception() {
+                CallContinueState::Error => CallContinueState::Error,
+                _ => CallContinueState::Return,
+            };
+        }
+    }
+
+    fn clear_args(&mut self) {
         for arg in self.get_args_mut() {
             if arg.is_blank() {
                 break
             }
             *arg = StackValue::Blank;
         }
-        state
     }
 
-    /// TODO: add way to pass in cmdline args
-    pub fn call_main(&mut self, class: runtime::Symbol, method: runtime::Symbol) {
+    /// Calls the main method with the program arguments as its `[String]` parameter
+    pub fn call_main(&mut self, class: runtime::Symbol, method: runtime::Symbol, args: &[String]) {
         let details = Runtime::get_static_method_details(
             class,
             method,
@@ -485,11 +616,32 @@ impl BytecodeContext {
             class_symbol: class,
             method_name: method,
         };
+        let args = self.create_args_array(args);
         self.active_bytecodes.push(details.bytecode);
-        // TODO: add passing of cmdline args
-        self.active_frames.push(StackFrame::new(method_name, details.block_positions));
-        self.vars[0] = StackValue::Reference(std::ptr::null_mut());
+        self.active_frames.push(StackFrame::new(method_name, details.block_positions, details.inline_caches, details.loops));
+        self.vars[0] = StackValue::Reference(args);
         self.main_loop();
+        if self.is_current_exception_set() {
+            self.report_uncaught_exception();
+        }
+    }
+
+    fn create_args_array(&mut self, args: &[String]) -> Reference {
+        let array = Runtime::new_object("core::Arrayobject");
+        runtime::core::arrayobject_init(self, array, args.len() as u64);
+        for (i, arg) in args.iter().enumerate() {
+            let string = Runtime::new_object("core::StringBuffer");
+            runtime::core::string_buffer_from_str(string as *mut runtime::core::StringBuffer, arg);
+            runtime::core::arrayobject_set(self, array, i as u64, string as u64);
+        }
+        array
+    }
+
+    /// Exits if a message handler let an exception escape since there is no caller that can handle it
+    pub fn check_for_uncaught_exception(&mut self) {
+        if self.is_current_exception_set() {
+            self.report_uncaught_exception();
+        }
     }
 
     /// returns true if call finished without any errors
@@ -498,15 +650,16 @@ impl BytecodeContext {
         &mut self,
         specified: runtime::Symbol,
         method_name: runtime::Symbol,
+        inline_cache: Option<&InlineCache>,
         return_slot: Option<&mut StackValue>,
     ) -> bool {
-        let result = self.invoke_virtual(specified, method_name, return_slot);
+        let result = self.invoke_virtual(specified, method_name, inline_cache, return_slot);
         match result {
             CallContinueState::Success => false,
             CallContinueState::Return => true,
             CallContinueState::ExecuteFunction => {
                 self.main_loop();
-                true
+                !self.is_current_exception_set()
             }
             CallContinueState::Error => false,
         }
@@ -525,7 +678,7 @@ impl BytecodeContext {
             CallContinueState::Return => true,
             CallContinueState::ExecuteFunction => {
                 self.main_loop();
-                true
+                !self.is_current_exception_set()
             }
             CallContinueState::Error => false,
         }
@@ -535,15 +688,16 @@ impl BytecodeContext {
         &mut self,
         interface_name: runtime::Symbol,
         method_name: runtime::Symbol,
+        inline_cache: Option<&InlineCache>,
         return_slot: Option<&mut StackValue>,
     ) -> bool {
-        let result = self.invoke_interface(interface_name, method_name, return_slot);
+        let result = self.invoke_interface(interface_name, method_name, inline_cache, return_slot);
         match result {
             CallContinueState::Success => false,
             CallContinueState::Return => true,
             CallContinueState::ExecuteFunction => {
                 self.main_loop();
-                true
+                !self.is_current_exception_set()
             }
             CallContinueState::Error => false,
         }
@@ -554,6 +708,10 @@ impl BytecodeContext {
             self.check_and_do_garbage_collection();
             return;
         }
+        // We only run until the frame we started with returns.
+        // This keeps calls made from native code from running their caller's bytecode.
+        let starting_depth = self.active_frames.len();
+        let starting_stack_height = self.operand_stack.len();
         loop {
             let active_bytecode = self.active_bytecodes[self.active_bytecodes.len() - 1];
             assert_ne!(*self.current_frame().ip(), active_bytecode.len());
@@ -561,9 +719,12 @@ impl BytecodeContext {
             *self.current_frame_mut().ip_mut() += 1;
 
             if !self.interpret(bytecode) {
+                if self.is_current_exception_set() && self.catch_exception(starting_depth, starting_stack_height) {
+                    continue;
+                }
                 break;
             }
-            if self.active_frames.is_empty() {
+            if self.active_frames.len() < starting_depth {
                 break;
             }
         }
@@ -571,12 +732,85 @@ impl BytecodeContext {
 
     pub fn run_bytecode(&mut self, bytecode: &'static [Bytecode], block_positions: &'static FxHashMap<usize, usize>) {
         self.active_bytecodes.push(bytecode);
-        self.active_frames.push(StackFrame::new(MethodName::StaticMethod { method_name: 0, class_symbol: 0 }, block_positions));
+        self.active_frames.push(StackFrame::new(MethodName::StaticMethod { method_name: 0, class_symbol: 0 }, block_positions, &NO_INLINE_CACHES, &NO_LOOPS));
         self.main_loop();
         self.pop();
     }
 
-    fn check_for_garbage_collection(&mut self) -> bool {
+    /// Counts a jump back to the start of a loop. Once the loop is hot, the rest of the frame runs in compiled code that starts at the loop.
+    /// Returns what `interpret` should return.
+    fn loop_back_edge(&mut self) -> bool {
+        let StackFrame::Full { current_block, loops, method_name, exception_handlers, .. } = self.current_frame() else {
+            unreachable!("only bytecode frames can jump")
+        };
+        let (block, loops, method_name) = (*current_block, *loops, *method_name);
+        // The handlers of a frame are blocks of its bytecode, so frames in a try stay in the interpreter
+        let has_handlers = !exception_handlers.is_empty();
+
+        let threshold = jit_threshold();
+        let Some(count) = loops.count_back_edge(block) else {
+            return true;
+        };
+        if threshold == 0 || count < threshold || has_handlers {
+            return true;
+        }
+        let entry = loops.entry(
+            block,
+            || self.local_types(),
+            || request_to_jit_method(method_name, Tier::Loop(block)),
+        );
+        let Some((code, return_type)) = entry else {
+            return true;
+        };
+
+        let mut locals = self.vars.current()
+            .iter()
+            .map(|value| match value {
+                StackValue::Reference(reference) => *reference as u64,
+                StackValue::Blank => 0,
+                value => value.into_u64(),
+            })
+            .collect::<Vec<_>>();
+        // The compiled code keeps the variables and bytecode of the frame, it just runs without an instruction pointer
+        self.active_frames.pop();
+        self.active_frames.push(StackFrame::new_light(method_name));
+        let mut return_value = StackValue::Blank;
+        call_function_pointer(
+            self,
+            &mut [StackValue::Int64(locals.as_mut_ptr() as u64)],
+            code,
+            return_type,
+            &mut return_value,
+        );
+        self.pop();
+        if let Some(tail_call) = self.tail_call.take() {
+            // The call the frame ended with takes its place, like it does in the interpreter
+            let (details, method_name) = self.tail_call_target(tail_call);
+            self.call_function(details, method_name, None);
+        } else if !return_value.is_blank() {
+            self.push_value(return_value);
+        }
+        !self.is_current_exception_set() && !self.active_frames.is_empty()
+    }
+
+    /// The types of the variables of the current frame, which compiled code entering a loop is made for
+    fn local_types(&self) -> LocalTypes {
+        self.vars.current()
+            .iter()
+            .map(|value| match value {
+                StackValue::Int8(_) => Some(runtime::class::TypeTag::U8),
+                StackValue::Int16(_) => Some(runtime::class::TypeTag::U16),
+                StackValue::Int32(_) => Some(runtime::class::TypeTag::U32),
+                StackValue::Int64(_) => Some(runtime::class::TypeTag::U64),
+                StackValue::Float32(_) => Some(runtime::class::TypeTag::F32),
+                StackValue::Float64(_) => Some(runtime::class::TypeTag::F64),
+                StackValue::Reference(_) => Some(runtime::class::TypeTag::Object),
+                StackValue::Blank => None,
+            })
+            .collect()
+    }
+
+    pub(crate) fn check_for_garbage_collection(&mut self) -> bool {
         //println!("attempting to read");
         match DO_GARBAGE_COLLECTION.try_read() {
             Ok(_) => true,
@@ -597,17 +831,26 @@ impl BytecodeContext {
 
     }
 
+    /// Has to be called whenever a reference is stored in an object.
+    /// Minor collections don't explore old objects, so they need to know which old objects could point to young objects.
+    pub fn write_barrier(&mut self, object: Reference) {
+        self.remembered_set.insert(WrappedReference(object));
+    }
+
     pub fn collect_garbage(&mut self) {
         let mut references = HashSet::new();
 
+        GarbageCollection::remember(std::mem::take(&mut self.remembered_set));
+
         self.collect_interpreter_references(&mut references);
         self.collect_jit_references(&mut references);
 
 
+        let epoch = GarbageCollection::collection_epoch();
         GarbageCollection::send_references(references);
         loop {
             //println!("spinlock");
-            if self.check_for_garbage_collection() {
+            if GarbageCollection::collection_epoch() != epoch {
                 return
             }
             std::thread::yield_now();
@@ -637,6 +880,14 @@ impl BytecodeContext {
                 _ => {}
             }
         }
+        for var in self.vars.iter() {
+            if let StackValue::Reference(value) = var {
+                references.insert(WrappedReference(*value));
+            }
+        }
+        if self.is_current_exception_set() {
+            references.insert(WrappedReference(self.current_exception));
+        }
     }
 
     fn collect_jit_references(&mut self, references: &mut HashSet<WrappedReference>) {
@@ -661,6 +912,40 @@ impl BytecodeContext {
         self.dereference_stack_pointer(&info, references);
     }
 
+    /// Gets the method and source position of every active frame, innermost first
+    pub fn stack_trace(&self) -> Vec<(MethodName, Option<SourceLocation>)> {
+        // Jitted frames have no instruction pointer of their own, so we pair them with the native frames
+        let mut native_frames = Vec::new();
+        rowan_unwind::backtrace(|frame| {
+            if frame.is_jitted() {
+                native_frames.push(frame.ip());
+            }
+            true
+        });
+        let mut native_frames = native_frames.into_iter();
+
+        let mut trace = Vec::new();
+        for frame in self.active_frames.iter().rev() {
+            let method_name = *frame.method_name();
+            // Static initializers run without a method of their own
+            if let MethodName::StaticMethod { class_symbol: 0, method_name: 0 } = method_name {
+                continue;
+            }
+            let location = if frame.is_for_bytecode() {
+                // The instruction pointer has already moved past the instruction being run
+                Runtime::get_bytecode_location(&method_name, frame.ip().saturating_sub(1))
+            } else if Runtime::is_method_jitted(&method_name) {
+                // The native instruction pointer is a return address, so we step back into the call
+                native_frames.next()
+                    .and_then(|ip| Runtime::get_native_location(&method_name, ip - 1))
+            } else {
+                None
+            };
+            trace.push((method_name, location));
+        }
+        trace
+    }
+
     fn dereference_stack_pointer(
         &mut self,
         info: &[(MethodName, usize, usize)],
@@ -705,8 +990,8 @@ impl BytecodeContext {
             Bytecode::LoadF64(value) => {
                 self.push_value(StackValue::from(*value));
             }
-            Bytecode::LoadSymbol(_sym) => {
-                todo!("LoadSymbol")
+            Bytecode::LoadSymbol(sym) => {
+                self.push_value(StackValue::from(*sym));
             }
             Bytecode::Pop => {
                 self.pop_value();
@@ -1383,6 +1668,11 @@ impl BytecodeContext {
                         let value = lhs == rhs;
                         self.push_value(StackValue::from(value as u8))
                     }
+                    // Objects that aren't records are compared by identity
+                    (StackValue::Reference(lhs), StackValue::Reference(rhs)) => {
+                        let value = lhs == rhs;
+                        self.push_value(StackValue::from(value as u8))
+                    }
                     _ => {
                         todo!("Throw error saying that types should match if they are different")
                     }
@@ -1416,6 +1706,11 @@ impl BytecodeContext {
                         let value = lhs != rhs;
                         self.push_value(StackValue::from(value as u8))
                     }
+                    // Objects that aren't records are compared by identity
+                    (StackValue::Reference(lhs), StackValue::Reference(rhs)) => {
+                        let value = lhs != rhs;
+                        self.push_value(StackValue::from(value as u8))
+                    }
                     _ => {
                         todo!("Throw error saying that types should match if they are different")
                     }
@@ -2155,7 +2450,7 @@ impl BytecodeContext {
                             _ => {}
                         }
                     }
-                    (TypeTag::F32, StackValue::Float64(value)) => {
+                    (TypeTag::F64, StackValue::Float64(value)) => {
                         Object::set_f64(self, object, *access, *parent_name, *index, value);
                         match self.handle_exception() {
                             CallContinueState::Error => return false,
@@ -2179,24 +2474,69 @@ impl BytecodeContext {
                     StackValue::Reference(object) => object,
                     _ => todo!("report needing object")
                 };
-                let object = unsafe {
-                    object.as_ref().expect("check for null pointer")
-                };
-                let result = object.class as u64 == *sym;
+                // Null isn't an instance of any class
+                let result = !object.is_null() && Self::class_hierarchy(object)
+                    .into_iter()
+                    .any(|class| class as u64 == *sym);
                 self.push_value(StackValue::from(result as u8));
             }
             Bytecode::InvokeVirt(specified, method_name) => {
+                let inline_cache = self.current_frame().inline_cache();
                 return match self.invoke_virtual(
                     *specified as runtime::Symbol,
                     *method_name as runtime::Symbol,
+                    inline_cache,
                     None
                 ) {
                     CallContinueState::Error => false,
                     _ => true,
                 }
             }
-            Bytecode::InvokeVirtTail(..) => {
-                todo!("Tail Recursion Virtual")
+            Bytecode::InvokeVirtTail(specified, method_name) => {
+                // The callee takes the place of the current frame instead of returning to it
+                let inline_cache = self.current_frame().inline_cache();
+                self.pop();
+                return match self.invoke_virtual(
+                    *specified as runtime::Symbol,
+                    *method_name as runtime::Symbol,
+                    inline_cache,
+                    None
+                ) {
+                    CallContinueState::Error => false,
+                    _ => self.active_frames.len() != 0,
+                }
+            }
+            Bytecode::EmitSignal(class_name, signal_name) => {
+                Runtime::emit_signal(self, *class_name as runtime::Symbol, *signal_name as runtime::Symbol);
+            }
+            Bytecode::EmitStaticSignal(class_name, signal_name) => {
+                Runtime::emit_static_signal(self, *class_name as runtime::Symbol, *signal_name as runtime::Symbol);
+            }
+            Bytecode::ConnectSignal(signal_name, class_name, method_name) => {
+                let receiver = self.pop_value();
+                let emitter = self.pop_value();
+                let (StackValue::Reference(receiver), StackValue::Reference(emitter)) = (receiver, emitter) else {
+                    runtime::core::throw_null_pointer_exception(self);
+                    return false;
+                };
+                if receiver.is_null() || emitter.is_null() {
+                    runtime::core::throw_null_pointer_exception(self);
+                    return false;
+                }
+                Runtime::connect_signal(emitter, receiver, *signal_name as runtime::Symbol, *class_name as runtime::Symbol, *method_name as runtime::Symbol);
+            }
+            Bytecode::DisconnectSignal(signal_name, class_name, method_name) => {
+                let receiver = self.pop_value();
+                let emitter = self.pop_value();
+                let (StackValue::Reference(receiver), StackValue::Reference(emitter)) = (receiver, emitter) else {
+                    runtime::core::throw_null_pointer_exception(self);
+                    return false;
+                };
+                if receiver.is_null() || emitter.is_null() {
+                    runtime::core::throw_null_pointer_exception(self);
+                    return false;
+                }
+                Runtime::disconnect_signal(emitter, receiver, *signal_name as runtime::Symbol, *class_name as runtime::Symbol, *method_name as runtime::Symbol);
             }
             Bytecode::InvokeStatic(class_name, method_name) => {
                 return match self.invoke_static(
@@ -2208,21 +2548,41 @@ impl BytecodeContext {
                     _ => true,
                 }
             }
-            Bytecode::InvokeStaticTail(..) => {
-                todo!("Tail Recursion Static")
+            Bytecode::InvokeStaticTail(class_name, method_name) => {
+                self.pop();
+                return match self.invoke_static(
+                    *class_name as runtime::Symbol,
+                    *method_name as runtime::Symbol,
+                    None,
+                ) {
+                    CallContinueState::Error => false,
+                    _ => self.active_frames.len() != 0,
+                }
             }
             Bytecode::InvokeInterface(interface_name, method_name) => {
+                let inline_cache = self.current_frame().inline_cache();
                 return match self.invoke_interface(
                     *interface_name as runtime::Symbol,
                     *method_name as runtime::Symbol,
+                    inline_cache,
                     None
                 ) {
                     CallContinueState::Error => false,
                     _ => true,
                 }
             }
-            Bytecode::InvokeInterfaceTail(..) => {
-                todo!("Tail Recursion Interface")
+            Bytecode::InvokeInterfaceTail(interface_name, method_name) => {
+                let inline_cache = self.current_frame().inline_cache();
+                self.pop();
+                return match self.invoke_interface(
+                    *interface_name as runtime::Symbol,
+                    *method_name as runtime::Symbol,
+                    inline_cache,
+                    None
+                ) {
+                    CallContinueState::Error => false,
+                    _ => self.active_frames.len() != 0,
+                }
             }
             Bytecode::GetStaticMember(class, index, ty) => {
                 match ty {
@@ -2312,11 +2672,12 @@ impl BytecodeContext {
                     return false;
                 }
             }
-            Bytecode::RegisterException(..) => {
-                todo!("registering of exceptions");
+            Bytecode::RegisterException(class, offset) => {
+                let stack_height = self.operand_stack.len();
+                self.current_frame_mut().register_exception(*class as runtime::Symbol, *offset as isize, stack_height);
             }
-            Bytecode::UnregisterException(..) => {
-                todo!("unregistering of exceptions");
+            Bytecode::UnregisterException(class) => {
+                self.current_frame_mut().unregister_exception(*class as runtime::Symbol);
             }
             Bytecode::Throw => {
                 let exception = self.pop_value();
@@ -2324,13 +2685,17 @@ impl BytecodeContext {
                     StackValue::Reference(exception) => exception,
                     _ => todo!("report exception needing to be an object"),
                 };
-                self.current_exception = exception;
+                self.set_exception(exception);
+                return false;
             }
             Bytecode::StartBlock(_) => {
                 self.check_and_do_garbage_collection();
             }
             Bytecode::Goto(offset) => {
                 self.current_frame_mut().goto(*offset as isize);
+                if *offset <= 0 {
+                    return self.loop_back_edge();
+                }
             }
             Bytecode::If(then_offset, else_offset) => {
                 let value = self.pop_value();
@@ -2339,14 +2704,27 @@ impl BytecodeContext {
                     _ => todo!("report invalid type for boolean"),
                 };
 
-                if boolean != 0 {
-                    self.current_frame_mut().goto(*then_offset as isize);
+                let offset = if boolean != 0 {
+                    *then_offset
                 } else {
-                    self.current_frame_mut().goto(*else_offset as isize);
+                    *else_offset
+                };
+                self.current_frame_mut().goto(offset as isize);
+                if offset <= 0 {
+                    return self.loop_back_edge();
                 }
             }
-            Bytecode::Switch(..) => {
-                todo!("switching conditional");
+            Bytecode::Switch(cases, default) => {
+                let value = self.pop_value().as_u64();
+                let offset = match (cases.get(value as usize), default) {
+                    (Some(offset), _) => *offset,
+                    (None, Some(offset)) => *offset,
+                    (None, None) => {
+                        runtime::core::throw_index_out_of_bounds(self, cases.len() as u64, value);
+                        return false;
+                    }
+                };
+                self.current_frame_mut().goto(offset as isize);
             }
         }
         true
@@ -2477,4 +2855,71 @@ impl BytecodeContext {
             _ => panic!("invalid type")
         }
     }
+
+    /// Sets up the frame of a method that jitted code calls directly instead of through `call_virtual_function` or `call_interface_function`.
+    /// The arguments have already been stored, so they move into the new frame like they do in `call_function`.
+    pub extern "C" fn jit_enter_method(&mut self, method_name: u64, function: u64) {
+        let method_name = unsafe { *(method_name as *const MethodName) };
+        let function = unsafe { &*(function as *const Function) };
+        let details = function.create_details(method_name);
+        self.push(details.bytecode, false, method_name, details.block_positions, details.inline_caches, details.loops);
+        for arg in self.get_args_mut() {
+            if arg.is_blank() {
+                break
+            }
+            *arg = StackValue::Blank;
+        }
+    }
+
+    /// Removes the frame set up by `jit_enter_method` once the method returns
+    pub extern "C" fn jit_leave_method(&mut self) {
+        self.pop();
+    }
+
+    pub extern "C" fn jit_register_exception(&mut self, class: u64, block: u64) {
+        self.current_frame_mut().register_jit_exception(class as runtime::Symbol, block as usize);
+    }
+
+    pub extern "C" fn jit_unregister_exception(&mut self, class: u64) {
+        self.current_frame_mut().unregister_exception(class as runtime::Symbol);
+    }
+
+    pub extern "C" fn jit_throw(&mut self, exception: u64) {
+        self.set_exception(exception as Reference);
+    }
+
+    /// Leaves a virtual call for the runtime to make once the jitted method returns, which is how it makes a tail call
+    pub extern "C" fn jit_tail_call_virtual(&mut self, class: u64, method: u64, inline_cache: u64) {
+        let inline_cache = unsafe { (inline_cache as *const InlineCache).as_ref() };
+        self.tail_call = Some(TailCall::Virtual(class as runtime::Symbol, method as runtime::Symbol, inline_cache));
+    }
+
+    pub extern "C" fn jit_tail_call_static(&mut self, class: u64, method: u64) {
+        self.tail_call = Some(TailCall::Static(class as runtime::Symbol, method as runtime::Symbol));
+    }
+
+    pub extern "C" fn jit_tail_call_interface(&mut self, interface: u64, method: u64, inline_cache: u64) {
+        let inline_cache = unsafe { (inline_cache as *const InlineCache).as_ref() };
+        self.tail_call = Some(TailCall::Interface(interface as runtime::Symbol, method as runtime::Symbol, inline_cache));
+    }
+
+    /// Throws for a switch without a default whose value isn't one of its cases
+    pub extern "C" fn jit_switch_out_of_range(&mut self, cases: u64, value: u64) {
+        runtime::core::throw_index_out_of_bounds(self, cases, value);
+    }
+
+    /// Finds the handler of the thrown exception in the current jitted frame.
+    /// Returns the block of the handler plus one so that 0 can mean the exception wasn't caught.
+    pub extern "C" fn jit_catch_exception(&mut self) -> u64 {
+        let classes = Self::class_hierarchy(self.current_exception);
+        match self.current_frame_mut().catch_jit_exception(&classes) {
+            Some(block) => block as u64 + 1,
+            None => 0,
+        }
+    }
+
+    /// Takes the caught exception so that the handler block can bind it
+    pub extern "C" fn jit_take_exception(&mut self) -> u64 {
+        self.take_exception() as u64
+    }
 }
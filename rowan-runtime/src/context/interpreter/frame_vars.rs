@@ -19,7 +19,15 @@ impl FrameVars {
     }
     
     pub fn pop(&mut self) {
-        self.frame_offset.pop();
+        // The variables of the popped frame are dropped so that the garbage collector doesn't see stale references
+        if let Some(start) = self.frame_offset.pop() {
+            self.vars.truncate(start);
+        }
+    }
+
+    /// The variables of every active frame
+    pub fn iter(&self) -> std::slice::Iter<'_, StackValue> {
+        self.vars.iter()
     }
     
     fn add_if_needed(&mut self, position: usize) {
@@ -31,6 +39,14 @@ impl FrameVars {
         }
     }
     
+    /// The variables of the current frame
+    pub fn current(&self) -> &[StackValue] {
+        let Some(start) = self.frame_offset.last().cloned() else {
+            unreachable!("We should always have at least one frame")
+        };
+        &self.vars[start..]
+    }
+
     pub fn get(&self, index: usize) -> Option<&StackValue> {
         let Some(start) = self.frame_offset.last().cloned() else {
             unreachable!("We should always have at least one frame")
@@ -1,18 +1,34 @@
 use std::collections::HashSet;
 use fxhash::FxHashMap;
 use crate::context::{MethodName, StackValue, WrappedReference};
+use crate::runtime::inline_cache::{InlineCache, InlineCaches};
+use crate::runtime::osr::Loops;
+use crate::runtime::Symbol;
 
+/// A catch block that was registered with `RegisterException`
+pub struct ExceptionHandler {
+    pub class: Symbol,
+    pub block: usize,
+    /// The height of the operand stack when the handler was registered
+    pub stack_height: usize,
+}
 
 pub enum StackFrame {
     Full {
         ip: usize,
         current_block: usize,
         block_positions: &'static FxHashMap<usize, usize>,
+        inline_caches: &'static InlineCaches,
+        loops: &'static Loops,
         //variables: [StackValue; 256],
         method_name: MethodName,
+        /// The most recently registered handler is last
+        exception_handlers: Vec<ExceptionHandler>,
     },
     Light {
         method_name: MethodName,
+        /// Handlers registered by jitted code, the most recently registered handler is last
+        exception_handlers: Vec<ExceptionHandler>,
     }
 }
 
@@ -21,6 +37,8 @@ impl StackFrame {
         //args: &[StackValue],
         method_name: MethodName,
         block_positions: &'static FxHashMap<usize, usize>,
+        inline_caches: &'static InlineCaches,
+        loops: &'static Loops,
     ) -> Self {
         /*let mut variables = [StackValue::Blank; 256];
         for (arg, variable) in args.iter().zip(variables.iter_mut()) {
@@ -33,8 +51,11 @@ impl StackFrame {
             ip: 0,
             current_block: 0,
             block_positions,
+            inline_caches,
+            loops,
             //variables,
             method_name,
+            exception_handlers: Vec::new(),
         }
     }
 
@@ -46,13 +67,13 @@ impl StackFrame {
     }
 
     pub fn new_light(method_name: MethodName) -> Self {
-        StackFrame::Light { method_name }
+        StackFrame::Light { method_name, exception_handlers: Vec::new() }
     }
 
     pub fn method_name(&self) -> &MethodName {
         match self {
             StackFrame::Full { method_name, .. } => method_name,
-            StackFrame::Light { method_name } => method_name,
+            StackFrame::Light { method_name, .. } => method_name,
         }
     }
 
@@ -77,6 +98,14 @@ impl StackFrame {
         }
     }
 
+    /// Gets the inline cache of the call being run, which is right before the instruction pointer
+    pub fn inline_cache(&self) -> Option<&'static InlineCache> {
+        match self {
+            StackFrame::Full { ip, inline_caches, .. } => inline_caches.get(&(*ip - 1)),
+            StackFrame::Light { .. } => None,
+        }
+    }
+
     pub fn ip_mut(&mut self) -> &mut usize {
         match self {
             StackFrame::Full { ip, .. } => ip,
@@ -102,6 +131,88 @@ impl StackFrame {
         }
     }
 
+    pub fn register_exception(&mut self, class: Symbol, block_offset: isize, stack_height: usize) {
+        match self {
+            StackFrame::Full {
+                current_block,
+                exception_handlers,
+                ..
+            } => {
+                let block = (*current_block as isize + block_offset) as usize;
+                exception_handlers.push(ExceptionHandler {
+                    class,
+                    block,
+                    stack_height,
+                });
+            }
+            StackFrame::Light { .. } => unreachable!("can't register exceptions with light stack frame")
+        }
+    }
+
+    /// Registers a handler for jitted code, which knows the block of the handler ahead of time
+    pub fn register_jit_exception(&mut self, class: Symbol, block: usize) {
+        match self {
+            StackFrame::Light {
+                exception_handlers,
+                ..
+            } => {
+                exception_handlers.push(ExceptionHandler {
+                    class,
+                    block,
+                    stack_height: 0,
+                });
+            }
+            StackFrame::Full { .. } => unreachable!("jitted code always runs with a light stack frame")
+        }
+    }
+
+    /// Removes the most recently registered handler for the class
+    pub fn unregister_exception(&mut self, class: Symbol) {
+        let (StackFrame::Full { exception_handlers, .. } | StackFrame::Light { exception_handlers, .. }) = self;
+        if let Some(index) = exception_handlers.iter().rposition(|handler| handler.class == class) {
+            exception_handlers.remove(index);
+        }
+    }
+
+    /// Finds the most recently registered handler for any of the classes of an exception.
+    /// The handler and every handler registered after it are removed, and execution continues at the start of its block.
+    /// Returns the height the operand stack should be cut down to.
+    pub fn catch_exception(&mut self, classes: &[Symbol]) -> Option<usize> {
+        match self {
+            StackFrame::Full {
+                block_positions,
+                ip,
+                current_block,
+                exception_handlers,
+                ..
+            } => {
+                let index = exception_handlers.iter()
+                    .rposition(|handler| classes.contains(&handler.class))?;
+                let handler = exception_handlers.drain(index..).next().unwrap();
+                *ip = block_positions[&handler.block];
+                *current_block = handler.block;
+                Some(handler.stack_height)
+            }
+            StackFrame::Light { .. } => None,
+        }
+    }
+
+    /// Like `catch_exception`, but for jitted code which jumps to the returned block itself
+    pub fn catch_jit_exception(&mut self, classes: &[Symbol]) -> Option<usize> {
+        match self {
+            StackFrame::Light {
+                exception_handlers,
+                ..
+            } => {
+                let index = exception_handlers.iter()
+                    .rposition(|handler| classes.contains(&handler.class))?;
+                let handler = exception_handlers.drain(index..).next().unwrap();
+                Some(handler.block)
+            }
+            StackFrame::Full { .. } => None,
+        }
+    }
+
     /*pub fn vars_len(&self) -> usize {
         match self {
             StackFrame::Full {
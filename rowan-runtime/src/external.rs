@@ -285,9 +285,9 @@ pub extern "C" fn rowan_call_virtual_function(
     let mut return_value = StackValue::Blank;
 
     let result = if return_slot.is_some() {
-        context.invoke_virtual_extern(class, method_name, Some(&mut return_value))
+        context.invoke_virtual_extern(class, method_name, None, Some(&mut return_value))
     } else {
-        context.invoke_virtual_extern(class, method_name, None)
+        context.invoke_virtual_extern(class, method_name, None, None)
     };
 
     match return_slot {
@@ -375,9 +375,9 @@ pub extern "C" fn rowan_call_interface_function(
     let mut return_value = StackValue::Blank;
 
     let result = if return_slot.is_some() {
-        context.invoke_interface_extern(interface, method_name, Some(&mut return_value))
+        context.invoke_interface_extern(interface, method_name, None, Some(&mut return_value))
     } else {
-        context.invoke_interface_extern(interface, method_name, None)
+        context.invoke_interface_extern(interface, method_name, None, None)
     };
 
     match return_slot {
@@ -395,8 +395,8 @@ pub extern "C" fn rowan_call_interface_function(
 }
 
 #[unsafe(no_mangle)]
-pub extern "C" fn rowan_set_exception(_context: &mut Runtime, _exception: Reference) {
-    //context.set_exception(exception);
+pub extern "C" fn rowan_set_exception(context: &mut BytecodeContext, exception: Reference) {
+    context.set_exception(exception);
 }
 
 #[unsafe(no_mangle)]
@@ -1,37 +1,148 @@
 use std::{collections::HashMap, io::Read};
 use std::path::PathBuf;
+use clap::{Parser, ValueEnum};
 use rowan_shared::classfile::ClassFile;
+use rowan_shared::package::Package;
 use rowan_shared::RowanClassFile;
-use runtime::{core, Runtime};
+use runtime::{aot, core, Runtime};
 use crate::context::BytecodeContext;
 use crate::runtime::garbage_collection::{GarbageCollection};
-use crate::runtime::jit::{set_jit_sender, JITController};
+use crate::runtime::heartbeat::Heartbeat;
+use crate::runtime::jit::{set_jit_sender, set_jit_threshold, JITController};
+use crate::runtime::scheduler::Scheduler;
 
 mod runtime;
 mod fake_lock;
 mod external;
 mod context;
 
+#[derive(Parser, Debug)]
+#[command(name = "rowan", about = "Links and runs compiled Rowan class files")]
+pub struct Args {
+    /// The class files or packages to link
+    #[arg(required = true)]
+    pub class_files: Vec<String>,
+
+    /// The heap size that causes a full collection, like 512M or 4G
+    #[arg(long, value_parser = parse_size)]
+    pub max_heap: Option<i64>,
+
+    /// How much can be allocated between minor collections, like 32M
+    #[arg(long, value_parser = parse_size)]
+    pub nursery_size: Option<i64>,
+
+    /// How the garbage collector decides what to look at
+    #[arg(long, value_enum, default_value_t = GcPolicy::Generational)]
+    pub gc: GcPolicy,
+
+    /// Print what every garbage collection did to stderr
+    #[arg(long)]
+    pub gc_stats: bool,
+
+    /// When methods get compiled to native code
+    #[arg(long, value_enum, default_value_t = JitMode::On)]
+    pub jit: JitMode,
+
+    /// The number of calls a method needs before it gets compiled
+    #[arg(long, default_value_t = 1000)]
+    pub jit_threshold: u64,
+
+    /// Write the names of compiled methods to /tmp/perf-<pid>.map so that perf can show them
+    #[arg(long)]
+    pub perf_map: bool,
+
+    /// Compile every method into a shared object at this path instead of running the program
+    #[arg(long, value_name = "FILE")]
+    pub aot_compile: Option<PathBuf>,
+
+    /// Use the methods that `--aot-compile` put in this shared object instead of compiling them while running
+    #[arg(long, value_name = "FILE")]
+    pub aot: Option<PathBuf>,
+
+    /// The arguments given to the program, which come after `--`
+    #[arg(last = true)]
+    pub program_args: Vec<String>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum GcPolicy {
+    /// Collect new objects often and the whole heap only when it is full
+    Generational,
+    /// Collect the whole heap every time it is full
+    Full,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum JitMode {
+    /// Compile methods once they have been called `--jit-threshold` times
+    On,
+    /// Never compile methods
+    Off,
+    /// Compile methods on their first call
+    Eager,
+}
+
+/// Parses a size in bytes with an optional K, M or G suffix
+fn parse_size(size: &str) -> Result<i64, String> {
+    let size = size.trim();
+    let (digits, multiplier) = match size.char_indices().last() {
+        Some((index, 'k' | 'K')) => (&size[..index], 1024),
+        Some((index, 'm' | 'M')) => (&size[..index], 1024 * 1024),
+        Some((index, 'g' | 'G')) => (&size[..index], 1024 * 1024 * 1024),
+        _ => (size, 1),
+    };
+    let value = digits.parse::<i64>()
+        .map_err(|_| format!("invalid size `{size}`, expected something like 512M or 4G"))?;
+    value.checked_mul(multiplier)
+        .filter(|size| *size > 0)
+        .ok_or_else(|| format!("size `{size}` is out of range"))
+}
+
 /// The start function for calling the main method in Rowan.
 /// This function will parse commandline arguments from a Rust Context so don't call it from anywhere else.
 /// It will initialize the state of the Rowan runtime by configuring the VM, linking core, and user classes, and start the garbage collector.
-/// After that, it will call the main method.
+/// After that, it will call the main method and then send heartbeats to the object graph.
 #[unsafe(no_mangle)]
 pub extern "C" fn rowan_main() {
     env_logger::init();
-    let args = std::env::args().collect::<Vec<String>>();
-
-    if args.len() < 2 {
-        return
+    let args = Args::parse();
+    // Static initializers run while linking, so the JIT has to be configured first
+    set_jit_threshold(match args.jit {
+        JitMode::On => args.jit_threshold,
+        JitMode::Off => 0,
+        JitMode::Eager => 1,
+    });
+    if args.perf_map {
+        rowan_register_debug::enable_perf_map();
     }
 
-    let (class_files, paths): (Vec<RowanClassFile>, Vec<PathBuf>) = args[1..].iter().map(|f| {
+    let mut main_class = None;
+    let mut binaries = Vec::new();
+    for f in args.class_files.iter() {
         println!("{}", f);
         let mut file = std::fs::File::open(f).unwrap();
         let mut output = Vec::new();
         file.read_to_end(&mut output).unwrap();
-        (rowan_shared::load_binary(&output), PathBuf::from(f))
-    }).unzip();
+        if !Package::is_package(&output) {
+            binaries.push((output, PathBuf::from(f)));
+            continue;
+        }
+        // Entries of a package are placed under its path so that native libraries are found next to their classes
+        let package = Package::from_binary(&output);
+        main_class.get_or_insert(package.manifest.main_class);
+        for entry in package.entries {
+            let path = PathBuf::from(f).join(&entry.path);
+            if entry.is_class_file() {
+                binaries.push((entry.data, path));
+            } else {
+                Runtime::add_bundled_library(path, entry.data);
+            }
+        }
+    }
+
+    let (class_files, paths): (Vec<RowanClassFile>, Vec<PathBuf>) = binaries.into_iter()
+        .map(|(binary, path)| (rowan_shared::load_binary(&binary), path))
+        .unzip();
 
     let mut classes = Vec::new();
     let mut class_paths = Vec::new();
@@ -100,7 +211,8 @@ pub extern "C" fn rowan_main() {
         &mut pre_class_table,
         &mut pre_interface_table,
         &mut interfaces_map,
-        &mut vtables_map);
+        &mut vtables_map,
+        main_class.as_deref());
 
     Runtime::link_interfaces(
         interfaces,
@@ -112,6 +224,21 @@ pub extern "C" fn rowan_main() {
 
     Runtime::finish_linking_classes(pre_class_table);
 
+    // Compiled code has the symbols of the linked classes baked in, so it is written and loaded only after linking
+    if let Some(path) = &args.aot_compile {
+        if let Err(e) = aot::compile(path) {
+            eprintln!("Unable to compile {}: {e}", path.display());
+            std::process::exit(1);
+        }
+        return;
+    }
+    if let Some(path) = &args.aot {
+        // Without the library the methods still get compiled by the JIT, only later
+        if let Err(e) = aot::load(path) {
+            eprintln!("Unable to load {}: {e}", path.display());
+        }
+    }
+
     let (jit_sender, jit_receiver) = std::sync::mpsc::channel();
 
     set_jit_sender(jit_sender);
@@ -123,12 +250,23 @@ pub extern "C" fn rowan_main() {
 
 
     //println!("String Map: {string_map:#?}");
-    GarbageCollection::initialize(None);
+    let max_nursery_size = match args.gc {
+        GcPolicy::Generational => args.nursery_size,
+        GcPolicy::Full => Some(i64::MAX),
+    };
+    GarbageCollection::initialize(args.max_heap, max_nursery_size, args.gc_stats);
+    Scheduler::initialize(None);
 
     let mut context = BytecodeContext::new();
 
+    // An instance of the main class is the root of the object graph, so its ready method comes first
+    let heartbeat = Heartbeat::new();
+    heartbeat.attach_root(&mut context, Runtime::new_object(main_symbol));
+
     //println!("main_symbol: {}, main_method_symbol: {}", main_symbol, main_method_symbol);
-    context.call_main(main_symbol, main_method_symbol);
+    context.call_main(main_symbol, main_method_symbol, &args.program_args);
+
+    heartbeat.run(&mut context);
 
     /*let method = context.get_static_method(main_symbol, main_method_symbol);
 
@@ -149,3 +287,26 @@ pub extern "C" fn rowan_main() {
         std::process::exit(1);
     }*/
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_size_suffixes() {
+        assert_eq!(parse_size("512"), Ok(512));
+        assert_eq!(parse_size("4k"), Ok(4 * 1024));
+        assert_eq!(parse_size("512M"), Ok(512 * 1024 * 1024));
+        assert_eq!(parse_size(" 2G "), Ok(2 * 1024 * 1024 * 1024));
+    }
+
+    #[test]
+    fn test_parse_size_rejects_invalid_sizes() {
+        assert!(parse_size("").is_err());
+        assert!(parse_size("M").is_err());
+        assert!(parse_size("12T").is_err());
+        assert!(parse_size("0").is_err());
+        assert!(parse_size("-4M").is_err());
+        assert!(parse_size("9223372036854775807G").is_err());
+    }
+}
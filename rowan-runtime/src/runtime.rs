@@ -4,9 +4,9 @@ use std::sync::{Arc, RwLock};
 use class::Class;
 
 use cranelift::prelude::Signature;
-use jit::{JITCompiler, JITController};
+use jit::{JITCompiler, JITController, Tier};
 use linker::TableEntry;
-use object::Object;
+use object::{GcClasses, Object};
 use rowan_shared::classfile::ClassFile;
 use core::VMClass;
 use tables::{class_table::ClassTable, object_table::ObjectTable, string_table::StringTable, symbol_table::{SymbolEntry, SymbolTable}, vtable::{FunctionValue, VTables}};
@@ -28,14 +28,24 @@ pub mod object;
 pub mod core;
 pub mod linker;
 pub mod jit;
+pub mod aot;
+pub mod inline_cache;
+pub mod osr;
 pub mod garbage_collection;
+pub mod heartbeat;
+pub mod message;
+pub mod scheduler;
 mod interface;
 
-pub use tables::FunctionDetails;
+pub use tables::{Function, FunctionDetails, SourceLocation};
 use crate::runtime::core::StringBuffer;
 use crate::runtime::interface::{Interface, InterfaceImpl};
 use crate::runtime::tables::interface_table::InterfaceTable;
 use crate::runtime::tables::native_object_table::NativeObjectTable;
+use crate::runtime::tables::signal_table::{Connection, SignalTable};
+use crate::runtime::message::{Message, MessageQueues};
+use crate::runtime::garbage_collection::LiveObjects;
+use crate::runtime::inline_cache::InlineCache;
 
 pub type Symbol = usize;
 
@@ -108,6 +118,11 @@ static INTERFACE_TABLE: LazyLock<RwLock<InterfaceTable>> = LazyLock::new(|| {
     RwLock::new(table)
 });
 
+static SIGNAL_TABLE: LazyLock<RwLock<SignalTable>> = LazyLock::new(|| {
+    let table = SignalTable::new();
+    RwLock::new(table)
+});
+
 static INTERFACE_MAP: LazyLock<RwLock<HashMap<&'static str, Symbol>>> = LazyLock::new(|| {
     let map = HashMap::new();
     RwLock::new(map)
@@ -527,6 +542,14 @@ impl Runtime {
         1
     }
 
+    /// Makes a native library from a package available to the linker as if it were at path
+    pub fn add_bundled_library(path: PathBuf, contents: Vec<u8>) {
+        let Ok(mut library_table) = LIBRARY_TABLE.write() else {
+            panic!("Lock poisoned");
+        };
+        library_table.add_bundled(path.to_str().unwrap().to_string(), contents);
+    }
+
     pub fn link_classes(
         classes: Vec<ClassFile>,
         class_locations: Vec<PathBuf>,
@@ -537,6 +560,7 @@ impl Runtime {
         // The second hashmap is the class that has a custom version of the vtable
         // For example, two matching symbols means that that is the vtable of that particular class
         vtables_map: &mut HashMap<Symbol, HashMap<Symbol, Vec<(Symbol, Vec<rowan_shared::TypeTag>, linker::MethodLocation, Box<[Bytecode]>, FunctionValue, Signature)>>>,
+        main_class: Option<&str>,
     ) -> (Symbol, Symbol) {
         let Ok(mut string_table) = STRING_TABLE.write() else {
             panic!("Lock poisoned");
@@ -559,6 +583,9 @@ impl Runtime {
         let Ok(mut string_map) = STRING_MAP.write() else {
             panic!("Lock poisoned");
         };
+        let Ok(mut signal_table) = SIGNAL_TABLE.write() else {
+            panic!("Lock poisoned");
+        };
 
         let out = linker::link_class_files(
             classes,
@@ -574,6 +601,8 @@ impl Runtime {
             class_map.borrow_mut(),
             interface_map,
             &mut library_table,
+            &mut signal_table,
+            main_class,
         ).unwrap();
 
         //println!("class_map: {:#?}", &class_map);
@@ -716,6 +745,21 @@ impl Runtime {
         class_symbol: Symbol,
         method_name: Symbol,
     ) -> FunctionDetails {
+        Self::get_virtual_method(object_class_symbol, class_symbol, method_name)
+            .create_details(MethodName::VirtualMethod {
+                object_class_symbol,
+                class_symbol,
+                method_name,
+            })
+    }
+
+    /// Finds the method that a virtual call on an object of `object_class_symbol` ends up calling.
+    /// Functions are never freed, which is what lets inline caches hold on to them.
+    pub fn get_virtual_method(
+        object_class_symbol: Symbol,
+        class_symbol: Symbol,
+        method_name: Symbol,
+    ) -> &'static Function {
         let Ok(symbol_table) = SYMBOL_TABLE.read() else {
             panic!("Lock poisoned");
         };
@@ -741,17 +785,25 @@ impl Runtime {
         let vtable = &vtables_table[vtable_index];
         let function = vtable.get_function(method_name).expect("unable to find function");
 
-        function.create_details(MethodName::VirtualMethod {
-            object_class_symbol,
-            class_symbol,
-            method_name,
-        })
+        unsafe { &*(function as *const Function) }
     }
 
     pub fn get_static_method_details(
         class_symbol: Symbol,
         method_name: Symbol,
     ) -> FunctionDetails {
+        Self::get_static_method(class_symbol, method_name)
+            .create_details(MethodName::StaticMethod {
+                class_symbol,
+                method_name,
+            })
+    }
+
+    /// Finds the static method `method_name` of `class_symbol`
+    pub fn get_static_method(
+        class_symbol: Symbol,
+        method_name: Symbol,
+    ) -> &'static Function {
         let Ok(symbol_table) = SYMBOL_TABLE.read() else {
             unreachable!("Lock poisoned");
         };
@@ -774,10 +826,7 @@ impl Runtime {
         let vtable = &vtables_table[vtable_index];
         let function = vtable.get_function(method_name).expect("unable to get function");
 
-        function.create_details(MethodName::StaticMethod {
-            class_symbol,
-            method_name,
-        })
+        unsafe { &*(function as *const Function) }
     }
 
     pub fn get_interface_method_details(
@@ -785,6 +834,20 @@ impl Runtime {
         interface_symbol: Symbol,
         method_name: Symbol,
     ) -> FunctionDetails {
+        Self::get_interface_method(class_symbol, interface_symbol, method_name)
+            .create_details(MethodName::InterfaceMethod {
+                class_symbol,
+                interface_symbol,
+                method_name,
+            })
+    }
+
+    /// Finds the method that an interface call on an object of `class_symbol` ends up calling
+    pub fn get_interface_method(
+        class_symbol: Symbol,
+        interface_symbol: Symbol,
+        method_name: Symbol,
+    ) -> &'static Function {
         let Ok(symbol_table) = SYMBOL_TABLE.read() else {
             panic!("Lock poisoned");
         };
@@ -805,17 +868,9 @@ impl Runtime {
         };
 
         let vtable = &vtables_table[vtable_index];
-        println!("getting interface method: {vtable_index}");
-        println!("mapper: {:#?}",vtable.symbol_mapper);
-        println!("method name: {}", method_name);
         let function = vtable.get_function(method_name).expect("unable to get function");
 
-
-        function.create_details(MethodName::InterfaceMethod {
-            class_symbol,
-            interface_symbol,
-            method_name,
-        })
+        unsafe { &*(function as *const Function) }
     }
 
     pub fn get_object_field(
@@ -976,6 +1031,7 @@ impl Runtime {
                             }
                         }
                         (TypeTag::Object, StackValue::Reference(v)) => {
+                            context.write_barrier(object);
                             unsafe {
                                 object.set(offset, v)
                             }
@@ -999,6 +1055,7 @@ impl Runtime {
         object_class_symbol: Symbol,
         class_symbol: Symbol,
         method_name: Symbol,
+        tier: Tier,
     ) {
         let Ok(symbol_table) = SYMBOL_TABLE.read() else {
             panic!("Lock poisoned");
@@ -1026,24 +1083,20 @@ impl Runtime {
         let vtable = &vtables_table[vtable_index];
         let function = vtable.get_function(method_name).unwrap();
 
-        let value = function.value.lock().unwrap();
-        match &*value {
-            FunctionValue::Bytecode(_) => {
-                drop(value);
-                let mut compiler = Runtime::create_jit_compiler();
-                let Ok(mut jit_controller) = JIT_CONTROLLER.write() else {
-                    panic!("Lock poisoned");
-                };
+        if function.can_compile(tier) {
+            let mut compiler = Runtime::create_jit_compiler();
+            let Ok(mut jit_controller) = JIT_CONTROLLER.write() else {
+                panic!("Lock poisoned");
+            };
 
-                compiler.compile(function, &mut jit_controller.module).unwrap();
-            }
-            _ => {}
-        };
+            compiler.compile(MethodName::VirtualMethod { object_class_symbol, class_symbol, method_name }, tier, function, &mut jit_controller.module).unwrap();
+        }
     }
 
     pub fn jit_static_method(
         class_symbol: Symbol,
         method_name: Symbol,
+        tier: Tier,
     ) {
         let Ok(symbol_table) = SYMBOL_TABLE.read() else {
             unreachable!("Lock poisoned");
@@ -1068,28 +1121,24 @@ impl Runtime {
         let vtable = &vtables_table[vtable_index];
         let function = vtable.get_function(method_name).unwrap();
 
-        let value = function.value.lock().unwrap();
-        match &*value {
-            FunctionValue::Bytecode(_) => {
-                drop(value);
-                let mut compiler = Runtime::create_jit_compiler();
-                let Ok(mut jit_controller) = JIT_CONTROLLER.write() else {
-                    unreachable!("Lock poisoned");
-                };
+        if function.can_compile(tier) {
+            let mut compiler = Runtime::create_jit_compiler();
+            let Ok(mut jit_controller) = JIT_CONTROLLER.write() else {
+                unreachable!("Lock poisoned");
+            };
 
-                match compiler.compile(function, &mut jit_controller.module) {
-                    Ok(_) => {}
-                    Err(e) => panic!("Compilation error:\n{}", e)
-                }
+            match compiler.compile(MethodName::StaticMethod { class_symbol, method_name }, tier, function, &mut jit_controller.module) {
+                Ok(_) => {}
+                Err(e) => panic!("Compilation error:\n{}", e)
             }
-            _ => {}
-        };
+        }
     }
 
     pub fn jit_interface_method(
         class_symbol: Symbol,
         interface_symbol: Symbol,
         method_name: Symbol,
+        tier: Tier,
     ) {
         let Ok(symbol_table) = SYMBOL_TABLE.read() else {
             panic!("Lock poisoned");
@@ -1104,7 +1153,7 @@ impl Runtime {
         };
 
         let class = &class_table[class_index];
-        let vtable_index = if let Some(index) = class.get_vtable(&class_symbol) {
+        let vtable_index = if let Some(index) = class.get_vtable(&interface_symbol) {
             index
         } else {
             panic!("unable to find vtable");
@@ -1117,19 +1166,14 @@ impl Runtime {
         let vtable = &vtables_table[vtable_index];
         let function = vtable.get_function(method_name).unwrap();
 
-        let value = function.value.lock().unwrap();
-        match &*value {
-            FunctionValue::Bytecode(_) => {
-                drop(value);
-                let mut compiler = Runtime::create_jit_compiler();
-                let Ok(mut jit_controller) = JIT_CONTROLLER.write() else {
-                    panic!("Lock poisoned");
-                };
+        if function.can_compile(tier) {
+            let mut compiler = Runtime::create_jit_compiler();
+            let Ok(mut jit_controller) = JIT_CONTROLLER.write() else {
+                panic!("Lock poisoned");
+            };
 
-                compiler.compile(function, &mut jit_controller.module).unwrap();
-            }
-            _ => {}
-        };
+            compiler.compile(MethodName::InterfaceMethod { class_symbol, interface_symbol, method_name }, tier, function, &mut jit_controller.module).unwrap();
+        }
     }
 
     pub fn create_jit_compiler() -> JITCompiler {
@@ -1169,6 +1213,56 @@ impl Runtime {
         string_table.get_string(index)
     }
 
+    /// Runs `f` on the function a frame is running, if the function can be found
+    fn with_function<R>(method_name: &MethodName, f: impl FnOnce(&Function) -> R) -> Option<R> {
+        let Ok(symbol_table) = SYMBOL_TABLE.read() else {
+            panic!("Lock poisoned");
+        };
+        let Ok(class_table) = CLASS_TABLE.read() else {
+            panic!("Lock poisoned");
+        };
+        let Ok(vtables_table) = VTABLES.read() else {
+            panic!("Lock poisoned");
+        };
+
+        let (class_symbol, vtable_class_symbol, method_name) = match method_name {
+            MethodName::StaticMethod { class_symbol, method_name } => (*class_symbol, None, *method_name),
+            MethodName::VirtualMethod { object_class_symbol, class_symbol, method_name } => {
+                (*object_class_symbol, Some(*class_symbol), *method_name)
+            }
+            MethodName::InterfaceMethod { class_symbol, interface_symbol, method_name } => {
+                (*class_symbol, Some(*interface_symbol), *method_name)
+            }
+        };
+        let SymbolEntry::ClassRef(class_index) = symbol_table[class_symbol] else {
+            return None;
+        };
+        let class = &class_table[class_index];
+        let vtable_index = match vtable_class_symbol {
+            Some(vtable_class_symbol) => class.get_vtable(&vtable_class_symbol)?,
+            None => class.static_methods,
+        };
+        let function = vtables_table[vtable_index].get_function(method_name)?;
+        Some(f(function))
+    }
+
+    /// Finds the source position of the bytecode at `ip` in an interpreted frame
+    pub fn get_bytecode_location(method_name: &MethodName, ip: usize) -> Option<SourceLocation> {
+        Self::with_function(method_name, |function| function.find_location(ip)).flatten()
+    }
+
+    /// Finds the source position of a native address in a jitted frame
+    pub fn get_native_location(method_name: &MethodName, address: usize) -> Option<SourceLocation> {
+        Self::with_function(method_name, |function| function.find_native_location(address)).flatten()
+    }
+
+    /// Checks if a frame is running the jitted version of its function
+    pub fn is_method_jitted(method_name: &MethodName) -> bool {
+        Self::with_function(method_name, |function| {
+            matches!(&*function.value.lock().unwrap(), FunctionValue::Compiled(..))
+        }).unwrap_or(false)
+    }
+
     pub fn get_virtual_method_signature(class_symbol: Symbol, method_name: Symbol) -> (Signature, bool) {
         let Ok(symbol_table) = SYMBOL_TABLE.read() else {
             panic!("Lock poisoned");
@@ -1206,6 +1300,32 @@ impl Runtime {
 
     }
 
+    pub fn get_interface_method_signature(interface_symbol: Symbol, method_name: Symbol) -> (Signature, bool) {
+        let Ok(symbol_table) = SYMBOL_TABLE.read() else {
+            panic!("Lock poisoned");
+        };
+        let Ok(interface_table) = INTERFACE_TABLE.read() else {
+            panic!("Lock poisoned");
+        };
+
+        let SymbolEntry::InterfaceRef(interface_index) = symbol_table[interface_symbol] else {
+            panic!("interface wasn't an interface");
+        };
+
+        let vtable_index = interface_table[interface_index].vtable;
+        let Ok(vtables_table) = VTABLES.read() else {
+            panic!("Lock poisoned");
+        };
+
+        let vtable = &vtables_table[vtable_index];
+        let function = vtable.get_function(method_name).unwrap();
+        let is_object = match function.return_type {
+            crate::runtime::class::TypeTag::Object => true,
+            _ => false,
+        };
+        (function.signature.clone(), is_object)
+    }
+
     pub fn get_static_method_signature(
         class_symbol: Symbol, 
         method_name: Symbol
@@ -1367,7 +1487,7 @@ impl Runtime {
                     let vtable = &vtables_table[vtable_index];
                     let function = vtable.get_function(*method_name).expect("unable to get function");
                     let value = function.value.lock().unwrap();
-                    let FunctionValue::Compiled(_, map) = &*value else {
+                    let FunctionValue::Compiled(_, map, _) = &*value else {
                         unreachable!("we are trying to access the stack of a non-compiled function");
                     };
                     if let Some(offsets) = map.get(ip) {
@@ -1404,7 +1524,7 @@ impl Runtime {
                     let function = vtable.get_function(*method_name).expect("unable to find function");
 
                     let value = &*function.value.lock().unwrap();
-                    let FunctionValue::Compiled(_, map) = value else {
+                    let FunctionValue::Compiled(_, map, _) = value else {
                         let Ok(string_table) = STRING_TABLE.read() else {
                             unreachable!("we are trying to access the stack of a non-compiled function");
                         };
@@ -1447,7 +1567,7 @@ impl Runtime {
                     let function = vtable.get_function(*method_name).expect("unable to find function");
 
                     let value = &*function.value.lock().unwrap();
-                    let FunctionValue::Compiled(_, map) = value else {
+                    let FunctionValue::Compiled(_, map, _) = value else {
                         let Ok(string_table) = STRING_TABLE.read() else {
                             unreachable!("we are trying to access the stack of a non-compiled function");
                         };
@@ -1471,11 +1591,36 @@ impl Runtime {
     }
 
 
-    pub fn gc_explore_object(reference: Reference, live_objects: &mut HashSet<Reference>) {
-        Object::garbage_collect(reference, live_objects);
+    pub fn gc_explore_object(reference: Reference, classes: &GcClasses, live_objects: &mut LiveObjects) {
+        Object::garbage_collect(reference, classes, live_objects);
+    }
+
+    /// Explores the old objects that have had references stored in them
+    pub fn gc_explore_remembered_objects(objects: HashSet<WrappedReference>, classes: &GcClasses, live_objects: &mut LiveObjects) {
+        let objects = {
+            let Ok(object_table) = OBJECT_TABLE.read() else {
+                panic!("Lock poisoned");
+            };
+            // Young objects are only live if something else points to them
+            objects.into_iter()
+                .map(|object| object.0)
+                .filter(|object| object_table.contains(object) && !live_objects.is_collectable(*object))
+                .collect::<Vec<_>>()
+        };
+        for object in objects {
+            Object::garbage_collect_remembered(object, classes, live_objects);
+        }
+    }
+
+    pub fn gc_take_nursery() -> HashSet<Reference> {
+        let Ok(mut object_table) = OBJECT_TABLE.write() else {
+            panic!("Lock poisoned");
+        };
+        object_table.take_nursery()
     }
 
-    pub fn gc_collect_garbage(live_objects: &HashSet<Reference>) {
+    /// Frees every object that wasn't found to be live and returns how many were freed
+    pub fn gc_collect_garbage(live_objects: &LiveObjects) -> usize {
         let Ok(symbol_table) = SYMBOL_TABLE.read() else {
             panic!("Lock poisoned");
         };
@@ -1486,19 +1631,42 @@ impl Runtime {
             panic!("Lock poisoned");
         };
 
-        let mut objects_to_delete = Vec::new();
-
-        for (i, _) in object_table.iter().enumerate() {
-            if !live_objects.contains(&(i as Reference)) {
-                objects_to_delete.push(i as Reference);
-            }
-        }
+        // Minor collections only have to look at the nursery
+        let objects_to_delete = live_objects.dead_young_objects().unwrap_or_else(|| {
+            object_table.iter()
+                .filter(|reference| !live_objects.contains(reference))
+                .copied()
+                .collect()
+        });
 
         //println!("Survived: {live_objects:?}");
 
+        let freed = objects_to_delete.len();
         for reference in objects_to_delete {
             object_table.free(reference, &symbol_table, &class_table);
         }
+
+        let Ok(mut signal_table) = SIGNAL_TABLE.write() else {
+            panic!("Lock poisoned");
+        };
+        signal_table.remove_dead(|reference| object_table.contains(reference));
+        freed
+    }
+
+    /// Marks the objects connected to the signals of live objects as live
+    pub fn collect_signal_receivers(classes: &GcClasses, live_objects: &mut LiveObjects) {
+        let Ok(signal_table) = SIGNAL_TABLE.read() else {
+            panic!("Lock poisoned");
+        };
+        loop {
+            let receivers = signal_table.collect_receivers(live_objects);
+            if receivers.is_empty() {
+                break;
+            }
+            for receiver in receivers {
+                Runtime::gc_explore_object(receiver, classes, live_objects);
+            }
+        }
     }
 
     pub fn collect_static_members(live_objects: &mut HashSet<Reference>) {
@@ -1577,6 +1745,91 @@ impl Runtime {
         helper.get_static_member(class, index as usize).expect("todo: throw exception")
     }
 
+    /// Queues a call to every method connected to the signal.
+    /// The emitter is argument 0 and the payload is in the arguments after it.
+    pub fn emit_signal(ctx: &mut BytecodeContext, class: Symbol, signal: Symbol) {
+        let arg_count = {
+            let Ok(symbol_table) = SYMBOL_TABLE.read() else {
+                panic!("Lock poisoned");
+            };
+            let SymbolEntry::ClassRef(class_index) = symbol_table[class] else {
+                panic!("class wasn't a class");
+            };
+            let Ok(class_table) = CLASS_TABLE.read() else {
+                panic!("Lock poisoned");
+            };
+            let class = &class_table[class_index];
+            class.get_signal(signal).expect("Signal not linked").arguments.len()
+        };
+
+        let args = ctx.get_args_mut();
+        let StackValue::Reference(emitter) = args[0] else {
+            panic!("Emitter wasn't an object");
+        };
+        let payload = args[1..=arg_count].to_vec();
+        for arg in args.iter_mut() {
+            if arg.is_blank() {
+                break
+            }
+            *arg = StackValue::Blank;
+        }
+
+        let Ok(signal_table) = SIGNAL_TABLE.read() else {
+            panic!("Lock poisoned");
+        };
+        for Connection { receiver, class, method, .. } in signal_table.get_connections(emitter, signal) {
+            MessageQueues::send(Message::new(receiver, class, method, payload.clone()));
+        }
+    }
+
+    /// Queues a call to every static method linked as a listener of the static signal.
+    /// The payload starts at argument 0.
+    pub fn emit_static_signal(ctx: &mut BytecodeContext, class: Symbol, signal: Symbol) {
+        let arg_count = {
+            let Ok(symbol_table) = SYMBOL_TABLE.read() else {
+                panic!("Lock poisoned");
+            };
+            let SymbolEntry::ClassRef(class_index) = symbol_table[class] else {
+                panic!("class wasn't a class");
+            };
+            let Ok(class_table) = CLASS_TABLE.read() else {
+                panic!("Lock poisoned");
+            };
+            let class = &class_table[class_index];
+            class.get_signal(signal).expect("Signal not linked").arguments.len()
+        };
+
+        let args = ctx.get_args_mut();
+        let payload = args[..arg_count].to_vec();
+        for arg in args.iter_mut() {
+            if arg.is_blank() {
+                break
+            }
+            *arg = StackValue::Blank;
+        }
+
+        let Ok(signal_table) = SIGNAL_TABLE.read() else {
+            panic!("Lock poisoned");
+        };
+        for (class, method) in signal_table.get_listeners(signal) {
+            MessageQueues::send(Message::new_static(*class, *method, payload.clone()));
+        }
+    }
+
+    pub fn connect_signal(emitter: Reference, receiver: Reference, signal: Symbol, class: Symbol, method: Symbol) {
+        let Ok(mut signal_table) = SIGNAL_TABLE.write() else {
+            panic!("Lock poisoned");
+        };
+        signal_table.connect(emitter, Connection::new(signal, receiver, class, method));
+    }
+
+    pub fn disconnect_signal(emitter: Reference, receiver: Reference, signal: Symbol, class: Symbol, method: Symbol) {
+        let Ok(mut signal_table) = SIGNAL_TABLE.write() else {
+            panic!("Lock poisoned");
+        };
+        signal_table.disconnect(emitter, Connection::new(signal, receiver, class, method));
+    }
+
     pub fn set_static_member<T>(_ctx: &mut BytecodeContext, class: Symbol, index: u64, value: T)
     where
         RuntimeHelper: StaticMemberAccess<T> {
@@ -1586,10 +1839,28 @@ impl Runtime {
 }
 
 
-pub extern "C" fn call_virtual_function(context: &mut BytecodeContext, class_symbol: u64, method_name: u64) {
+/// `inline_cache` points to the cache of the call site, or is null if the call site doesn't have one
+pub extern "C" fn call_virtual_function(context: &mut BytecodeContext, class_symbol: u64, method_name: u64, inline_cache: u64) {
     let class_symbol = class_symbol as Symbol;
     let method_name = method_name as Symbol;
-    context.invoke_virtual_extern(class_symbol, method_name, None);
+    let inline_cache = unsafe { (inline_cache as *const InlineCache).as_ref() };
+    context.invoke_virtual_extern(class_symbol, method_name, inline_cache, None);
+}
+
+pub extern "C" fn emit_signal(context: &mut BytecodeContext, class_symbol: u64, signal_name: u64) {
+    Runtime::emit_signal(context, class_symbol as Symbol, signal_name as Symbol);
+}
+
+pub extern "C" fn emit_static_signal(context: &mut BytecodeContext, class_symbol: u64, signal_name: u64) {
+    Runtime::emit_static_signal(context, class_symbol as Symbol, signal_name as Symbol);
+}
+
+pub extern "C" fn connect_signal(emitter: u64, receiver: u64, signal_name: u64, class_symbol: u64, method_name: u64) {
+    Runtime::connect_signal(emitter as Reference, receiver as Reference, signal_name as Symbol, class_symbol as Symbol, method_name as Symbol);
+}
+
+pub extern "C" fn disconnect_signal(emitter: u64, receiver: u64, signal_name: u64, class_symbol: u64, method_name: u64) {
+    Runtime::disconnect_signal(emitter as Reference, receiver as Reference, signal_name as Symbol, class_symbol as Symbol, method_name as Symbol);
 }
 
 pub extern "C" fn new_object(class_symbol: u64) -> u64 {
@@ -1604,6 +1875,28 @@ pub extern "C" fn call_static_function(context: &mut BytecodeContext, class_symb
     context.invoke_static_extern(class_symbol, method_name, None);
 }
 
+pub extern "C" fn call_interface_function(context: &mut BytecodeContext, interface_symbol: u64, method_name: u64, inline_cache: u64) {
+    let interface_symbol = interface_symbol as Symbol;
+    let method_name = method_name as Symbol;
+    let inline_cache = unsafe { (inline_cache as *const InlineCache).as_ref() };
+    context.invoke_interface_extern(interface_symbol, method_name, inline_cache, None);
+}
+
+pub extern "C" fn object_class(object: u64) -> u64 {
+    let object = unsafe { (object as Reference).as_ref().expect("report null pointer") };
+    object.class as u64
+}
+
+pub extern "C" fn is_a(object: u64, class_symbol: u64) -> u8 {
+    // Null isn't an instance of any class
+    if object == 0 {
+        return 0;
+    }
+    BytecodeContext::class_hierarchy(object as Reference)
+        .into_iter()
+        .any(|class| class as u64 == class_symbol) as u8
+}
+
 pub extern "C" fn get_static_member8(context: &mut BytecodeContext, class_symbol: u64, member_index: u64) -> u8 {
     Runtime::get_static_member::<u8>(context, class_symbol as Symbol, member_index)
 }
@@ -0,0 +1,457 @@
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use std::process::Command;
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+use cranelift::prelude::*;
+use cranelift_module::{DataDescription, Linkage, Module};
+use cranelift_object::{ObjectBuilder, ObjectModule};
+use fxhash::FxHasher;
+use libloading::Library;
+use log::warn;
+use rowan_shared::bytecode::linked::Bytecode;
+use super::{Runtime, CLASS_TABLE, STRING_TABLE, SYMBOL_TABLE, VTABLES};
+use super::inline_cache::NO_INLINE_CACHES;
+use super::jit::{register_debug_info, runtime_symbols, CompiledFunction, JITCompiler};
+use super::tables::string_table::StringTable;
+use super::tables::symbol_table::{SymbolEntry, SymbolTable};
+use super::tables::vtable::{Function, FunctionValue, TIER_BASELINE};
+use crate::context::MethodName;
+
+/// Marks the start of the metadata so that a library from something else isn't mistaken for one of ours
+const MAGIC: u64 = u64::from_le_bytes(*b"rowanaot");
+
+const STATIC_METHOD: u64 = 0;
+const VIRTUAL_METHOD: u64 = 1;
+const INTERFACE_METHOD: u64 = 2;
+
+/// Compiles every bytecode method of the linked classes into a shared object at `path`.
+/// The code calls into the runtime through slots that `load` fills in, since the library can't link against the runtime.
+/// Symbols are baked into the code, so the library only works for a program that links to the same symbol table.
+pub fn compile(path: &Path) -> Result<(), String> {
+    let mut flag_builder = settings::builder();
+    flag_builder.set("use_colocated_libcalls", "false").unwrap();
+    flag_builder.set("is_pic", "true").unwrap();
+    let isa_builder = cranelift_native::builder().unwrap_or_else(|msg| {
+        panic!("host machine is not supported: {}", msg);
+    });
+    let isa = isa_builder
+        .finish(settings::Flags::new(flag_builder))
+        .map_err(|e| e.to_string())?;
+    let builder = ObjectBuilder::new(isa, "rowan_aot", cranelift_module::default_libcall_names())
+        .map_err(|e| e.to_string())?;
+    let mut module = ObjectModule::new(builder);
+
+    let methods = methods();
+    let mut metadata = vec![MAGIC, fingerprint(), methods.len() as u64];
+    for (index, (method_name, function)) in methods.into_iter().enumerate() {
+        let mut compiler = JITCompiler::new(module.make_context());
+        // Inline caches only exist at runtime, so every call goes through the runtime
+        let translated = compiler.translate(
+            method_name,
+            &function.arguments,
+            &function.return_type,
+            &function.bytecode,
+            &NO_INLINE_CACHES,
+            false,
+            None,
+            &mut module,
+        );
+        let compiled = translated.and_then(|_| {
+            let id = module.declare_function(&format!("rowan_method_{index}"), Linkage::Export, compiler.signature())
+                .map_err(|e| e.to_string())?;
+            compiler.define(id, &mut module)
+        });
+        write_method_name(&mut metadata, method_name);
+        // The method is left to the JIT, which only fails on it if it gets called
+        let compiled = match compiled {
+            Ok(compiled) => compiled,
+            Err(e) => {
+                warn!("unable to compile {} ahead of time: {e}", Runtime::get_string(method_name.method_symbol()));
+                metadata.push(u64::MAX);
+                continue;
+            }
+        };
+        metadata.push(bytecode_hash(&function.bytecode));
+        write_compiled_function(&mut metadata, compiled);
+    }
+
+    let imports = module.declarations()
+        .get_functions()
+        .filter(|(_, declaration)| declaration.linkage == Linkage::Import)
+        .map(|(id, declaration)| (declaration.linkage_name(id).into_owned(), declaration.signature.clone()))
+        .collect::<Vec<_>>();
+    for (name, signature) in imports {
+        define_import(&mut module, &name, &signature)?;
+    }
+
+    let mut description = DataDescription::new();
+    description.define(metadata.into_iter().flat_map(u64::to_le_bytes).collect());
+    description.set_align(8);
+    let id = module.declare_data("rowan_aot_metadata", Linkage::Export, false, false)
+        .map_err(|e| e.to_string())?;
+    module.define_data(id, &description).map_err(|e| e.to_string())?;
+
+    let object = module.finish().emit().map_err(|e| e.to_string())?;
+    let object_path = path.with_extension("o");
+    std::fs::write(&object_path, object).map_err(|e| e.to_string())?;
+    let compiler = std::env::var("CC").unwrap_or_else(|_| String::from("cc"));
+    let status = Command::new(&compiler)
+        .arg("-shared")
+        .arg("-o")
+        .arg(path)
+        .arg(&object_path)
+        .status()
+        .map_err(|e| format!("unable to run {compiler}: {e}"))?;
+    std::fs::remove_file(&object_path).map_err(|e| e.to_string())?;
+    if !status.success() {
+        return Err(format!("{compiler} failed to link {}", path.display()));
+    }
+
+    Ok(())
+}
+
+/// Loads a library written by `compile` and uses its code for the methods it has.
+/// Methods whose bytecode has changed since are left to the interpreter and the JIT.
+pub fn load(path: &Path) -> Result<(), String> {
+    let library = unsafe { Library::new(path) }.map_err(|e| e.to_string())?;
+    // The code is used until the program exits
+    let library: &'static Library = Box::leak(Box::new(library));
+
+    let metadata = unsafe { library.get::<*const u64>(b"rowan_aot_metadata") }
+        .map_err(|e| e.to_string())?;
+    let metadata = *metadata;
+    let mut reader = Reader { words: metadata };
+    if reader.next() != MAGIC {
+        return Err(format!("{} wasn't compiled by rowan", path.display()));
+    }
+    if reader.next() != fingerprint() {
+        return Err(format!("{} was compiled for a different program", path.display()));
+    }
+
+    for (name, pointer) in runtime_symbols() {
+        let slot = unsafe { library.get::<*mut *const u8>(format!("rowan_import_{name}").as_bytes()) };
+        if let Ok(slot) = slot {
+            unsafe { **slot = pointer };
+        }
+    }
+
+    let mut start = usize::MAX;
+    let mut end = 0;
+    let method_count = reader.next();
+    for index in 0..method_count {
+        let method_name = reader.method_name();
+        let hash = reader.next();
+        if hash == u64::MAX {
+            continue;
+        }
+        let compiled = reader.compiled_function();
+
+        let function = match method_name {
+            MethodName::StaticMethod { class_symbol, method_name } => {
+                Runtime::get_static_method(class_symbol, method_name)
+            }
+            MethodName::VirtualMethod { object_class_symbol, class_symbol, method_name } => {
+                Runtime::get_virtual_method(object_class_symbol, class_symbol, method_name)
+            }
+            MethodName::InterfaceMethod { class_symbol, interface_symbol, method_name } => {
+                Runtime::get_interface_method(class_symbol, interface_symbol, method_name)
+            }
+        };
+        if hash != bytecode_hash(&function.bytecode) {
+            continue;
+        }
+        let code = unsafe { library.get::<*const ()>(format!("rowan_method_{index}").as_bytes()) }
+            .map_err(|e| e.to_string())?;
+        let code = *code;
+
+        let mut value = function.value.lock().unwrap();
+        if !matches!(*value, FunctionValue::Bytecode(_)) {
+            continue;
+        }
+        let size = compiled.size;
+        let (object_locations, bytecode_ranges) = compiled.place(code);
+        register_debug_info(method_name, function, code, size, &bytecode_ranges);
+        *value = FunctionValue::Compiled(code, object_locations.into_iter().collect(), bytecode_ranges.into_boxed_slice());
+        function.tier.store(TIER_BASELINE, Ordering::Relaxed);
+
+        start = start.min(code as usize);
+        end = end.max(code as usize + size);
+    }
+
+    if start < end {
+        rowan_unwind::register_library(start as *const (), end - start);
+    }
+
+    Ok(())
+}
+
+/// Every linked method that runs bytecode along with the name it is looked up by.
+/// Inherited methods are shared between the vtables of a class and its parents, so they only show up once.
+fn methods() -> Vec<(MethodName, &'static Function)> {
+    let Ok(symbol_table) = SYMBOL_TABLE.read() else {
+        panic!("Lock poisoned");
+    };
+    let Ok(class_table) = CLASS_TABLE.read() else {
+        panic!("Lock poisoned");
+    };
+    let Ok(vtables_table) = VTABLES.read() else {
+        panic!("Lock poisoned");
+    };
+
+    let mut seen = HashSet::new();
+    let mut methods = Vec::new();
+    let mut add = |method_name: MethodName, function: &Arc<Function>| {
+        let is_bytecode = matches!(*function.value.lock().unwrap(), FunctionValue::Bytecode(_));
+        if is_bytecode && !function.bytecode.is_empty() && seen.insert(Arc::as_ptr(function)) {
+            methods.push((method_name, unsafe { &*Arc::as_ptr(function) }));
+        }
+    };
+
+    for (class_symbol, entry) in symbol_table.iter().enumerate() {
+        let SymbolEntry::ClassRef(class_index) = entry else {
+            continue;
+        };
+        let class = &class_table[*class_index];
+        for function in vtables_table[class.static_methods].table.iter() {
+            add(MethodName::StaticMethod {
+                class_symbol,
+                method_name: function.name,
+            }, function);
+        }
+
+        let mut vtables = class.vtables.iter().collect::<Vec<_>>();
+        vtables.sort_by_key(|(symbol, _)| **symbol);
+        for (symbol, vtable_index) in vtables {
+            for function in vtables_table[*vtable_index].table.iter() {
+                let method_name = match symbol_table[*symbol] {
+                    SymbolEntry::InterfaceRef(_) => MethodName::InterfaceMethod {
+                        class_symbol,
+                        interface_symbol: *symbol,
+                        method_name: function.name,
+                    },
+                    _ => MethodName::VirtualMethod {
+                        object_class_symbol: class_symbol,
+                        class_symbol: *symbol,
+                        method_name: function.name,
+                    },
+                };
+                add(method_name, function);
+            }
+        }
+    }
+
+    methods
+}
+
+/// Hashes the symbol table, which compiled code has the indices of baked in
+fn fingerprint() -> u64 {
+    let Ok(symbol_table) = SYMBOL_TABLE.read() else {
+        panic!("Lock poisoned");
+    };
+    let Ok(string_table) = STRING_TABLE.read() else {
+        panic!("Lock poisoned");
+    };
+    hash_symbols(&symbol_table, &string_table)
+}
+
+fn hash_symbols(symbol_table: &SymbolTable, string_table: &StringTable) -> u64 {
+    let mut hasher = FxHasher::default();
+    env!("CARGO_PKG_VERSION").hash(&mut hasher);
+    for entry in symbol_table.iter() {
+        match entry {
+            SymbolEntry::StringRef(index) => (0, string_table.get_string(*index)).hash(&mut hasher),
+            SymbolEntry::ClassRef(index) => (1, *index).hash(&mut hasher),
+            SymbolEntry::InterfaceRef(index) => (2, *index).hash(&mut hasher),
+        }
+    }
+    hasher.finish()
+}
+
+/// Bytecode has floats in it, so it is hashed by how it prints
+fn bytecode_hash(bytecode: &[Bytecode]) -> u64 {
+    let mut hasher = FxHasher::default();
+    format!("{bytecode:?}").hash(&mut hasher);
+    // A hash of `u64::MAX` marks a method that failed to compile
+    hasher.finish().min(u64::MAX - 1)
+}
+
+/// Defines a runtime function that the code calls as a stub that jumps through the slot `rowan_import_<name>`
+fn define_import(module: &mut ObjectModule, name: &str, signature: &Signature) -> Result<(), String> {
+    let slot = module.declare_data(&format!("rowan_import_{name}"), Linkage::Export, true, false)
+        .map_err(|e| e.to_string())?;
+    let mut description = DataDescription::new();
+    description.define_zeroinit(8);
+    description.set_align(8);
+    module.define_data(slot, &description).map_err(|e| e.to_string())?;
+
+    let id = module.declare_function(name, Linkage::Local, signature)
+        .map_err(|e| e.to_string())?;
+    let mut context = module.make_context();
+    context.func.signature = signature.clone();
+    let mut builder_context = FunctionBuilderContext::new();
+    let mut builder = FunctionBuilder::new(&mut context.func, &mut builder_context);
+    let block = builder.create_block();
+    builder.append_block_params_for_function_params(block);
+    builder.switch_to_block(block);
+    builder.seal_block(block);
+
+    let slot = module.declare_data_in_func(slot, builder.func);
+    let slot = builder.ins().symbol_value(types::I64, slot);
+    let target = builder.ins().load(types::I64, MemFlags::trusted(), slot, 0);
+    let signature = builder.import_signature(signature.clone());
+    let arguments = builder.block_params(block).to_vec();
+    let call = builder.ins().call_indirect(signature, target, &arguments);
+    let results = builder.inst_results(call).to_vec();
+    builder.ins().return_(&results);
+    builder.finalize();
+
+    module.define_function(id, &mut context).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn write_method_name(metadata: &mut Vec<u64>, method_name: MethodName) {
+    let words = match method_name {
+        MethodName::StaticMethod { class_symbol, method_name } => {
+            [STATIC_METHOD, class_symbol as u64, method_name as u64, 0]
+        }
+        MethodName::VirtualMethod { object_class_symbol, class_symbol, method_name } => {
+            [VIRTUAL_METHOD, object_class_symbol as u64, class_symbol as u64, method_name as u64]
+        }
+        MethodName::InterfaceMethod { class_symbol, interface_symbol, method_name } => {
+            [INTERFACE_METHOD, class_symbol as u64, interface_symbol as u64, method_name as u64]
+        }
+    };
+    metadata.extend(words);
+}
+
+fn write_compiled_function(metadata: &mut Vec<u64>, compiled: CompiledFunction) {
+    metadata.push(compiled.size as u64);
+    metadata.push(compiled.object_locations.len() as u64);
+    for (offset, objects) in compiled.object_locations {
+        metadata.push(offset as u64);
+        metadata.push(objects.len() as u64);
+        metadata.extend(objects.into_iter().map(u64::from));
+    }
+    metadata.push(compiled.bytecode_ranges.len() as u64);
+    for (start, end, index) in compiled.bytecode_ranges {
+        metadata.extend([start as u64, end as u64, index as u64]);
+    }
+}
+
+/// Reads back what `write_method_name` and `write_compiled_function` wrote
+struct Reader {
+    words: *const u64,
+}
+
+impl Reader {
+    fn next(&mut self) -> u64 {
+        let word = unsafe { self.words.read_unaligned() };
+        self.words = unsafe { self.words.add(1) };
+        word
+    }
+
+    fn method_name(&mut self) -> MethodName {
+        let kind = self.next();
+        let [first, second, third] = [self.next(), self.next(), self.next()].map(|word| word as usize);
+        match kind {
+            STATIC_METHOD => MethodName::StaticMethod { class_symbol: first, method_name: second },
+            VIRTUAL_METHOD => MethodName::VirtualMethod {
+                object_class_symbol: first,
+                class_symbol: second,
+                method_name: third,
+            },
+            INTERFACE_METHOD => MethodName::InterfaceMethod {
+                class_symbol: first,
+                interface_symbol: second,
+                method_name: third,
+            },
+            _ => panic!("unknown method kind {kind}"),
+        }
+    }
+
+    fn compiled_function(&mut self) -> CompiledFunction {
+        let size = self.next() as usize;
+        let object_locations = (0..self.next())
+            .map(|_| {
+                let offset = self.next() as usize;
+                let objects = (0..self.next()).map(|_| self.next() as u32).collect();
+                (offset, objects)
+            })
+            .collect();
+        let bytecode_ranges = (0..self.next())
+            .map(|_| (self.next() as usize, self.next() as usize, self.next() as usize))
+            .collect();
+        CompiledFunction { size, object_locations, bytecode_ranges }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tables(strings: &[&str]) -> (SymbolTable, StringTable) {
+        let mut symbol_table = SymbolTable::new();
+        let mut string_table = StringTable::new();
+        for string in strings {
+            symbol_table.add_string(string_table.add_string(string));
+        }
+        (symbol_table, string_table)
+    }
+
+    #[test]
+    fn test_fingerprint_changes_with_the_symbol_table() {
+        let (symbol_table, string_table) = tables(&["main::Main", "main"]);
+        let fingerprint = hash_symbols(&symbol_table, &string_table);
+
+        let (same_symbol_table, same_string_table) = tables(&["main::Main", "main"]);
+        assert_eq!(hash_symbols(&same_symbol_table, &same_string_table), fingerprint);
+
+        let (renamed_symbol_table, renamed_string_table) = tables(&["main::Main", "start"]);
+        assert_ne!(hash_symbols(&renamed_symbol_table, &renamed_string_table), fingerprint);
+
+        let (reordered_symbol_table, reordered_string_table) = tables(&["main", "main::Main"]);
+        assert_ne!(hash_symbols(&reordered_symbol_table, &reordered_string_table), fingerprint);
+
+        let (mut longer_symbol_table, longer_string_table) = tables(&["main::Main", "main"]);
+        longer_symbol_table.add_class(0);
+        assert_ne!(hash_symbols(&longer_symbol_table, &longer_string_table), fingerprint);
+    }
+
+    #[test]
+    fn test_bytecode_hash_depends_on_the_bytecode() {
+        let bytecode = [Bytecode::LoadU64(1), Bytecode::ReturnVoid];
+        assert_eq!(bytecode_hash(&bytecode), bytecode_hash(&bytecode));
+        assert_ne!(bytecode_hash(&bytecode), bytecode_hash(&[Bytecode::LoadU64(2), Bytecode::ReturnVoid]));
+        assert_ne!(bytecode_hash(&bytecode), u64::MAX);
+    }
+
+    #[test]
+    fn test_metadata_reads_back() {
+        let method_names = [
+            MethodName::StaticMethod { class_symbol: 1, method_name: 2 },
+            MethodName::VirtualMethod { object_class_symbol: 3, class_symbol: 4, method_name: 5 },
+            MethodName::InterfaceMethod { class_symbol: 6, interface_symbol: 7, method_name: 8 },
+        ];
+        let mut metadata = Vec::new();
+        for method_name in method_names {
+            write_method_name(&mut metadata, method_name);
+        }
+        write_compiled_function(&mut metadata, CompiledFunction {
+            size: 64,
+            object_locations: vec![(16, vec![0, 8]), (32, Vec::new())],
+            bytecode_ranges: vec![(0, 16, 0), (16, 64, 3)],
+        });
+
+        let mut reader = Reader { words: metadata.as_ptr() };
+        for method_name in method_names {
+            assert_eq!(reader.method_name(), method_name);
+        }
+        let compiled = reader.compiled_function();
+        assert_eq!(compiled.size, 64);
+        assert_eq!(compiled.object_locations, vec![(16, vec![0, 8]), (32, Vec::new())]);
+        assert_eq!(compiled.bytecode_ranges, vec![(0, 16, 0), (16, 64, 3)]);
+    }
+}
@@ -1,5 +1,6 @@
 use std::collections::{HashMap, HashSet};
 use rowan_shared::bytecode::linked::Bytecode;
+use rowan_shared::classfile::ClassKind;
 use crate::context::WrappedReference;
 use crate::runtime::object::Object;
 use super::{Reference, Symbol, VTableIndex};
@@ -110,6 +111,8 @@ pub struct Class {
     pub init_function: Option<Box<[Bytecode]>>,
     pub drop_function: Option<extern "C" fn(&mut Object)>,
     pub interfaces: Vec<VTableIndex>,
+    pub signals: Vec<SignalInfo>,
+    pub kind: ClassKind,
 }
 
 impl Class {
@@ -134,6 +137,8 @@ impl Class {
             init_function,
             drop_function,
             interfaces: Vec::new(),
+            signals: Vec::new(),
+            kind: ClassKind::Class,
         }
     }
     
@@ -187,6 +192,10 @@ impl Class {
         }
     }
     
+    pub fn get_signal(&self, name: Symbol) -> Option<&SignalInfo> {
+        self.signals.iter().find(|signal| signal.name == name)
+    }
+
     pub fn add_interface(&mut self, symbol: Symbol, interface_vtable: VTableIndex) {
         self.interfaces.push(interface_vtable);
         self.vtables.insert(symbol, interface_vtable);
@@ -196,6 +205,23 @@ impl Class {
 unsafe impl Send for Class {}
 unsafe impl Sync for Class {}
 
+#[derive(Debug)]
+pub struct SignalInfo {
+    pub name: Symbol,
+    pub is_static: bool,
+    pub arguments: Vec<TypeTag>,
+}
+
+impl SignalInfo {
+    pub fn new(name: Symbol, is_static: bool, arguments: Vec<TypeTag>) -> Self {
+        SignalInfo {
+            name,
+            is_static,
+            arguments,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct MemberInfo {
     pub name: Symbol,
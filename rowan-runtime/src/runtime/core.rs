@@ -4,9 +4,9 @@ mod boxes;
 pub use strings::*;
 pub use boxes::*;
 
-use std::ptr::slice_from_raw_parts;
 use paste::paste;
 use super::{object::Object, Runtime, Reference, Symbol};
+use super::heartbeat::Heartbeat;
 use rowan_shared::TypeTag;
 use crate::context::BytecodeContext;
 
@@ -97,16 +97,214 @@ pub fn generate_object_class() -> VMClass {
                 object_downcast as *const (),
                 vec![TypeTag::Object, TypeTag::Object, TypeTag::U64]
                 ),
+            VMMethod::new(
+                "core::Object::try-downcast",
+                object_try_downcast as *const (),
+                vec![TypeTag::Object, TypeTag::Object, TypeTag::U64]
+                ),
+            VMMethod::new(
+                "core::Object::tick",
+                object_tick as *const (),
+                vec![TypeTag::Void, TypeTag::Object, TypeTag::F64]
+                ),
+            VMMethod::new(
+                "core::Object::ready",
+                object_ready as *const (),
+                vec![TypeTag::Void, TypeTag::Object]
+                ),
+            VMMethod::new(
+                "core::Object::add-child",
+                object_add_child as *const (),
+                vec![TypeTag::Void, TypeTag::Object, TypeTag::Object]
+                ),
+            VMMethod::new(
+                "core::Object::remove-child",
+                object_remove_child as *const (),
+                vec![TypeTag::Void, TypeTag::Object, TypeTag::Object]
+                ),
         ]
     );
 
-    VMClass::new("core::Object", "", vec![vtable], Vec::new(), Vec::new(), Vec::new())
+    let elements = vec![
+        VMMember::new("core::Object::attached-to", TypeTag::Object),
+        VMMember::new("core::Object::child-count", TypeTag::U64),
+        VMMember::new("core::Object::child-capacity", TypeTag::U64),
+        VMMember::new("core::Object::children", TypeTag::U64),
+    ];
+
+    let static_methods = vec![
+        VMMethod::new(
+            "core::Object::root",
+            object_root as *const (),
+            vec![TypeTag::Object]
+            ),
+    ];
+
+    VMClass::new("core::Object", "", vec![vtable], elements, static_methods, Vec::new())
+}
+
+/// The `core::Object` part of every object.
+/// It holds the position of the object in the object graph that the heartbeat walks.
+#[repr(C)]
+pub struct BaseObject {
+    pub class: Symbol,
+    pub parent_object: Reference,
+    pub custom_drop: Option<extern "C" fn(&mut Object)>,
+    /// The object this object is a child of
+    pub attached_to: Reference,
+    pub child_count: u64,
+    pub child_capacity: u64,
+    pub children: *mut Reference,
+}
+
+impl BaseObject {
+    /// Walks the parent objects of an object until it finds the `core::Object` part.
+    /// Returns null if the object is null.
+    pub fn from_reference(object: Reference) -> *mut BaseObject {
+        let object_class = Runtime::get_class_symbol("core::Object");
+        let mut current = object;
+        while let Some(object) = unsafe { current.as_ref() } {
+            if object.class == object_class {
+                return current as *mut BaseObject;
+            }
+            current = object.parent_object;
+        }
+        std::ptr::null_mut()
+    }
+
+    pub fn children(&self) -> &[Reference] {
+        if self.children.is_null() {
+            return &[];
+        }
+        unsafe { std::slice::from_raw_parts(self.children, self.child_count as usize) }
+    }
+
+    fn push_child(&mut self, child: Reference) {
+        use std::alloc::*;
+        if self.child_count == self.child_capacity {
+            let new_capacity = if self.child_capacity == 0 {
+                4
+            } else {
+                self.child_capacity as usize * 2
+            };
+            let layout = Layout::array::<Reference>(new_capacity).expect("children layout is wrong or too big");
+            let pointer = unsafe { alloc(layout) } as *mut Reference;
+            if pointer.is_null() {
+                eprintln!("Out of memory");
+                handle_alloc_error(layout);
+            }
+            if !self.children.is_null() {
+                unsafe {
+                    std::ptr::copy_nonoverlapping(self.children, pointer, self.child_count as usize);
+                }
+                let old_layout = Layout::array::<Reference>(self.child_capacity as usize).expect("children layout is wrong or too big");
+                unsafe {
+                    dealloc(self.children as *mut u8, old_layout);
+                }
+            }
+            self.children = pointer;
+            self.child_capacity = new_capacity as u64;
+            self.custom_drop = Some(base_object_drop);
+        }
+        unsafe {
+            self.children.add(self.child_count as usize).write(child);
+        }
+        self.child_count += 1;
+    }
+
+    /// Removes a child while keeping the order of the other children.
+    /// Returns false if the object wasn't a child.
+    fn remove_child(&mut self, child: Reference) -> bool {
+        let Some(index) = self.children().iter().position(|c| *c == child) else {
+            return false;
+        };
+        unsafe {
+            std::ptr::copy(
+                self.children.add(index + 1),
+                self.children.add(index),
+                self.child_count as usize - index - 1
+            );
+        }
+        self.child_count -= 1;
+        true
+    }
+}
+
+extern "C" fn base_object_drop(object: &mut Object) {
+    use std::alloc::*;
+    let object = unsafe { (object as *mut Object as *mut BaseObject).as_mut().unwrap() };
+    if object.children.is_null() {
+        return;
+    }
+    let layout = Layout::array::<Reference>(object.child_capacity as usize).expect("children layout is wrong or too big");
+    unsafe {
+        dealloc(object.children as *mut u8, layout);
+    }
+    object.children = std::ptr::null_mut();
+}
+
+/// Returns the object at the top of the object graph so that `main` can attach objects to it
+extern "C" fn object_root(_: &BytecodeContext) -> Reference {
+    Heartbeat::root()
+}
+
+extern "C" fn object_tick(_: &mut BytecodeContext, _: Reference, _: f64) {}
+
+extern "C" fn object_ready(_: &mut BytecodeContext, _: Reference) {}
+
+/// Attaches `child` to `this` so that it receives the heartbeat after `this` does.
+/// Throws if the child is already attached somewhere or is `this` or one of its ancestors, since that would make a cycle.
+extern "C" fn object_add_child(context: &mut BytecodeContext, this: Reference, child: Reference) {
+    let base = BaseObject::from_reference(this);
+    let child_base = BaseObject::from_reference(child);
+    let (Some(base), Some(child_base)) = (unsafe { base.as_mut() }, unsafe { child_base.as_mut() }) else {
+        throw_null_pointer_exception(context);
+        return;
+    };
+
+    if !child_base.attached_to.is_null() {
+        throw_exception(context, "Object is already attached, remove it from its parent first");
+        return;
+    }
+    let mut ancestor = this;
+    while !ancestor.is_null() {
+        if ancestor == child {
+            throw_exception(context, "Object can't be attached to itself or one of its children");
+            return;
+        }
+        let ancestor_base = BaseObject::from_reference(ancestor);
+        ancestor = unsafe { ancestor_base.as_ref().unwrap() }.attached_to;
+    }
+
+    base.push_child(child);
+    child_base.attached_to = this;
+    context.write_barrier(this);
+    context.write_barrier(child);
+
+    Heartbeat::new().ready(context, child);
+}
+
+extern "C" fn object_remove_child(context: &mut BytecodeContext, this: Reference, child: Reference) {
+    let base = BaseObject::from_reference(this);
+    let Some(base) = (unsafe { base.as_mut() }) else {
+        throw_null_pointer_exception(context);
+        return;
+    };
+    if !base.remove_child(child) {
+        return;
+    }
+    let child_base = BaseObject::from_reference(child);
+    let child_base = unsafe { child_base.as_mut().unwrap() };
+    child_base.attached_to = std::ptr::null_mut();
 }
 
 
 extern "C" fn object_downcast(context: &mut BytecodeContext, this: Reference, class_index: u64) -> Reference {
     let object = this;
-    let object = unsafe { object.as_mut().unwrap() };
+    // The walk up the parent objects ends at null when the object isn't of the class
+    let Some(object) = (unsafe { object.as_mut() }) else {
+        return std::ptr::null_mut();
+    };
     if object.class == class_index as Symbol {
         this
     } else {
@@ -117,6 +315,20 @@ extern "C" fn object_downcast(context: &mut BytecodeContext, this: Reference, cl
     }
 }
 
+/// Like `downcast` but wraps the result in a `std::core::Option` instead of returning null
+extern "C" fn object_try_downcast(context: &mut BytecodeContext, this: Reference, class_index: u64) -> Reference {
+    let object = object_downcast(context, this, class_index);
+    if object.is_null() {
+        return Runtime::new_object("std::core::Noneobject");
+    }
+    let some = Runtime::new_object("std::core::Someobject");
+    unsafe { some.as_mut().unwrap() }
+        .set_safe(0, object)
+        .expect("Some should have a value field");
+    context.write_barrier(some);
+    some
+}
+
 pub fn generate_printer_class() -> VMClass {
     let vtable = VMVTable::new(
         "core::Printer",
@@ -158,11 +370,10 @@ extern "C" fn printer_println_float(context: &mut BytecodeContext, _: Reference,
 }
 
 extern "C" fn printer_println(context: &mut BytecodeContext, _: Reference, string: Reference) {
-    let object = string;
-    let object = unsafe { object.as_ref().unwrap() };
-    let length = unsafe { object.get::<u64>(0) };
-    let pointer = unsafe { object.get::<u64>(8) };
-    let pointer = pointer as *mut u8;
+    // Interned strings and string buffers store their bytes differently
+    let mut pointer = std::ptr::null();
+    let mut length = 0;
+    crate::external::rowan_get_string_buffer(string, &mut pointer, &mut length);
     let slice = unsafe { std::slice::from_raw_parts(pointer, length as usize) };
     let string = unsafe { std::str::from_utf8_unchecked(slice) };
     println!("{}", string);
@@ -315,9 +526,9 @@ macro_rules! array_create_get {
                 let length = object.length;
                 let pointer = pointer as *mut $ty;
                 if index >= length {
-                    let exception = Runtime::new_object("IndexOutOfBounds");
+                    let exception = Runtime::new_object("core::IndexOutOfBounds");
                     out_of_bounds_init(context, exception, length, index);
-                    //context.set_exception(exception);
+                    context.set_exception(exception);
                     return 0 as $ty;
                 }
 
@@ -329,8 +540,17 @@ macro_rules! array_create_get {
     };
 }
 
+macro_rules! array_write_barrier {
+    (object, $context:ident, $this:ident, $value:ident) => {
+        if $value != 0 {
+            $context.write_barrier($this);
+        }
+    };
+    ($variant:tt, $context:ident, $this:ident, $value:ident) => {};
+}
+
 macro_rules! array_create_set {
-    ($variant:expr, $fn_name:ident, $ty:ty) => {
+    ($variant:tt, $fn_name:ident, $ty:ty) => {
         paste! {
             pub extern "C" fn $fn_name(context: &mut BytecodeContext, this: Reference, index: u64, value: $ty) {
                 let object = this;
@@ -340,11 +560,12 @@ macro_rules! array_create_set {
                 let length = object.length;
                 let pointer = pointer as *mut $ty;
                 if index >= length {
-                    let exception = Runtime::new_object("IndexOutOfBounds");
+                    let exception = Runtime::new_object("core::IndexOutOfBounds");
                     out_of_bounds_init(context, exception, length, index);
-                    //context.set_exception(exception);
+                    context.set_exception(exception);
                     return;
                 }
+                array_write_barrier!($variant, context, this, value);
                 unsafe { *pointer.add(index as usize) = value }
             }
         }
@@ -380,7 +601,7 @@ macro_rules! array_create_drop {
 }
 
 macro_rules! create_array_class {
-    ($variant:expr, $ty:ty) => {
+    ($variant:tt, $ty:ty) => {
 
         paste!{
         array_create_class!($variant, [< generate_array $variant _class >], [<Array $variant >]);
@@ -440,7 +661,7 @@ extern "C" fn array_len(_: &mut Runtime, this: Reference) -> u64 {
 #[repr(C)]
 struct Exception {
     pub class: Symbol,
-    pub parent_objects: Box<[Reference]>,
+    pub parent_object: Reference,
     pub custom_drop: Option<fn(&mut Object)>,
     pub message: Reference,
     pub stack_length: u64,
@@ -468,6 +689,11 @@ pub fn generate_exception_class() -> VMClass {
                 exception_print_stack_trace as *const (),
                 vec![TypeTag::Void, TypeTag::Object]
             ),
+            VMMethod::new(
+                "core::Exception::new",
+                exception_new as *const (),
+                vec![TypeTag::Object, TypeTag::Object, TypeTag::Object]
+            ),
         ]
     );
 
@@ -481,10 +707,33 @@ pub fn generate_exception_class() -> VMClass {
     VMClass::new("core::Exception", "core::Object", vec![vtable], elements, Vec::new(), Vec::new())
 }
 
-extern "C" fn exception_init(_: &BytecodeContext, this: Reference, message: Reference) {
+/// Finds the `core::Exception` part of an object that inherits from it
+fn as_exception(this: Reference) -> *mut Exception {
+    let exception_class = Runtime::get_class_symbol("core::Exception");
+    let mut object = this;
+    while let Some(current) = unsafe { object.as_ref() } {
+        if current.class == exception_class {
+            return object as *mut Exception;
+        }
+        object = current.parent_object;
+    }
+    panic!("object is not an exception");
+}
+
+/// Gets the message of an exception for reporting it
+pub fn exception_message(this: Reference) -> String {
+    let object = as_exception(this);
+    let object = unsafe { object.as_ref().unwrap() };
+    if object.message.is_null() {
+        return String::new();
+    }
+    string_contents(object.message)
+}
+
+extern "C" fn exception_init(context: &mut BytecodeContext, this: Reference, message: Reference) {
     use std::alloc::*;
-    let object = this;
-    let object = object as *mut Exception;
+    context.write_barrier(this);
+    let object = as_exception(this);
     let object = unsafe { object.as_mut().unwrap() };
     object.message = message;
     object.stack_length = 0;
@@ -501,69 +750,94 @@ extern "C" fn exception_init(_: &BytecodeContext, this: Reference, message: Refe
     object.stack_pointer = pointer as *mut Reference;
 }
 
-pub extern "C" fn exception_fill_in_stack_trace(_context: &mut Runtime, this: Reference) {
-    let object = this;
-    let object = object as *mut Exception;
+/// The constructor that `new Exception(message)` calls
+extern "C" fn exception_new(context: &mut BytecodeContext, this: Reference, message: Reference) -> Reference {
+    exception_init(context, this, message);
+    this
+}
+
+/// Records the active frames as the exception's stack trace, replacing any previous trace
+pub extern "C" fn exception_fill_in_stack_trace(context: &mut BytecodeContext, this: Reference) {
+    let trace = context.stack_trace();
+    context.write_barrier(this);
+    let object = as_exception(this);
+    unsafe { object.as_mut().unwrap() }.stack_length = 0;
+
+    for (method_name, location) in trace {
+        let backtrace = Runtime::new_object("core::Backtrace");
+        let function_name = interned_string_init(method_name.method_symbol() as u64) as Reference;
+        let (file_name, line, column) = match location {
+            Some(location) => (interned_string_init(location.file as u64) as Reference, location.line, location.column),
+            None => (std::ptr::null_mut(), 0, 0),
+        };
+        backtrace_init(context, backtrace, function_name, file_name, line, column);
+        exception_push_backtrace(object, backtrace);
+    }
+}
+
+/// Records the stack trace of an exception that is being thrown for the first time
+pub fn exception_capture_stack_trace(context: &mut BytecodeContext, this: Reference) {
+    let object = as_exception(this);
+    if unsafe { object.as_ref().unwrap() }.stack_length == 0 {
+        exception_fill_in_stack_trace(context, this);
+    }
+}
+
+fn exception_push_backtrace(object: *mut Exception, backtrace: Reference) {
+    use std::alloc::*;
     let object = unsafe { object.as_mut().unwrap() };
     let length = object.stack_length;
-    let mut capacity = object.stack_capacity;
+    let capacity = object.stack_capacity;
     let pointer = object.stack_pointer;
 
-    let (pointer, capacity) = if length == capacity {
-        use std::alloc::*;
-        let layout = Layout::array::<u64>(capacity as usize * 2).expect("stack-trace layout is wrong or too big");
+    if length == capacity {
+        // The stack trace buffer is missing if a subclass never called the exception's init
+        let new_capacity = if capacity == 0 { 4 } else { capacity * 2 };
+        let layout = Layout::array::<u64>(new_capacity as usize).expect("stack-trace layout is wrong or too big");
         let new_pointer = unsafe { alloc(layout) };
         if new_pointer.is_null() {
             eprintln!("Out of memory");
             handle_alloc_error(layout);
         }
-        unsafe {
-            std::ptr::copy_nonoverlapping(pointer, new_pointer as *mut Reference, capacity as usize);
+        if capacity != 0 {
+            unsafe {
+                std::ptr::copy_nonoverlapping(pointer, new_pointer as *mut Reference, capacity as usize);
+            }
+            let layout = Layout::array::<u64>(capacity as usize).expect("stack-trace layout is wrong or too big");
+            unsafe { dealloc(pointer as *mut u8, layout) };
         }
-        let layout = Layout::array::<u64>(capacity as usize).expect("stack-trace layout is wrong or too big");
-        unsafe { dealloc(pointer as *mut u8, layout) };
-        capacity = capacity * 2;
-        (new_pointer as *mut Reference, capacity)
-    } else {
-        (pointer as *mut Reference, capacity)
-    };
-    object.stack_capacity = capacity;
-    object.stack_pointer = pointer;
-
-    let backtrace = Runtime::new_object("Backtrace");
-
-    /*let method_name = context.get_current_method();
-
-    backtrace_init(context, backtrace, method_name,0, 0);*/
+        object.stack_capacity = new_capacity;
+        object.stack_pointer = new_pointer as *mut Reference;
+    }
 
     unsafe {
-        pointer.add(length as usize).write(backtrace);
+        object.stack_pointer.add(length as usize).write(backtrace);
     }
     object.stack_length = length + 1;
 }
 
-pub extern "C" fn exception_print_stack_trace(context: &mut Runtime, this: Reference) {
-    let object = this;
-    let object = object as *mut Exception;
-    let object = unsafe { object.as_mut().unwrap() };
-    let length = object.stack_length;
-    let pointer = object.stack_pointer;
-
-    unsafe {
-        let slice = slice_from_raw_parts(pointer, length as usize).as_ref().unwrap();
+/// Gets the backtraces of an exception, innermost frame first
+pub fn exception_backtraces(this: Reference) -> &'static [Reference] {
+    let object = unsafe { as_exception(this).as_ref().unwrap() };
+    if object.stack_length == 0 {
+        return &[];
+    }
+    unsafe { std::slice::from_raw_parts(object.stack_pointer, object.stack_length as usize) }
+}
 
-        for backtrace in slice {
-            backtrace_display(context, *backtrace);
-        }
+pub extern "C" fn exception_print_stack_trace(context: &mut BytecodeContext, this: Reference) {
+    for backtrace in exception_backtraces(this) {
+        backtrace_display(context, *backtrace);
     }
 }
 
 #[repr(C)]
 struct Backtrace {
     pub class: Symbol,
-    pub parent_objects: Box<[Reference]>,
+    pub parent_object: Reference,
     pub custom_drop: Option<fn(&mut Object)>,
     pub function_name: Reference,
+    pub file_name: Reference,
     pub line_number: u64,
     pub column_number: u64,
 }
@@ -576,7 +850,7 @@ pub fn generate_backtrace_class() -> VMClass {
             VMMethod::new(
                 "core::Backtrace::init",
                 backtrace_init as *const (),
-                vec![TypeTag::Void, TypeTag::Object, TypeTag::Object, TypeTag::U64, TypeTag::U64]
+                vec![TypeTag::Void, TypeTag::Object, TypeTag::Object, TypeTag::Object, TypeTag::U64, TypeTag::U64]
             ),
             VMMethod::new(
                 "core::Backtrace::display",
@@ -588,39 +862,45 @@ pub fn generate_backtrace_class() -> VMClass {
 
     let elements = vec![
         VMMember::new("function-name", TypeTag::Object),
-        VMMember::new("line-number", TypeTag::Object),
-        VMMember::new("column-number", TypeTag::Object),
+        VMMember::new("file-name", TypeTag::Object),
+        VMMember::new("line-number", TypeTag::U64),
+        VMMember::new("column-number", TypeTag::U64),
     ];
 
     VMClass::new("core::Backtrace", "core::Object", vec![vtable], elements, Vec::new(), Vec::new())
 }
 
-extern "C" fn backtrace_init(_context: &mut Runtime, this: Reference, function_name: Reference, line: u64, column: u64) {
+extern "C" fn backtrace_init(_context: &mut BytecodeContext, this: Reference, function_name: Reference, file_name: Reference, line: u64, column: u64) {
     let object = this;
     let object = object as *mut Backtrace;
     let object = unsafe { object.as_mut().unwrap() };
     object.function_name = function_name;
+    object.file_name = file_name;
     object.line_number = line;
     object.column_number = column;
 }
 
-extern "C" fn backtrace_display(_context: &mut Runtime, this: Reference) {
+/// Reads a string object into a Rust string
+fn string_contents(string: Reference) -> String {
+    let mut buffer = std::ptr::null();
+    let mut length = 0;
+    crate::external::rowan_get_string_buffer(string, &mut buffer, &mut length);
+    let slice = unsafe { std::slice::from_raw_parts(buffer, length as usize) };
+    String::from_utf8_lossy(slice).into_owned()
+}
+
+extern "C" fn backtrace_display(_context: &mut BytecodeContext, this: Reference) {
     let object = this;
     let object = object as *mut Backtrace;
     let object = unsafe { object.as_ref().unwrap() };
-    let function_name = object.function_name;
-    let line = object.line_number;
-    let column = object.column_number;
+    let function_name = string_contents(object.function_name);
 
-    let string = function_name;
-    let string = string as *mut StringBuffer;
-    let string = unsafe { string.as_ref().unwrap() };
-    let string_length = string.length;
-    let string_pointer = string.buffer;
-    let string_slice = slice_from_raw_parts(string_pointer as *const u8, string_length as usize);
-    let str = unsafe { std::str::from_utf8_unchecked(string_slice.as_ref().unwrap()) };
-
-    println!("{} {}:{}", str, line, column);
+    if object.file_name.is_null() {
+        eprintln!("\tat {} (unknown source)", function_name);
+    } else {
+        let file_name = string_contents(object.file_name);
+        eprintln!("\tat {} ({}:{}:{})", function_name, file_name, object.line_number, object.column_number);
+    }
 }
 
 pub fn generate_index_out_of_bounds_class() -> VMClass {
@@ -633,6 +913,11 @@ pub fn generate_index_out_of_bounds_class() -> VMClass {
                 out_of_bounds_init as *const (),
                 vec![TypeTag::Void, TypeTag::Object, TypeTag::U64, TypeTag::U64]
             ),
+            VMMethod::new(
+                "core::IndexOutOfBounds::new",
+                out_of_bounds_new as *const (),
+                vec![TypeTag::Object, TypeTag::Object, TypeTag::U64, TypeTag::U64]
+            ),
         ]
     );
 
@@ -655,6 +940,12 @@ extern "C" fn out_of_bounds_init(context: &mut BytecodeContext, this: Reference,
     exception_init(context, base_exception, message as Reference);
 }
 
+/// The constructor that `new IndexOutOfBounds(length, index)` calls
+extern "C" fn out_of_bounds_new(context: &mut BytecodeContext, this: Reference, bounds: u64, index: u64) -> Reference {
+    out_of_bounds_init(context, this, bounds, index);
+    this
+}
+
 pub fn generate_null_pointer_class() -> VMClass {
     let vtable = VMVTable::new(
         "core::NullPointerException",
@@ -665,6 +956,11 @@ pub fn generate_null_pointer_class() -> VMClass {
                 null_pointer_init as *const (),
                 vec![TypeTag::Void, TypeTag::Object]
             ),
+            VMMethod::new(
+                "core::NullPointerException::new",
+                null_pointer_new as *const (),
+                vec![TypeTag::Object, TypeTag::Object]
+            ),
         ]
     );
 
@@ -674,7 +970,7 @@ pub fn generate_null_pointer_class() -> VMClass {
     VMClass::new("core::NullPointerException", "core::Exception", vec![vtable], elements, Vec::new(), Vec::new())
 }
 
-pub extern "C" fn null_pointer_init(context: &BytecodeContext, this: Reference) {
+pub extern "C" fn null_pointer_init(context: &mut BytecodeContext, this: Reference) {
     let object = this;
     let object = unsafe { object.as_mut().unwrap() };
 
@@ -687,3 +983,31 @@ pub extern "C" fn null_pointer_init(context: &BytecodeContext, this: Reference)
     exception_init(context, base_exception, message as Reference);
 }
 
+/// The constructor that `new NullPointerException()` calls
+extern "C" fn null_pointer_new(context: &mut BytecodeContext, this: Reference) -> Reference {
+    null_pointer_init(context, this);
+    this
+}
+
+/// Throws a `core::NullPointerException` from a native method
+pub fn throw_null_pointer_exception(context: &mut BytecodeContext) {
+    let exception = Runtime::new_object("core::NullPointerException");
+    null_pointer_init(context, exception);
+    context.set_exception(exception);
+}
+
+pub fn throw_index_out_of_bounds(context: &mut BytecodeContext, length: u64, index: u64) {
+    let exception = Runtime::new_object("core::IndexOutOfBounds");
+    out_of_bounds_init(context, exception, length, index);
+    context.set_exception(exception);
+}
+
+/// Throws a `core::Exception` with the message from a native method
+pub fn throw_exception(context: &mut BytecodeContext, message: &str) {
+    let exception = Runtime::new_object("core::Exception");
+    let message_buffer = Runtime::new_object("core::StringBuffer") as *mut StringBuffer;
+    string_buffer_from_str(message_buffer, message);
+    exception_init(context, exception, message_buffer as Reference);
+    context.set_exception(exception);
+}
+
@@ -1,10 +1,14 @@
 use std::collections::HashSet;
-use std::sync::atomic::{AtomicI64, Ordering};
-use std::sync::LazyLock;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::{LazyLock, Mutex};
 use std::sync::mpsc::{Receiver, Sender};
-use std::thread::yield_now;
+use std::time::Instant;
 use crate::fake_lock::FakeLock;
 use crate::runtime::{Runtime, Reference, WrappedReference, DO_GARBAGE_COLLECTION, THREAD_COUNT};
+use crate::runtime::heartbeat::Heartbeat;
+use crate::runtime::object::GcClasses;
+use crate::runtime::message::MessageQueues;
+use crate::runtime::scheduler::Scheduler;
 
 static MAX_HEAP_SIZE: LazyLock<AtomicI64> = LazyLock::new(|| {
     AtomicI64::new(4 * 1024 * 1024 * 1024) // 4 GB
@@ -14,22 +18,112 @@ static CURRENT_HEAP_SIZE: LazyLock<AtomicI64> = LazyLock::new(|| {
     AtomicI64::new(0)
 });
 
+static MAX_NURSERY_SIZE: LazyLock<AtomicI64> = LazyLock::new(|| {
+    AtomicI64::new(32 * 1024 * 1024) // 32 MB
+});
+
+/// The amount of memory allocated since the last collection
+static CURRENT_NURSERY_SIZE: LazyLock<AtomicI64> = LazyLock::new(|| {
+    AtomicI64::new(0)
+});
+
+/// Counts the finished collections.
+/// Threads wait for this to change instead of waiting for the lock to be released since the next collection may take the lock first.
+static COLLECTION_EPOCH: AtomicU64 = AtomicU64::new(0);
+
+/// Old objects that have had a reference stored in them since the last collection.
+/// Threads keep their own sets and hand them over before taking part in a collection or when they stop.
+static REMEMBERED_SET: LazyLock<Mutex<HashSet<WrappedReference>>> = LazyLock::new(|| {
+    Mutex::new(HashSet::new())
+});
+
 static GC_SENDER: LazyLock<FakeLock<Option<Sender<HashSet<WrappedReference>>>>> = LazyLock::new(|| {
     FakeLock::new(None)
 });
 
-static TRIGGER_COLLECTION: LazyLock<FakeLock<Option<Sender<()>>>> = LazyLock::new(|| {
+static TRIGGER_COLLECTION: LazyLock<FakeLock<Option<Sender<Collection>>>> = LazyLock::new(|| {
     FakeLock::new(None)
 });
 
+/// The kinds of collection ordered by how much of the heap they look at
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Collection {
+    /// Only looks at the objects allocated since the last collection.
+    /// The old objects are treated as live and the remembered set tells us which of them point into the nursery.
+    Minor,
+    /// Looks at every object
+    Major,
+}
+
+/// The objects that have been found to be live during a collection
+pub struct LiveObjects {
+    marked: HashSet<Reference>,
+    /// The objects allocated since the last collection.
+    /// This is only present for minor collections.
+    nursery: Option<HashSet<Reference>>,
+}
+
+impl LiveObjects {
+    pub fn new(collection: Collection, nursery: HashSet<Reference>) -> Self {
+        let nursery = match collection {
+            Collection::Minor => Some(nursery),
+            Collection::Major => None,
+        };
+        LiveObjects {
+            marked: HashSet::new(),
+            nursery,
+        }
+    }
+
+    /// Returns true if the object has to be explored.
+    /// Old objects are never explored during a minor collection since they are all treated as live.
+    pub fn mark(&mut self, reference: Reference) -> bool {
+        if !self.is_collectable(reference) {
+            return false;
+        }
+        self.marked.insert(reference)
+    }
+
+    pub fn marked_count(&self) -> usize {
+        self.marked.len()
+    }
+
+    pub fn marked(&self) -> impl Iterator<Item = Reference> + '_ {
+        self.marked.iter().copied()
+    }
+
+    pub fn contains(&self, reference: &Reference) -> bool {
+        !self.is_collectable(*reference) || self.marked.contains(reference)
+    }
+
+    /// Returns true if the object can be freed by the current collection
+    pub fn is_collectable(&self, reference: Reference) -> bool {
+        match &self.nursery {
+            Some(nursery) => nursery.contains(&reference),
+            None => true,
+        }
+    }
+
+    /// Returns the objects of the nursery that weren't marked.
+    /// Returns None if this is a major collection.
+    pub fn dead_young_objects(&self) -> Option<Vec<Reference>> {
+        let nursery = self.nursery.as_ref()?;
+        Some(nursery.iter()
+            .filter(|reference| !self.marked.contains(*reference))
+            .copied()
+            .collect())
+    }
+}
+
 pub struct GarbageCollection {
-    live_objects: HashSet<Reference>,
     gc_receiver: Receiver<HashSet<WrappedReference>>,
-    start_collection: Receiver<()>,
+    start_collection: Receiver<Collection>,
+    /// Prints what every collection did to stderr
+    print_stats: bool,
 }
 
 impl GarbageCollection {
-    pub fn new() -> Self {
+    pub fn new(print_stats: bool) -> Self {
         let (gc_sender, gc_receiver) = std::sync::mpsc::channel();
         let (trigger_collection, start_collection) = std::sync::mpsc::channel();
 
@@ -39,9 +133,9 @@ impl GarbageCollection {
         }
 
         Self {
-            live_objects: HashSet::new(),
             gc_receiver,
             start_collection,
+            print_stats,
         }
     }
 
@@ -52,36 +146,71 @@ impl GarbageCollection {
             .send(live_memory)
             .unwrap()
     }
+
+    /// Hands over the objects that a thread has stored references in
+    pub fn remember(objects: HashSet<WrappedReference>) {
+        if objects.is_empty() {
+            return;
+        }
+        let Ok(mut remembered_set) = REMEMBERED_SET.lock() else {
+            panic!("Lock poisoned");
+        };
+        remembered_set.extend(objects);
+    }
     
-    pub fn trigger_gc() {
-        TRIGGER_COLLECTION.read().as_ref().unwrap().send(()).unwrap();
+    pub fn collection_epoch() -> u64 {
+        COLLECTION_EPOCH.load(Ordering::Acquire)
+    }
+
+    pub fn trigger_gc(collection: Collection) {
+        // Objects can be allocated by static initializers before the collector has started
+        let Some(trigger_collection) = TRIGGER_COLLECTION.read().as_ref() else {
+            return;
+        };
+        trigger_collection.send(collection).unwrap();
     }
     
     pub fn update_heap_size(size: i64) {
-        let size = CURRENT_HEAP_SIZE.fetch_add(size, Ordering::Relaxed) + size;
-        if size > MAX_HEAP_SIZE.load(Ordering::Relaxed) {
-            Self::trigger_gc()
+        let heap_size = CURRENT_HEAP_SIZE.fetch_add(size, Ordering::Relaxed) + size;
+        if heap_size > MAX_HEAP_SIZE.load(Ordering::Relaxed) {
+            Self::trigger_gc(Collection::Major);
+            return;
+        }
+        if size > 0 {
+            let nursery_size = CURRENT_NURSERY_SIZE.fetch_add(size, Ordering::Relaxed) + size;
+            if nursery_size > MAX_NURSERY_SIZE.load(Ordering::Relaxed) {
+                Self::trigger_gc(Collection::Minor)
+            }
         }
     }
     
-    pub fn initialize(max_heap_size: Option<i64>) {
+    /// Starts the collector thread.
+    /// A nursery size of `i64::MAX` turns off minor collections, so every collection looks at the whole heap.
+    pub fn initialize(max_heap_size: Option<i64>, max_nursery_size: Option<i64>, print_stats: bool) {
         max_heap_size.map(|size| {
             MAX_HEAP_SIZE.store(size, Ordering::Relaxed);
         });
+        max_nursery_size.map(|size| {
+            MAX_NURSERY_SIZE.store(size, Ordering::Relaxed);
+        });
         std::thread::Builder::new().name("Garbage Collection".to_owned())
             .spawn(move || {
-                let mut gc = GarbageCollection::new();
+                let mut gc = GarbageCollection::new(print_stats);
                 gc.main_loop()
             }).expect("Thread 'new' panicked at 'Garbage Collection'");
     }
 
     pub fn main_loop(&mut self) {
         loop {
-            match self.start_collection.recv() {
-                Ok(_) => {},
+            let mut collection = match self.start_collection.recv() {
+                Ok(collection) => collection,
                 Err(_) => {
                     break;
                 }
+            };
+            // A major collection covers any minor collection that was asked for
+            while let Ok(next) = self.start_collection.try_recv() {
+                collection = collection.max(next);
             }
             
             let mut thread_count = {
@@ -91,15 +220,16 @@ impl GarbageCollection {
             let lock = {
                 DO_GARBAGE_COLLECTION.write().unwrap()
             };
+            // Idle threads are asleep, so they have to be woken up to hand over their roots
+            Scheduler::wake_for_collection();
+            let start = Instant::now();
 
+            let mut roots = HashSet::new();
             loop {
                 match self.gc_receiver.recv() {
                     Ok(live_objects) => {
                         //println!("Received live objects: {live_objects:?}");
-                        for live_object in live_objects.iter() {
-                            Runtime::gc_explore_object(live_object.0, &mut self.live_objects);
-                            self.live_objects.insert(live_object.0);
-                        }
+                        roots.extend(live_objects.into_iter().map(|live_object| live_object.0));
                         thread_count -= 1;
 
                         if thread_count == 0 {
@@ -109,15 +239,48 @@ impl GarbageCollection {
                     Err(_) => panic!("GarbageCollection sender closed"),
                 }
             }
-            let mut static_objects = HashSet::new();
-            Runtime::collect_static_members(&mut static_objects);
-            for live_object in static_objects.into_iter() {
-                Runtime::gc_explore_object(live_object, &mut self.live_objects);
-                self.live_objects.insert(live_object);
+            Runtime::collect_static_members(&mut roots);
+            Heartbeat::collect_roots(&mut roots);
+            MessageQueues::collect_roots(&mut roots);
+            Scheduler::collect_roots(&mut roots);
+
+            // Every thread has stopped, so nothing can be allocated or written to until we are done
+            let nursery = Runtime::gc_take_nursery();
+            let remembered_set = {
+                let Ok(mut remembered_set) = REMEMBERED_SET.lock() else {
+                    panic!("Lock poisoned");
+                };
+                std::mem::take(&mut *remembered_set)
+            };
+
+            let classes = GcClasses::new();
+            let mut live_objects = LiveObjects::new(collection, nursery);
+            for root in roots.into_iter() {
+                Runtime::gc_explore_object(root, &classes, &mut live_objects);
+            }
+            if collection == Collection::Minor {
+                Runtime::gc_explore_remembered_objects(remembered_set, &classes, &mut live_objects);
             }
+            Runtime::collect_signal_receivers(&classes, &mut live_objects);
 
-            Runtime::gc_collect_garbage(&self.live_objects);
-            self.live_objects.clear();
+            let freed = Runtime::gc_collect_garbage(&live_objects);
+            CURRENT_NURSERY_SIZE.store(0, Ordering::Relaxed);
+            if self.print_stats {
+                eprintln!(
+                    "[gc] {} collection: freed {} objects, {} survived, heap at {} KB, paused for {:?}",
+                    match collection {
+                        Collection::Minor => "minor",
+                        Collection::Major => "major",
+                    },
+                    freed,
+                    live_objects.marked_count(),
+                    CURRENT_HEAP_SIZE.load(Ordering::Relaxed) / 1024,
+                    start.elapsed(),
+                );
+            }
+            // Allocations made before this collection may have asked for more collections
+            while self.start_collection.try_recv().is_ok() {}
+            COLLECTION_EPOCH.fetch_add(1, Ordering::AcqRel);
             drop(lock);
         }
     }
@@ -126,4 +289,44 @@ impl GarbageCollection {
 unsafe impl Send for GarbageCollection {}
 unsafe impl Sync for GarbageCollection {}
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn object(address: usize) -> Reference {
+        address as Reference
+    }
 
+    #[test]
+    fn test_major_collection_marks_every_object_once() {
+        let mut live_objects = LiveObjects::new(Collection::Major, HashSet::new());
+        assert!(live_objects.is_collectable(object(8)));
+        assert!(!live_objects.contains(&object(8)));
+        assert!(live_objects.mark(object(8)));
+        assert!(!live_objects.mark(object(8)));
+        assert!(live_objects.contains(&object(8)));
+        assert_eq!(live_objects.marked_count(), 1);
+        assert_eq!(live_objects.dead_young_objects(), None);
+    }
+
+    #[test]
+    fn test_minor_collection_treats_old_objects_as_live() {
+        let nursery = HashSet::from([object(8), object(16)]);
+        let mut live_objects = LiveObjects::new(Collection::Minor, nursery);
+        assert!(!live_objects.is_collectable(object(24)));
+        assert!(live_objects.contains(&object(24)));
+        assert!(!live_objects.mark(object(24)));
+        assert_eq!(live_objects.marked_count(), 0);
+    }
+
+    #[test]
+    fn test_minor_collection_frees_unmarked_young_objects() {
+        let nursery = HashSet::from([object(8), object(16), object(24)]);
+        let mut live_objects = LiveObjects::new(Collection::Minor, nursery);
+        assert!(live_objects.mark(object(16)));
+        assert!(!live_objects.contains(&object(8)));
+        let mut dead = live_objects.dead_young_objects().unwrap();
+        dead.sort();
+        assert_eq!(dead, vec![object(8), object(24)]);
+    }
+}
@@ -0,0 +1,130 @@
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicPtr, Ordering};
+use std::time::{Duration, Instant};
+use crate::context::{BytecodeContext, StackValue};
+use crate::runtime::core::BaseObject;
+use crate::runtime::message::MessageQueues;
+use crate::runtime::scheduler::Scheduler;
+use crate::runtime::{Reference, Runtime, Symbol};
+
+/// The object at the top of the object graph.
+/// Everything attached to it, directly or through other objects, receives the heartbeat.
+static HEARTBEAT_ROOT: AtomicPtr<super::object::Object> = AtomicPtr::new(std::ptr::null_mut());
+
+/// The shortest time between the start of two heartbeats.
+/// A heartbeat that finishes early sleeps for the rest of it instead of spinning.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Drives the `tick` and `ready` methods of the objects in the object graph.
+pub struct Heartbeat {
+    object_symbol: Symbol,
+    tick_symbol: Symbol,
+    ready_symbol: Symbol,
+}
+
+impl Heartbeat {
+    pub fn new() -> Self {
+        let (object_symbol, tick_symbol) = Runtime::get_virtual_method_name("core::Object", "core::Object::tick")
+            .expect("core::Object::tick wasn't linked");
+        let (_, ready_symbol) = Runtime::get_virtual_method_name("core::Object", "core::Object::ready")
+            .expect("core::Object::ready wasn't linked");
+
+        Self {
+            object_symbol,
+            tick_symbol,
+            ready_symbol,
+        }
+    }
+
+    /// Makes `root` the top of the object graph and calls its `ready` method.
+    pub fn attach_root(&self, context: &mut BytecodeContext, root: Reference) {
+        HEARTBEAT_ROOT.store(root, Ordering::Release);
+        self.ready(context, root);
+    }
+
+    pub fn root() -> Reference {
+        HEARTBEAT_ROOT.load(Ordering::Acquire)
+    }
+
+    /// Sends heartbeats until the root object no longer has any children and there are no signals left to deliver.
+    /// The delta passed to `tick` is the amount of seconds since the last heartbeat.
+    /// Signals emitted before or during a heartbeat are delivered at the end of it.
+    /// Heartbeats happen at most once every `HEARTBEAT_INTERVAL`.
+    pub fn run(&self, context: &mut BytecodeContext) {
+        let root = HEARTBEAT_ROOT.load(Ordering::Acquire);
+        let mut last_heartbeat = Instant::now();
+        loop {
+            let base = BaseObject::from_reference(root);
+            let Some(base) = (unsafe { base.as_ref() }) else {
+                break;
+            };
+            if base.child_count == 0 && !MessageQueues::has_pending() {
+                break;
+            }
+
+            let elapsed = last_heartbeat.elapsed();
+            if elapsed < HEARTBEAT_INTERVAL {
+                std::thread::sleep(HEARTBEAT_INTERVAL - elapsed);
+            }
+            let now = Instant::now();
+            let delta = now.duration_since(last_heartbeat).as_secs_f64();
+            last_heartbeat = now;
+
+            if base.child_count != 0 {
+                self.tick(context, root, delta);
+            }
+            self.deliver_messages(context);
+            context.check_and_do_garbage_collection();
+        }
+    }
+
+    /// Delivers the signals that were emitted since the last delivery.
+    /// Signals emitted by the connected methods go into the other queue.
+    fn deliver_messages(&self, context: &mut BytecodeContext) {
+        Scheduler::deliver(context, MessageQueues::swap());
+    }
+
+    pub fn ready(&self, context: &mut BytecodeContext, object: Reference) {
+        self.invoke(context, object, self.ready_symbol, &[]);
+    }
+
+    /// Ticks an object and then its children in the order they were attached.
+    fn tick(&self, context: &mut BytecodeContext, object: Reference, delta: f64) {
+        self.invoke(context, object, self.tick_symbol, &[StackValue::Float64(delta)]);
+
+        let base = BaseObject::from_reference(object);
+        let base = unsafe { base.as_ref().unwrap() };
+        // Children may attach or remove other children while ticking, so we tick the children that were there before
+        let children = base.children().to_vec();
+        for child in children {
+            self.tick(context, child, delta);
+        }
+    }
+
+    fn invoke(&self, context: &mut BytecodeContext, object: Reference, method_name: Symbol, args: &[StackValue]) {
+        Self::clear_args(context);
+        context.store_argument(0, StackValue::Reference(object));
+        for (i, arg) in args.iter().enumerate() {
+            context.store_argument(i as u8 + 1, *arg);
+        }
+        context.invoke_virtual_extern(self.object_symbol, method_name, None, None);
+        // There is no caller that could catch an exception thrown by a callback
+        context.check_for_uncaught_exception();
+    }
+
+    pub(crate) fn clear_args(context: &mut BytecodeContext) {
+        for arg in context.get_args_mut() {
+            if arg.is_blank() {
+                break
+            }
+            *arg = StackValue::Blank;
+        }
+    }
+
+    pub fn collect_roots(live_objects: &mut HashSet<Reference>) {
+        let root = HEARTBEAT_ROOT.load(Ordering::Acquire);
+        if !root.is_null() {
+            live_objects.insert(root);
+        }
+    }
+}
@@ -0,0 +1,220 @@
+use std::sync::LazyLock;
+use std::sync::atomic::{AtomicBool, AtomicPtr, AtomicU64, AtomicUsize, Ordering};
+use fxhash::FxHashMap;
+use rowan_shared::bytecode::linked::Bytecode;
+use crate::runtime::Symbol;
+use crate::runtime::tables::vtable::Function;
+
+/// The most classes a call site remembers before it is treated as megamorphic
+pub const INLINE_CACHE_SIZE: usize = 4;
+
+/// The inline caches of a function, by the bytecode index of the call they belong to
+pub type InlineCaches = FxHashMap<usize, InlineCache>;
+
+/// The caches of bytecode that runs outside of a function, like static initializers
+pub static NO_INLINE_CACHES: LazyLock<InlineCaches> = LazyLock::new(InlineCaches::default);
+
+/// Creates an empty cache for every virtual and interface call in the bytecode
+pub fn create_inline_caches(bytecode: &[Bytecode]) -> InlineCaches {
+    bytecode.iter()
+        .enumerate()
+        .filter(|(_, bytecode)| matches!(
+            bytecode,
+            Bytecode::InvokeVirt(..) | Bytecode::InvokeVirtTail(..) |
+            Bytecode::InvokeInterface(..) | Bytecode::InvokeInterfaceTail(..)
+        ))
+        .map(|(index, _)| (index, InlineCache::new()))
+        .collect()
+}
+
+/// A receiver class that a call site has seen and the method it ended up calling
+struct CacheEntry {
+    /// The class symbol plus one, so that zero can mean the entry is empty
+    class: AtomicUsize,
+    function: AtomicPtr<Function>,
+    hits: AtomicU64,
+}
+
+/// What a call site has seen, as far as the second tier of the JIT cares
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CacheState {
+    /// The call hasn't happened yet
+    Uninitialized,
+    /// Every call has been with the same class of receiver
+    Monomorphic,
+    /// The call has seen a few classes of receiver
+    Polymorphic,
+    /// The call has seen too many classes of receiver to be worth checking for each one
+    Megamorphic,
+}
+
+/// Remembers the methods a virtual or interface call site ended up calling for each class of receiver.
+/// This lets the interpreter skip the class and vtable lookups, and tells the JIT which methods it can call directly.
+///
+/// Entries are only ever added, so they can be read without locking.
+/// A writer claims an entry by setting its function first and only then publishes the class.
+pub struct InlineCache {
+    entries: [CacheEntry; INLINE_CACHE_SIZE],
+    megamorphic: AtomicBool,
+}
+
+impl InlineCache {
+    pub fn new() -> Self {
+        InlineCache {
+            entries: std::array::from_fn(|_| CacheEntry {
+                class: AtomicUsize::new(0),
+                function: AtomicPtr::new(std::ptr::null_mut()),
+                hits: AtomicU64::new(0),
+            }),
+            megamorphic: AtomicBool::new(false),
+        }
+    }
+
+    /// Finds the method to call for a receiver of `class`, calling `lookup` and remembering the result if the class is new.
+    /// Functions live as long as their vtables, which are never freed.
+    pub fn get_or_insert(&self, class: Symbol, lookup: impl FnOnce() -> &'static Function) -> &'static Function {
+        for entry in self.entries.iter() {
+            let entry_class = entry.class.load(Ordering::Acquire);
+            if entry_class == class + 1 {
+                entry.hits.fetch_add(1, Ordering::Relaxed);
+                return unsafe { &*entry.function.load(Ordering::Relaxed) };
+            }
+            if entry_class == 0 {
+                break;
+            }
+        }
+
+        let function = lookup();
+        self.insert(class, function);
+        function
+    }
+
+    fn insert(&self, class: Symbol, function: &'static Function) {
+        if self.megamorphic.load(Ordering::Relaxed) {
+            return;
+        }
+        let function = function as *const Function as *mut Function;
+        for entry in self.entries.iter() {
+            let claimed = entry.function.compare_exchange(
+                std::ptr::null_mut(),
+                function,
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            );
+            if claimed.is_ok() {
+                entry.hits.store(1, Ordering::Relaxed);
+                entry.class.store(class + 1, Ordering::Release);
+                return;
+            }
+            // Another thread may be adding the same class, in which case the entry is still usable
+            if entry.class.load(Ordering::Acquire) == class + 1 {
+                return;
+            }
+        }
+        self.megamorphic.store(true, Ordering::Relaxed);
+    }
+
+    pub fn state(&self) -> CacheState {
+        if self.megamorphic.load(Ordering::Relaxed) {
+            return CacheState::Megamorphic;
+        }
+        match self.targets().len() {
+            0 => CacheState::Uninitialized,
+            1 => CacheState::Monomorphic,
+            _ => CacheState::Polymorphic,
+        }
+    }
+
+    /// The classes the call site has seen along with the method called for them, most called first
+    pub fn targets(&self) -> Vec<(Symbol, &'static Function)> {
+        let mut targets = self.entries.iter()
+            .filter_map(|entry| {
+                let class = entry.class.load(Ordering::Acquire);
+                if class == 0 {
+                    return None;
+                }
+                let function = unsafe { &*entry.function.load(Ordering::Relaxed) };
+                Some((class - 1, function, entry.hits.load(Ordering::Relaxed)))
+            })
+            .collect::<Vec<_>>();
+        targets.sort_by(|(_, _, a), (_, _, b)| b.cmp(a));
+        targets.into_iter()
+            .map(|(class, function, _)| (class, function))
+            .collect()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cranelift::prelude::Signature;
+    use cranelift::prelude::isa::CallConv;
+    use crate::runtime::class::TypeTag;
+    use crate::runtime::tables::vtable::FunctionValue;
+
+    fn function(name: Symbol) -> &'static Function {
+        Box::leak(Box::new(Function::new(
+            name,
+            Box::new([]),
+            FunctionValue::Blank,
+            Box::new([]),
+            TypeTag::Void,
+            Signature::new(CallConv::SystemV),
+            Box::new(FxHashMap::default()),
+            Box::new([]),
+        )))
+    }
+
+    #[test]
+    fn test_caches_are_created_for_virtual_calls() {
+        let bytecode = vec![
+            Bytecode::Nop,
+            Bytecode::InvokeVirt(1, 2),
+            Bytecode::ReturnVoid,
+            Bytecode::InvokeInterface(3, 4),
+        ];
+        let mut indices = create_inline_caches(&bytecode).into_keys().collect::<Vec<_>>();
+        indices.sort();
+        assert_eq!(indices, vec![1, 3]);
+    }
+
+    #[test]
+    fn test_cache_goes_through_every_state() {
+        let cache = InlineCache::new();
+        assert_eq!(cache.state(), CacheState::Uninitialized);
+
+        cache.get_or_insert(0, || function(10));
+        assert_eq!(cache.state(), CacheState::Monomorphic);
+
+        for class in 1..INLINE_CACHE_SIZE {
+            cache.get_or_insert(class, || function(10 + class));
+        }
+        assert_eq!(cache.state(), CacheState::Polymorphic);
+
+        cache.get_or_insert(INLINE_CACHE_SIZE, || function(20));
+        assert_eq!(cache.state(), CacheState::Megamorphic);
+    }
+
+    #[test]
+    fn test_seen_class_skips_the_lookup() {
+        let cache = InlineCache::new();
+        let first = cache.get_or_insert(7, || function(1));
+        let second = cache.get_or_insert(7, || panic!("class 7 should be cached"));
+        assert!(std::ptr::eq(first, second));
+        assert_eq!(cache.state(), CacheState::Monomorphic);
+    }
+
+    #[test]
+    fn test_targets_are_sorted_by_hits() {
+        let cache = InlineCache::new();
+        cache.get_or_insert(1, || function(1));
+        for _ in 0..3 {
+            cache.get_or_insert(2, || function(2));
+        }
+        let targets = cache.targets().into_iter()
+            .map(|(class, function)| (class, function.name))
+            .collect::<Vec<_>>();
+        assert_eq!(targets, vec![(2, 2), (1, 1)]);
+    }
+}
@@ -1,5 +1,6 @@
 use std::collections::{HashMap};
 use std::sync::LazyLock;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::mpsc::{Receiver, Sender};
 use codegen::{ir::self, CodegenError};
 use cranelift::prelude::*;
@@ -8,25 +9,154 @@ use cranelift_module::{FuncId, FuncOrDataId, Linkage, Module, ModuleError, Modul
 use rowan_shared::bytecode::linked::Bytecode;
 
 use rowan_shared::TypeTag;
-use super::{tables::vtable::{Function, FunctionValue}, Runtime, Symbol};
+use super::{tables::vtable::{Function, FunctionValue, TIER_BASELINE, TIER_OPTIMIZED}, Runtime, Symbol};
+use super::inline_cache::{CacheState, InlineCache, InlineCaches};
+use super::object::Object;
+use super::osr::LoopEntry;
 use cranelift::codegen::ir::BlockArg;
 use log::trace;
+use rowan_register_debug::SourceLine;
 use crate::context::{BytecodeContext, MethodName};
 use crate::fake_lock::FakeLock;
 use crate::runtime;
 
-static JIT_SENDER: LazyLock<FakeLock<Option<Sender<MethodName>>>> = LazyLock::new(|| {
+static JIT_SENDER: LazyLock<FakeLock<Option<Sender<(MethodName, Tier)>>>> = LazyLock::new(|| {
     FakeLock::new(None)
 });
 
-pub fn set_jit_sender(sender: Sender<MethodName>) {
+pub fn set_jit_sender(sender: Sender<(MethodName, Tier)>) {
     JIT_SENDER.write().replace(sender);
 }
 
-pub fn request_to_jit_method(name: MethodName) {
+/// The number of calls a method needs before it gets compiled.
+/// A threshold of 0 turns the JIT off.
+static JIT_THRESHOLD: AtomicU64 = AtomicU64::new(1000);
+
+pub fn set_jit_threshold(threshold: u64) {
+    JIT_THRESHOLD.store(threshold, Ordering::Relaxed);
+}
+
+pub fn jit_threshold() -> u64 {
+    JIT_THRESHOLD.load(Ordering::Relaxed)
+}
+
+/// How many times the threshold a compiled method has to be called before it is optimized
+pub const TIER_TWO_FACTOR: u64 = 10;
+
+/// What the JIT compiles a method into
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Tier {
+    /// Translates the bytecode as it is
+    Baseline,
+    /// Uses the inline caches of the method to inline or directly call the methods it calls
+    Optimized,
+    /// Code that an interpreted frame jumps into at the start of the loop in the block, see `Loops`
+    Loop(usize),
+}
+
+pub fn request_to_jit_method(name: MethodName, tier: Tier) {
     JIT_SENDER.read()
         .as_ref()
-        .map(|sender| sender.send(name));
+        .map(|sender| sender.send((name, tier)));
+}
+
+/// The functions of the runtime that compiled code calls, by the name it imports them with
+pub fn runtime_symbols() -> Vec<(&'static str, *const u8)> {
+    vec![
+        ("call_virtual_function", super::call_virtual_function as *const u8),
+        ("call_static_function", super::call_static_function as *const u8),
+        ("call_interface_function", super::call_interface_function as *const u8),
+        ("object_class", super::object_class as *const u8),
+        ("is_a", super::is_a as *const u8),
+        ("check_and_do_garbage_collection", Runtime::check_and_do_garbage_collection as *const u8),
+        ("new_object", super::new_object as *const u8),
+        ("emit_signal", super::emit_signal as *const u8),
+        ("emit_static_signal", super::emit_static_signal as *const u8),
+        ("connect_signal", super::connect_signal as *const u8),
+        ("disconnect_signal", super::disconnect_signal as *const u8),
+        ("array8_init", super::core::array8_init as *const u8),
+        ("array8_set", super::core::array8_set as *const u8),
+        ("array8_get", super::core::array8_get as *const u8),
+        ("array16_init", super::core::array16_init as *const u8),
+        ("array16_set", super::core::array16_set as *const u8),
+        ("array16_get", super::core::array16_get as *const u8),
+        ("array32_init", super::core::array32_init as *const u8),
+        ("array32_set", super::core::array32_set as *const u8),
+        ("array32_get", super::core::array32_get as *const u8),
+        ("array64_init", super::core::array64_init as *const u8),
+        ("array64_set", super::core::array64_set as *const u8),
+        ("array64_get", super::core::array64_get as *const u8),
+        ("arrayobject_set", super::core::arrayobject_set as *const u8),
+        ("arrayobject_get", super::core::arrayobject_get as *const u8),
+        ("arrayf32_init", super::core::arrayf32_init as *const u8),
+        ("arrayf32_set", super::core::arrayf32_set as *const u8),
+        ("arrayf32_get", super::core::arrayf32_get as *const u8),
+        ("arrayf64_init", super::core::arrayf64_init as *const u8),
+        ("arrayf64_set", super::core::arrayf64_set as *const u8),
+        ("arrayf64_get", super::core::arrayf64_get as *const u8),
+        ("context_should_unwind", Runtime::should_unwind as *const u8),
+        ("member8_get", super::object::Object::get_8 as *const u8),
+        ("member16_get", super::object::Object::get_16 as *const u8),
+        ("member32_get", super::object::Object::get_32 as *const u8),
+        ("member64_get", super::object::Object::get_64 as *const u8),
+        ("memberobject_get", super::object::Object::get_object as *const u8),
+        ("memberf32_get", super::object::Object::get_f32 as *const u8),
+        ("memberf64_get", super::object::Object::get_f64 as *const u8),
+        ("member8_set", super::object::Object::set_8 as *const u8),
+        ("member16_set", super::object::Object::set_16 as *const u8),
+        ("member32_set", super::object::Object::set_32 as *const u8),
+        ("member64_set", super::object::Object::set_64 as *const u8),
+        ("memberobject_set", super::object::Object::set_object as *const u8),
+        ("memberf32_set", super::object::Object::set_f32 as *const u8),
+        ("memberf64_set", super::object::Object::set_f64 as *const u8),
+        ("static_member8_get", super::get_static_member8 as *const u8),
+        ("static_member16_get", super::get_static_member16 as *const u8),
+        ("static_member32_get", super::get_static_member32 as *const u8),
+        ("static_member64_get", super::get_static_member64 as *const u8),
+        ("static_memberf32_get", super::get_static_memberf32 as *const u8),
+        ("static_memberf64_get", super::get_static_memberf64 as *const u8),
+        ("static_memberobject_get", super::get_static_memberobject as *const u8),
+        ("static_member8_set", super::set_static_member8 as *const u8),
+        ("static_member16_set", super::set_static_member16 as *const u8),
+        ("static_member32_set", super::set_static_member32 as *const u8),
+        ("static_member64_set", super::set_static_member64 as *const u8),
+        ("static_memberf32_set", super::set_static_memberf32 as *const u8),
+        ("static_memberf64_set", super::set_static_memberf64 as *const u8),
+        ("static_memberobject_set", super::set_static_memberobject as *const u8),
+        ("store_argument_int8", BytecodeContext::store_argument_int8 as *const u8),
+        ("store_argument_int16", BytecodeContext::store_argument_int16 as *const u8),
+        ("store_argument_int32", BytecodeContext::store_argument_int32 as *const u8),
+        ("store_argument_int64", BytecodeContext::store_argument_int64 as *const u8),
+        ("store_argument_object", BytecodeContext::store_argument_object as *const u8),
+        ("store_argument_float32", BytecodeContext::store_argument_float32 as *const u8),
+        ("store_argument_float64", BytecodeContext::store_argument_float64 as *const u8),
+        ("fetch_argument_int8", BytecodeContext::fetch_argument_int8 as *const u8),
+        ("fetch_argument_int16", BytecodeContext::fetch_argument_int16 as *const u8),
+        ("fetch_argument_int32", BytecodeContext::fetch_argument_int32 as *const u8),
+        ("fetch_argument_int64", BytecodeContext::fetch_argument_int64 as *const u8),
+        ("fetch_argument_object", BytecodeContext::fetch_argument_object as *const u8),
+        ("fetch_argument_float32", BytecodeContext::fetch_argument_float32 as *const u8),
+        ("fetch_argument_float64", BytecodeContext::fetch_argument_float64 as *const u8),
+        ("fetch_return_int8", BytecodeContext::fetch_return_int8 as *const u8),
+        ("fetch_return_int16", BytecodeContext::fetch_return_int16 as *const u8),
+        ("fetch_return_int32", BytecodeContext::fetch_return_int32 as *const u8),
+        ("fetch_return_int64", BytecodeContext::fetch_return_int64 as *const u8),
+        ("fetch_return_object", BytecodeContext::fetch_return_object as *const u8),
+        ("fetch_return_float32", BytecodeContext::fetch_return_float32 as *const u8),
+        ("fetch_return_float64", BytecodeContext::fetch_return_float64 as *const u8),
+        ("interned_string_init", super::core::interned_string_init as *const u8),
+        ("jit_register_exception", BytecodeContext::jit_register_exception as *const u8),
+        ("jit_unregister_exception", BytecodeContext::jit_unregister_exception as *const u8),
+        ("jit_throw", BytecodeContext::jit_throw as *const u8),
+        ("jit_switch_out_of_range", BytecodeContext::jit_switch_out_of_range as *const u8),
+        ("jit_tail_call_virtual", BytecodeContext::jit_tail_call_virtual as *const u8),
+        ("jit_tail_call_static", BytecodeContext::jit_tail_call_static as *const u8),
+        ("jit_tail_call_interface", BytecodeContext::jit_tail_call_interface as *const u8),
+        ("jit_catch_exception", BytecodeContext::jit_catch_exception as *const u8),
+        ("jit_take_exception", BytecodeContext::jit_take_exception as *const u8),
+        ("jit_enter_method", BytecodeContext::jit_enter_method as *const u8),
+        ("jit_leave_method", BytecodeContext::jit_leave_method as *const u8),
+    ]
 }
 
 pub struct JITController {
@@ -46,81 +176,9 @@ impl Default for JITController {
             .finish(settings::Flags::new(flag_builder))
             .unwrap();
         let mut builder = JITBuilder::with_isa(isa, cranelift_module::default_libcall_names());
-        builder.symbol("call_virtual_function", super::call_virtual_function as *const u8);
-        builder.symbol("call_static_function", super::call_static_function as *const u8);
-        builder.symbol("check_and_do_garbage_collection", Runtime::check_and_do_garbage_collection as *const u8);
-        builder.symbol("new_object", super::new_object as *const u8);
-        builder.symbol("array8_init", super::core::array8_init as *const u8);
-        builder.symbol("array8_set", super::core::array8_set as *const u8);
-        builder.symbol("array8_get", super::core::array8_get as *const u8);
-        builder.symbol("array16_init", super::core::array16_init as *const u8);
-        builder.symbol("array16_set", super::core::array16_set as *const u8);
-        builder.symbol("array16_get", super::core::array16_get as *const u8);
-        builder.symbol("array32_init", super::core::array32_init as *const u8);
-        builder.symbol("array32_set", super::core::array32_set as *const u8);
-        builder.symbol("array32_get", super::core::array32_get as *const u8);
-        builder.symbol("array64_init", super::core::array64_init as *const u8);
-        builder.symbol("array64_set", super::core::array64_set as *const u8);
-        builder.symbol("array64_get", super::core::array64_get as *const u8);
-        builder.symbol("arrayobject_set", super::core::arrayobject_set as *const u8);
-        builder.symbol("arrayobject_get", super::core::arrayobject_get as *const u8);
-        builder.symbol("arrayf32_init", super::core::arrayf32_init as *const u8);
-        builder.symbol("arrayf32_set", super::core::arrayf32_set as *const u8);
-        builder.symbol("arrayf32_get", super::core::arrayf32_get as *const u8);
-        builder.symbol("arrayf64_init", super::core::arrayf64_init as *const u8);
-        builder.symbol("arrayf64_set", super::core::arrayf64_set as *const u8);
-        builder.symbol("arrayf64_get", super::core::arrayf64_get as *const u8);
-        builder.symbol("context_should_unwind", Runtime::should_unwind as *const u8);
-        builder.symbol("member8_get", super::object::Object::get_8 as *const u8);
-        builder.symbol("member16_get", super::object::Object::get_16 as *const u8);
-        builder.symbol("member32_get", super::object::Object::get_32 as *const u8);
-        builder.symbol("member64_get", super::object::Object::get_64 as *const u8);
-        builder.symbol("memberobject_get", super::object::Object::get_object as *const u8);
-        builder.symbol("memberf32_get", super::object::Object::get_f32 as *const u8);
-        builder.symbol("memberf64_get", super::object::Object::get_f64 as *const u8);
-        builder.symbol("member8_set", super::object::Object::set_8 as *const u8);
-        builder.symbol("member16_set", super::object::Object::set_16 as *const u8);
-        builder.symbol("member32_set", super::object::Object::set_32 as *const u8);
-        builder.symbol("member64_set", super::object::Object::set_64 as *const u8);
-        builder.symbol("memberobject_set", super::object::Object::set_object as *const u8);
-        builder.symbol("memberf32_set", super::object::Object::set_f32 as *const u8);
-        builder.symbol("memberf64_set", super::object::Object::set_f64 as *const u8);
-        builder.symbol("static_member8_get", super::get_static_member8 as *const u8);
-        builder.symbol("static_member16_get", super::get_static_member16 as *const u8);
-        builder.symbol("static_member32_get", super::get_static_member32 as *const u8);
-        builder.symbol("static_member64_get", super::get_static_member64 as *const u8);
-        builder.symbol("static_memberf32_get", super::get_static_memberf32 as *const u8);
-        builder.symbol("static_memberf64_get", super::get_static_memberf64 as *const u8);
-        builder.symbol("static_memberobject_get", super::get_static_memberobject as *const u8);
-        builder.symbol("static_member8_set", super::set_static_member8 as *const u8);
-        builder.symbol("static_member16_set", super::set_static_member16 as *const u8);
-        builder.symbol("static_member32_set", super::set_static_member32 as *const u8);
-        builder.symbol("static_member64_set", super::set_static_member64 as *const u8);
-        builder.symbol("static_memberf32_set", super::set_static_memberf32 as *const u8);
-        builder.symbol("static_memberf64_set", super::set_static_memberf64 as *const u8);
-        builder.symbol("static_memberobject_set", super::set_static_memberobject as *const u8);
-        builder.symbol("store_argument_int8", BytecodeContext::store_argument_int8 as *const u8);
-        builder.symbol("store_argument_int16", BytecodeContext::store_argument_int16 as *const u8);
-        builder.symbol("store_argument_int32", BytecodeContext::store_argument_int32 as *const u8);
-        builder.symbol("store_argument_int64", BytecodeContext::store_argument_int64 as *const u8);
-        builder.symbol("store_argument_object", BytecodeContext::store_argument_object as *const u8);
-        builder.symbol("store_argument_float32", BytecodeContext::store_argument_float32 as *const u8);
-        builder.symbol("store_argument_float64", BytecodeContext::store_argument_float64 as *const u8);
-        builder.symbol("fetch_argument_int8", BytecodeContext::fetch_argument_int8 as *const u8);
-        builder.symbol("fetch_argument_int16", BytecodeContext::fetch_argument_int16 as *const u8);
-        builder.symbol("fetch_argument_int32", BytecodeContext::fetch_argument_int32 as *const u8);
-        builder.symbol("fetch_argument_int64", BytecodeContext::fetch_argument_int64 as *const u8);
-        builder.symbol("fetch_argument_object", BytecodeContext::fetch_argument_object as *const u8);
-        builder.symbol("fetch_argument_float32", BytecodeContext::fetch_argument_float32 as *const u8);
-        builder.symbol("fetch_argument_float64", BytecodeContext::fetch_argument_float64 as *const u8);
-        builder.symbol("fetch_return_int8", BytecodeContext::fetch_return_int8 as *const u8);
-        builder.symbol("fetch_return_int16", BytecodeContext::fetch_return_int16 as *const u8);
-        builder.symbol("fetch_return_int32", BytecodeContext::fetch_return_int32 as *const u8);
-        builder.symbol("fetch_return_int64", BytecodeContext::fetch_return_int64 as *const u8);
-        builder.symbol("fetch_return_object", BytecodeContext::fetch_return_object as *const u8);
-        builder.symbol("fetch_return_float32", BytecodeContext::fetch_return_float32 as *const u8);
-        builder.symbol("fetch_return_float64", BytecodeContext::fetch_return_float64 as *const u8);
-        builder.symbol("interned_string_init", super::core::interned_string_init as *const u8);
+        for (name, pointer) in runtime_symbols() {
+            builder.symbol(name, pointer);
+        }
         let module = JITModule::new(builder);
 
         Self {
@@ -131,31 +189,31 @@ impl Default for JITController {
 
 impl JITController {
 
-    pub fn jit_thread(recv: Receiver<MethodName>) {
+    pub fn jit_thread(recv: Receiver<(MethodName, Tier)>) {
         loop {
-            let result = match recv.recv() {
-                Ok(method) => method,
+            let (result, tier) = match recv.recv() {
+                Ok(request) => request,
                 Err(_) => break,
             };
             //println!("doing jit");
 
             match result {
                 MethodName::StaticMethod { class_symbol, method_name } => {
-                    Runtime::jit_static_method(class_symbol, method_name);
+                    Runtime::jit_static_method(class_symbol, method_name, tier);
                 }
                 MethodName::VirtualMethod {
                     object_class_symbol,
                     class_symbol,
                     method_name
                 } => {
-                    Runtime::jit_virtual_method(object_class_symbol, class_symbol, method_name);
+                    Runtime::jit_virtual_method(object_class_symbol, class_symbol, method_name, tier);
                 }
                 MethodName::InterfaceMethod {
                     class_symbol, 
                     interface_symbol, 
                     method_name
                 } => {
-                    Runtime::jit_interface_method(class_symbol, interface_symbol, method_name);
+                    Runtime::jit_interface_method(class_symbol, interface_symbol, method_name, tier);
                 }
             }
             //println!("done");
@@ -201,6 +259,10 @@ impl JITController {
         self.module.declare_function(name, Linkage::Export, signature)
     }
 
+    pub fn declare_anonymous_function(&mut self, signature: &Signature) -> ModuleResult<FuncId> {
+        self.module.declare_anonymous_function(signature)
+    }
+
     pub fn new_context(&self) -> codegen::Context {
         self.module.make_context()
     }
@@ -211,6 +273,107 @@ unsafe impl Send for JITController {}
 unsafe impl Sync for JITController {}
 
 
+/// Names the compiled code of a method for debuggers and profilers.
+/// Method names already include their class, so only interface methods need the implementing class added.
+pub fn register_debug_info(
+    method_name: MethodName,
+    function: &Function,
+    code: *const (),
+    size: usize,
+    bytecode_ranges: &[(usize, usize, usize)],
+) {
+    let method = Runtime::get_string(method_name.method_symbol());
+    let name = match method_name {
+        MethodName::InterfaceMethod { class_symbol, .. } => match method.rsplit_once("::") {
+            Some((interface, method)) => format!("<{} as {interface}>::{method}", Runtime::get_class_name(class_symbol)),
+            None => format!("{}::{method}", Runtime::get_class_name(class_symbol)),
+        },
+        _ => method.to_string(),
+    };
+
+    let mut ranges = bytecode_ranges.to_vec();
+    ranges.sort_by_key(|(start, _, _)| *start);
+    let lines = ranges.into_iter()
+        .filter_map(|(start, _, index)| {
+            let location = function.find_location(index)?;
+            Some(SourceLine {
+                address: start,
+                file: Runtime::get_string(location.file),
+                line: location.line,
+                column: location.column,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    rowan_register_debug::register_name(&name, code, size, &lines);
+}
+
+/// The most instructions a method can have and still be inlined
+const MAX_INLINE_SIZE: usize = 24;
+
+/// Whether a method is small and simple enough to be inlined.
+/// It has to run straight through to its return without calling anything, branching or touching exceptions.
+/// Whether the method has a tail call, which compiled code hands back to the runtime instead of making
+fn makes_tail_calls(function: &Function) -> bool {
+    function.bytecode.iter().any(|bytecode| matches!(
+        bytecode,
+        Bytecode::InvokeVirtTail(..) | Bytecode::InvokeStaticTail(..) | Bytecode::InvokeInterfaceTail(..)
+    ))
+}
+
+fn can_inline(function: &Function) -> bool {
+    let Some((last, body)) = function.bytecode.split_last() else {
+        return false;
+    };
+    function.bytecode.len() <= MAX_INLINE_SIZE
+        && matches!(last, Bytecode::Return | Bytecode::ReturnVoid)
+        && body.iter().enumerate().all(|(index, bytecode)| match bytecode {
+            Bytecode::StartBlock(_) => index == 0,
+            Bytecode::Nop | Bytecode::Breakpoint |
+            Bytecode::LoadU8(_) | Bytecode::LoadU16(_) | Bytecode::LoadU32(_) | Bytecode::LoadU64(_) |
+            Bytecode::LoadI8(_) | Bytecode::LoadI16(_) | Bytecode::LoadI32(_) | Bytecode::LoadI64(_) |
+            Bytecode::LoadF32(_) | Bytecode::LoadF64(_) | Bytecode::LoadSymbol(_) |
+            Bytecode::Pop | Bytecode::Dup | Bytecode::Swap | Bytecode::StoreLocal(_) | Bytecode::LoadLocal(_) |
+            Bytecode::AddInt | Bytecode::SubInt | Bytecode::MulInt |
+            Bytecode::AddFloat | Bytecode::SubFloat | Bytecode::MulFloat | Bytecode::DivFloat | Bytecode::ModFloat |
+            Bytecode::SatAddIntUnsigned | Bytecode::SatSubIntUnsigned |
+            Bytecode::And | Bytecode::Or | Bytecode::Xor | Bytecode::Not |
+            Bytecode::Shl | Bytecode::AShr | Bytecode::LShr | Bytecode::Neg |
+            Bytecode::EqualSigned | Bytecode::NotEqualSigned | Bytecode::EqualUnsigned | Bytecode::NotEqualUnsigned |
+            Bytecode::GreaterSigned | Bytecode::LessSigned | Bytecode::GreaterOrEqualSigned | Bytecode::LessOrEqualSigned |
+            Bytecode::GreaterUnsigned | Bytecode::LessUnsigned | Bytecode::GreaterOrEqualUnsigned | Bytecode::LessOrEqualUnsigned |
+            Bytecode::EqualFloat | Bytecode::NotEqualFloat | Bytecode::GreaterFloat | Bytecode::LessFloat |
+            Bytecode::GreaterOrEqualFloat | Bytecode::LessOrEqualFloat |
+            Bytecode::Convert(_) | Bytecode::BinaryConvert(_) |
+            Bytecode::GetField(..) | Bytecode::SetField(..) | Bytecode::IsA(_) | Bytecode::GetStrRef(_) => true,
+            _ => false,
+        })
+}
+
+/// The size of a function that was just defined, along with its stack maps and the bytecode its instructions came from.
+/// Everything is relative to the start of the function until it is placed.
+pub struct CompiledFunction {
+    pub size: usize,
+    pub object_locations: Vec<(usize, Vec<u32>)>,
+    pub bytecode_ranges: Vec<(usize, usize, usize)>,
+}
+
+impl CompiledFunction {
+    /// Moves the stack maps and bytecode ranges to the native addresses of the code
+    pub fn place(self, code: *const ()) -> (Vec<(usize, Vec<u32>)>, Vec<(usize, usize, usize)>) {
+        let object_locations = self.object_locations.into_iter()
+            .map(|(offset, objects)| {
+                //println!("offset: {offset:x}");
+                (offset + code as usize, objects)
+            })
+            .collect();
+        let bytecode_ranges = self.bytecode_ranges.into_iter()
+            .map(|(start, end, index)| (start + code as usize, end + code as usize, index))
+            .collect();
+        (object_locations, bytecode_ranges)
+    }
+}
+
 pub struct JITCompiler {
     builder_context: FunctionBuilderContext,
     context: codegen::Context,
@@ -224,29 +387,161 @@ impl JITCompiler {
         }
     }
 
+    /// The signature of the function that was last translated
+    pub fn signature(&self) -> &Signature {
+        &self.context.func.signature
+    }
+
     pub fn compile(
         &mut self,
+        method_name: MethodName,
+        tier: Tier,
         function: &Function,
         module: &mut JITModule,
     ) -> Result<(), String> {
-
+        if let Tier::Loop(block) = tier {
+            return self.compile_loop_entry(method_name, block, function, module);
+        }
 
         let bytecode = function.bytecode.as_ref();
-        let id = {
+        // The optimized code gets a function of its own since the baseline code may still be running.
+        // That code never goes away, so its stack maps and bytecode ranges are kept for the garbage collector and stack traces.
+        let (id, old_object_locations, old_bytecode_ranges) = {
             let value = &*function.value.lock().unwrap();
-            match value {
-                FunctionValue::Bytecode(id) => {
-                    *id
+            match (value, tier) {
+                (FunctionValue::Bytecode(id), Tier::Baseline) => {
+                    (Some(*id), HashMap::new(), Vec::new())
+                }
+                (FunctionValue::Compiled(_, object_locations, bytecode_ranges), Tier::Optimized) => {
+                    (None, object_locations.clone(), bytecode_ranges.to_vec())
                 }
-                _ => unreachable!("can compile only bytecode functions"),
+                _ => unreachable!("can compile only bytecode functions or optimize compiled ones"),
             }
         };
 
         trace!("[Translating]");
-        self.translate(&function.arguments, &function.return_type, &bytecode, module)?;
+        self.translate(
+            method_name,
+            &function.arguments,
+            &function.return_type,
+            &bytecode,
+            &function.inline_caches,
+            tier == Tier::Optimized,
+            None,
+            module,
+        )?;
+
+        let id = match id {
+            Some(id) => id,
+            None => module.declare_anonymous_function(&self.context.func.signature)
+                .map_err(|e| format!("{}", e))?,
+        };
+        let (code, size, locations, bytecode_ranges) = self.finalize(id, module)?;
+
+        let mut object_locations = old_object_locations;
+        object_locations.extend(locations);
+        //println!("object locations: {:#x?}", object_locations);
+
+        register_debug_info(method_name, function, code, size, &bytecode_ranges);
+        let bytecode_ranges = bytecode_ranges.into_iter()
+            .chain(old_bytecode_ranges)
+            .collect::<Vec<_>>()
+            .into_boxed_slice();
+
+        let new_function_value = FunctionValue::Compiled(code, object_locations, bytecode_ranges);
 
+        *function.value.lock().unwrap() = new_function_value;
+        let tier = match tier {
+            Tier::Baseline => TIER_BASELINE,
+            Tier::Optimized => TIER_OPTIMIZED,
+            Tier::Loop(_) => unreachable!("loop entries are compiled on their own"),
+        };
+        function.tier.store(tier, Ordering::Relaxed);
+
+        Ok(())
+    }
 
+    /// Compiles code that enters the method at the loop starting at `block`, taking the variables of the interpreted frame it replaces.
+    /// A loop that can't be entered like that is marked as failed, which leaves its frames in the interpreter.
+    fn compile_loop_entry(
+        &mut self,
+        method_name: MethodName,
+        block: usize,
+        function: &Function,
+        module: &mut JITModule,
+    ) -> Result<(), String> {
+        let Some(locals) = function.loops.requested_locals(block) else {
+            return Ok(());
+        };
+        // The stack maps and bytecode ranges of the entry are kept with the ones of the compiled method
+        if !function.value.lock().unwrap().is_compiled() {
+            self.compile(method_name, Tier::Baseline, function, module)?;
+        }
 
+        trace!("[Translating loop entry]");
+        let translated = self.translate(
+            method_name,
+            &function.arguments,
+            &function.return_type,
+            &function.bytecode,
+            &function.inline_caches,
+            false,
+            Some((block, &locals)),
+            module,
+        );
+        if let Err(e) = translated {
+            trace!("can't enter the loop at block {block}: {e}");
+            module.clear_context(&mut self.context);
+            function.loops.set_entry(block, LoopEntry::Failed);
+            return Ok(());
+        }
+
+        let id = module.declare_anonymous_function(&self.context.func.signature)
+            .map_err(|e| format!("{}", e))?;
+        let (code, size, locations, bytecode_ranges) = self.finalize(id, module)?;
+        register_debug_info(method_name, function, code, size, &bytecode_ranges);
+
+        let mut value = function.value.lock().unwrap();
+        let FunctionValue::Compiled(_, object_locations, old_bytecode_ranges) = &mut *value else {
+            unreachable!("the method was compiled before its loop");
+        };
+        object_locations.extend(locations);
+        *old_bytecode_ranges = old_bytecode_ranges.iter()
+            .cloned()
+            .chain(bytecode_ranges)
+            .collect();
+        drop(value);
+
+        function.loops.set_entry(block, LoopEntry::Compiled {
+            code,
+            locals,
+            return_type: function.return_type,
+        });
+
+        Ok(())
+    }
+
+    /// Defines the translated function in the JIT and finalizes it.
+    /// Returns its code and size along with its stack maps and the bytecode its instructions came from, by native address.
+    fn finalize(
+        &mut self,
+        id: FuncId,
+        module: &mut JITModule,
+    ) -> Result<(*const (), usize, Vec<(usize, Vec<u32>)>, Vec<(usize, usize, usize)>), String> {
+        let compiled = self.define(id, module)?;
+        module.finalize_definitions().unwrap();
+
+        let code = module.get_finalized_function(id) as *const ();
+        rowan_unwind::register(code, compiled.size);
+        //println!("code: {:x}", code as usize);
+        let size = compiled.size;
+        let (object_locations, bytecode_ranges) = compiled.place(code);
+
+        Ok((code, size, object_locations, bytecode_ranges))
+    }
+
+    /// Defines the translated function in the module, which can be the JIT or an object file
+    pub fn define(&mut self, id: FuncId, module: &mut dyn Module) -> Result<CompiledFunction, String> {
         //println!("[Defining]");
         module
             .define_function(id, &mut self.context)
@@ -274,45 +569,46 @@ impl JITCompiler {
             let objects = map.entries()
                 .map(|(_, offset)| offset)
                 .collect::<Vec<_>>();
-            object_locations.push((*location, objects));
+            object_locations.push((*location as usize, objects));
         }
-        let locations = object_locations;
-        let size = compiled_code.buffer.total_size();
+        let bytecode_ranges = compiled_code.buffer.get_srclocs_sorted()
+            .iter()
+            .filter(|srcloc| !srcloc.loc.is_default())
+            .map(|srcloc| (srcloc.start as usize, srcloc.end as usize, srcloc.loc.bits() as usize))
+            .collect::<Vec<_>>();
+        let size = compiled_code.buffer.total_size() as usize;
         trace!("resulting function:\n{}", self.context.func);
         module.clear_context(&mut self.context);
 
-        module.finalize_definitions().unwrap();
-        
-        let code = module.get_finalized_function(id) as *const ();
-        rowan_unwind::register(code, size as usize);
-        //println!("code: {:x}", code as usize);
-        let mut object_locations = HashMap::new();
-        locations.into_iter()
-            .for_each(|(offset, objects)| {
-                //println!("offset: {offset:x}");
-                object_locations.insert(offset as usize + code as usize, objects);
-            });
-
-        //println!("object locations: {:#x?}", object_locations);
-
-        let new_function_value = FunctionValue::Compiled(code, object_locations);
-
-        *function.value.lock().unwrap() = new_function_value;
-
-        Ok(())
+        Ok(CompiledFunction {
+            size,
+            object_locations,
+            bytecode_ranges,
+        })
     }
 
     pub fn translate(
         &mut self,
+        method_name: MethodName,
         arg_types: &[runtime::class::TypeTag],
         return_type: &runtime::class::TypeTag,
         bytecode: &[Bytecode],
-        module: &mut JITModule
+        inline_caches: &InlineCaches,
+        optimize: bool,
+        loop_entry: Option<(usize, &[Option<runtime::class::TypeTag>])>,
+        module: &mut dyn Module
     ) -> Result<(), String> {
 
         self.context.func.signature.params.push(AbiParam::new(types::I64));
 
-        for ty in arg_types {
+        // Code entering a loop gets a pointer to the variables of the interpreted frame instead of the arguments
+        let parameter_types = if loop_entry.is_some() {
+            self.context.func.signature.params.push(AbiParam::new(types::I64));
+            &[]
+        } else {
+            arg_types
+        };
+        for ty in parameter_types {
             let ty = match ty {
                 runtime::class::TypeTag::U8 | runtime::class::TypeTag::I8 => ir::types::I8,
                 runtime::class::TypeTag::U16 | runtime::class::TypeTag::I16 => ir::types::I16,
@@ -343,8 +639,12 @@ impl JITCompiler {
         }
 
         let mut function_translator = FunctionTranslator::new(
+            method_name,
             arg_types,
             return_type.clone(),
+            inline_caches,
+            optimize,
+            loop_entry,
             &mut self.context,
             &mut self.builder_context,
         );
@@ -362,22 +662,43 @@ impl JITCompiler {
 
 
 pub struct FunctionTranslator<'a> {
+    /// The method being translated, which lets tail calls to itself become loops
+    method_name: MethodName,
     return_type: runtime::class::TypeTag,
     builder: FunctionBuilder<'a>,
     context_var: Variable,
-    call_args: [Option<(ir::Type, bool)>; 256],
+    /// The variables holding the arguments the function was called with
+    parameters: Vec<Variable>,
+    call_args: [Option<(Value, ir::Type, bool)>; 256],
     variables: [Option<(Variable, ir::Type, bool)>; 256],
     current_variable: usize,
     stack: Vec<Option<(Value, ir::Type, bool)>>,
     blocks: Vec<Block>,
     current_block: usize,
-    block_arg_types: HashMap<usize, Vec<ir::Type>>,
+    /// The types of the stack values that the jumps to a block pass it and whether they are objects
+    block_arg_types: HashMap<usize, Vec<(ir::Type, bool)>>,
+    /// The blocks that catch exceptions, which start with the caught exception on the stack
+    handler_blocks: Vec<usize>,
+    /// Set after a trap until the next block starts
+    unreachable: bool,
+    inline_caches: &'a InlineCaches,
+    /// Whether calls with inline caches should be inlined or called directly
+    optimize: bool,
+    /// The index of the call that the bytecode being translated was inlined into and the block its return jumps to
+    inlined_at: Option<(usize, Block)>,
+    /// For code entering a loop, the bytecode block of the loop, the block that enters it,
+    /// the pointer to the variables of the interpreted frame and their types
+    loop_entry: Option<(usize, Block, Value, Vec<Option<ir::Type>>)>,
 }
 
 impl FunctionTranslator<'_> {
     pub fn new<'a>(
+        method_name: MethodName,
         arg_types: &[runtime::class::TypeTag],
         return_type: runtime::class::TypeTag,
+        inline_caches: &'a InlineCaches,
+        optimize: bool,
+        loop_entry: Option<(usize, &[Option<runtime::class::TypeTag>])>,
         context: &'a mut codegen::Context,
         builder_context: &'a mut FunctionBuilderContext,
     ) -> FunctionTranslator<'a> {
@@ -390,20 +711,22 @@ impl FunctionTranslator<'_> {
         builder.switch_to_block(entry_block);
         builder.seal_block(entry_block);
         let start_block = builder.create_block();
-        builder.ins().jump(start_block, &[]);
+        // Code entering a loop starts there instead, see `fill_loop_entry`
+        let loop_entry_block = loop_entry.map(|_| builder.create_block());
+        builder.ins().jump(loop_entry_block.unwrap_or(start_block), &[]);
 
         let mut block_arg_types = HashMap::new();
 
         let mut variables = [None; 256];
+        let mut parameters = Vec::new();
         let mut current_variable = 1;
-        let mut start_block_args = Vec::new();
 
         let context_var = Variable::new(0);
         builder.declare_var(context_var, types::I64);
         builder.def_var(context_var, builder.block_params(entry_block)[0]);
 
         let block_params = builder.block_params(entry_block).iter().cloned().collect::<Vec<_>>();
-        for (i, (value, ty)) in block_params.iter().skip(1).zip(arg_types.iter()).enumerate() {
+        for (i, ty) in arg_types.iter().enumerate() {
             let (ty, is_object) = match ty {
                 runtime::class::TypeTag::U8 | runtime::class::TypeTag::I8 => (ir::types::I8, false),
                 runtime::class::TypeTag::U16 | runtime::class::TypeTag::I16 => (ir::types::I16, false),
@@ -416,23 +739,43 @@ impl FunctionTranslator<'_> {
                 runtime::class::TypeTag::Object => (ir::types::I64, true),
                 runtime::class::TypeTag::Sized(_) => unreachable!("Native Members are not ABI Compatible"),
             };
-            start_block_args.push(ty.clone());
             let var = Variable::new(i + 1);
             builder.declare_var(var, ty);
-            builder.def_var(var, *value);
+            if loop_entry.is_none() {
+                builder.def_var(var, block_params[i + 1]);
+            }
             if is_object {
                 builder.declare_var_needs_stack_map(var);
             }
             variables[i] = Some((var, ty, is_object));
+            parameters.push(var);
             current_variable += 1;
         }
 
-        block_arg_types.insert(0, start_block_args);
+        // The start of the function is only entered with an empty stack
+        block_arg_types.insert(0, Vec::new());
+
+        let loop_entry = loop_entry.zip(loop_entry_block)
+            .map(|((block, locals), entry_block)| {
+                let locals_types = locals.iter()
+                    .map(|tag| tag.map(|tag| match tag {
+                        runtime::class::TypeTag::U8 | runtime::class::TypeTag::I8 => ir::types::I8,
+                        runtime::class::TypeTag::U16 | runtime::class::TypeTag::I16 => ir::types::I16,
+                        runtime::class::TypeTag::U32 | runtime::class::TypeTag::I32 => ir::types::I32,
+                        runtime::class::TypeTag::F32 => ir::types::F32,
+                        runtime::class::TypeTag::F64 => ir::types::F64,
+                        _ => ir::types::I64,
+                    }))
+                    .collect();
+                (block, entry_block, block_params[1], locals_types)
+            });
 
         FunctionTranslator {
+            method_name,
             return_type,
             builder,
             context_var,
+            parameters,
             call_args: [None; 256],
             variables,
             current_variable,
@@ -440,6 +783,12 @@ impl FunctionTranslator<'_> {
             blocks: vec![start_block],
             current_block: 0,
             block_arg_types,
+            handler_blocks: Vec::new(),
+            unreachable: false,
+            inline_caches,
+            optimize,
+            inlined_at: None,
+            loop_entry,
         }
     }
 
@@ -447,14 +796,9 @@ impl FunctionTranslator<'_> {
         self.blocks.push(self.builder.create_block());
     }
 
-    pub fn set_argument(&mut self, module: &mut JITModule, pos: u8, value: Value, ty: ir::Type, is_object: bool) {
+    pub fn set_argument(&mut self, module: &mut dyn Module, pos: u8, value: Value, ty: ir::Type, is_object: bool) {
         // println!("setting argument");
-        if let Some((arg_ty, arg_is_object)) = &mut self.call_args[pos as usize] {
-            *arg_ty = ty;
-            *arg_is_object = is_object;
-        } else {
-            self.call_args[pos as usize] = Some((ty, is_object));
-        }
+        self.call_args[pos as usize] = Some((value, ty, is_object));
 
         let name = match ty {
             types::I8 => "store_argument_int8",
@@ -495,14 +839,15 @@ impl FunctionTranslator<'_> {
     pub fn set_var(&mut self, pos: u8, value: Value, ty: ir::Type, is_object: bool) {
         //println!("setting var");
         if let Some((var, var_ty, var_is_object)) = &mut self.variables[pos as usize] {
-            if var_ty != &ty {
+            // A slot can be reused by a variable of another type once the first one goes out of scope
+            if var_ty != &ty || *var_is_object != is_object {
                 //println!("duplicate variable slot");
                 self.current_variable += 1;
                 let new_arg = Variable::new(self.current_variable);
+                self.builder.declare_var(new_arg, ty);
                 if is_object {
                     self.builder.declare_var_needs_stack_map(new_arg);
                 }
-                self.builder.declare_var(new_arg, ty);
                 self.builder.def_var(new_arg, value);
                 *var = new_arg;
                 *var_ty = ty;
@@ -573,36 +918,123 @@ impl FunctionTranslator<'_> {
         output
     }
 
-    pub fn get_args_as_vec_type(&self) -> Vec<Type> {
-        let mut output = Vec::new();
-        let mut iterator = self.stack.iter().flatten();
-        while let Some((_, ty,_)) = iterator.next() {
-            output.push(*ty);
+    /// Gets the block that a jump to `block_index` goes to along with the values on the stack to pass it.
+    /// The first jump to a block gives it a parameter for each value, like when `?` is part of a larger expression,
+    /// and every other jump to it has to leave values of the same types on the stack.
+    fn jump_target(&mut self, block_index: usize) -> Result<(Block, Vec<BlockArg>), String> {
+        while self.blocks.len() <= block_index + 1 {
+            self.add_block();
         }
-        output
+
+        let block = self.blocks[block_index];
+        let stack_types = self.stack.iter()
+            .flatten()
+            .map(|(_, ty, is_object)| (*ty, *is_object))
+            .collect::<Vec<_>>();
+        match self.block_arg_types.get(&block_index) {
+            Some(block_args) if *block_args != stack_types => {
+                return Err(format!("block {block_index} is jumped to with a stack of {stack_types:?} but expects {block_args:?}"));
+            }
+            Some(_) => {}
+            None => {
+                for (ty, _) in stack_types.iter() {
+                    self.builder.append_block_param(block, *ty);
+                }
+                self.block_arg_types.insert(block_index, stack_types);
+            }
+        }
+
+        let args = self.get_args_as_vec()
+            .into_iter()
+            .map(BlockArg::Value)
+            .collect();
+        Ok((block, args))
     }
 
-    fn restore_stack(&mut self, _block_index: usize, _stack: &[Value]) {
-        return;
-        //println!("restoring");
-        /*let stack_iter = stack.iter();
-        for ((value, arg_value), ty) in self.stack.iter_mut().zip(stack_iter).zip(self.block_arg_types.get(&block_index).unwrap()) {
-            *value = Some((*arg_value, *ty,));
+    /// Replaces the stack left by the block before with the values that the jumps to this block passed it.
+    /// A block that nothing has jumped to yet starts with an empty stack, so later jumps to it can't pass any values.
+    fn restore_stack(&mut self, block_index: usize, stack: &[Value]) -> Result<(), String> {
+        let block_args = self.block_arg_types.entry(block_index)
+            .or_default()
+            .clone();
+        if block_args.len() != stack.len() {
+            return Err(format!("block {block_index} has {} parameters but expects {} values on the stack", stack.len(), block_args.len()));
         }
-        let remaining = stack.iter()
-            .zip(self.block_arg_types.get(&block_index).unwrap())
-            .map(|(arg_value, ty)| Some((*arg_value, *ty)));
-        self.stack.extend(remaining.skip(self.stack.len()));*/
 
+        self.stack = stack.iter()
+            .zip(block_args)
+            .map(|(value, (ty, is_object))| {
+                if is_object {
+                    self.builder.declare_value_needs_stack_map(*value);
+                }
+                Some((*value, ty, is_object))
+            })
+            .collect();
+        Ok(())
     }
 
 
-    pub fn translate(&mut self, bytecode: &[Bytecode], module: &mut JITModule) -> Result<(), String> {
+    pub fn translate(&mut self, bytecode: &[Bytecode], module: &mut dyn Module) -> Result<(), String> {
 
         //println!("\nBytecode: {:#?}", bytecode);
 
+        // Handlers are jumped to from wherever an exception can be thrown and some jumps are skipped as unreachable,
+        // so every block has to exist up front
+        let mut current_block = 0;
+        let mut last_block = 0;
         for bytecode in bytecode.iter() {
+            match bytecode {
+                Bytecode::StartBlock(index) => {
+                    current_block = *index as usize;
+                    last_block = last_block.max(current_block);
+                }
+                Bytecode::RegisterException(_, offset) => {
+                    self.handler_blocks.push((current_block as i64 + *offset) as usize);
+                }
+                _ => {}
+            }
+        }
+        self.handler_blocks.sort();
+        self.handler_blocks.dedup();
+        // Handlers are jumped to without any values, the caught exception is pushed once they start
+        for handler in self.handler_blocks.iter() {
+            self.block_arg_types.insert(*handler, Vec::new());
+        }
+        while self.blocks.len() <= last_block + 1 {
+            self.add_block();
+        }
+
+        self.translate_instructions(bytecode, module)?;
+        if let Some((block, ..)) = self.loop_entry {
+            return Err(format!("block {block} was never reached"));
+        }
+
+        // The block after a try that always returns is never reached, but it still needs an end
+        if let Some(block) = self.builder.current_block() {
+            let is_filled = self.builder.func.layout.last_inst(block)
+                .is_some_and(|inst| self.builder.func.dfg.insts[inst].opcode().is_terminator());
+            if !is_filled {
+                self.trap();
+            }
+        }
+        Ok(())
+    }
+
+    /// Translates each instruction of the bytecode into the current function
+    fn translate_instructions(&mut self, bytecode: &[Bytecode], module: &mut dyn Module) -> Result<(), String> {
+        for (index, bytecode) in bytecode.iter().enumerate() {
+            // Nothing after a trap can run, so translation picks up again at the next block
+            if self.unreachable && !matches!(bytecode, Bytecode::StartBlock(_)) {
+                continue;
+            }
             //println!("{:?}", bytecode);
+            // The source location lets us map native addresses back to bytecode for stack traces.
+            // Inlined code is reported as the call it was inlined into.
+            let location = match self.inlined_at {
+                Some((call_index, _)) => call_index,
+                None => index,
+            };
+            self.builder.set_srcloc(ir::SourceLoc::new(location as u32));
             match bytecode {
                 Bytecode::Nop | Bytecode::Breakpoint => {}
                 Bytecode::LoadU8(value) => {
@@ -663,8 +1095,13 @@ impl FunctionTranslator<'_> {
                     self.set_var(*index, value, ty, is_object);
                 }
                 Bytecode::LoadLocal(index) => {
-                    let (value, ty, is_object) = self.get_var(*index);
-                    self.push(value, ty, is_object);
+                    // Finally blocks load locals that are only set on the paths that use them
+                    if self.variables[*index as usize].is_none() {
+                        self.trap();
+                    } else {
+                        let (value, ty, is_object) = self.get_var(*index);
+                        self.push(value, ty, is_object);
+                    }
                 }
                 Bytecode::StoreArgument(index) => {
                     let (value, ty, is_object) = self.pop();
@@ -908,7 +1345,16 @@ impl FunctionTranslator<'_> {
                     let value_out = self.builder.ins().fcmp(FloatCC::UnorderedOrLessThanOrEqual, value_lhs, value_rhs);
                     self.push(value_out, ir::types::I8, false);
                 }
-                // TODO: implement conversions
+                Bytecode::Convert(tag) => {
+                    let (value, ty, _) = self.pop();
+                    let (value, ty) = self.convert(value, ty, tag)?;
+                    self.push(value, ty, false);
+                }
+                Bytecode::BinaryConvert(tag) => {
+                    let (value, ty, _) = self.pop();
+                    let (value, ty) = self.binary_convert(value, ty, tag)?;
+                    self.push(value, ty, false);
+                }
                 Bytecode::CreateArray(tag) => {
                     // println!("create array");
                     let new_object_id = if let Some(id) = module.get_name("new_object") {
@@ -929,25 +1375,25 @@ impl FunctionTranslator<'_> {
 
                     let array_symbol = match tag {
                         TypeTag::U8 | TypeTag::I8 => {
-                            self.builder.ins().iconst(ir::types::I64, Runtime::get_class_symbol("Array8") as i64)
+                            self.builder.ins().iconst(ir::types::I64, Runtime::get_class_symbol("core::Array8") as i64)
                         }
                         TypeTag::U16 | TypeTag::I16 => {
-                            self.builder.ins().iconst(ir::types::I64, Runtime::get_class_symbol("Array16") as i64)
+                            self.builder.ins().iconst(ir::types::I64, Runtime::get_class_symbol("core::Array16") as i64)
                         }
                         TypeTag::U32 | TypeTag::I32 => {
-                            self.builder.ins().iconst(ir::types::I64, Runtime::get_class_symbol("Array32") as i64)
+                            self.builder.ins().iconst(ir::types::I64, Runtime::get_class_symbol("core::Array32") as i64)
                         }
                         TypeTag::U64 | TypeTag::I64 => {
-                            self.builder.ins().iconst(ir::types::I64, Runtime::get_class_symbol("Array64") as i64)
+                            self.builder.ins().iconst(ir::types::I64, Runtime::get_class_symbol("core::Array64") as i64)
                         }
                         TypeTag::Object | TypeTag::Str | TypeTag::Void => {
-                            self.builder.ins().iconst(ir::types::I64, Runtime::get_class_symbol("Arrayobject") as i64)
+                            self.builder.ins().iconst(ir::types::I64, Runtime::get_class_symbol("core::Arrayobject") as i64)
                         }
                         TypeTag::F32 => {
-                            self.builder.ins().iconst(ir::types::I64, Runtime::get_class_symbol("Arrayf32") as i64)
+                            self.builder.ins().iconst(ir::types::I64, Runtime::get_class_symbol("core::Arrayf32") as i64)
                         }
                         TypeTag::F64 => {
-                            self.builder.ins().iconst(ir::types::I64, Runtime::get_class_symbol("Arrayf64") as i64)
+                            self.builder.ins().iconst(ir::types::I64, Runtime::get_class_symbol("core::Arrayf64") as i64)
                         }
                         TypeTag::Native => unreachable!("Native Type not ABI compatible"),
                     };
@@ -1275,144 +1721,56 @@ impl FunctionTranslator<'_> {
                     self.create_bail_block(module, None, &[]);
                 }
                 Bytecode::InvokeVirt(class_name, method_name) => {
-
-                    let mut call_virt_func = module.make_signature();
-                    call_virt_func.params.push(AbiParam::new(cranelift::codegen::ir::types::I64));
-                    call_virt_func.params.push(AbiParam::new(cranelift::codegen::ir::types::I64));
-                    call_virt_func.params.push(AbiParam::new(cranelift::codegen::ir::types::I64));
-
-                    let fn_id = module.declare_function("call_virtual_function", Linkage::Import, &call_virt_func).unwrap();
-
-                    let call_virt_func_func = module.declare_func_in_func(fn_id, self.builder.func);
-                    
-                    let class_name_value = self.builder
-                        .ins()
-                        .iconst(cranelift::codegen::ir::types::I64, i64::from(i64::from_le_bytes(class_name.to_le_bytes())));
-                    let method_name_value = self.builder
-                        .ins()
-                        .iconst(cranelift::codegen::ir::types::I64, i64::from(i64::from_le_bytes(method_name.to_le_bytes())));
-
-                    let context_value = self.builder.use_var(self.context_var);
-
-                    let _ = self.builder
-                        .ins()
-                        .call(call_virt_func_func, &[
-                            context_value,
-                            class_name_value,
-                            method_name_value,
-                        ]);
-
-
                     let (sig, returns_object) = Runtime::get_virtual_method_signature(*class_name as Symbol, *method_name as Symbol);
-                    if !sig.returns.is_empty() {
-
-                        let name = match sig.returns[0].value_type {
-                            types::I8 => "fetch_return_int8",
-                            types::I16 => "fetch_return_int16",
-                            types::I32 => "fetch_return_int32",
-                            types::I64 if returns_object => "fetch_return_object",
-                            types::I64 if !returns_object => "fetch_return_int64",
-                            types::F32 => "fetch_return_float32",
-                            types::F64 => "fetch_return_float64",
-                            _ => unreachable!()
-                        };
-
-                        let fetch_return = if let Some(id) = module.get_name(name) {
-                            match id {
-                                FuncOrDataId::Func(id) => id,
-                                _ => unreachable!("cannot create array object from data id"),
-                            }
-                        } else {
-                            let mut fetch_return = module.make_signature();
-                            fetch_return.params.push(AbiParam::new(cranelift::codegen::ir::types::I64));
-                            fetch_return.returns.push(sig.returns[0]);
-
-                            let fn_id = module.declare_function(name, Linkage::Import, &fetch_return).unwrap();
-                            fn_id
-                        };
-
-                        let fetch_return = module.declare_func_in_func(fetch_return, self.builder.func);
-
-                        let context_value = self.builder.use_var(self.context_var);
-
-                        let results = self.builder.ins()
-                            .call(fetch_return, &[context_value]);
-
-                        let value = self.builder.inst_results(results)[0];
-                        self.push(value , sig.returns[0].value_type, returns_object)
-                    }
-
-                    //let method_value = self.builder.inst_results(method_instructions)[0];
-                    //self.create_bail_block(module, Some(types::I64), &[BlockArg::Value(method_value)]);
+                    let inline_cache = self.inline_caches.get(&index);
+                    self.invoke_cached(module, index, "call_virtual_function", *class_name, *method_name, &sig, returns_object, inline_cache);
                 }
                 Bytecode::InvokeStatic(class_name, method_name) => {
-                    let mut call_static_func = module.make_signature();
-                    call_static_func.params.push(AbiParam::new(cranelift::codegen::ir::types::I64));
-                    call_static_func.params.push(AbiParam::new(cranelift::codegen::ir::types::I64));
-                    call_static_func.params.push(AbiParam::new(cranelift::codegen::ir::types::I64));
-
-                    let fn_id = module.declare_function("call_static_function", Linkage::Import, &call_static_func).unwrap();
-
-                    let call_static_func_func = module.declare_func_in_func(fn_id, self.builder.func);
-
-                    let class_name_value = self.builder
-                        .ins()
-                        .iconst(cranelift::codegen::ir::types::I64, i64::from(i64::from_le_bytes(class_name.to_le_bytes())));
-                    
-                    let method_name_value = self.builder
-                        .ins()
-                        .iconst(cranelift::codegen::ir::types::I64, i64::from(i64::from_le_bytes(method_name.to_le_bytes())));
-
-
-                    let context_value = self.builder.use_var(self.context_var);
-                    let _ = self.builder
-                        .ins()
-                        .call(call_static_func_func, &[
-                            context_value,
-                            class_name_value,
-                            method_name_value,
-                        ]);
-
                     let (sig, returns_object) = Runtime::get_static_method_signature(*class_name as Symbol, *method_name as Symbol);
-                    if !sig.returns.is_empty() {
-
-                        let name = match sig.returns[0].value_type {
-                            types::I8 => "fetch_return_int8",
-                            types::I16 => "fetch_return_int16",
-                            types::I32 => "fetch_return_int32",
-                            types::I64 if returns_object => "fetch_return_object",
-                            types::I64 if !returns_object => "fetch_return_int64",
-                            types::F32 => "fetch_return_float32",
-                            types::F64 => "fetch_return_float64",
-                            _ => unreachable!()
-                        };
-
-                        let fetch_return = if let Some(id) = module.get_name(name) {
-                            match id {
-                                FuncOrDataId::Func(id) => id,
-                                _ => unreachable!("cannot create array object from data id"),
-                            }
-                        } else {
-                            let mut fetch_return = module.make_signature();
-                            fetch_return.params.push(AbiParam::new(cranelift::codegen::ir::types::I64));
-                            fetch_return.returns.push(sig.returns[0]);
-
-                            let fn_id = module.declare_function(name, Linkage::Import, &fetch_return).unwrap();
-                            fn_id
-                        };
-
-                        let fetch_return = module.declare_func_in_func(fetch_return, self.builder.func);
-
-                        let context_value = self.builder.use_var(self.context_var);
-
-                        let results = self.builder.ins()
-                            .call(fetch_return, &[context_value]);
-
-                        let value = self.builder.inst_results(results)[0];
-                        self.push(value , sig.returns[0].value_type, returns_object)
+                    self.invoke(module, "call_static_function", *class_name, *method_name, &sig, returns_object, None);
+                }
+                Bytecode::InvokeInterface(interface_name, method_name) => {
+                    let (sig, returns_object) = Runtime::get_interface_method_signature(*interface_name as Symbol, *method_name as Symbol);
+                    let inline_cache = self.inline_caches.get(&index);
+                    self.invoke_cached(module, index, "call_interface_function", *interface_name, *method_name, &sig, returns_object, inline_cache);
+                }
+                Bytecode::InvokeVirtTail(class_name, method_name) => {
+                    let receiver_class = match self.method_name {
+                        MethodName::VirtualMethod { object_class_symbol, class_symbol, method_name: name }
+                            if class_symbol as u64 == *class_name && name as u64 == *method_name => Some(object_class_symbol),
+                        _ => None,
+                    };
+                    let inline_cache = self.inline_caches.get(&index);
+                    self.invoke_tail(module, receiver_class, "jit_tail_call_virtual", *class_name, *method_name, inline_cache);
+                }
+                Bytecode::InvokeStaticTail(class_name, method_name) => {
+                    let is_recursive = matches!(
+                        self.method_name,
+                        MethodName::StaticMethod { class_symbol, method_name: name }
+                            if class_symbol as u64 == *class_name && name as u64 == *method_name
+                    );
+                    if is_recursive {
+                        self.jump_to_start();
+                    } else {
+                        self.invoke_tail(module, None, "jit_tail_call_static", *class_name, *method_name, None);
                     }
-                    //let method_value = self.builder.inst_results(method_instructions)[0];
-                    //self.create_bail_block(module, Some(types::I64), &[BlockArg::Value(method_value)]);
+                }
+                Bytecode::InvokeInterfaceTail(interface_name, method_name) => {
+                    let receiver_class = match self.method_name {
+                        MethodName::InterfaceMethod { class_symbol, interface_symbol, method_name: name }
+                            if interface_symbol as u64 == *interface_name && name as u64 == *method_name => Some(class_symbol),
+                        _ => None,
+                    };
+                    let inline_cache = self.inline_caches.get(&index);
+                    self.invoke_tail(module, receiver_class, "jit_tail_call_interface", *interface_name, *method_name, inline_cache);
+                }
+                Bytecode::IsA(class_symbol) => {
+                    let is_a = self.import_function(module, "is_a", &[types::I64, types::I64], &[types::I8]);
+                    let (object, _, _) = self.pop();
+                    let class_value = self.builder.ins().iconst(types::I64, i64::from_le_bytes(class_symbol.to_le_bytes()));
+                    let result = self.builder.ins().call(is_a, &[object, class_value]);
+                    let value = self.builder.inst_results(result)[0];
+                    self.push(value, types::I8, false);
                 }
                 Bytecode::GetStaticMember(class_name, index, type_tag) => {
                     let mut is_object = false;
@@ -1563,17 +1921,57 @@ impl FunctionTranslator<'_> {
 
                     self.push(value, ir::types::I64, true);
                 }
+                // Finally blocks end with both kinds of return, but only the one that matches the signature can run
                 Bytecode::Return => {
                     let (return_value, _, _) = self.pop();
-                    self.builder.ins().return_(&[return_value]);
+                    if let Some((_, exit_block)) = self.inlined_at {
+                        self.builder.ins().jump(exit_block, &[BlockArg::Value(return_value)]);
+                    } else if let runtime::class::TypeTag::Void = self.return_type {
+                        self.trap();
+                    } else {
+                        self.builder.ins().return_(&[return_value]);
+                    }
                 }
                 Bytecode::ReturnVoid => {
-                    self.builder.ins().return_(&[]);
+                    if let Some((_, exit_block)) = self.inlined_at {
+                        self.builder.ins().jump(exit_block, &[]);
+                    } else if let runtime::class::TypeTag::Void = self.return_type {
+                        self.builder.ins().return_(&[]);
+                    } else {
+                        self.trap();
+                    }
+                }
+                Bytecode::RegisterException(class_symbol, offset) => {
+                    let register = self.import_function(module, "jit_register_exception", &[types::I64, types::I64, types::I64], &[]);
+                    let block = (self.current_block as i64 + *offset) as usize;
+                    let context_value = self.builder.use_var(self.context_var);
+                    let class_value = self.builder.ins().iconst(types::I64, i64::from_le_bytes(class_symbol.to_le_bytes()));
+                    let block_value = self.builder.ins().iconst(types::I64, block as i64);
+                    let _ = self.builder.ins().call(register, &[context_value, class_value, block_value]);
                 }
+                Bytecode::UnregisterException(class_symbol) => {
+                    let unregister = self.import_function(module, "jit_unregister_exception", &[types::I64, types::I64], &[]);
+                    let context_value = self.builder.use_var(self.context_var);
+                    let class_value = self.builder.ins().iconst(types::I64, i64::from_le_bytes(class_symbol.to_le_bytes()));
+                    let _ = self.builder.ins().call(unregister, &[context_value, class_value]);
+                }
+                Bytecode::Throw => {
+                    let throw = self.import_function(module, "jit_throw", &[types::I64, types::I64], &[]);
+                    let (exception, _, _) = self.pop();
+                    let context_value = self.builder.use_var(self.context_var);
+                    let _ = self.builder.ins().call(throw, &[context_value, exception]);
+                    self.unwind(module);
+                }
+                // Inlined methods are a single block that continues the block of the call
+                Bytecode::StartBlock(_) if self.inlined_at.is_some() => {}
                 Bytecode::StartBlock(index) => {
+                    if self.loop_entry.as_ref().is_some_and(|(block, ..)| *block == *index as usize) {
+                        self.fill_loop_entry()?;
+                    }
+                    self.unreachable = false;
                     let block= self.blocks[*index as usize];
                     let params = self.builder.block_params(block).to_vec();
-                    self.restore_stack(*index as usize, &params);
+                    self.restore_stack(*index as usize, &params)?;
                     self.builder.switch_to_block(block);
                     self.current_block = *index as usize;
 
@@ -1597,73 +1995,198 @@ impl FunctionTranslator<'_> {
                     let _ = self.builder.ins()
                         .call(check_gc, &[context_value]);
 
+                    if self.handler_blocks.contains(&(*index as usize)) {
+                        let take_exception = self.import_function(module, "jit_take_exception", &[types::I64], &[types::I64]);
+                        let context_value = self.builder.use_var(self.context_var);
+                        let result = self.builder.ins().call(take_exception, &[context_value]);
+                        let exception = self.builder.inst_results(result)[0];
+                        self.builder.declare_value_needs_stack_map(exception);
+                        self.push(exception, types::I64, true);
+                    }
                 }
                 Bytecode::Goto(offset) => {
                     let block = (self.current_block as i64 + *offset) as usize;
-
-                    while self.blocks.len() <= block + 1 {
-                        self.add_block();
-                    }
-
-                    let block_args = self.get_args_as_vec_type();
-                    self.block_arg_types.insert(block, block_args);
-
-                    let block_index = block;
-                    let block = &mut self.blocks[block];
-                    if self.builder.block_params(*block).len() == 0 {
-                        for ty in self.block_arg_types.get(&block_index).unwrap().iter() {
-                            self.builder.append_block_param(*block, *ty);
-                        }
-                    }
-                    self.builder.ins().jump(*block, &[]);
+                    let (block, args) = self.jump_target(block)?;
+                    self.builder.ins().jump(block, &args);
                 }
                 Bytecode::If(then_offset, else_offset) => {
                     let (value, _, _) = self.pop();
                     let then_block = (self.current_block as i64 + *then_offset) as usize;
                     let else_block = (self.current_block as i64 + *else_offset) as usize;
 
-                    while self.blocks.len() <= then_block + 1 || self.blocks.len() <= else_block + 1 {
-                        self.add_block();
-                    }
+                    let (then_block, then_args) = self.jump_target(then_block)?;
+                    let (else_block, else_args) = self.jump_target(else_block)?;
+
+                    self.builder.ins().brif(
+                        value,
+                        then_block,
+                        &then_args,
+                        else_block,
+                        &else_args,
+                    );
+                }
+                Bytecode::EmitSignal(class_name, signal_name) => {
+                    let emit_signal = if let Some(id) = module.get_name("emit_signal") {
+                        match id {
+                            FuncOrDataId::Func(id) => id,
+                            _ => unreachable!("cannot emit signal from data id"),
+                        }
+                    } else {
+                        let mut emit_signal = module.make_signature();
+                        emit_signal.params.push(AbiParam::new(cranelift::codegen::ir::types::I64));
+                        emit_signal.params.push(AbiParam::new(cranelift::codegen::ir::types::I64));
+                        emit_signal.params.push(AbiParam::new(cranelift::codegen::ir::types::I64));
 
-                    let block_args = self.get_args_as_vec_type();
-                    self.block_arg_types.insert(then_block, block_args);
+                        let fn_id = module.declare_function("emit_signal", Linkage::Import, &emit_signal).unwrap();
+                        fn_id
+                    };
 
-                    let block_args= self.get_args_as_vec_type();
-                    self.block_arg_types.insert(else_block, block_args);
+                    let class_name_value = self.builder.ins().iconst(cranelift::codegen::ir::types::I64, i64::from_le_bytes(class_name.to_le_bytes()));
+                    let signal_name_value = self.builder.ins().iconst(cranelift::codegen::ir::types::I64, i64::from_le_bytes(signal_name.to_le_bytes()));
 
+                    let context_value = self.builder.use_var(self.context_var);
 
-                    let then_index = then_block;
-                    let then_block = self.blocks[then_block];
-                    if self.builder.block_params(then_block).len() == 0 {
-                        for ty in self.block_arg_types.get(&then_index).unwrap().iter() {
-                            self.builder.append_block_param(then_block, *ty);
+                    let emit_signal = module.declare_func_in_func(emit_signal, self.builder.func);
+                    let _ = self.builder.ins().call(emit_signal, &[context_value, class_name_value, signal_name_value]);
+                }
+                Bytecode::EmitStaticSignal(class_name, signal_name) => {
+                    let emit_static_signal = if let Some(id) = module.get_name("emit_static_signal") {
+                        match id {
+                            FuncOrDataId::Func(id) => id,
+                            _ => unreachable!("cannot emit static signal from data id"),
                         }
-                    }
+                    } else {
+                        let mut emit_static_signal = module.make_signature();
+                        emit_static_signal.params.push(AbiParam::new(cranelift::codegen::ir::types::I64));
+                        emit_static_signal.params.push(AbiParam::new(cranelift::codegen::ir::types::I64));
+                        emit_static_signal.params.push(AbiParam::new(cranelift::codegen::ir::types::I64));
+
+                        let fn_id = module.declare_function("emit_static_signal", Linkage::Import, &emit_static_signal).unwrap();
+                        fn_id
+                    };
+
+                    let class_name_value = self.builder.ins().iconst(cranelift::codegen::ir::types::I64, i64::from_le_bytes(class_name.to_le_bytes()));
+                    let signal_name_value = self.builder.ins().iconst(cranelift::codegen::ir::types::I64, i64::from_le_bytes(signal_name.to_le_bytes()));
+
+                    let context_value = self.builder.use_var(self.context_var);
 
-                    let else_index = else_block;
-                    let else_block = self.blocks[else_block];
-                    if self.builder.block_params(else_block).len() == 0 {
-                        for ty in self.block_arg_types.get(&else_index).unwrap().iter() {
-                            self.builder.append_block_param(else_block, *ty);
+                    let emit_static_signal = module.declare_func_in_func(emit_static_signal, self.builder.func);
+                    let _ = self.builder.ins().call(emit_static_signal, &[context_value, class_name_value, signal_name_value]);
+                }
+                Bytecode::ConnectSignal(signal_name, class_name, method_name) => {
+                    let connect_signal = if let Some(id) = module.get_name("connect_signal") {
+                        match id {
+                            FuncOrDataId::Func(id) => id,
+                            _ => unreachable!("cannot connect_signal from data id"),
                         }
-                    }
+                    } else {
+                        let mut connect_signal = module.make_signature();
+                        connect_signal.params.push(AbiParam::new(cranelift::codegen::ir::types::I64));
+                        connect_signal.params.push(AbiParam::new(cranelift::codegen::ir::types::I64));
+                        connect_signal.params.push(AbiParam::new(cranelift::codegen::ir::types::I64));
+                        connect_signal.params.push(AbiParam::new(cranelift::codegen::ir::types::I64));
+                        connect_signal.params.push(AbiParam::new(cranelift::codegen::ir::types::I64));
+
+                        let fn_id = module.declare_function("connect_signal", Linkage::Import, &connect_signal).unwrap();
+                        fn_id
+                    };
 
-                    self.builder.ins().brif(
-                        value,
-                        then_block,
-                        &[],
-                        else_block,
-                        &[],
-                    );
+                    let (receiver, _, _) = self.pop();
+                    let (emitter, _, _) = self.pop();
+
+                    let signal_name_value = self.builder.ins().iconst(cranelift::codegen::ir::types::I64, i64::from_le_bytes(signal_name.to_le_bytes()));
+                    let class_name_value = self.builder.ins().iconst(cranelift::codegen::ir::types::I64, i64::from_le_bytes(class_name.to_le_bytes()));
+                    let method_name_value = self.builder.ins().iconst(cranelift::codegen::ir::types::I64, i64::from_le_bytes(method_name.to_le_bytes()));
+
+                    let connect_signal = module.declare_func_in_func(connect_signal, self.builder.func);
+                    let _ = self.builder.ins().call(connect_signal, &[emitter, receiver, signal_name_value, class_name_value, method_name_value]);
+                }
+                Bytecode::DisconnectSignal(signal_name, class_name, method_name) => {
+                    let disconnect_signal = if let Some(id) = module.get_name("disconnect_signal") {
+                        match id {
+                            FuncOrDataId::Func(id) => id,
+                            _ => unreachable!("cannot disconnect_signal from data id"),
+                        }
+                    } else {
+                        let mut disconnect_signal = module.make_signature();
+                        disconnect_signal.params.push(AbiParam::new(cranelift::codegen::ir::types::I64));
+                        disconnect_signal.params.push(AbiParam::new(cranelift::codegen::ir::types::I64));
+                        disconnect_signal.params.push(AbiParam::new(cranelift::codegen::ir::types::I64));
+                        disconnect_signal.params.push(AbiParam::new(cranelift::codegen::ir::types::I64));
+                        disconnect_signal.params.push(AbiParam::new(cranelift::codegen::ir::types::I64));
+
+                        let fn_id = module.declare_function("disconnect_signal", Linkage::Import, &disconnect_signal).unwrap();
+                        fn_id
+                    };
+
+                    let (receiver, _, _) = self.pop();
+                    let (emitter, _, _) = self.pop();
+
+                    let signal_name_value = self.builder.ins().iconst(cranelift::codegen::ir::types::I64, i64::from_le_bytes(signal_name.to_le_bytes()));
+                    let class_name_value = self.builder.ins().iconst(cranelift::codegen::ir::types::I64, i64::from_le_bytes(class_name.to_le_bytes()));
+                    let method_name_value = self.builder.ins().iconst(cranelift::codegen::ir::types::I64, i64::from_le_bytes(method_name.to_le_bytes()));
+
+                    let disconnect_signal = module.declare_func_in_func(disconnect_signal, self.builder.func);
+                    let _ = self.builder.ins().call(disconnect_signal, &[emitter, receiver, signal_name_value, class_name_value, method_name_value]);
+                }
+                Bytecode::Switch(offsets, default_offset) => {
+                    let (value, ty, _) = self.pop();
+                    let targets = offsets.iter()
+                        .map(|offset| (self.current_block as i64 + *offset) as usize)
+                        .collect::<Vec<_>>();
+                    let default_target = default_offset.map(|offset| (self.current_block as i64 + offset) as usize);
+
+                    let targets = targets.into_iter()
+                        .map(|block| self.jump_target(block))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    let default_target = default_target.map(|block| self.jump_target(block))
+                        .transpose()?;
+
+                    let wide_value = match ty {
+                        types::I64 => value,
+                        _ => self.builder.ins().uextend(types::I64, value),
+                    };
+                    // br_table takes a 32 bit index, so anything that doesn't fit is sent to the default
+                    let index = match ty {
+                        types::I8 | types::I16 => self.builder.ins().uextend(types::I32, value),
+                        types::I32 => value,
+                        _ => {
+                            let cases = self.builder.ins().iconst(types::I64, targets.len() as i64);
+                            let in_range = self.builder.ins().icmp(IntCC::UnsignedLessThan, value, cases);
+                            let index = self.builder.ins().select(in_range, value, cases);
+                            self.builder.ins().ireduce(types::I32, index)
+                        }
+                    };
+
+                    // Without a default a value that isn't one of the cases throws
+                    let out_of_range_block = self.builder.create_block();
+                    let default_call = match &default_target {
+                        Some((block, args)) => self.builder.func.dfg.block_call(*block, args),
+                        None => self.builder.func.dfg.block_call(out_of_range_block, &[]),
+                    };
+                    let table = targets.iter()
+                        .map(|(block, args)| self.builder.func.dfg.block_call(*block, args))
+                        .collect::<Vec<_>>();
+                    let jump_table = self.builder.create_jump_table(JumpTableData::new(default_call, &table));
+                    self.builder.ins().br_table(index, jump_table);
+
+                    if default_target.is_none() {
+                        self.builder.switch_to_block(out_of_range_block);
+                        let out_of_range = self.import_function(module, "jit_switch_out_of_range", &[types::I64, types::I64, types::I64], &[]);
+                        let context_value = self.builder.use_var(self.context_var);
+                        let cases = self.builder.ins().iconst(types::I64, targets.len() as i64);
+                        let _ = self.builder.ins().call(out_of_range, &[context_value, cases, wide_value]);
+                        self.unwind(module);
+                        self.unreachable = true;
+                        self.builder.seal_block(out_of_range_block);
+                    }
                 }
-                x => todo!("remaining ops {:?}", x),
             }
         }
         Ok(())
     }
 
-    fn create_bail_block(&mut self, module: &mut JITModule, return_type: Option<Type>, return_value: &[BlockArg]) {
+    fn create_bail_block(&mut self, module: &mut dyn Module, return_type: Option<Type>, return_value: &[BlockArg]) {
         let should_unwind_id = if let Some(id) = module.get_name("context_should_unwind") {
             match id {
                 FuncOrDataId::Func(id) => id,
@@ -1696,7 +2219,40 @@ impl FunctionTranslator<'_> {
             .brif(boolean, bail_block, &[], new_block, return_value);
 
         self.builder.switch_to_block(bail_block);
+        self.unwind(module);
+        self.builder.seal_block(bail_block);
+        self.builder.switch_to_block(new_block);
+    }
+
+    /// Ends the current block with code that can never run
+    fn trap(&mut self) {
+        self.builder.ins().trap(TrapCode::user(1).unwrap());
+        self.unreachable = true;
+    }
 
+    /// Leaves the function because of a thrown exception, unless one of its own handlers catches it
+    fn unwind(&mut self, module: &mut dyn Module) {
+        if !self.handler_blocks.is_empty() {
+            let catch_exception = self.import_function(module, "jit_catch_exception", &[types::I64], &[types::I64]);
+            let context_value = self.builder.use_var(self.context_var);
+            let result = self.builder.ins().call(catch_exception, &[context_value]);
+            let caught_block = self.builder.inst_results(result)[0];
+
+            for handler in self.handler_blocks.clone() {
+                let handler_value = self.builder.ins().iconst(types::I64, handler as i64 + 1);
+                let is_handler = self.builder.ins().icmp(IntCC::Equal, caught_block, handler_value);
+                let next_block = self.builder.create_block();
+                self.builder.ins().brif(is_handler, self.blocks[handler], &[], next_block, &[]);
+                self.builder.switch_to_block(next_block);
+                self.builder.seal_block(next_block);
+            }
+        }
+
+        self.return_default();
+    }
+
+    /// Returns a zero of the return type of the method for when what it returns doesn't matter
+    fn return_default(&mut self) {
         let returns: &[Value] = match self.return_type {
             runtime::class::TypeTag::U8 | runtime::class::TypeTag::I8 => &[self.builder.ins().iconst(types::I8, 0)],
             runtime::class::TypeTag::U16 | runtime::class::TypeTag::I16 => &[self.builder.ins().iconst(types::I16, 0)],
@@ -1710,9 +2266,492 @@ impl FunctionTranslator<'_> {
         };
         let ret_result = self.builder.ins().return_(returns);
         self.builder.inst_results(ret_result);
-        self.builder.seal_block(bail_block);
-        self.builder.switch_to_block(new_block);
+    }
+
+    /// Gets a runtime function that jitted code can call, declaring it the first time it is used
+    fn import_function(&mut self, module: &mut dyn Module, name: &str, params: &[Type], returns: &[Type]) -> ir::FuncRef {
+        let id = if let Some(id) = module.get_name(name) {
+            match id {
+                FuncOrDataId::Func(id) => id,
+                _ => unreachable!("{} is not a function", name),
+            }
+        } else {
+            let mut signature = module.make_signature();
+            signature.params.extend(params.iter().map(|ty| AbiParam::new(*ty)));
+            signature.returns.extend(returns.iter().map(|ty| AbiParam::new(*ty)));
+
+            module.declare_function(name, Linkage::Import, &signature).unwrap()
+        };
+
+        module.declare_func_in_func(id, self.builder.func)
+    }
+
+    /// Calls a method through one of the runtime's call functions and pushes what it returns
+    fn invoke(
+        &mut self,
+        module: &mut dyn Module,
+        call_function: &str,
+        class_name: u64,
+        method_name: u64,
+        sig: &Signature,
+        returns_object: bool,
+        inline_cache: Option<&InlineCache>,
+    ) {
+        let class_name_value = self.builder
+            .ins()
+            .iconst(cranelift::codegen::ir::types::I64, i64::from_le_bytes(class_name.to_le_bytes()));
+        let method_name_value = self.builder
+            .ins()
+            .iconst(cranelift::codegen::ir::types::I64, i64::from_le_bytes(method_name.to_le_bytes()));
+
+        let context_value = self.builder.use_var(self.context_var);
+
+        // Virtual and interface calls hand the runtime their inline cache so that it can fill it in
+        if matches!(call_function, "call_virtual_function" | "call_interface_function") {
+            let call_function = self.import_function(module, call_function, &[types::I64, types::I64, types::I64, types::I64], &[]);
+            let inline_cache = inline_cache.map_or(std::ptr::null(), |cache| cache as *const InlineCache);
+            let inline_cache_value = self.builder.ins().iconst(types::I64, inline_cache as i64);
+            let _ = self.builder
+                .ins()
+                .call(call_function, &[
+                    context_value,
+                    class_name_value,
+                    method_name_value,
+                    inline_cache_value,
+                ]);
+        } else {
+            let call_function = self.import_function(module, call_function, &[types::I64, types::I64, types::I64], &[]);
+            let _ = self.builder
+                .ins()
+                .call(call_function, &[
+                    context_value,
+                    class_name_value,
+                    method_name_value,
+                ]);
+        }
+
+        self.create_bail_block(module, None, &[]);
+
+        if !sig.returns.is_empty() {
+            let name = match sig.returns[0].value_type {
+                types::I8 => "fetch_return_int8",
+                types::I16 => "fetch_return_int16",
+                types::I32 => "fetch_return_int32",
+                types::I64 if returns_object => "fetch_return_object",
+                types::I64 if !returns_object => "fetch_return_int64",
+                types::F32 => "fetch_return_float32",
+                types::F64 => "fetch_return_float64",
+                _ => unreachable!()
+            };
+
+            let fetch_return = self.import_function(module, name, &[types::I64], &[sig.returns[0].value_type]);
+
+            let context_value = self.builder.use_var(self.context_var);
+
+            let results = self.builder.ins()
+                .call(fetch_return, &[context_value]);
 
-        // TODO: add check to see if exception was caught and jump to the right block
+            let value = self.builder.inst_results(results)[0];
+            self.push(value, sig.returns[0].value_type, returns_object)
+        }
+    }
+
+    /// Calls a method and returns what it returns.
+    /// `receiver_class` is set when the call could be to this method, in which case the receiver is checked
+    /// so that calling this method again loops instead of growing the stack.
+    /// Calls to other methods are left with the runtime through `tail_call_function`,
+    /// which makes them after this method has returned so that they don't use a native stack frame.
+    fn invoke_tail(
+        &mut self,
+        module: &mut dyn Module,
+        receiver_class: Option<Symbol>,
+        tail_call_function: &str,
+        class_name: u64,
+        method_name: u64,
+        inline_cache: Option<&InlineCache>,
+    ) {
+        if let Some(receiver_class) = receiver_class {
+            let object_class = self.import_function(module, "object_class", &[types::I64], &[types::I64]);
+            let (receiver, _, _) = self.call_args[0].expect("method calls always have a receiver");
+            let result = self.builder.ins().call(object_class, &[receiver]);
+            let class_value = self.builder.inst_results(result)[0];
+            let is_recursive = self.builder.ins().icmp_imm(IntCC::Equal, class_value, receiver_class as i64);
+
+            let loop_block = self.builder.create_block();
+            let call_block = self.builder.create_block();
+            self.builder.ins().brif(is_recursive, loop_block, &[], call_block, &[]);
+
+            self.builder.switch_to_block(loop_block);
+            self.builder.seal_block(loop_block);
+            self.jump_to_start();
+
+            self.builder.switch_to_block(call_block);
+            self.builder.seal_block(call_block);
+        }
+
+        let class_name_value = self.builder.ins().iconst(types::I64, i64::from_le_bytes(class_name.to_le_bytes()));
+        let method_name_value = self.builder.ins().iconst(types::I64, i64::from_le_bytes(method_name.to_le_bytes()));
+        let context_value = self.builder.use_var(self.context_var);
+        if tail_call_function == "jit_tail_call_static" {
+            let tail_call = self.import_function(module, tail_call_function, &[types::I64, types::I64, types::I64], &[]);
+            let _ = self.builder.ins().call(tail_call, &[context_value, class_name_value, method_name_value]);
+        } else {
+            let tail_call = self.import_function(module, tail_call_function, &[types::I64, types::I64, types::I64, types::I64], &[]);
+            let inline_cache = inline_cache.map_or(std::ptr::null(), |cache| cache as *const InlineCache);
+            let inline_cache_value = self.builder.ins().iconst(types::I64, inline_cache as i64);
+            let _ = self.builder.ins().call(tail_call, &[context_value, class_name_value, method_name_value, inline_cache_value]);
+        }
+        // What the method returns here is never looked at since the runtime makes the call in its place
+        self.return_default();
+    }
+
+    /// Calls a virtual or interface method, first checking the receiver against the classes its inline cache has seen.
+    /// Small methods of those classes are inlined and compiled ones are called without looking them up,
+    /// anything else goes through the runtime like `invoke` does.
+    fn invoke_cached(
+        &mut self,
+        module: &mut dyn Module,
+        index: usize,
+        call_function: &str,
+        class_name: u64,
+        method_name: u64,
+        sig: &Signature,
+        returns_object: bool,
+        inline_cache: Option<&InlineCache>,
+    ) {
+        let targets = match inline_cache {
+            Some(cache) if self.optimize && matches!(cache.state(), CacheState::Monomorphic | CacheState::Polymorphic) => {
+                cache.targets()
+            }
+            _ => Vec::new(),
+        };
+        if targets.is_empty() {
+            self.invoke(module, call_function, class_name, method_name, sig, returns_object, inline_cache);
+            return;
+        }
+
+        let (receiver, _, _) = self.call_args[0].expect("method calls always have a receiver");
+        let return_type = sig.returns.first().map(|param| param.value_type);
+        let merge_block = self.builder.create_block();
+        if let Some(ty) = return_type {
+            self.builder.append_block_param(merge_block, ty);
+        }
+        let lookup_block = self.builder.create_block();
+
+        // A null receiver is left for the runtime to report
+        let check_block = self.builder.create_block();
+        self.builder.ins().brif(receiver, check_block, &[], lookup_block, &[]);
+        self.builder.switch_to_block(check_block);
+        self.builder.seal_block(check_block);
+        let class_value = self.builder.ins().load(types::I64, MemFlags::trusted(), receiver, std::mem::offset_of!(Object, class) as i32);
+
+        for (class, function) in targets {
+            let target_name = match call_function {
+                "call_virtual_function" => MethodName::VirtualMethod {
+                    object_class_symbol: class,
+                    class_symbol: class_name as Symbol,
+                    method_name: method_name as Symbol,
+                },
+                _ => MethodName::InterfaceMethod {
+                    class_symbol: class,
+                    interface_symbol: class_name as Symbol,
+                    method_name: method_name as Symbol,
+                },
+            };
+
+            let hit_block = self.builder.create_block();
+            let next_block = self.builder.create_block();
+            let is_class = self.builder.ins().icmp_imm(IntCC::Equal, class_value, class as i64);
+            self.builder.ins().brif(is_class, hit_block, &[], next_block, &[]);
+            self.builder.switch_to_block(hit_block);
+            self.builder.seal_block(hit_block);
+
+            if !self.call_target(module, index, target_name, function, sig, returns_object) {
+                self.invoke(module, call_function, class_name, method_name, sig, returns_object, inline_cache);
+            }
+            self.jump_with_result(merge_block, return_type.is_some());
+
+            self.builder.switch_to_block(next_block);
+            self.builder.seal_block(next_block);
+        }
+        self.builder.ins().jump(lookup_block, &[]);
+
+        self.builder.switch_to_block(lookup_block);
+        self.builder.seal_block(lookup_block);
+        self.invoke(module, call_function, class_name, method_name, sig, returns_object, inline_cache);
+        self.jump_with_result(merge_block, return_type.is_some());
+
+        self.builder.switch_to_block(merge_block);
+        self.builder.seal_block(merge_block);
+        if let Some(ty) = return_type {
+            let value = self.builder.block_params(merge_block)[0];
+            if returns_object {
+                self.builder.declare_value_needs_stack_map(value);
+            }
+            self.push(value, ty, returns_object);
+        }
+    }
+
+    /// Jumps to the block where the paths of a call meet, passing along what the call pushed if it returns anything
+    fn jump_with_result(&mut self, block: Block, has_result: bool) {
+        if has_result {
+            let (value, _, _) = self.pop();
+            self.builder.ins().jump(block, &[BlockArg::Value(value)]);
+        } else {
+            self.builder.ins().jump(block, &[]);
+        }
+    }
+
+    /// Calls a method that the receiver is known to resolve to, inlining it if it is small enough.
+    /// Returns false if the method isn't compiled yet or doesn't fit the call, in which case nothing was emitted.
+    fn call_target(
+        &mut self,
+        module: &mut dyn Module,
+        index: usize,
+        target_name: MethodName,
+        function: &'static Function,
+        sig: &Signature,
+        returns_object: bool,
+    ) -> bool {
+        let arguments_match = function.signature.params.len() == function.arguments.len() + 1
+            && function.signature.params.iter()
+                .skip(1)
+                .enumerate()
+                .all(|(i, param)| matches!(self.call_args[i], Some((_, ty, _)) if ty == param.value_type));
+        let returns_match = function.signature.returns.iter()
+            .map(|param| param.value_type)
+            .eq(sig.returns.iter().map(|param| param.value_type));
+        if !arguments_match || !returns_match {
+            return false;
+        }
+
+        if can_inline(function) {
+            self.inline_method(module, index, function, sig, returns_object);
+            return true;
+        }
+        // Methods that end with a tail call leave it for whoever called them through the runtime to make
+        if makes_tail_calls(function) {
+            return false;
+        }
+
+        let code = match &*function.value.lock().unwrap() {
+            FunctionValue::Compiled(code, _, _) => *code,
+            _ => return false,
+        };
+
+        // The frame of the method needs its name for as long as the code can run, which is until the program exits
+        let target_name = Box::leak(Box::new(target_name)) as *const MethodName;
+        let enter_method = self.import_function(module, "jit_enter_method", &[types::I64, types::I64, types::I64], &[]);
+        let context_value = self.builder.use_var(self.context_var);
+        let target_name_value = self.builder.ins().iconst(types::I64, target_name as i64);
+        let function_value = self.builder.ins().iconst(types::I64, function as *const Function as i64);
+        let _ = self.builder.ins().call(enter_method, &[context_value, target_name_value, function_value]);
+
+        let mut args = vec![context_value];
+        for i in 0..function.arguments.len() {
+            let (value, _, _) = self.call_args[i].expect("checked that the call has every argument");
+            args.push(value);
+        }
+        let signature = self.builder.import_signature(function.signature.clone());
+        let code_value = self.builder.ins().iconst(types::I64, code as i64);
+        let call = self.builder.ins().call_indirect(signature, code_value, &args);
+        let result = self.builder.inst_results(call).first().copied();
+
+        let leave_method = self.import_function(module, "jit_leave_method", &[types::I64], &[]);
+        let context_value = self.builder.use_var(self.context_var);
+        let _ = self.builder.ins().call(leave_method, &[context_value]);
+
+        match result {
+            Some(value) => {
+                let ty = sig.returns[0].value_type;
+                if returns_object {
+                    self.builder.declare_value_needs_stack_map(value);
+                }
+                self.create_bail_block(module, Some(ty), &[BlockArg::Value(value)]);
+                self.push(value, ty, returns_object);
+            }
+            None => self.create_bail_block(module, None, &[]),
+        }
+        true
+    }
+
+    /// Translates the bytecode of a method in place of a call to it.
+    /// The method gets its own variables and stack, which start from the arguments of the call.
+    fn inline_method(
+        &mut self,
+        module: &mut dyn Module,
+        index: usize,
+        function: &Function,
+        sig: &Signature,
+        returns_object: bool,
+    ) {
+        let call_args = std::mem::replace(&mut self.call_args, [None; 256]);
+        let variables = std::mem::replace(&mut self.variables, [None; 256]);
+        let stack = std::mem::take(&mut self.stack);
+        for (i, arg) in call_args.iter().take(function.arguments.len()).enumerate() {
+            let (value, ty, is_object) = arg.expect("checked that the call has every argument");
+            self.set_var(i as u8, value, ty, is_object);
+        }
+
+        let exit_block = self.builder.create_block();
+        let return_type = sig.returns.first().map(|param| param.value_type);
+        if let Some(ty) = return_type {
+            self.builder.append_block_param(exit_block, ty);
+        }
+
+        let inlined_at = self.inlined_at.replace((index, exit_block));
+        // Inlined code never starts a block, so it can't enter a loop and fail
+        let _ = self.translate_instructions(&function.bytecode, module);
+        self.inlined_at = inlined_at;
+
+        self.call_args = call_args;
+        self.variables = variables;
+        self.stack = stack;
+        self.builder.switch_to_block(exit_block);
+        self.builder.seal_block(exit_block);
+        self.unreachable = false;
+        if let Some(ty) = return_type {
+            let value = self.builder.block_params(exit_block)[0];
+            if returns_object {
+                self.builder.declare_value_needs_stack_map(value);
+            }
+            self.push(value, ty, returns_object);
+        }
+    }
+
+    /// Fills the block that code entering a loop starts at.
+    /// It loads the variables of the interpreted frame and jumps to the start of the loop.
+    fn fill_loop_entry(&mut self) -> Result<(), String> {
+        let Some((block, entry_block, locals, locals_types)) = self.loop_entry.take() else {
+            return Ok(());
+        };
+        let loop_block = self.blocks[block];
+        if !self.block_arg_types.entry(block).or_default().is_empty() {
+            return Err(format!("block {block} starts with values on the stack"));
+        }
+
+        self.builder.switch_to_block(entry_block);
+        self.builder.seal_block(entry_block);
+        for (i, ty) in locals_types.into_iter().enumerate() {
+            // Variables that aren't set by the time the loop starts are only used inside of it
+            let (Some(ty), Some((var, var_ty, is_object))) = (ty, self.variables[i]) else {
+                continue;
+            };
+            if ty != var_ty {
+                return Err(format!("variable {i} is a {ty} in the interpreter but a {var_ty} at block {block}"));
+            }
+            let value = self.builder.ins().load(ty, MemFlags::trusted(), locals, (i * 8) as i32);
+            if is_object {
+                self.builder.declare_value_needs_stack_map(value);
+            }
+            self.builder.def_var(var, value);
+        }
+        self.builder.ins().jump(loop_block, &[]);
+        Ok(())
+    }
+
+    /// Restarts the function with the call arguments as its parameters, which is how a method calls itself as a tail call
+    fn jump_to_start(&mut self) {
+        for (i, parameter) in self.parameters.clone().into_iter().enumerate() {
+            let (value, _, _) = self.call_args[i].expect("tail call is missing an argument");
+            self.builder.def_var(parameter, value);
+        }
+        self.builder.ins().jump(self.blocks[0], &[]);
+    }
+
+    /// Converts a value to the type of the tag like Rust's `as` does.
+    /// Integers on the stack don't carry their sign, so they are treated as unsigned like the interpreter does.
+    /// Objects can't be converted, so methods that try are left to the interpreter.
+    fn convert(&mut self, value: Value, ty: Type, tag: &TypeTag) -> Result<(Value, Type), String> {
+        let (target, signed) = match tag {
+            TypeTag::U8 => (types::I8, false),
+            TypeTag::U16 => (types::I16, false),
+            TypeTag::U32 => (types::I32, false),
+            TypeTag::U64 => (types::I64, false),
+            TypeTag::I8 => (types::I8, true),
+            TypeTag::I16 => (types::I16, true),
+            TypeTag::I32 => (types::I32, true),
+            TypeTag::I64 => (types::I64, true),
+            TypeTag::F32 => (types::F32, true),
+            TypeTag::F64 => (types::F64, true),
+            TypeTag::Object => return Err(String::from("objects can't be converted")),
+            _ => unreachable!("Invalid Type Tag"),
+        };
+
+        let value = match (ty.is_float(), target.is_float()) {
+            (false, false) => self.resize_int(value, ty, target),
+            (false, true) => {
+                let wide_type = if ty.bits() < 32 { types::I32 } else { ty };
+                let value = self.resize_int(value, ty, wide_type);
+                self.builder.ins().fcvt_from_uint(target, value)
+            }
+            (true, true) if ty == target => value,
+            (true, true) if target == types::F64 => self.builder.ins().fpromote(target, value),
+            (true, true) => self.builder.ins().fdemote(target, value),
+            (true, false) if target.bits() >= 32 => {
+                if signed {
+                    self.builder.ins().fcvt_to_sint_sat(target, value)
+                } else {
+                    self.builder.ins().fcvt_to_uint_sat(target, value)
+                }
+            }
+            (true, false) => {
+                // Small integers saturate through a 32 bit conversion
+                let bits = target.bits();
+                let value = if signed {
+                    let value = self.builder.ins().fcvt_to_sint_sat(types::I32, value);
+                    let min = self.builder.ins().iconst(types::I32, -(1i64 << (bits - 1)));
+                    let max = self.builder.ins().iconst(types::I32, (1i64 << (bits - 1)) - 1);
+                    let value = self.builder.ins().smax(value, min);
+                    self.builder.ins().smin(value, max)
+                } else {
+                    let value = self.builder.ins().fcvt_to_uint_sat(types::I32, value);
+                    let max = self.builder.ins().iconst(types::I32, (1i64 << bits) - 1);
+                    self.builder.ins().umin(value, max)
+                };
+                self.builder.ins().ireduce(target, value)
+            }
+        };
+        Ok((value, target))
+    }
+
+    /// Reinterprets the bytes of a value as the type of the tag.
+    /// Like the interpreter, bytes are cut off or zero filled when the sizes differ.
+    fn binary_convert(&mut self, value: Value, ty: Type, tag: &TypeTag) -> Result<(Value, Type), String> {
+        let target = match tag {
+            TypeTag::U8 | TypeTag::I8 => types::I8,
+            TypeTag::U16 | TypeTag::I16 => types::I16,
+            TypeTag::U32 | TypeTag::I32 => types::I32,
+            TypeTag::U64 | TypeTag::I64 => types::I64,
+            TypeTag::F32 => types::F32,
+            TypeTag::F64 => types::F64,
+            TypeTag::Object => return Err(String::from("objects can't be converted")),
+            _ => unreachable!("Invalid Type Tag"),
+        };
+
+        let int_type = Type::int(ty.bits() as u16).unwrap();
+        let target_int_type = Type::int(target.bits() as u16).unwrap();
+        let value = if ty.is_float() {
+            self.builder.ins().bitcast(int_type, MemFlags::new(), value)
+        } else {
+            value
+        };
+        let value = self.resize_int(value, int_type, target_int_type);
+        let value = if target.is_float() {
+            self.builder.ins().bitcast(target, MemFlags::new(), value)
+        } else {
+            value
+        };
+        Ok((value, target))
+    }
+
+    fn resize_int(&mut self, value: Value, ty: Type, target: Type) -> Value {
+        if ty.bits() > target.bits() {
+            self.builder.ins().ireduce(target, value)
+        } else if ty.bits() < target.bits() {
+            self.builder.ins().uextend(target, value)
+        } else {
+            value
+        }
     }
 }
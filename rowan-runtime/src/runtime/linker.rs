@@ -3,7 +3,7 @@ use std::path::PathBuf;
 use std::sync::Arc;
 use cranelift::prelude::Signature;
 use fxhash::FxHashMap;
-use rowan_shared::classfile::{ClassFile, VTableEntry};
+use rowan_shared::classfile::{BytecodeEntry, ClassFile, VTableEntry};
 use rowan_shared::{bytecode, classfile, RowanClassFileUtils, TypeTag};
 use rowan_shared::interfacefile::InterfaceFile;
 use rowan_shared::interfaceimplfile::InterfaceImplFile;
@@ -13,7 +13,8 @@ use crate::runtime::object::Object;
 use crate::runtime::tables::class_table::ClassTable;
 use crate::runtime::tables::interface_table::InterfaceTable;
 use crate::runtime::tables::native_object_table::NativeObjectTable;
-use super::{class::{self, Class, MemberInfo}, jit::JITController, core::{VMClass, VMMember, VMMethod, VMVTable}, tables::{string_table::StringTable, symbol_table::{SymbolEntry, SymbolTable}, vtable::{Function, FunctionValue, VTable, VTables}}, Symbol, VTableIndex};
+use crate::runtime::tables::signal_table::SignalTable;
+use super::{class::{self, Class, MemberInfo, SignalInfo}, jit::JITController, core::{VMClass, VMMember, VMMethod, VMVTable}, tables::{string_table::StringTable, symbol_table::{SymbolEntry, SymbolTable}, vtable::{Function, FunctionValue, SourceLocation, VTable, VTables}}, Symbol, VTableIndex};
 
 #[derive(Debug)]
 pub enum TableEntry<T> {
@@ -23,7 +24,7 @@ pub enum TableEntry<T> {
 
 #[derive(Debug, Clone)]
 pub enum MethodLocation {
-    Bytecode(Vec<u8>),
+    Bytecode(BytecodeEntry),
     Native(String),
     Blank,
 }
@@ -48,6 +49,8 @@ pub fn link_class_files(
     class_map: &mut HashMap<&'static str, Symbol>,
     interface_map: &mut HashMap<&'static str, Symbol>,
     library_table: &mut NativeObjectTable,
+    signal_table: &mut SignalTable,
+    main_class: Option<&str>,
 ) -> Result<(Symbol, Symbol), ()> {
 
     let mut main_class_symbol = None;
@@ -73,7 +76,7 @@ pub fn link_class_files(
 
             symbol
         };
-        if name_str.ends_with("Main") {
+        if is_main_class(main_class, name_str) {
             main_class_symbol = Some(class_symbol);
         }
 
@@ -161,7 +164,7 @@ pub fn link_class_files(
                         .replace("-", "_dash_");
                     MethodLocation::Native(string)
                 } else {
-                    MethodLocation::Bytecode(class.index_bytecode_table(*bytecode).code.clone())
+                    MethodLocation::Bytecode(class.index_bytecode_table(*bytecode).clone())
                 };
 
                 let sig = jit_controller.create_signature(&signature[1..], &signature[0]);
@@ -182,9 +185,9 @@ pub fn link_class_files(
         }
     }
 
-    let mut class_parts: Vec<(&str, PathBuf, Symbol, Symbol, Symbol, Vec<MemberInfo>, Vec<(Symbol, Vec<TypeTag>, MethodLocation)>, &ClassFile, Vec<Symbol>, Vec<ClassMember>, Vec<u8>)> = Vec::new();
+    let mut class_parts: Vec<(&str, PathBuf, Symbol, Symbol, Symbol, Vec<MemberInfo>, Vec<(Symbol, Vec<TypeTag>, MethodLocation)>, &ClassFile, Vec<Symbol>, Vec<ClassMember>, Vec<u8>, Vec<SignalInfo>)> = Vec::new();
     for (class, mut location) in classes.iter().zip(class_locations.into_iter()) {
-        let ClassFile { name, parent, members, static_methods, vtables, static_members, static_init, .. } = &class;
+        let ClassFile { name, parent, members, signals, static_methods, vtables, static_members, static_init, .. } = &class;
         let class_name_str = class.index_string_table(*name);
         
         let class_symbol = *class_map.get(class_name_str).unwrap();
@@ -221,23 +224,12 @@ pub fn link_class_files(
 
                     string.push_str("__get_dash_size");
 
-                    let value = if let Some(library) = library_table.get_mut(&location.to_str().unwrap()) {
-                        let symbol = unsafe {
-                            let symbol = library.get::<extern "C" fn() -> usize>(string.as_bytes()).expect("TODO: handle missing function reference");
-                            *symbol
-                        };
-                        class::TypeTag::Sized(symbol())
-                    } else {
-                        let (symbol, lib) = unsafe {
-                            let lib = libloading::Library::new(&location).expect("Handle Missing library");
-                            let symbol = lib.get::<extern "C" fn() -> usize>(string.as_bytes()).expect("TODO: handle missing function reference");
-
-                            (*symbol, lib)
-                        };
-                        library_table.insert(location.to_str().unwrap().to_string(), lib);
-
-                        class::TypeTag::Sized(symbol())
+                    let library = library_table.load(&location);
+                    let symbol = unsafe {
+                        let symbol = library.get::<extern "C" fn() -> usize>(string.as_bytes()).expect("TODO: handle missing function reference");
+                        *symbol
                     };
+                    let value = class::TypeTag::Sized(symbol());
 
                     location.pop();
                     value
@@ -248,9 +240,33 @@ pub fn link_class_files(
             class_members.push(MemberInfo::new(name_symbol, type_tag));
         }
 
+        let mut class_signals = Vec::new();
+        for signal in signals {
+            let classfile::Signal { name, is_static, signature } = signal;
+
+            let name_str = class.index_string_table(*name);
+            let name_symbol = if let Some(symbol) = string_map.get(name_str) {
+                *symbol
+            } else {
+                let string_table_index = string_table.add_string(name_str);
+                let name_str = string_table.get_string(string_table_index);
+                let symbol = symbol_table.add_string(string_table_index);
+                string_map.insert(name_str, symbol);
+                symbol
+            };
+
+            // The first type of the signature is the void return type
+            let arguments = class.signature_table[*signature as usize].types[1..]
+                .iter()
+                .map(convert_type)
+                .collect();
+
+            class_signals.push(SignalInfo::new(name_symbol, *is_static, arguments));
+        }
+
         let mut static_method_functions = Vec::new();
         for function in static_methods.functions.iter() {
-            let VTableEntry { name, signature, bytecode, .. } = function;
+            let VTableEntry { name, responds_to, signature, bytecode } = function;
 
             let name_str = class.index_string_table(*name);
             let name_symbol = if let Some(symbol) = string_map.get(name_str) {
@@ -263,7 +279,21 @@ pub fn link_class_files(
                 symbol
             };
 
-            if name_str.ends_with("main") {
+            if *responds_to != 0 {
+                let signal_str = class.index_string_table(*responds_to);
+                let signal_symbol = if let Some(symbol) = string_map.get(signal_str) {
+                    *symbol
+                } else {
+                    let string_table_index = string_table.add_string(signal_str);
+                    let signal_str = string_table.get_string(string_table_index);
+                    let symbol = symbol_table.add_string(string_table_index);
+                    string_map.insert(signal_str, symbol);
+                    symbol
+                };
+                signal_table.add_listener(signal_symbol, class_symbol, name_symbol);
+            }
+
+            if is_main_method(main_class, name_str) {
                 main_method_symbol = Some(name_symbol);
             }
 
@@ -277,7 +307,7 @@ pub fn link_class_files(
                     .replace("-", "_dash_");
                 MethodLocation::Native(string)
             } else {
-                MethodLocation::Bytecode(class.index_bytecode_table(*bytecode).code.clone())
+                MethodLocation::Bytecode(class.index_bytecode_table(*bytecode).clone())
             };
             static_method_functions.push((name_symbol, signature, function))
         }
@@ -361,13 +391,14 @@ pub fn link_class_files(
             Vec::new()
         };
 
-        class_parts.push((class_name_str, location, class_symbol, class_name_symbol, parent_symbol, class_members, static_method_functions, class, vtables_to_link, static_members, static_init));
+        class_parts.push((class_name_str, location, class_symbol, class_name_symbol, parent_symbol, class_members, static_method_functions, class, vtables_to_link, static_members, static_init, class_signals));
     }
     let mut class_parts_to_try_again;
     loop {
         class_parts_to_try_again = Vec::new();
         'outer: for class_part in class_parts {
-            let (class_name_str, mut location, class_symbol, class_name_symbol, parent, members, static_methods, class, vtables, static_members, static_init) = class_part;
+            let (class_name_str, mut location, class_symbol, class_name_symbol, parent, members, static_methods, class, vtables, static_members, static_init, signals) = class_part;
+            let class_file_kind = class.kind;
             let mut vtables_to_add = Vec::new();
             // Source class is one of the parents of the derived class
             // This is used to disambiguate
@@ -401,7 +432,7 @@ pub fn link_class_files(
 
                             let (bytecode, value, sig) = match method_location {
                                 MethodLocation::Bytecode(bytecode) => {
-                                    let bytecode = link_bytecode(class, &bytecode, string_map, class_map, string_table, symbol_table, class_table, interface_table, interface_map);
+                                    let bytecode = link_bytecode(class, &bytecode.code, string_map, class_map, string_table, symbol_table, class_table, interface_table, interface_map);
                                     let value = FunctionValue::Bytecode(func_id);
                                     (bytecode.into(), value, sig)
                                 }
@@ -411,22 +442,12 @@ pub fn link_class_files(
 
                                     location.push(name);
 
-                                    let value = if let Some(library) = library_table.get_mut(&location.to_str().unwrap()) {
-                                        let symbol = unsafe {
-                                            let symbol = library.get::<*const ()>(string.as_bytes()).expect("TODO: handle missing function reference");
-                                            *symbol
-                                        };
-                                        FunctionValue::Native(symbol)
-                                    } else {
-                                        let (symbol, lib) = unsafe {
-                                            let lib = libloading::Library::new(&location).expect("Handle Missing library");
-                                            let symbol = lib.get::<*const ()>(string.as_bytes()).expect("TODO: handle missing function reference");
-
-                                            (*symbol, lib)
-                                        };
-                                        library_table.insert(location.to_str().unwrap().to_string(), lib);
-                                        FunctionValue::Native(symbol)
+                                    let library = library_table.load(&location);
+                                    let symbol = unsafe {
+                                        let symbol = library.get::<*const ()>(string.as_bytes()).expect("TODO: handle missing function reference");
+                                        *symbol
                                     };
+                                    let value = FunctionValue::Native(symbol);
 
                                     location.pop();
 
@@ -448,7 +469,7 @@ pub fn link_class_files(
                     *vtables_map.get_mut(class_name).unwrap().get_mut(class_name).unwrap() = functions.clone();
 
                     let functions = functions.into_iter()
-                        .map(|(name_symbol, signature, _, bytecode, value, sig)| {
+                        .map(|(name_symbol, signature, method_location, bytecode, value, sig)| {
                             let return_type = convert_type(&signature[0]);
                             let arguments = signature[1..]
                                 .iter()
@@ -456,8 +477,12 @@ pub fn link_class_files(
                                 .collect::<Vec<_>>();
 
                             let block_positions = Box::new(create_block_positions(bytecode.as_ref()));
+                            let lines = match method_location {
+                                MethodLocation::Bytecode(entry) => link_lines(class, &entry, string_map, string_table, symbol_table),
+                                _ => Box::new([]),
+                            };
 
-                            Function::new(name_symbol, bytecode, value, arguments.into(), return_type, sig, block_positions)
+                            Function::new(name_symbol, bytecode, value, arguments.into(), return_type, sig, block_positions, lines)
                         })
                         .collect::<Vec<_>>();
 
@@ -483,7 +508,7 @@ pub fn link_class_files(
                     for (_,_,_,_,value, _) in base_functions {
                         if value.is_blank() {
                             // We bail if any of base has not yet been linked
-                            class_parts_to_try_again.push((class_name_str, location, class_symbol, class_name_symbol, parent, members, static_methods, class, vtables, static_members, static_init));
+                            class_parts_to_try_again.push((class_name_str, location, class_symbol, class_name_symbol, parent, members, static_methods, class, vtables, static_members, static_init, signals));
                             continue 'outer;
                         }
                     }
@@ -502,10 +527,12 @@ pub fn link_class_files(
 
                             let (bytecode, value) = match derived_bytecode {
                                 MethodLocation::Bytecode(bytecode) => {
-                                    let name = &string_table[*name_index];
-                                    let func_id = jit_controller.declare_function(name, &sig).expect("Failed to declare function");
+                                    // Every override has the name of the base method, so we qualify it with the class
+                                    let method_name = string_table[*name_index].rsplit("::").next().unwrap();
+                                    let name = format!("{class_name_str}::{method_name}");
+                                    let func_id = jit_controller.declare_function(&name, &sig).expect("Failed to declare function");
 
-                                    let bytecode = link_bytecode(class, &bytecode, string_map, class_map, string_table, symbol_table, class_table, interface_table, interface_map);
+                                    let bytecode = link_bytecode(class, &bytecode.code, string_map, class_map, string_table, symbol_table, class_table, interface_table, interface_map);
                                     let value = FunctionValue::Bytecode(func_id);
                                     (bytecode.into(), value)
                                 }
@@ -515,22 +542,12 @@ pub fn link_class_files(
 
                                     location.push(name);
 
-                                    let value = if let Some(library) = library_table.get_mut(&location.to_str().unwrap()) {
-                                        let symbol = unsafe {
-                                            let symbol = library.get::<*const ()>(string.as_bytes()).expect("TODO: handle missing function reference");
-                                            *symbol
-                                        };
-                                        FunctionValue::Native(symbol)
-                                    } else {
-                                        let (symbol, lib) = unsafe {
-                                            let lib = libloading::Library::new(&location).expect("Handle Missing library");
-                                            let symbol = lib.get::<*const ()>(string.as_bytes()).expect("TODO: handle missing function reference");
-
-                                            (*symbol, lib)
-                                        };
-                                        library_table.insert(location.to_str().unwrap().to_string(), lib);
-                                        FunctionValue::Native(symbol)
+                                    let library = library_table.load(&location);
+                                    let symbol = unsafe {
+                                        let symbol = library.get::<*const ()>(string.as_bytes()).expect("TODO: handle missing function reference");
+                                        *symbol
                                     };
+                                    let value = FunctionValue::Native(symbol);
 
                                     location.pop();
 
@@ -546,10 +563,10 @@ pub fn link_class_files(
                             (*derived_name_symbol, derived_signature.clone(), derived_bytecode.clone(), bytecode, value, sig.clone())
                         })
                         .collect::<Vec<_>>();
-                    *vtables_map.get_mut(class_name).unwrap().get_mut(class_name).unwrap() = functions.clone();
+                    *vtables_map.get_mut(class_name).unwrap().get_mut(&class_symbol).unwrap() = functions.clone();
 
                     let functions = functions.into_iter()
-                        .map(|(name_symbol, signature, _, bytecode, value, sig)| {
+                        .map(|(name_symbol, signature, method_location, bytecode, value, sig)| {
                             let return_type = convert_type(&signature[0]);
                             let arguments = signature[1..]
                                 .iter()
@@ -557,8 +574,12 @@ pub fn link_class_files(
                                 .collect::<Vec<_>>();
 
                             let block_positions = Box::new(create_block_positions(bytecode.as_ref()));
+                            let lines = match method_location {
+                                MethodLocation::Bytecode(entry) => link_lines(class, &entry, string_map, string_table, symbol_table),
+                                _ => Box::new([]),
+                            };
 
-                            Function::new(name_symbol, bytecode, value, arguments.into(), return_type, sig, block_positions)
+                            Function::new(name_symbol, bytecode, value, arguments.into(), return_type, sig, block_positions, lines)
                         })
                         .collect::<Vec<_>>();
 
@@ -578,7 +599,7 @@ pub fn link_class_files(
             match add_parent_vtables(&mut class_vtable_mapper, parent, class_table, symbol_table, &mut HashSet::new()) {
                 Err(_) => {
                     // We bail if any of base has not yet been linked
-                    class_parts_to_try_again.push((class_name_str, location, class_symbol, class_name_symbol, parent, members, static_methods, class, vtables, static_members, static_init));
+                    class_parts_to_try_again.push((class_name_str, location, class_symbol, class_name_symbol, parent, members, static_methods, class, vtables, static_members, static_init, signals));
                     continue 'outer;
                 }
                 _ => {},
@@ -605,7 +626,7 @@ pub fn link_class_files(
                             .map(convert_type)
                             .collect::<Vec<_>>();
 
-                        let (bytecode, value, sig) = match location {
+                        let (bytecode, value, sig, lines) = match location {
                             MethodLocation::Blank => panic!("we should be bytecode"),
                             MethodLocation::Native(string) => {
                                 let name = class_name_str.split("::").collect::<Vec<&str>>().last().unwrap().to_string();
@@ -613,37 +634,28 @@ pub fn link_class_files(
 
                                 location_path.push(name);
 
-                                let value = if let Some(library) = library_table.get_mut(&location_path.to_str().unwrap()) {
-                                    let symbol = unsafe {
-                                        let symbol = library.get::<*const ()>(string.as_bytes()).expect("TODO: handle missing function reference");
-                                        *symbol
-                                    };
-                                    FunctionValue::Native(symbol)
-                                } else {
-                                    let (symbol, lib) = unsafe {
-                                        let lib = libloading::Library::new(&location_path).expect("Handle Missing library");
-                                        let symbol = lib.get::<*const ()>(string.as_bytes()).expect("TODO: handle missing function reference");
-
-                                        (*symbol, lib)
-                                    };
-                                    library_table.insert(location_path.to_str().unwrap().to_string(), lib);
-                                    FunctionValue::Native(symbol)
+                                let library = library_table.load(&location_path);
+                                let symbol = unsafe {
+                                    let symbol = library.get::<*const ()>(string.as_bytes()).expect("TODO: handle missing function reference");
+                                    *symbol
                                 };
+                                let value = FunctionValue::Native(symbol);
 
                                 location_path.pop();
 
-                                (Box::new([]) as Box<[rowan_shared::bytecode::linked::Bytecode]>, value, cranelift_sig)
+                                (Box::new([]) as Box<[rowan_shared::bytecode::linked::Bytecode]>, value, cranelift_sig, Box::new([]) as Box<[SourceLocation]>)
                             },
                             MethodLocation::Bytecode(code) => {
-                                let bytecode = link_bytecode(class, &code, string_map, class_map, string_table, symbol_table, class_table, interface_table, interface_map);
+                                let bytecode = link_bytecode(class, &code.code, string_map, class_map, string_table, symbol_table, class_table, interface_table, interface_map);
+                                let lines = link_lines(class, &code, string_map, string_table, symbol_table);
                                 let value = FunctionValue::Bytecode(func_id);
-                                (bytecode.into(), value, cranelift_sig)
+                                (bytecode.into(), value, cranelift_sig, lines)
                             }
                         };
 
                         let block_positions = Box::new(create_block_positions(bytecode.as_ref()));
 
-                        Function::new(name_symbol, bytecode, value, arguments.into(), return_type, sig, block_positions)
+                        Function::new(name_symbol, bytecode, value, arguments.into(), return_type, sig, block_positions, lines)
                     })
                     .collect::<Vec<_>>(), location_path)
             };
@@ -668,31 +680,16 @@ pub fn link_class_files(
             let members_has_natives = members.iter().any(|m| m.has_native_type());
 
             let custom_drop = if members_has_natives {
-                let custom_drop = if let Some(library) = library_table.get_mut(&location.to_str().unwrap()) {
-                    let symbol = unsafe {
-                        let symbol = library.get::<*const ()>(b"custom_drop");
-                        symbol.map(|symbol| *symbol).ok()
-                    };
-
-                    symbol.map(|symbol| {
-                        unsafe {
-                            std::mem::transmute::<_, extern "C" fn(&mut Object)>(symbol)
-                        }
-                    })
-                } else {
-                    let (symbol, lib) = unsafe {
-                        let lib = libloading::Library::new(&location).expect("Handle Missing library");
-                        let symbol = lib.get::<*const ()>(b"custom_drop");
-
-                        (symbol.map(|symbol| *symbol).ok(), lib)
-                    };
-                    library_table.insert(location.to_str().unwrap().to_string(), lib);
-                    symbol.map(|symbol| {
-                        unsafe {
-                            std::mem::transmute::<_, extern "C" fn(&mut Object)>(symbol)
-                        }
-                    })
+                let library = library_table.load(&location);
+                let symbol = unsafe {
+                    let symbol = library.get::<*const ()>(b"custom_drop");
+                    symbol.map(|symbol| *symbol).ok()
                 };
+                let custom_drop = symbol.map(|symbol| {
+                    unsafe {
+                        std::mem::transmute::<_, extern "C" fn(&mut Object)>(symbol)
+                    }
+                });
                 custom_drop
             } else {
                 None
@@ -700,7 +697,9 @@ pub fn link_class_files(
             location.pop();
 
             // Create new class
-            let class = Class::new(class_name_symbol, parent, class_vtable_mapper, members, vtable_index, static_members, static_init, custom_drop);
+            let mut class = Class::new(class_name_symbol, parent, class_vtable_mapper, members, vtable_index, static_members, static_init, custom_drop);
+            class.signals = signals;
+            class.kind = class_file_kind;
 
             let SymbolEntry::ClassRef(class_index) = &symbol_table[class_symbol] else {
                 unreachable!("Class symbol should have been a symbol to a class");
@@ -720,6 +719,21 @@ pub fn link_class_files(
     }
 }
 
+/// Without a main class from a package manifest, any class ending in `Main` is taken
+fn is_main_class(main_class: Option<&str>, class_name: &str) -> bool {
+    match main_class {
+        Some(main_class) => class_name == main_class,
+        None => class_name.ends_with("Main"),
+    }
+}
+
+fn is_main_method(main_class: Option<&str>, method_name: &str) -> bool {
+    match main_class {
+        Some(main_class) => method_name.strip_prefix(main_class) == Some("::main"),
+        None => method_name.ends_with("main"),
+    }
+}
+
 fn convert_type(tag: &rowan_shared::TypeTag) -> class::TypeTag {
     match tag {
         rowan_shared::TypeTag::U8 => class::TypeTag::U8,
@@ -1184,6 +1198,96 @@ fn link_bytecode(
             compiled::Bytecode::Switch(branches, default) => {
                 output.push(linked::Bytecode::Switch(branches, default));
             }
+            compiled::Bytecode::EmitSignal(class_index, signal_index) => {
+                let class_str = class_file.index_string_table(class_index);
+                let class_symbol: Symbol = *class_map.get(class_str).expect("Class not loaded yet");
+
+                let signal_str = class_file.index_string_table(signal_index);
+                let signal_symbol: Symbol = if let Some(symbol) = string_map.get(signal_str) {
+                    *symbol
+                } else {
+                    let index = string_table.add_string(signal_str);
+                    let signal_str = string_table.get_string(index);
+                    let symbol = symbol_table.add_string(index);
+                    string_map.insert(signal_str, symbol);
+                    symbol
+                };
+
+                output.push(linked::Bytecode::EmitSignal(class_symbol as u64, signal_symbol as u64));
+            }
+            compiled::Bytecode::ConnectSignal(signal_index, class_index, method_index) => {
+                let signal_str = class_file.index_string_table(signal_index);
+                let signal_symbol: Symbol = if let Some(symbol) = string_map.get(signal_str) {
+                    *symbol
+                } else {
+                    let index = string_table.add_string(signal_str);
+                    let signal_str = string_table.get_string(index);
+                    let symbol = symbol_table.add_string(index);
+                    string_map.insert(signal_str, symbol);
+                    symbol
+                };
+
+                let class_str = class_file.index_string_table(class_index);
+                let class_symbol: Symbol = *class_map.get(class_str).expect("Class not loaded yet");
+
+                let method_str = class_file.index_string_table(method_index);
+                let method_symbol: Symbol = if let Some(symbol) = string_map.get(method_str) {
+                    *symbol
+                } else {
+                    let index = string_table.add_string(method_str);
+                    let method_str = string_table.get_string(index);
+                    let symbol = symbol_table.add_string(index);
+                    string_map.insert(method_str, symbol);
+                    symbol
+                };
+
+                output.push(linked::Bytecode::ConnectSignal(signal_symbol as u64, class_symbol as u64, method_symbol as u64));
+            }
+            compiled::Bytecode::DisconnectSignal(signal_index, class_index, method_index) => {
+                let signal_str = class_file.index_string_table(signal_index);
+                let signal_symbol: Symbol = if let Some(symbol) = string_map.get(signal_str) {
+                    *symbol
+                } else {
+                    let index = string_table.add_string(signal_str);
+                    let signal_str = string_table.get_string(index);
+                    let symbol = symbol_table.add_string(index);
+                    string_map.insert(signal_str, symbol);
+                    symbol
+                };
+
+                let class_str = class_file.index_string_table(class_index);
+                let class_symbol: Symbol = *class_map.get(class_str).expect("Class not loaded yet");
+
+                let method_str = class_file.index_string_table(method_index);
+                let method_symbol: Symbol = if let Some(symbol) = string_map.get(method_str) {
+                    *symbol
+                } else {
+                    let index = string_table.add_string(method_str);
+                    let method_str = string_table.get_string(index);
+                    let symbol = symbol_table.add_string(index);
+                    string_map.insert(method_str, symbol);
+                    symbol
+                };
+
+                output.push(linked::Bytecode::DisconnectSignal(signal_symbol as u64, class_symbol as u64, method_symbol as u64));
+            }
+            compiled::Bytecode::EmitStaticSignal(class_index, signal_index) => {
+                let class_str = class_file.index_string_table(class_index);
+                let class_symbol: Symbol = *class_map.get(class_str).expect("Class not loaded yet");
+
+                let signal_str = class_file.index_string_table(signal_index);
+                let signal_symbol: Symbol = if let Some(symbol) = string_map.get(signal_str) {
+                    *symbol
+                } else {
+                    let index = string_table.add_string(signal_str);
+                    let signal_str = string_table.get_string(index);
+                    let symbol = symbol_table.add_string(index);
+                    string_map.insert(signal_str, symbol);
+                    symbol
+                };
+
+                output.push(linked::Bytecode::EmitStaticSignal(class_symbol as u64, signal_symbol as u64));
+            }
         }
     }
 
@@ -1439,7 +1543,7 @@ pub fn link_vm_classes(
                                 .map(convert_type)
                                 .collect::<Vec<_>>();
 
-                            Function::new(name_symbol, code, value, arguments.into(), return_type, sig, Box::new(FxHashMap::default()))
+                            Function::new(name_symbol, code, value, arguments.into(), return_type, sig, Box::new(FxHashMap::default()), Box::new([]))
                         })
                         .collect::<Vec<_>>();
 
@@ -1492,7 +1596,7 @@ pub fn link_vm_classes(
                                 .map(convert_type)
                                 .collect::<Vec<_>>();
 
-                            Function::new(name_symbol, code, value, arguments.into(), return_type, sig, Box::new(FxHashMap::default()))
+                            Function::new(name_symbol, code, value, arguments.into(), return_type, sig, Box::new(FxHashMap::default()), Box::new([]))
                         })
                         .collect::<Vec<_>>();
 
@@ -1529,7 +1633,7 @@ pub fn link_vm_classes(
                     let return_type = convert_type(&signature[0]);
 
 
-                    Function::new(name_symbol, Box::new([]) as Box<[rowan_shared::bytecode::linked::Bytecode]>, value, arguments, return_type, sig, Box::new(FxHashMap::default()))
+                    Function::new(name_symbol, Box::new([]) as Box<[rowan_shared::bytecode::linked::Bytecode]>, value, arguments, return_type, sig, Box::new(FxHashMap::default()), Box::new([]))
                 })
                 .collect::<Vec<_>>();
 
@@ -1607,6 +1711,48 @@ fn add_library_mod(name: &str) -> String {
     format!("{name}.so")
 }
 
+/// Converts a line table from byte offsets to bytecode indices and interns the file names
+fn link_lines(
+    class_file: &dyn RowanClassFileUtils,
+    entry: &BytecodeEntry,
+    string_map: &mut HashMap<&'static str, Symbol>,
+    string_table: &mut StringTable,
+    symbol_table: &mut SymbolTable,
+) -> Box<[SourceLocation]> {
+    if entry.lines.is_empty() {
+        return Box::new([]);
+    }
+    let compiled_code: Vec<rowan_shared::bytecode::compiled::Bytecode> =
+        rowan_shared::bytecode::compiled::Bytecode::try_from(&mut entry.code.iter()).unwrap();
+    let mut offsets = Vec::with_capacity(compiled_code.len());
+    let mut offset = 0;
+    for code in compiled_code.iter() {
+        offsets.push(offset);
+        offset += code.into_binary().len() as u64;
+    }
+
+    entry.lines.iter()
+        .map(|line| {
+            let file = class_file.index_string_table(line.file);
+            let file = if let Some(symbol) = string_map.get(file) {
+                *symbol
+            } else {
+                let string_table_index = string_table.add_string(file);
+                let file = string_table.get_string(string_table_index);
+                let symbol = symbol_table.add_string(string_table_index);
+                string_map.insert(file, symbol);
+                symbol
+            };
+            SourceLocation {
+                index: offsets.partition_point(|offset| *offset < line.bytecode_offset),
+                file,
+                line: line.line,
+                column: line.column,
+            }
+        })
+        .collect()
+}
+
 fn create_block_positions(bytecode: &[bytecode::linked::Bytecode]) -> FxHashMap<usize, usize> {
     let mut block_positions = FxHashMap::default();
     for (i, bytecode) in bytecode.iter().enumerate() {
@@ -1667,7 +1813,8 @@ pub fn link_interfaces(
             let VTableEntry {
                 name,
                 signature,
-                bytecode
+                bytecode,
+                ..
             } = function;
             let method_name = interface.index_string_table(*name);
             let method_name_symbol = if let Some(symbol) = string_map.get(method_name) {
@@ -1708,15 +1855,16 @@ pub fn link_interfaces(
                     interface_map,
                 );
                 let block_positions = Box::new(create_block_positions(&bytecode));
+                let lines = link_lines(interface, bytecode_entry, string_map, string_table, symbol_table);
 
                 let bytecode = bytecode.into_boxed_slice();
                 let func_id = jit_controller.declare_function(method_name, &signature).unwrap();
                 let function_value = FunctionValue::Bytecode(func_id);
 
-                functions_to_add.push(Function::new(method_name_symbol, bytecode, function_value, args, return_type, signature, block_positions));
+                functions_to_add.push(Function::new(method_name_symbol, bytecode, function_value, args, return_type, signature, block_positions, lines));
                 vtable_mapper.insert(method_name_symbol, index);
             } else {
-                functions_to_add.push(Function::new(method_name_symbol, Box::new([]), FunctionValue::Blank, args, return_type, signature, Box::new(FxHashMap::default())));
+                functions_to_add.push(Function::new(method_name_symbol, Box::new([]), FunctionValue::Blank, args, return_type, signature, Box::new(FxHashMap::default()), Box::new([])));
                 vtable_mapper.insert(method_name_symbol, index);
             }
         }
@@ -1788,7 +1936,8 @@ pub fn link_interfaces(
             let VTableEntry {
                 name,
                 signature,
-                bytecode
+                bytecode,
+                ..
             } = function;
 
             let method_name = r#impl.index_string_table(*name);
@@ -1817,11 +1966,13 @@ pub fn link_interfaces(
                     interface_map,
                 );
                 let block_positions = Box::new(create_block_positions(&bytecode));
+                let lines = link_lines(&r#impl, bytecode_entry, string_map, string_table, symbol_table);
                 let signature_entry = &signature_table[*signature as usize];
 
                 let bytecode = bytecode.into_boxed_slice();
                 let signature = jit_controller.create_signature(&signature_entry.types[1..], &signature_entry.types[0]);
-                let func_id = jit_controller.declare_function(method_name, &signature).unwrap();
+                // Every class implementing the interface has a method with this name, so the compiled code can't be named by it
+                let func_id = jit_controller.declare_anonymous_function(&signature).unwrap();
                 let function_value = FunctionValue::Bytecode(func_id);
 
                 let args = signature_entry.types[1..].iter()
@@ -1832,7 +1983,7 @@ pub fn link_interfaces(
                 let return_type = convert_type(&signature_entry.types[0]);
 
                 vtable.table[i] = Arc::new(
-                    Function::new(method_name_symbol, bytecode, function_value, args, return_type, signature, block_positions)
+                    Function::new(method_name_symbol, bytecode, function_value, args, return_type, signature, block_positions, lines)
                 );
             }
         }
@@ -0,0 +1,131 @@
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+use crate::context::StackValue;
+use crate::runtime::{Reference, Symbol};
+
+/// A call to a connected method that is waiting to be delivered.
+/// Messages for static signals have a null receiver and call a static method.
+pub struct Message {
+    pub receiver: Reference,
+    /// The class used to find the vtable of the method
+    pub class: Symbol,
+    pub method: Symbol,
+    pub args: Vec<StackValue>,
+}
+
+impl Message {
+    pub fn new(receiver: Reference, class: Symbol, method: Symbol, args: Vec<StackValue>) -> Self {
+        Message {
+            receiver,
+            class,
+            method,
+            args,
+        }
+    }
+
+    pub fn new_static(class: Symbol, method: Symbol, args: Vec<StackValue>) -> Self {
+        Message::new(std::ptr::null_mut(), class, method, args)
+    }
+
+    pub(crate) fn collect_references(&self, live_objects: &mut HashSet<Reference>) {
+        if !self.receiver.is_null() {
+            live_objects.insert(self.receiver);
+        }
+        for arg in self.args.iter() {
+            if let StackValue::Reference(reference) = arg {
+                live_objects.insert(*reference);
+            }
+        }
+    }
+}
+
+struct MessageNode {
+    message: Message,
+    next: *mut MessageNode,
+}
+
+/// A lockless queue that any thread can push messages onto.
+/// Messages are pushed onto the head of a linked list and are put back in order when the queue is drained.
+pub struct MessageQueue {
+    head: AtomicPtr<MessageNode>,
+}
+
+impl MessageQueue {
+    pub const fn new() -> Self {
+        MessageQueue {
+            head: AtomicPtr::new(std::ptr::null_mut()),
+        }
+    }
+
+    pub fn push(&self, message: Message) {
+        let node = Box::into_raw(Box::new(MessageNode {
+            message,
+            next: std::ptr::null_mut(),
+        }));
+        let mut head = self.head.load(Ordering::Acquire);
+        loop {
+            unsafe {
+                (*node).next = head;
+            }
+            match self.head.compare_exchange_weak(head, node, Ordering::AcqRel, Ordering::Acquire) {
+                Ok(_) => break,
+                Err(current) => head = current,
+            }
+        }
+    }
+
+    /// Takes all the messages out of the queue in the order they were pushed
+    pub fn drain(&self) -> Vec<Message> {
+        let mut node = self.head.swap(std::ptr::null_mut(), Ordering::AcqRel);
+        let mut output = Vec::new();
+        while !node.is_null() {
+            let boxed = unsafe { Box::from_raw(node) };
+            node = boxed.next;
+            output.push(boxed.message);
+        }
+        output.reverse();
+        output
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.head.load(Ordering::Acquire).is_null()
+    }
+
+    /// This should only be called while the world is stopped for garbage collection
+    fn collect_roots(&self, live_objects: &mut HashSet<Reference>) {
+        let mut node = self.head.load(Ordering::Acquire);
+        while let Some(current) = unsafe { node.as_ref() } {
+            current.message.collect_references(live_objects);
+            node = current.next;
+        }
+    }
+}
+
+/// Emitted signals go into the active queue while the other one is being delivered.
+/// This means that a signal emitted while handling a signal gets delivered on the next heartbeat.
+static MESSAGE_QUEUES: [MessageQueue; 2] = [MessageQueue::new(), MessageQueue::new()];
+static ACTIVE_QUEUE: AtomicUsize = AtomicUsize::new(0);
+
+pub struct MessageQueues;
+
+impl MessageQueues {
+    pub fn send(message: Message) {
+        MESSAGE_QUEUES[ACTIVE_QUEUE.load(Ordering::Acquire)].push(message);
+    }
+
+    /// Makes the other queue active and returns the messages of the previously active queue
+    pub fn swap() -> Vec<Message> {
+        let inactive = ACTIVE_QUEUE.fetch_xor(1, Ordering::AcqRel);
+        MESSAGE_QUEUES[inactive].drain()
+    }
+
+    pub fn has_pending() -> bool {
+        MESSAGE_QUEUES.iter().any(|queue| !queue.is_empty())
+    }
+
+    pub fn collect_roots(live_objects: &mut HashSet<Reference>) {
+        for queue in MESSAGE_QUEUES.iter() {
+            queue.collect_roots(live_objects);
+        }
+    }
+}
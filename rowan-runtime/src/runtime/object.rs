@@ -1,10 +1,25 @@
-use std::collections::HashSet;
 use crate::context::BytecodeContext;
-use crate::runtime::core::Array;
-use crate::runtime::garbage_collection::GarbageCollection;
+use crate::runtime::core::{Array, BaseObject};
+use crate::runtime::garbage_collection::{GarbageCollection, LiveObjects};
 use super::{Runtime, Reference, Symbol};
 
+/// The classes whose objects point to other objects outside of their members.
+/// They are looked up once per collection instead of for every object that gets marked.
+pub struct GcClasses {
+    array_object: Symbol,
+    exception: Symbol,
+    base_object: Symbol,
+}
 
+impl GcClasses {
+    pub fn new() -> Self {
+        GcClasses {
+            array_object: Runtime::get_class_symbol("core::Arrayobject"),
+            exception: Runtime::get_class_symbol("core::Exception"),
+            base_object: Runtime::get_class_symbol("core::Object"),
+        }
+    }
+}
 
 #[repr(C)]
 pub struct Object {
@@ -129,11 +144,10 @@ impl Object {
         let class = unsafe { class.as_ref()? };
         let mut pointer_offset = 0;
         for field in class.members.iter() {
-            let offset_part = field.get_size_and_padding();
-            pointer_offset += field.get_size_and_padding();
             if offset == 0 {
                 break;
             }
+            pointer_offset += field.get_size_and_padding();
             offset -= 1;
         }
 
@@ -151,10 +165,10 @@ impl Object {
         let class = unsafe { class.as_ref().unwrap() };
         let mut pointer_offset = 0;
         for field in class.members.iter() {
-            pointer_offset += field.get_size();
             if offset == 0 {
                 break;
             }
+            pointer_offset += field.get_size_and_padding();
             offset -= 1;
         }
         
@@ -188,7 +202,8 @@ impl Object {
 
         let parent = unsafe { object.parent_object.as_ref().unwrap() };
 
-        if parent.class == parent_symbol as Symbol {
+        // A parent symbol of 0 means that the class could be any of the parents
+        if parent_symbol == 0 || parent.class == parent_symbol as Symbol {
             if parent.class == class_symbol as Symbol {
                 return parent.get_safe(offset as usize).unwrap();
             }
@@ -202,17 +217,17 @@ impl Object {
     }
     fn get_internal_helper<T: Sized + Default>(context: &mut BytecodeContext, this: Reference, class_symbol: u64, offset: u64) -> Option<T> {
         let object = this;
-        let object = unsafe { object.as_ref().unwrap() };
+        let object = unsafe { object.as_ref()? };
 
         if object.class == class_symbol as Symbol {
-            return object.get_safe(offset as usize).unwrap();
+            return object.get_safe(offset as usize);
         }
 
-        let parent = unsafe { object.parent_object.as_ref().unwrap() };
+        let parent = unsafe { object.parent_object.as_ref()? };
 
 
         if parent.class == class_symbol as Symbol {
-            return parent.get_safe(offset as usize).unwrap();
+            return parent.get_safe(offset as usize);
         }
 
         Self::get_internal_helper(context, parent.parent_object, class_symbol, offset)
@@ -257,7 +272,8 @@ impl Object {
 
         let parent = unsafe { object.parent_object.as_mut().unwrap() };
 
-        if parent.class == parent_symbol as Symbol {
+        // A parent symbol of 0 means that the class could be any of the parents
+        if parent_symbol == 0 || parent.class == parent_symbol as Symbol {
             if parent.class == class_symbol as Symbol {
                 return parent.set_safe(offset as usize, value).unwrap();
             }
@@ -271,16 +287,16 @@ impl Object {
     }
     fn set_internal_helper<T: Sized + Default + Copy>(context: &mut BytecodeContext, this: Reference, class_symbol: u64, offset: u64, value: T) -> Option<()> {
         let object = this;
-        let object = unsafe { object.as_mut().unwrap() };
+        let object = unsafe { object.as_mut()? };
 
         if object.class == class_symbol as Symbol {
-            return object.get_safe(offset as usize).unwrap();
+            return object.set_safe(offset as usize, value);
         }
 
-        let parent = unsafe { object.parent_object.as_mut().unwrap() };
+        let parent = unsafe { object.parent_object.as_mut()? };
 
         if parent.class == class_symbol as Symbol {
-            return parent.get_safe(offset as usize).unwrap();
+            return parent.set_safe(offset as usize, value);
         }
 
         Self::set_internal_helper(context, parent.parent_object, class_symbol, offset, value)
@@ -303,6 +319,9 @@ impl Object {
     }
 
     pub extern "C" fn set_object(context: &mut BytecodeContext, this: Reference, class_symbol: u64, parent_symbol: u64, offset: u64, value: u64) {
+        if value != 0 {
+            context.write_barrier(this);
+        }
         Self::set_internal(context, this, class_symbol, parent_symbol, offset, value);
     }
 
@@ -314,24 +333,42 @@ impl Object {
         Self::set_internal(context, this, class_symbol, parent_symbol, offset, value);
     }
 
-    pub fn garbage_collect(this: Reference, live_objects: &mut HashSet<Reference>) {
+    pub fn garbage_collect(this: Reference, classes: &GcClasses, live_objects: &mut LiveObjects) {
         let object_ptr = this;
         let object = unsafe { object_ptr.as_ref() };
         let Some(object) = object else {
             return
         };
+        if !live_objects.mark(object_ptr) {
+            // We have already explored this object or it is old during a minor collection
+            return
+        }
+
+        Self::garbage_collect(object.parent_object, classes, live_objects);
+        Self::garbage_collect_members(object_ptr, classes, live_objects);
+    }
 
-        live_objects.insert(object.parent_object);
+    /// Explores what an old object points to during a minor collection.
+    /// The write barrier only records the object that was written to, so all of its parent objects are explored as well.
+    pub fn garbage_collect_remembered(this: Reference, classes: &GcClasses, live_objects: &mut LiveObjects) {
+        let mut current = this;
+        while let Some(object) = unsafe { current.as_ref() } {
+            Self::garbage_collect_members(current, classes, live_objects);
+            current = object.parent_object;
+        }
+    }
+
+    fn garbage_collect_members(object_ptr: Reference, classes: &GcClasses, live_objects: &mut LiveObjects) {
+        let object = unsafe { object_ptr.as_ref().unwrap() };
         let class = Runtime::get_class(object.class);
         let class = unsafe { class.as_ref().unwrap() };
         let live_objects_indices = class.get_object_member_indices();
         for index in live_objects_indices {
             let result = object.get_safe(index).unwrap();
-            Self::garbage_collect(result, live_objects);
+            Self::garbage_collect(result, classes, live_objects);
         }
 
-        let array_object = Runtime::get_class_symbol("core::Arrayobject");
-        if array_object == object.class {
+        if classes.array_object == object.class {
             let object_ptr = object_ptr as *mut Array;
             let object = unsafe { object_ptr.as_ref().unwrap() };
             let length = object.length;
@@ -339,10 +376,27 @@ impl Object {
             for i in 0..length {
                 unsafe {
                     let reference = pointer.add(i as usize).read();
-                    Self::garbage_collect(reference, live_objects);
+                    Self::garbage_collect(reference, classes, live_objects);
                 }
             }
-        } // TODO: do this for backtrace as well since it holds string objects
+        }
+
+        // The stack trace lives outside of the object, so its backtraces have to be walked by hand
+        if classes.exception == object.class {
+            for backtrace in crate::runtime::core::exception_backtraces(object_ptr) {
+                Self::garbage_collect(*backtrace, classes, live_objects);
+            }
+        }
+
+        if classes.base_object == object.class {
+            let object_ptr = object_ptr as *mut BaseObject;
+            let object = unsafe { object_ptr.as_ref().unwrap() };
+            for child in object.children() {
+                Self::garbage_collect(*child, classes, live_objects);
+            }
+            // Children point back to where they are attached, so a reachable child keeps its whole branch of the graph alive
+            Self::garbage_collect(object.attached_to, classes, live_objects);
+        }
 
     }
 }
@@ -0,0 +1,169 @@
+use std::sync::{LazyLock, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
+use fxhash::FxHashMap;
+use rowan_shared::bytecode::linked::Bytecode;
+use crate::runtime::class::TypeTag;
+
+/// The loops of bytecode that runs outside of a function, which never gets compiled
+pub static NO_LOOPS: LazyLock<Loops> = LazyLock::new(|| Loops::new(&[]));
+
+/// The types of a frame's variables, with `None` for the ones that haven't been set
+pub type LocalTypes = Box<[Option<TypeTag>]>;
+
+/// Compiled code that an interpreted frame can jump into at the start of one of its loops
+pub enum LoopEntry {
+    /// The JIT thread has been asked to compile an entry for locals of these types
+    Requested(LocalTypes),
+    /// Takes the context and a pointer to the values of the frame's variables, 8 bytes each, and runs the rest of the method
+    Compiled {
+        code: *const (),
+        locals: LocalTypes,
+        return_type: TypeTag,
+    },
+    /// The loop can't be entered in the middle of the method, like when there are values on the stack at its start
+    Failed,
+}
+
+/// Counts how often the loops of a function run so that a frame stuck in a long loop can move from the interpreter into compiled code.
+/// This is what gets methods that are only called once, like `main`, compiled.
+pub struct Loops {
+    /// How many times each loop header has been jumped back to, by block
+    back_edges: FxHashMap<usize, AtomicU64>,
+    entries: Mutex<FxHashMap<usize, LoopEntry>>,
+}
+
+unsafe impl Send for Loops {}
+unsafe impl Sync for Loops {}
+
+impl Loops {
+    /// Finds the loop headers of the bytecode, which are the blocks that a jump goes back to
+    pub fn new(bytecode: &[Bytecode]) -> Self {
+        let mut back_edges = FxHashMap::default();
+        let mut current_block = 0;
+        for bytecode in bytecode {
+            let offsets = match bytecode {
+                Bytecode::StartBlock(index) => {
+                    current_block = *index as i64;
+                    continue;
+                }
+                Bytecode::Goto(offset) => [Some(*offset), None],
+                Bytecode::If(then_offset, else_offset) => [Some(*then_offset), Some(*else_offset)],
+                _ => continue,
+            };
+            for offset in offsets.into_iter().flatten() {
+                if offset <= 0 {
+                    back_edges.insert((current_block + offset) as usize, AtomicU64::new(0));
+                }
+            }
+        }
+
+        Loops {
+            back_edges,
+            entries: Mutex::new(FxHashMap::default()),
+        }
+    }
+
+    /// Counts a jump back to `block` and returns how many there have been, or `None` if the block doesn't start a loop
+    pub fn count_back_edge(&self, block: usize) -> Option<u64> {
+        self.back_edges.get(&block)
+            .map(|count| count.fetch_add(1, Ordering::Relaxed) + 1)
+    }
+
+    /// Finds the code and return type to enter the loop at `block` with, if it has been compiled for locals of the current types.
+    /// The first time a hot loop gets here, `request` is called to have it compiled.
+    pub fn entry(
+        &self,
+        block: usize,
+        locals: impl FnOnce() -> LocalTypes,
+        request: impl FnOnce(),
+    ) -> Option<(*const (), TypeTag)> {
+        let mut entries = self.entries.lock().expect("Lock poisoned");
+        match entries.get(&block) {
+            None => {
+                entries.insert(block, LoopEntry::Requested(locals()));
+                drop(entries);
+                request();
+                None
+            }
+            Some(LoopEntry::Compiled { code, locals: types, return_type }) => {
+                if *types == locals() {
+                    Some((*code, *return_type))
+                } else {
+                    None
+                }
+            }
+            Some(LoopEntry::Requested(_) | LoopEntry::Failed) => None,
+        }
+    }
+
+    /// The types of the locals that the entry at `block` was requested for, if it still needs to be compiled
+    pub fn requested_locals(&self, block: usize) -> Option<LocalTypes> {
+        match self.entries.lock().expect("Lock poisoned").get(&block) {
+            Some(LoopEntry::Requested(locals)) => Some(locals.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn set_entry(&self, block: usize, entry: LoopEntry) {
+        self.entries.lock().expect("Lock poisoned").insert(block, entry);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn loop_bytecode() -> Vec<Bytecode> {
+        vec![
+            Bytecode::StartBlock(0),
+            Bytecode::Goto(1),
+            Bytecode::StartBlock(1),
+            Bytecode::If(1, 2),
+            Bytecode::StartBlock(2),
+            Bytecode::Goto(-1),
+            Bytecode::StartBlock(3),
+            Bytecode::ReturnVoid,
+        ]
+    }
+
+    #[test]
+    fn test_only_jumps_backwards_start_loops() {
+        let loops = Loops::new(&loop_bytecode());
+        assert_eq!(loops.count_back_edge(0), None);
+        assert_eq!(loops.count_back_edge(1), Some(1));
+        assert_eq!(loops.count_back_edge(1), Some(2));
+        assert_eq!(loops.count_back_edge(2), None);
+        assert_eq!(loops.count_back_edge(3), None);
+    }
+
+    #[test]
+    fn test_entry_is_requested_once() {
+        let loops = Loops::new(&loop_bytecode());
+        let locals = || vec![Some(TypeTag::U64), None].into_boxed_slice();
+        let mut requests = 0;
+        assert!(loops.entry(1, locals, || requests += 1).is_none());
+        assert!(loops.entry(1, locals, || requests += 1).is_none());
+        assert_eq!(requests, 1);
+        assert_eq!(loops.requested_locals(1), Some(locals()));
+        assert_eq!(loops.requested_locals(2), None);
+    }
+
+    #[test]
+    fn test_compiled_entry_needs_matching_locals() {
+        let loops = Loops::new(&loop_bytecode());
+        let code = 0x1000 as *const ();
+        loops.set_entry(1, LoopEntry::Compiled {
+            code,
+            locals: vec![Some(TypeTag::U64)].into_boxed_slice(),
+            return_type: TypeTag::Void,
+        });
+        let request = || panic!("a compiled entry shouldn't be requested");
+        assert_eq!(
+            loops.entry(1, || vec![Some(TypeTag::U64)].into_boxed_slice(), request),
+            Some((code, TypeTag::Void))
+        );
+        assert_eq!(loops.entry(1, || vec![Some(TypeTag::F64)].into_boxed_slice(), request), None);
+        assert_eq!(loops.requested_locals(1), None);
+    }
+}
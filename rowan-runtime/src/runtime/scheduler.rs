@@ -0,0 +1,391 @@
+use std::cmp::Ordering as CmpOrdering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::sync::{Condvar, LazyLock, Mutex};
+use std::sync::atomic::Ordering;
+use std::thread::Thread;
+use crate::context::{BytecodeContext, StackValue};
+use crate::runtime::garbage_collection::{Collection, LiveObjects};
+use crate::runtime::heartbeat::Heartbeat;
+use crate::runtime::message::Message;
+use crate::runtime::object::{GcClasses, Object};
+use crate::runtime::{Reference, Symbol, THREAD_COUNT};
+
+/// Something that only one thread may touch at a time while messages are being delivered.
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+enum Resource {
+    Object(Reference),
+    /// Static listeners share the static members of their class
+    Class(Symbol),
+}
+
+/// A message waiting in the priority queue.
+/// Messages with the same color don't share any objects, so they can be delivered at the same time.
+struct ScheduledMessage {
+    color: usize,
+    /// The position of the message in the order it was sent
+    sequence: usize,
+    message: Message,
+}
+
+impl PartialEq for ScheduledMessage {
+    fn eq(&self, other: &Self) -> bool {
+        self.color == other.color && self.sequence == other.sequence
+    }
+}
+
+impl Eq for ScheduledMessage {}
+
+impl PartialOrd for ScheduledMessage {
+    fn partial_cmp(&self, other: &Self) -> Option<CmpOrdering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ScheduledMessage {
+    /// Lower colors have a higher priority, ties are broken by the order the messages were sent in
+    fn cmp(&self, other: &Self) -> CmpOrdering {
+        other.color.cmp(&self.color)
+            .then_with(|| other.sequence.cmp(&self.sequence))
+    }
+}
+
+struct SchedulerState {
+    queue: BinaryHeap<ScheduledMessage>,
+    /// Only messages of this color may be taken from the queue
+    color: usize,
+    /// The number of messages of the current color that are being delivered
+    in_flight: usize,
+}
+
+static SCHEDULER: LazyLock<Mutex<SchedulerState>> = LazyLock::new(|| {
+    Mutex::new(SchedulerState {
+        queue: BinaryHeap::new(),
+        color: 0,
+        in_flight: 0,
+    })
+});
+
+/// Signaled when the last message of a color has been delivered or when a collection starts
+static DELIVERED: Condvar = Condvar::new();
+
+static WORKERS: LazyLock<Mutex<Vec<Thread>>> = LazyLock::new(|| {
+    Mutex::new(Vec::new())
+});
+
+/// Delivers messages on a pool of worker threads that each have their own `BytecodeContext`.
+pub struct Scheduler;
+
+impl Scheduler {
+    /// Starts the worker threads.
+    /// By default, there is one worker for every core besides the one the main thread is using.
+    pub fn initialize(worker_count: Option<usize>) {
+        let worker_count = worker_count.unwrap_or_else(|| {
+            std::thread::available_parallelism()
+                .map(|count| count.get() - 1)
+                .unwrap_or(0)
+        });
+        let Ok(mut workers) = WORKERS.lock() else {
+            panic!("Lock poisoned");
+        };
+        for i in 0..worker_count {
+            // Workers need to be counted before they start so that the garbage collector waits for them
+            THREAD_COUNT.read().fetch_add(1, Ordering::SeqCst);
+            let handle = std::thread::Builder::new().name(format!("Worker {i}"))
+                .spawn(Scheduler::worker_loop)
+                .expect("Thread 'new' panicked at 'Worker'");
+            workers.push(handle.thread().clone());
+        }
+    }
+
+    fn worker_loop() {
+        let mut context = BytecodeContext::new();
+        loop {
+            if Self::deliver_next(&mut context) {
+                continue;
+            }
+            // Idle workers still need to take part in garbage collection
+            context.check_and_do_garbage_collection();
+            // An unpark that happens before we park isn't lost, so a wakeup can't slip in between the check and here
+            std::thread::park();
+        }
+    }
+
+    /// Delivers the messages in the order of their colors.
+    /// The calling thread helps deliver the messages and only returns once all of them have been delivered.
+    pub fn deliver(context: &mut BytecodeContext, messages: Vec<Message>) {
+        if messages.is_empty() {
+            return;
+        }
+        {
+            let Ok(mut state) = SCHEDULER.lock() else {
+                panic!("Lock poisoned");
+            };
+            state.color = 0;
+            let classes = GcClasses::new();
+            state.queue.extend(Self::color(messages, |message| Self::resources(message, &classes)));
+        }
+        Self::wake_workers();
+
+        loop {
+            if Self::deliver_next(context) {
+                continue;
+            }
+            let Ok(mut state) = SCHEDULER.lock() else {
+                panic!("Lock poisoned");
+            };
+            if state.in_flight != 0 {
+                // The collector signals us after it has started, so we only wait if it hasn't started yet
+                if context.check_for_garbage_collection() {
+                    let Ok(_state) = DELIVERED.wait(state) else {
+                        panic!("Lock poisoned");
+                    };
+                } else {
+                    drop(state);
+                    context.check_and_do_garbage_collection();
+                }
+                continue;
+            }
+            // Every message of the current color has been delivered, so we can move on to the next one
+            let Some(next) = state.queue.peek() else {
+                break;
+            };
+            state.color = next.color;
+            drop(state);
+            Self::wake_workers();
+        }
+    }
+
+    /// Colors the conflict graph of the messages, where messages that share a resource are connected.
+    /// Each message gets a higher color than the earlier messages it is connected to.
+    /// This keeps the order that an object receives its messages in.
+    fn color(messages: Vec<Message>, resources: impl Fn(&Message) -> Vec<Resource>) -> Vec<ScheduledMessage> {
+        let mut last_colors: HashMap<Resource, usize> = HashMap::new();
+        messages.into_iter()
+            .enumerate()
+            .map(|(sequence, message)| {
+                let resources = resources(&message);
+                let color = resources.iter()
+                    .filter_map(|resource| last_colors.get(resource))
+                    .map(|color| color + 1)
+                    .max()
+                    .unwrap_or(0);
+                for resource in resources {
+                    last_colors.insert(resource, color);
+                }
+                ScheduledMessage {
+                    color,
+                    sequence,
+                    message,
+                }
+            })
+            .collect()
+    }
+
+    /// Everything a handler can touch, which is every object reachable from its receiver and the objects passed to it.
+    /// The objects are found the same way the garbage collector finds live objects.
+    /// Nothing can be freed while we look since the delivering thread doesn't take part in a collection until it is done.
+    fn resources(message: &Message, classes: &GcClasses) -> Vec<Resource> {
+        let mut reachable = LiveObjects::new(Collection::Major, HashSet::new());
+        let mut resources = Vec::new();
+        for root in Self::roots(message) {
+            match root {
+                Resource::Object(reference) => Object::garbage_collect(reference, classes, &mut reachable),
+                Resource::Class(_) => resources.push(root),
+            }
+        }
+        resources.extend(reachable.marked().map(Resource::Object));
+        resources
+    }
+
+    /// The receiver of a message, or its class for static listeners, and the objects passed to it
+    fn roots(message: &Message) -> Vec<Resource> {
+        let mut resources = Vec::new();
+        if message.receiver.is_null() {
+            resources.push(Resource::Class(message.class));
+        } else {
+            resources.push(Resource::Object(message.receiver));
+        }
+        for arg in message.args.iter() {
+            match arg {
+                StackValue::Reference(reference) if !reference.is_null() => {
+                    resources.push(Resource::Object(*reference));
+                }
+                _ => {}
+            }
+        }
+        resources
+    }
+
+    fn wake_workers() {
+        let Ok(workers) = WORKERS.lock() else {
+            panic!("Lock poisoned");
+        };
+        for worker in workers.iter() {
+            worker.unpark();
+        }
+    }
+
+    /// Returns false if there wasn't a message of the current color to deliver
+    fn deliver_next(context: &mut BytecodeContext) -> bool {
+        let message = {
+            let Ok(mut state) = SCHEDULER.lock() else {
+                panic!("Lock poisoned");
+            };
+            match state.queue.peek() {
+                Some(next) if next.color <= state.color => {}
+                _ => return false,
+            }
+            state.in_flight += 1;
+            state.queue.pop().unwrap().message
+        };
+
+        Self::invoke(context, message);
+
+        let Ok(mut state) = SCHEDULER.lock() else {
+            panic!("Lock poisoned");
+        };
+        state.in_flight -= 1;
+        if state.in_flight == 0 {
+            DELIVERED.notify_all();
+        }
+        true
+    }
+
+    fn invoke(context: &mut BytecodeContext, message: Message) {
+        let Message { receiver, class, method, args } = message;
+        Heartbeat::clear_args(context);
+        if receiver.is_null() {
+            for (i, arg) in args.into_iter().enumerate() {
+                context.store_argument(i as u8, arg);
+            }
+            context.invoke_static_extern(class, method, None);
+            context.check_for_uncaught_exception();
+            return;
+        }
+        context.store_argument(0, StackValue::Reference(receiver));
+        for (i, arg) in args.into_iter().enumerate() {
+            context.store_argument(i as u8 + 1, arg);
+        }
+        context.invoke_virtual_extern(class, method, None, None);
+        // There is no caller that could catch an exception thrown by a listener
+        context.check_for_uncaught_exception();
+    }
+
+    /// Wakes the idle workers and the thread waiting for a color to be delivered so that they take part in a collection.
+    /// This should be called once the collection has started.
+    pub fn wake_for_collection() {
+        Self::wake_workers();
+        // The lock makes sure that the delivering thread is either waiting or hasn't checked for a collection yet
+        let Ok(_state) = SCHEDULER.lock() else {
+            panic!("Lock poisoned");
+        };
+        DELIVERED.notify_all();
+    }
+
+    /// This should only be called while the world is stopped for garbage collection
+    pub fn collect_roots(live_objects: &mut HashSet<Reference>) {
+        let Ok(state) = SCHEDULER.lock() else {
+            panic!("Lock poisoned");
+        };
+        for scheduled in state.queue.iter() {
+            scheduled.message.collect_references(live_objects);
+        }
+    }
+}
+
+unsafe impl Send for ScheduledMessage {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn object(address: usize) -> Reference {
+        address as Reference
+    }
+
+    fn colors(messages: Vec<Message>) -> Vec<usize> {
+        Scheduler::color(messages, Scheduler::roots).into_iter()
+            .map(|scheduled| scheduled.color)
+            .collect()
+    }
+
+    #[test]
+    fn test_different_receivers_share_a_color() {
+        let messages = vec![
+            Message::new(object(8), 1, 2, Vec::new()),
+            Message::new(object(16), 1, 2, Vec::new()),
+            Message::new(object(24), 1, 2, Vec::new()),
+        ];
+        assert_eq!(colors(messages), vec![0, 0, 0]);
+    }
+
+    #[test]
+    fn test_same_receiver_keeps_its_order() {
+        let messages = vec![
+            Message::new(object(8), 1, 2, Vec::new()),
+            Message::new(object(16), 1, 2, Vec::new()),
+            Message::new(object(8), 1, 3, Vec::new()),
+            Message::new(object(8), 1, 2, Vec::new()),
+        ];
+        assert_eq!(colors(messages), vec![0, 0, 1, 2]);
+    }
+
+    #[test]
+    fn test_object_arguments_conflict() {
+        let messages = vec![
+            Message::new(object(8), 1, 2, Vec::new()),
+            Message::new(object(16), 1, 2, vec![StackValue::Reference(object(8))]),
+            Message::new(object(24), 1, 2, vec![StackValue::Reference(std::ptr::null_mut()), StackValue::Int64(8)]),
+            Message::new(object(32), 1, 2, vec![StackValue::Reference(object(16))]),
+        ];
+        assert_eq!(colors(messages), vec![0, 1, 0, 2]);
+    }
+
+    #[test]
+    fn test_messages_that_reach_the_same_object_conflict() {
+        let reachable = HashMap::from([
+            (object(8), vec![object(8), object(40)]),
+            (object(16), vec![object(16), object(40)]),
+            (object(24), vec![object(24), object(48)]),
+        ]);
+        let messages = vec![
+            Message::new(object(8), 1, 2, Vec::new()),
+            Message::new(object(16), 1, 2, Vec::new()),
+            Message::new(object(24), 1, 2, Vec::new()),
+        ];
+        let colors = Scheduler::color(messages, |message| {
+            reachable[&message.receiver].iter()
+                .map(|reference| Resource::Object(*reference))
+                .collect()
+        }).into_iter()
+            .map(|scheduled| scheduled.color)
+            .collect::<Vec<_>>();
+        assert_eq!(colors, vec![0, 1, 0]);
+    }
+
+    #[test]
+    fn test_static_messages_conflict_by_class() {
+        let messages = vec![
+            Message::new_static(1, 2, Vec::new()),
+            Message::new_static(4, 2, Vec::new()),
+            Message::new_static(1, 3, Vec::new()),
+        ];
+        assert_eq!(colors(messages), vec![0, 0, 1]);
+    }
+
+    #[test]
+    fn test_queue_orders_by_color_then_sequence() {
+        let messages = vec![
+            Message::new(object(8), 1, 2, Vec::new()),
+            Message::new(object(8), 1, 2, Vec::new()),
+            Message::new(object(16), 1, 2, Vec::new()),
+            Message::new(object(16), 1, 2, Vec::new()),
+        ];
+        let mut queue = BinaryHeap::new();
+        queue.extend(Scheduler::color(messages, Scheduler::roots));
+        let order = std::iter::from_fn(|| queue.pop())
+            .map(|scheduled| (scheduled.color, scheduled.sequence))
+            .collect::<Vec<_>>();
+        assert_eq!(order, vec![(0, 0), (0, 2), (1, 1), (1, 3)]);
+    }
+}
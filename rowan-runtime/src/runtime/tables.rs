@@ -5,5 +5,6 @@ pub mod string_table;
 pub mod object_table;
 pub mod native_object_table;
 pub mod interface_table;
+pub mod signal_table;
 
-pub use vtable::FunctionDetails;
+pub use vtable::{Function, FunctionDetails, SourceLocation};
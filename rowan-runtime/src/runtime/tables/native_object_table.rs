@@ -1,13 +1,16 @@
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use libloading::Library;
 
 pub struct NativeObjectTable {
     table: HashMap<String,Library>,
+    /// Native libraries that came out of a package, keyed by the path they would have on disk
+    bundled: HashMap<String, Vec<u8>>,
 }
 
 impl NativeObjectTable {
     pub fn new() -> Self {
-        Self { table: HashMap::new() }
+        Self { table: HashMap::new(), bundled: HashMap::new() }
     }
 
     pub fn insert(&mut self, path: String, lib: Library) {
@@ -18,5 +21,37 @@ impl NativeObjectTable {
         self.table.get_mut(path)
     }
 
-}
+    pub fn add_bundled(&mut self, path: String, contents: Vec<u8>) {
+        self.bundled.insert(path, contents);
+    }
+
+    /// Gets the library at path, opening it if this is the first time it is needed.
+    /// Bundled libraries are preferred over ones on disk.
+    pub fn load(&mut self, path: &Path) -> &mut Library {
+        let key = path.to_str().unwrap().to_string();
+        if !self.table.contains_key(&key) {
+            let lib = match self.bundled.remove(&key) {
+                Some(contents) => Self::open_bundled(&key, &contents),
+                None => unsafe { Library::new(path).expect("Handle Missing library") },
+            };
+            self.table.insert(key.clone(), lib);
+        }
+        self.table.get_mut(&key).unwrap()
+    }
 
+    /// The dynamic loader can only open files, so the library gets written to a temporary directory first
+    fn open_bundled(key: &str, contents: &[u8]) -> Library {
+        let mut extracted = std::env::temp_dir();
+        extracted.push(format!("rowan-{}", std::process::id()));
+        extracted.push(key.replace(['/', '\\'], "__"));
+        let directory = extracted.parent().map(PathBuf::from).unwrap();
+        std::fs::create_dir_all(&directory).expect("Unable to create directory for bundled library");
+        std::fs::write(&extracted, contents).expect("Unable to extract bundled library");
+
+        let lib = unsafe { Library::new(&extracted).expect("Handle Missing library") };
+        // The library stays mapped after its file is gone
+        let _ = std::fs::remove_file(&extracted);
+        let _ = std::fs::remove_dir(&directory);
+        lib
+    }
+}
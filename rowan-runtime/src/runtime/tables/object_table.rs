@@ -9,6 +9,8 @@ use super::{class_table::ClassTable, symbol_table::{SymbolEntry, SymbolTable}};
 pub struct ObjectTable {
     /// This is a hashset for ease of freeing up references
     table: HashSet<*mut Object>,
+    /// The objects allocated since the last collection
+    nursery: HashSet<*mut Object>,
     do_not_collect: HashSet<*mut Object>,
 
 }
@@ -17,6 +19,7 @@ impl ObjectTable {
     pub fn new() -> Self {
         ObjectTable {
             table: HashSet::new(),
+            nursery: HashSet::new(),
             do_not_collect: HashSet::new(),
         }
     }
@@ -26,9 +29,19 @@ impl ObjectTable {
             panic!("not in address space")
         }
         self.table.insert(ptr);
+        self.nursery.insert(ptr);
         ptr
     }
 
+    pub fn contains(&self, reference: &Reference) -> bool {
+        self.table.contains(reference)
+    }
+
+    /// Takes the objects allocated since the last collection, which makes them old
+    pub fn take_nursery(&mut self) -> HashSet<*mut Object> {
+        std::mem::take(&mut self.nursery)
+    }
+
     pub fn free(&mut self, reference: Reference, symbol_table: &SymbolTable, class_table: &ClassTable) {
         let pointer = reference;
 
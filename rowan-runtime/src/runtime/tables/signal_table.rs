@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+
+use crate::runtime::{Reference, Symbol};
+use crate::runtime::garbage_collection::LiveObjects;
+
+/// A method that gets called when a signal is emitted
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Connection {
+    pub signal: Symbol,
+    pub receiver: Reference,
+    /// The class used to find the vtable of the method
+    pub class: Symbol,
+    pub method: Symbol,
+}
+
+impl Connection {
+    pub fn new(signal: Symbol, receiver: Reference, class: Symbol, method: Symbol) -> Self {
+        Connection {
+            signal,
+            receiver,
+            class,
+            method,
+        }
+    }
+}
+
+/// Keeps track of which objects are connected to the signals of an object.
+/// It also holds the static methods that listen to static signals, these are added when linking.
+pub struct SignalTable {
+    table: HashMap<Reference, Vec<Connection>>,
+    /// Maps a static signal to the class and name of each static method listening to it
+    listeners: HashMap<Symbol, Vec<(Symbol, Symbol)>>,
+}
+
+impl SignalTable {
+    pub fn new() -> Self {
+        SignalTable {
+            table: HashMap::new(),
+            listeners: HashMap::new(),
+        }
+    }
+
+    pub fn add_listener(&mut self, signal: Symbol, class: Symbol, method: Symbol) {
+        self.listeners.entry(signal)
+            .or_insert_with(Vec::new)
+            .push((class, method));
+    }
+
+    /// Returns the static methods listening to a static signal in the order they were linked
+    pub fn get_listeners(&self, signal: Symbol) -> &[(Symbol, Symbol)] {
+        self.listeners.get(&signal)
+            .map(|listeners| listeners.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// Connecting the same method twice is a noop
+    pub fn connect(&mut self, emitter: Reference, connection: Connection) {
+        let connections = self.table.entry(emitter).or_insert_with(Vec::new);
+        if !connections.contains(&connection) {
+            connections.push(connection);
+        }
+    }
+
+    pub fn disconnect(&mut self, emitter: Reference, connection: Connection) {
+        let Some(connections) = self.table.get_mut(&emitter) else {
+            return;
+        };
+        connections.retain(|c| *c != connection);
+        if connections.is_empty() {
+            self.table.remove(&emitter);
+        }
+    }
+
+    /// Returns the connections of a signal in the order they were made
+    pub fn get_connections(&self, emitter: Reference, signal: Symbol) -> Vec<Connection> {
+        let Some(connections) = self.table.get(&emitter) else {
+            return Vec::new();
+        };
+        connections.iter()
+            .filter(|connection| connection.signal == signal)
+            .copied()
+            .collect()
+    }
+
+    /// Returns the receivers connected to live emitters that aren't yet known to be live.
+    pub fn collect_receivers(&self, live_objects: &LiveObjects) -> Vec<Reference> {
+        let mut output = Vec::new();
+        for (emitter, connections) in self.table.iter() {
+            if !live_objects.contains(emitter) {
+                continue;
+            }
+            for connection in connections {
+                if !connection.receiver.is_null() && !live_objects.contains(&connection.receiver) {
+                    output.push(connection.receiver);
+                }
+            }
+        }
+        output
+    }
+
+    /// Removes all the connections of objects that have been collected
+    pub fn remove_dead(&mut self, is_live: impl Fn(&Reference) -> bool) {
+        self.table.retain(|emitter, connections| {
+            if !is_live(emitter) {
+                return false;
+            }
+            connections.retain(|connection| is_live(&connection.receiver));
+            !connections.is_empty()
+        });
+    }
+}
+
+unsafe impl Send for SignalTable {}
+unsafe impl Sync for SignalTable {}
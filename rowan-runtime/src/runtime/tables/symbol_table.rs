@@ -37,6 +37,10 @@ impl SymbolTable {
         self.table.push(SymbolEntry::InterfaceRef(index));
         out
     }
+
+    pub fn iter(&self) -> impl Iterator<Item=&SymbolEntry> {
+        self.table.iter()
+    }
 }
 
 
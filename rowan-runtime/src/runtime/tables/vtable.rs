@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 use std::fmt::Debug;
 use std::ptr::NonNull;
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
 use std::sync::{Arc, Mutex, TryLockError};
 use cranelift::prelude::Signature;
 use cranelift_module::FuncId;
@@ -9,7 +9,9 @@ use fxhash::FxHashMap;
 use rowan_shared::bytecode::linked::Bytecode;
 use crate::context::MethodName;
 use crate::runtime::{class::TypeTag, Index, Symbol, VTableIndex};
-use crate::runtime::jit::request_to_jit_method;
+use crate::runtime::inline_cache::{create_inline_caches, InlineCaches};
+use crate::runtime::osr::Loops;
+use crate::runtime::jit::{jit_threshold, request_to_jit_method, Tier, TIER_TWO_FACTOR};
 
 pub struct FunctionDetails {
     pub bytecode: &'static [Bytecode],
@@ -17,6 +19,8 @@ pub struct FunctionDetails {
     pub return_type: TypeTag,
     pub fn_ptr: Option<NonNull<()>>,
     pub block_positions: &'static FxHashMap<usize, usize>,
+    pub inline_caches: &'static InlineCaches,
+    pub loops: &'static Loops,
 }
 
 #[derive(Clone)]
@@ -60,7 +64,30 @@ pub struct Function {
     pub return_type: TypeTag,
     pub signature: Signature,
     pub block_positions: Box<FxHashMap<usize, usize>>,
+    /// Where in the source the bytecode came from, sorted by bytecode index
+    pub lines: Box<[SourceLocation]>,
     pub times_called: AtomicU64,
+    /// How far the function has gotten through the tiers of the JIT, one of the `TIER_` constants
+    pub tier: AtomicU8,
+    pub inline_caches: InlineCaches,
+    pub loops: Loops,
+}
+
+/// The function has only been interpreted
+pub const TIER_INTERPRETED: u8 = 0;
+/// The function has been compiled without looking at what it calls
+pub const TIER_BASELINE: u8 = 1;
+/// The function is waiting to be compiled again using its inline caches
+pub const TIER_OPTIMIZING: u8 = 2;
+pub const TIER_OPTIMIZED: u8 = 3;
+
+/// The source position of the bytecode from `index` up to the next location
+#[derive(Debug, Copy, Clone)]
+pub struct SourceLocation {
+    pub index: usize,
+    pub file: Symbol,
+    pub line: u64,
+    pub column: u64,
 }
 
 impl Function {
@@ -72,7 +99,10 @@ impl Function {
         return_type: TypeTag,
         signature: Signature,
         block_positions: Box<FxHashMap<usize, usize>>,
+        lines: Box<[SourceLocation]>,
     ) -> Self {
+        let inline_caches = create_inline_caches(&bytecode);
+        let loops = Loops::new(&bytecode);
         Function {
             name,
             bytecode,
@@ -81,22 +111,52 @@ impl Function {
             return_type,
             signature,
             block_positions,
+            lines,
             times_called: AtomicU64::new(0),
+            tier: AtomicU8::new(TIER_INTERPRETED),
+            inline_caches,
+            loops,
+        }
+    }
+
+    /// Checks if the JIT still has to compile the function to reach `tier`
+    pub fn can_compile(&self, tier: Tier) -> bool {
+        match (&*self.value.lock().unwrap(), tier) {
+            (FunctionValue::Bytecode(_), Tier::Baseline) => true,
+            (FunctionValue::Compiled(..), Tier::Optimized) => self.tier.load(Ordering::Relaxed) == TIER_OPTIMIZING,
+            (FunctionValue::Bytecode(_) | FunctionValue::Compiled(..), Tier::Loop(block)) => self.loops.requested_locals(block).is_some(),
+            _ => false,
         }
     }
 
+    /// Finds the source position of the bytecode at `index`
+    pub fn find_location(&self, index: usize) -> Option<SourceLocation> {
+        let position = self.lines.partition_point(|location| location.index <= index);
+        position.checked_sub(1).map(|position| self.lines[position])
+    }
+
+    /// Finds the source position of a native address inside of the compiled version of this function
+    pub fn find_native_location(&self, address: usize) -> Option<SourceLocation> {
+        let value = self.value.lock().unwrap();
+        let FunctionValue::Compiled(_, _, ranges) = &*value else {
+            return None;
+        };
+        let (_, _, index) = ranges.iter()
+            .find(|(start, end, _)| *start <= address && address < *end)?;
+        self.find_location(*index)
+    }
+
     pub fn create_details(&self, name: MethodName) -> FunctionDetails {
         let times_called = self.times_called.fetch_add(1, Ordering::Relaxed) + 1;
 
-        // Tell the JIT Thread to compile this Function after 1000 accesses
-        // TODO: make this configurable
-        
-        if times_called == 1000 && !self.value.lock().unwrap().is_compiled() {
+        // Tell the JIT Thread to compile this Function once it has been called enough
+        let threshold = jit_threshold();
+        if threshold != 0 && times_called == threshold && !self.value.lock().unwrap().is_compiled() {
             //println!("Requesting JIT");
             match self.value.try_lock() {
                 Ok(mut guard) => {
-                    if guard.is_compiled() {
-                        request_to_jit_method(name)
+                    if !guard.is_compiled() {
+                        request_to_jit_method(name, Tier::Baseline)
                     }
                 }
                 Err(TryLockError::WouldBlock) => {}
@@ -104,8 +164,13 @@ impl Function {
                     panic!("Lock poisoned");
                 }
             }
-            
-            
+        }
+
+        // Methods that stay hot once compiled get compiled again with what their inline caches have seen
+        if threshold != 0 && times_called >= threshold.saturating_mul(TIER_TWO_FACTOR)
+            && self.tier.load(Ordering::Relaxed) == TIER_BASELINE
+            && self.tier.compare_exchange(TIER_BASELINE, TIER_OPTIMIZING, Ordering::Relaxed, Ordering::Relaxed).is_ok() {
+            request_to_jit_method(name, Tier::Optimized)
         }
 
         let bytecode_ptr = self.bytecode.as_ptr();
@@ -126,7 +191,7 @@ impl Function {
                     FunctionValue::Builtin(ptr) => {
                         NonNull::new(*ptr as *mut ())
                     }
-                    FunctionValue::Compiled(ptr, _) => {
+                    FunctionValue::Compiled(ptr, _, _) => {
                         NonNull::new(*ptr as *mut ())
                     }
                     FunctionValue::Native(ptr) => {
@@ -140,13 +205,17 @@ impl Function {
         };
 
         let block_positions = &*self.block_positions as *const FxHashMap<usize, usize>;
+        let inline_caches = &self.inline_caches as *const InlineCaches;
+        let loops = &self.loops as *const Loops;
 
         FunctionDetails {
             bytecode: bytecode_ref,
             arguments: arguments_ref,
             return_type: self.return_type,
             fn_ptr,
-            block_positions: unsafe { block_positions.as_ref().unwrap() }
+            block_positions: unsafe { block_positions.as_ref().unwrap() },
+            inline_caches: unsafe { inline_caches.as_ref().unwrap() },
+            loops: unsafe { loops.as_ref().unwrap() },
         }
     }
 }
@@ -167,7 +236,8 @@ pub enum FunctionValue {
     Builtin(*const ()),
     Bytecode(FuncId),
     /// The hashmap's key is a stack pointer address where we have spilled objects onto the stack
-    Compiled(*const (), HashMap<usize, Vec<u32>>),
+    /// The slice holds ranges of native addresses and the index of the bytecode they were translated from
+    Compiled(*const (), HashMap<usize, Vec<u32>>, Box<[(usize, usize, usize)]>),
     Native(*const ()),
     Blank,
 }
@@ -182,7 +252,7 @@ impl FunctionValue {
 
     pub fn is_compiled(&self) -> bool {
         match self {
-            FunctionValue::Compiled(_, _) => true,
+            FunctionValue::Compiled(_, _, _) => true,
             FunctionValue::Native(..) => true,
             FunctionValue::Builtin(..) => true,
             _ => false,
@@ -199,7 +269,7 @@ impl Debug for FunctionValue {
                     .finish()
             }
             FunctionValue::Blank => f.debug_struct("Blank").finish(),
-            FunctionValue::Compiled(ptr, _) => {
+            FunctionValue::Compiled(ptr, _, _) => {
                 f.debug_struct("Compiled")
                 .field("ptr", ptr)
                 .finish()
@@ -8,6 +8,8 @@ mod windows;
 struct JitFunctionMap {
     start: usize,
     end: usize,
+    /// Code that was compiled ahead of time, which is loaded far away from the code compiled at runtime
+    libraries: Vec<(usize, usize)>,
 }
 
 impl JitFunctionMap {
@@ -15,11 +17,13 @@ impl JitFunctionMap {
         JitFunctionMap {
             start: 0,
             end: 0,
+            libraries: Vec::new(),
         }
     }
 
     fn test_ip(&self, ip: usize) -> bool {
-        ip >= self.start && ip <= self.end
+        (ip >= self.start && ip <= self.end)
+            || self.libraries.iter().any(|(start, end)| ip >= *start && ip <= *end)
     }
 
     fn register(&mut self, pointer: usize, size: usize) {
@@ -29,6 +33,9 @@ impl JitFunctionMap {
             return;
         }
 
+        if pointer < self.start {
+            self.start = pointer;
+        }
         if pointer + size > self.end {
             self.end = pointer + size;
         }
@@ -49,6 +56,12 @@ pub fn register(pointer: *const (), size: usize) {
     map.register(pointer as usize, size);
 }
 
+/// Registers code that was loaded from a library, which is kept apart from the code registered with `register`
+pub fn register_library(pointer: *const (), size: usize) {
+    let mut map = JIT_MAP.write().expect("failed to lock JIT_MAP");
+    map.libraries.push((pointer as usize, pointer as usize + size));
+}
+
 pub struct Frame {
     sp: usize,
     ip: usize,
@@ -154,7 +154,7 @@ pub enum Bytecode {
     /// The first StringIndex is the class name we are accessing, the second StringIndex is another classname
     /// that allows for selecting the particular parent to access the field.
     SetField(StringIndex, StringIndex, u64, TypeTag),
-    /// Check if an object is of a specified class
+    /// Check if an object is of a specified class or inherits from it
     IsA(StringIndex),
     /// Invoke a virtual method on an object of the specified class
     /// The first is the class name that the method belongs to
@@ -217,6 +217,28 @@ pub enum Bytecode {
     /// Switch to one of several blocks based on the value of the top of the stack
     /// The first block is the default case
     Switch(Vec<BlockIdOffset>, Option<BlockIdOffset>),
+    /// Emit a signal from an object
+    /// The first StringIndex is the class name that declares the signal
+    /// The second StringIndex is the signal name
+    /// The emitting object is argument 0 and the payload is in the following arguments
+    EmitSignal(StringIndex, StringIndex),
+    /// Connect a method on an object to a signal on another object
+    /// This pops the receiving object off of the stack and then the emitting object
+    /// The first StringIndex is the signal name
+    /// The second StringIndex is the class name that the method belongs to
+    /// The third StringIndex is the method name
+    ConnectSignal(StringIndex, StringIndex, StringIndex),
+    /// Disconnect a method on an object from a signal on another object
+    /// This pops the receiving object off of the stack and then the emitting object
+    /// The first StringIndex is the signal name
+    /// The second StringIndex is the class name that the method belongs to
+    /// The third StringIndex is the method name
+    DisconnectSignal(StringIndex, StringIndex, StringIndex),
+    /// Emit a static signal to every method that was linked as a listener
+    /// The first StringIndex is the class name that declares the signal
+    /// The second StringIndex is the signal name
+    /// The payload starts at argument 0
+    EmitStaticSignal(StringIndex, StringIndex),
 }
 
 impl Bytecode {
@@ -663,6 +685,78 @@ impl Bytecode {
                     };
                     result.push(Bytecode::Switch(cases, default));
                 },
+                86 => {
+                    let class_name = u64::from_le_bytes([
+                        *iter.next().ok_or("Expected u8 value")?, *iter.next().ok_or("Expected u8 value")?,
+                        *iter.next().ok_or("Expected u8 value")?, *iter.next().ok_or("Expected u8 value")?,
+                        *iter.next().ok_or("Expected u8 value")?, *iter.next().ok_or("Expected u8 value")?,
+                        *iter.next().ok_or("Expected u8 value")?, *iter.next().ok_or("Expected u8 value")?,
+                    ]);
+                    let signal_name = u64::from_le_bytes([
+                        *iter.next().ok_or("Expected u8 value")?, *iter.next().ok_or("Expected u8 value")?,
+                        *iter.next().ok_or("Expected u8 value")?, *iter.next().ok_or("Expected u8 value")?,
+                        *iter.next().ok_or("Expected u8 value")?, *iter.next().ok_or("Expected u8 value")?,
+                        *iter.next().ok_or("Expected u8 value")?, *iter.next().ok_or("Expected u8 value")?,
+                    ]);
+                    result.push(Bytecode::EmitSignal(class_name, signal_name));
+                }
+                87 => {
+                    let signal_name = u64::from_le_bytes([
+                        *iter.next().ok_or("Expected u8 value")?, *iter.next().ok_or("Expected u8 value")?,
+                        *iter.next().ok_or("Expected u8 value")?, *iter.next().ok_or("Expected u8 value")?,
+                        *iter.next().ok_or("Expected u8 value")?, *iter.next().ok_or("Expected u8 value")?,
+                        *iter.next().ok_or("Expected u8 value")?, *iter.next().ok_or("Expected u8 value")?,
+                    ]);
+                    let class_name = u64::from_le_bytes([
+                        *iter.next().ok_or("Expected u8 value")?, *iter.next().ok_or("Expected u8 value")?,
+                        *iter.next().ok_or("Expected u8 value")?, *iter.next().ok_or("Expected u8 value")?,
+                        *iter.next().ok_or("Expected u8 value")?, *iter.next().ok_or("Expected u8 value")?,
+                        *iter.next().ok_or("Expected u8 value")?, *iter.next().ok_or("Expected u8 value")?,
+                    ]);
+                    let method_name = u64::from_le_bytes([
+                        *iter.next().ok_or("Expected u8 value")?, *iter.next().ok_or("Expected u8 value")?,
+                        *iter.next().ok_or("Expected u8 value")?, *iter.next().ok_or("Expected u8 value")?,
+                        *iter.next().ok_or("Expected u8 value")?, *iter.next().ok_or("Expected u8 value")?,
+                        *iter.next().ok_or("Expected u8 value")?, *iter.next().ok_or("Expected u8 value")?,
+                    ]);
+                    result.push(Bytecode::ConnectSignal(signal_name, class_name, method_name));
+                }
+                88 => {
+                    let signal_name = u64::from_le_bytes([
+                        *iter.next().ok_or("Expected u8 value")?, *iter.next().ok_or("Expected u8 value")?,
+                        *iter.next().ok_or("Expected u8 value")?, *iter.next().ok_or("Expected u8 value")?,
+                        *iter.next().ok_or("Expected u8 value")?, *iter.next().ok_or("Expected u8 value")?,
+                        *iter.next().ok_or("Expected u8 value")?, *iter.next().ok_or("Expected u8 value")?,
+                    ]);
+                    let class_name = u64::from_le_bytes([
+                        *iter.next().ok_or("Expected u8 value")?, *iter.next().ok_or("Expected u8 value")?,
+                        *iter.next().ok_or("Expected u8 value")?, *iter.next().ok_or("Expected u8 value")?,
+                        *iter.next().ok_or("Expected u8 value")?, *iter.next().ok_or("Expected u8 value")?,
+                        *iter.next().ok_or("Expected u8 value")?, *iter.next().ok_or("Expected u8 value")?,
+                    ]);
+                    let method_name = u64::from_le_bytes([
+                        *iter.next().ok_or("Expected u8 value")?, *iter.next().ok_or("Expected u8 value")?,
+                        *iter.next().ok_or("Expected u8 value")?, *iter.next().ok_or("Expected u8 value")?,
+                        *iter.next().ok_or("Expected u8 value")?, *iter.next().ok_or("Expected u8 value")?,
+                        *iter.next().ok_or("Expected u8 value")?, *iter.next().ok_or("Expected u8 value")?,
+                    ]);
+                    result.push(Bytecode::DisconnectSignal(signal_name, class_name, method_name));
+                }
+                89 => {
+                    let class_name = u64::from_le_bytes([
+                        *iter.next().ok_or("Expected u8 value")?, *iter.next().ok_or("Expected u8 value")?,
+                        *iter.next().ok_or("Expected u8 value")?, *iter.next().ok_or("Expected u8 value")?,
+                        *iter.next().ok_or("Expected u8 value")?, *iter.next().ok_or("Expected u8 value")?,
+                        *iter.next().ok_or("Expected u8 value")?, *iter.next().ok_or("Expected u8 value")?,
+                    ]);
+                    let signal_name = u64::from_le_bytes([
+                        *iter.next().ok_or("Expected u8 value")?, *iter.next().ok_or("Expected u8 value")?,
+                        *iter.next().ok_or("Expected u8 value")?, *iter.next().ok_or("Expected u8 value")?,
+                        *iter.next().ok_or("Expected u8 value")?, *iter.next().ok_or("Expected u8 value")?,
+                        *iter.next().ok_or("Expected u8 value")?, *iter.next().ok_or("Expected u8 value")?,
+                    ]);
+                    result.push(Bytecode::EmitStaticSignal(class_name, signal_name));
+                }
                 _ => return Err("Invalid opcode"),
             }
         }
@@ -903,6 +997,28 @@ impl Bytecode {
                     }
                 }
             },
+            Bytecode::EmitSignal(class_name, signal_name) => {
+                result.push(86);
+                result.extend_from_slice(&class_name.to_le_bytes());
+                result.extend_from_slice(&signal_name.to_le_bytes());
+            },
+            Bytecode::ConnectSignal(signal_name, class_name, method_name) => {
+                result.push(87);
+                result.extend_from_slice(&signal_name.to_le_bytes());
+                result.extend_from_slice(&class_name.to_le_bytes());
+                result.extend_from_slice(&method_name.to_le_bytes());
+            },
+            Bytecode::DisconnectSignal(signal_name, class_name, method_name) => {
+                result.push(88);
+                result.extend_from_slice(&signal_name.to_le_bytes());
+                result.extend_from_slice(&class_name.to_le_bytes());
+                result.extend_from_slice(&method_name.to_le_bytes());
+            },
+            Bytecode::EmitStaticSignal(class_name, signal_name) => {
+                result.push(89);
+                result.extend_from_slice(&class_name.to_le_bytes());
+                result.extend_from_slice(&signal_name.to_le_bytes());
+            },
         }
 
         result
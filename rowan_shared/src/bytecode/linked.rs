@@ -151,7 +151,7 @@ pub enum Bytecode {
     /// The first Symbol is the class name we are accessing, the second Symbol is another classname
     /// that allows for selecting the particular parent to access the field.
     SetField(Symbol, Symbol, u64, TypeTag),
-    /// Check if an object is of a specified class
+    /// Check if an object is of a specified class or inherits from it
     IsA(Symbol),
     /// Invoke a virtual method on an object of the specified class
     /// The first symbol is the method's originating class
@@ -216,5 +216,27 @@ pub enum Bytecode {
     /// Switch to one of several blocks based on the value of the top of the stack
     /// The first block is the default case
     Switch(Vec<BlockIdOffset>, Option<BlockIdOffset>),
+    /// Emit a signal from an object
+    /// The first Symbol is the class name that declares the signal
+    /// The second Symbol is the signal name
+    /// The emitting object is argument 0 and the payload is in the following arguments
+    EmitSignal(Symbol, Symbol),
+    /// Connect a method on an object to a signal on another object
+    /// This pops the receiving object off of the stack and then the emitting object
+    /// The first Symbol is the signal name
+    /// The second Symbol is the class name that the method belongs to
+    /// The third Symbol is the method name
+    ConnectSignal(Symbol, Symbol, Symbol),
+    /// Disconnect a method on an object from a signal on another object
+    /// This pops the receiving object off of the stack and then the emitting object
+    /// The first Symbol is the signal name
+    /// The second Symbol is the class name that the method belongs to
+    /// The third Symbol is the method name
+    DisconnectSignal(Symbol, Symbol, Symbol),
+    /// Emit a static signal to every method that was linked as a listener
+    /// The first Symbol is the class name that declares the signal
+    /// The second Symbol is the signal name
+    /// The payload starts at argument 0
+    EmitStaticSignal(Symbol, Symbol),
 }
 
@@ -15,7 +15,8 @@
 //!     minor_version: u8,
 //!     name: StringIndex,
 //!     parents_size: u8,
-//!     padding: [u8; 3],
+//!     kind: ClassKind,
+//!     padding: [u8; 2],
 //!     parents: [StringIndex; parents_size],
 //!     vtables_size: u64,
 //!     vtables: [VTable; vtables_size],
@@ -59,12 +60,28 @@
 //! BytecodeEntry {
 //!     code_size: u64,
 //!     code: [u8; code_size],
+//!     lines_size: u64,
+//!     lines: [LineEntry; lines_size],
+//! }
+//!
+//! LineEntry {
+//!     bytecode_offset: u64,
+//!     file: StringIndex,
+//!     line: u64,
+//!     column: u64,
 //! }
 //!
 //! StringEntry {
 //!     length: u64,
 //!     value: [u8; length],
 //! }
+//!
+//! enum ClassKind: u8 {
+//!     Class = 0,
+//!     Record = 1,
+//!     Union = 2,
+//!     Variant = 3,
+//! }
 //! ```
 //!
 use crate::{RowanClassFileUtils, TypeTag};
@@ -79,6 +96,47 @@ pub type BytecodeIndex = i64;
 /// Index into the signature table
 pub type SignatureIndex = u64;
 
+/// What kind of declaration a class was compiled from
+#[derive(PartialEq, Eq, PartialOrd, Hash, Debug, Copy, Clone, Default)]
+pub enum ClassKind {
+    #[default]
+    Class,
+    /// An immutable class that is compared by its fields
+    Record,
+    /// The base class of a tagged union
+    Union,
+    /// One case of a union, its parent is always the union
+    Variant,
+}
+
+impl ClassKind {
+    pub fn as_byte(&self) -> u8 {
+        match self {
+            ClassKind::Class => 0,
+            ClassKind::Record => 1,
+            ClassKind::Union => 2,
+            ClassKind::Variant => 3,
+        }
+    }
+
+    /// Records and union variants are values, their identity doesn't matter
+    pub fn is_value(&self) -> bool {
+        matches!(self, ClassKind::Record | ClassKind::Variant)
+    }
+}
+
+impl From<u8> for ClassKind {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => ClassKind::Class,
+            1 => ClassKind::Record,
+            2 => ClassKind::Union,
+            3 => ClassKind::Variant,
+            _ => unreachable!("Invalid class kind"),
+        }
+    }
+}
+
 #[derive(PartialEq, Debug)]
 pub struct ClassFile {
     /// Magic number to identify the file
@@ -95,10 +153,14 @@ pub struct ClassFile {
     pub name: StringIndex,
     /// Parent class name
     pub parent: StringIndex,
+    /// Whether this is a plain class, a record, or part of a union
+    pub kind: ClassKind,
     /// Virtual tables
     pub vtables: Vec<VTable>,
     /// Members and their types
     pub members: Vec<Member>,
+    /// Signals the class can emit
+    pub signals: Vec<Signal>,
     /// Static Method VTable
     pub static_methods: StaticMethods,
     /// Static Class Members
@@ -123,6 +185,7 @@ impl ClassFile {
         parent: StringIndex,
         vtables: Vec<VTable>,
         members: Vec<Member>,
+        signals: Vec<Signal>,
         static_methods: StaticMethods,
         static_members: Vec<Member>,
         static_init: BytecodeIndex,
@@ -138,8 +201,10 @@ impl ClassFile {
             patch_version: 0,
             name,
             parent,
+            kind: ClassKind::Class,
             vtables,
             members,
+            signals,
             static_methods,
             static_members,
             static_init,
@@ -170,7 +235,8 @@ impl ClassFile {
         ]);
         index += size_of::<StringIndex>();
 
-        index += 3; // 4 byte padding to align pointer
+        let kind = ClassKind::from(binary[index]);
+        index += 3; // kind and 2 bytes of padding to align pointer
 
         let vtables_size = u64::from_le_bytes([
             binary[index], binary[index + 1], binary[index + 2], binary[index + 3],
@@ -233,6 +299,33 @@ impl ClassFile {
             });
         }
 
+        let signals_size = u64::from_le_bytes([
+            binary[index], binary[index + 1], binary[index + 2], binary[index + 3],
+            binary[index + 4], binary[index + 5], binary[index + 6], binary[index + 7]
+        ]);
+        index += std::mem::size_of::<u64>();
+
+        let mut signals = Vec::new();
+        for _ in 0..signals_size {
+            let name = u64::from_le_bytes([
+                binary[index], binary[index + 1], binary[index + 2], binary[index + 3],
+                binary[index + 4], binary[index + 5], binary[index + 6], binary[index + 7]
+            ]);
+            index += size_of::<StringIndex>();
+            let is_static = binary[index] != 0;
+            index += size_of::<u8>();
+            let signature = u64::from_le_bytes([
+                binary[index], binary[index + 1], binary[index + 2], binary[index + 3],
+                binary[index + 4], binary[index + 5], binary[index + 6], binary[index + 7]
+            ]);
+            index += size_of::<SignatureIndex>();
+            signals.push(Signal {
+                name,
+                is_static,
+                signature,
+            });
+        }
+
         // dynamic padding for pointer alignment
         let padding = binary[index];
         index += padding as usize + 1;
@@ -301,10 +394,12 @@ impl ClassFile {
                     code_size as usize
                 )
             };
+            index += code_size as usize;
+            let lines = BytecodeEntry::read_lines(binary, &mut index);
             bytecode_table.push(BytecodeEntry {
-                code: code.to_vec()
+                code: code.to_vec(),
+                lines,
             });
-            index += code_size as usize;
         }
 
         let string_table_size = u64::from_le_bytes([
@@ -367,8 +462,10 @@ impl ClassFile {
             patch_version,
             name,
             parent,
+            kind,
             vtables,
             members,
+            signals,
             static_methods,
             static_members,
             static_init,
@@ -401,7 +498,8 @@ impl ClassFile {
         binary.extend_from_slice(&self.name.to_le_bytes());
         binary.extend_from_slice(&self.parent.to_le_bytes());
 
-        binary.extend_from_slice(&[0u8; 3]); // Padding of 3 Bytes
+        binary.push(self.kind.as_byte());
+        binary.extend_from_slice(&[0u8; 2]); // Padding of 2 Bytes
 
         binary.extend_from_slice(&self.vtables.len().to_le_bytes());
         for vtable in &self.vtables {
@@ -410,6 +508,7 @@ impl ClassFile {
             binary.extend_from_slice(&(vtable.functions.len() as u64).to_le_bytes());
             for function in &vtable.functions {
                 binary.extend_from_slice(&function.name.to_le_bytes());
+                binary.extend_from_slice(&function.responds_to.to_le_bytes());
                 binary.extend_from_slice(&function.signature.to_le_bytes());
                 binary.extend_from_slice(&function.bytecode.to_le_bytes());
             }
@@ -419,6 +518,12 @@ impl ClassFile {
             binary.extend_from_slice(&member.name.to_le_bytes());
             binary.push(member.type_tag.as_byte());
         }
+        binary.extend_from_slice(&(self.signals.len() as u64).to_le_bytes());
+        for signal in self.signals.iter() {
+            binary.extend_from_slice(&signal.name.to_le_bytes());
+            binary.push(signal.is_static as u8);
+            binary.extend_from_slice(&signal.signature.to_le_bytes());
+        }
         // Adding padding if we are not aligned
         if binary.len() % 8 == 0 {
             binary.push(7);
@@ -434,6 +539,7 @@ impl ClassFile {
         binary.extend_from_slice(&(self.static_methods.functions.len() as u64).to_le_bytes());
         for function in &self.static_methods.functions {
             binary.extend_from_slice(&function.name.to_le_bytes());
+            binary.extend_from_slice(&function.responds_to.to_le_bytes());
             binary.extend_from_slice(&function.signature.to_le_bytes());
             binary.extend_from_slice(&function.bytecode.to_le_bytes());
         }
@@ -450,6 +556,7 @@ impl ClassFile {
         for bytecode in &self.bytecode_table {
             binary.extend_from_slice(&(bytecode.code.len() as u64).to_le_bytes());
             binary.extend_from_slice(&bytecode.code);
+            bytecode.write_lines(&mut binary);
         }
         binary.extend_from_slice(&(self.string_table.len() as u64).to_le_bytes());
         for string in &self.string_table {
@@ -471,6 +578,7 @@ impl ClassFile {
         self.name = 0;
         self.vtables.clear();
         self.members.clear();
+        self.signals.clear();
         self.static_methods.functions.clear();
         self.bytecode_table.clear();
         self.string_table.clear();
@@ -516,6 +624,26 @@ impl Member {
     }
 }
 
+/// Represents a signal that a class can emit
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Signal {
+    pub name: StringIndex,
+    pub is_static: bool,
+    /// The signature of the signal's payload
+    /// The return type is always void
+    pub signature: SignatureIndex,
+}
+
+impl Signal {
+    pub fn new(name: StringIndex, is_static: bool, signature: SignatureIndex) -> Self {
+        Signal {
+            name,
+            is_static,
+            signature
+        }
+    }
+}
+
 #[derive(PartialEq, Debug, Clone)]
 pub struct StaticMethods {
     pub functions: Vec<VTableEntry>,
@@ -556,6 +684,9 @@ impl VTable {
 pub struct VTableEntry {
     /// The name of the function
     pub name: StringIndex,
+    /// The name of the static signal that this function listens to
+    /// Zero means that the function doesn't listen to a signal
+    pub responds_to: StringIndex,
     /// The signature of the function
     pub signature: SignatureIndex,
     /// The index of the bytecode for this function
@@ -567,15 +698,85 @@ pub struct VTableEntry {
 /// This is a slice of bytes
 #[derive(PartialEq, Debug, Clone)]
 pub struct BytecodeEntry {
-    pub code: Vec<u8>, 
+    pub code: Vec<u8>,
+    /// Maps byte offsets in `code` to source positions, sorted by offset
+    pub lines: Vec<LineEntry>,
 }
 
 impl BytecodeEntry {
     pub fn new<B: AsRef<[u8]>>(code: B) -> BytecodeEntry {
         BytecodeEntry {
-            code: code.as_ref().to_vec()
+            code: code.as_ref().to_vec(),
+            lines: Vec::new(),
         }
     }
+
+    pub fn with_lines<B: AsRef<[u8]>>(code: B, lines: Vec<LineEntry>) -> BytecodeEntry {
+        BytecodeEntry {
+            code: code.as_ref().to_vec(),
+            lines,
+        }
+    }
+
+    /// Finds the source position of the instruction at `bytecode_offset`
+    pub fn find_line(&self, bytecode_offset: u64) -> Option<&LineEntry> {
+        let index = self.lines.partition_point(|entry| entry.bytecode_offset <= bytecode_offset);
+        if index == 0 {
+            None
+        } else {
+            Some(&self.lines[index - 1])
+        }
+    }
+
+    pub(crate) fn read_lines(binary: &[u8], index: &mut usize) -> Vec<LineEntry> {
+        let read_u64 = |index: &mut usize| {
+            let value = u64::from_le_bytes([
+                binary[*index], binary[*index + 1], binary[*index + 2], binary[*index + 3],
+                binary[*index + 4], binary[*index + 5], binary[*index + 6], binary[*index + 7]
+            ]);
+            *index += std::mem::size_of::<u64>();
+            value
+        };
+        let lines_size = read_u64(index);
+        let mut lines = Vec::with_capacity(lines_size as usize);
+        for _ in 0..lines_size {
+            let bytecode_offset = read_u64(index);
+            let file = read_u64(index);
+            let line = read_u64(index);
+            let column = read_u64(index);
+            lines.push(LineEntry {
+                bytecode_offset,
+                file,
+                line,
+                column,
+            });
+        }
+        lines
+    }
+
+    pub(crate) fn write_lines(&self, binary: &mut Vec<u8>) {
+        binary.extend_from_slice(&(self.lines.len() as u64).to_le_bytes());
+        for line in &self.lines {
+            binary.extend_from_slice(&line.bytecode_offset.to_le_bytes());
+            binary.extend_from_slice(&line.file.to_le_bytes());
+            binary.extend_from_slice(&line.line.to_le_bytes());
+            binary.extend_from_slice(&line.column.to_le_bytes());
+        }
+    }
+}
+
+/// Represents a source position for a range of bytecode
+/// The entry covers every instruction from `bytecode_offset` up to the next entry
+#[derive(PartialEq, Debug, Copy, Clone, Default)]
+pub struct LineEntry {
+    /// The byte offset into the code of the first instruction covered
+    pub bytecode_offset: u64,
+    /// The name of the source file
+    pub file: StringIndex,
+    /// The line number, starting at 1
+    pub line: u64,
+    /// The column number, starting at 1
+    pub column: u64,
 }
 
 /// Represents a string entry in the string table
@@ -622,7 +823,6 @@ mod tests {
 
     #[test]
     fn test_class_into_binary_and_back() {
-        let parents = vec![1, 2];
         let vtables = vec![
             VTable {
                 class_name: 1,
@@ -630,6 +830,7 @@ mod tests {
                 functions: [
                     VTableEntry {
                         name: 1,
+                        responds_to: 0,
                         signature: 3,
                         bytecode: 4
                     }
@@ -644,11 +845,23 @@ mod tests {
             }
         ];
 
+        let signals = vec![
+            Signal::new(1, false, 1)
+        ];
+
         let static_methods = StaticMethods::new(Vec::new());
 
         let bytecode_table = vec![
             BytecodeEntry {
-                code: vec![0, 1, 2, 3]
+                code: vec![0, 1, 2, 3],
+                lines: vec![
+                    LineEntry {
+                        bytecode_offset: 0,
+                        file: 1,
+                        line: 4,
+                        column: 7,
+                    }
+                ]
             }
         ];
 
@@ -670,11 +883,16 @@ mod tests {
             major_version: 1,
             minor_version: 2,
             patch_version: 3,
+            r#type: 0,
             name: 1,
-            parents,
+            parent: 2,
+            kind: ClassKind::Class,
             vtables,
             members,
+            signals,
             static_methods,
+            static_members: Vec::new(),
+            static_init: 0,
             bytecode_table,
             string_table,
             signature_table
@@ -134,10 +134,12 @@ impl InterfaceImplFile {
                     code_size as usize
                 )
             };
+            index += code_size as usize;
+            let lines = BytecodeEntry::read_lines(binary, &mut index);
             bytecode_table.push(BytecodeEntry {
-                code: code.to_vec()
+                code: code.to_vec(),
+                lines,
             });
-            index += code_size as usize;
         }
 
         let string_table_size = u64::from_le_bytes([
@@ -225,6 +227,7 @@ impl InterfaceImplFile {
         binary.extend_from_slice(&(self.vtable.functions.len() as u64).to_le_bytes());
         for function in &self.vtable.functions {
             binary.extend_from_slice(&function.name.to_le_bytes());
+            binary.extend_from_slice(&function.responds_to.to_le_bytes());
             binary.extend_from_slice(&function.signature.to_le_bytes());
             binary.extend_from_slice(&function.bytecode.to_le_bytes());
         }
@@ -232,6 +235,7 @@ impl InterfaceImplFile {
         for bytecode in &self.bytecode_table {
             binary.extend_from_slice(&(bytecode.code.len() as u64).to_le_bytes());
             binary.extend_from_slice(&bytecode.code);
+            bytecode.write_lines(&mut binary);
         }
         binary.extend_from_slice(&(self.string_table.len() as u64).to_le_bytes());
         for string in &self.string_table {
@@ -6,6 +6,7 @@ pub mod bytecode;
 pub mod classfile;
 pub mod interfacefile;
 pub mod interfaceimplfile;
+pub mod package;
 
 /// Represents a type tag for a member or parameter
 /// This represents all the primitive types
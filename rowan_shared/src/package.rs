@@ -0,0 +1,178 @@
+//! This module defines the structure of a package
+//! A package bundles the class files of a program together with its native libraries into one file.
+//! The manifest names the class whose `main` method starts the program.
+//!
+//! Here is the structure of the package as binary data using a vaguely Rust-like format:
+//! ```ignore
+//! struct Package {
+//!     magic: [u8; 4], // always b"RPKG"
+//!     version: u8,
+//!     main_class_size: u64,
+//!     main_class: [u8; main_class_size],
+//!     entries_size: u64,
+//!     entries: [Entry; entries_size],
+//! }
+//!
+//! struct Entry {
+//!     path_size: u64,
+//!     path: [u8; path_size],
+//!     data_size: u64,
+//!     data: [u8; data_size],
+//! }
+//! ```
+//! Paths are relative and always use `/` as the separator, for example `main/Main.class` or `std/console/Console.so`.
+
+pub const PACKAGE_MAGIC: [u8; 4] = *b"RPKG";
+pub const PACKAGE_VERSION: u8 = 1;
+
+/// The file extension used for packages
+pub const PACKAGE_EXTENSION: &str = "rpk";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Manifest {
+    /// The fully qualified name of the class with the main method, like `main::Main`
+    pub main_class: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PackageEntry {
+    pub path: String,
+    pub data: Vec<u8>,
+}
+
+impl PackageEntry {
+    pub fn is_class_file(&self) -> bool {
+        self.path.ends_with(".class")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Package {
+    pub manifest: Manifest,
+    pub entries: Vec<PackageEntry>,
+}
+
+impl Package {
+    pub fn new(main_class: impl Into<String>) -> Self {
+        Package {
+            manifest: Manifest { main_class: main_class.into() },
+            entries: Vec::new(),
+        }
+    }
+
+    /// Returns true if the binary starts like a package rather than a class file
+    pub fn is_package(binary: &[u8]) -> bool {
+        binary.starts_with(&PACKAGE_MAGIC)
+    }
+
+    /// Adds a file to the package, replacing any file that was already at that path
+    pub fn add_entry(&mut self, path: impl Into<String>, data: Vec<u8>) {
+        let path = path.into();
+        if let Some(entry) = self.entries.iter_mut().find(|entry| entry.path == path) {
+            entry.data = data;
+            return;
+        }
+        self.entries.push(PackageEntry { path, data });
+    }
+
+    pub fn get(&self, path: &str) -> Option<&[u8]> {
+        self.entries.iter()
+            .find(|entry| entry.path == path)
+            .map(|entry| entry.data.as_slice())
+    }
+
+    pub fn from_binary(binary: &[u8]) -> Self {
+        assert!(Package::is_package(binary), "Binary is not a package");
+        let mut index = PACKAGE_MAGIC.len();
+        let version = binary[index];
+        assert_eq!(version, PACKAGE_VERSION, "Unsupported package version");
+        index += 1;
+
+        let main_class = read_bytes(binary, &mut index);
+        let main_class = String::from_utf8(main_class.to_vec()).expect("main class name was not utf-8");
+
+        let entries_size = read_u64(binary, &mut index);
+        let mut entries = Vec::with_capacity(entries_size as usize);
+        for _ in 0..entries_size {
+            let path = read_bytes(binary, &mut index);
+            let path = String::from_utf8(path.to_vec()).expect("package path was not utf-8");
+            let data = read_bytes(binary, &mut index).to_vec();
+            entries.push(PackageEntry { path, data });
+        }
+
+        Package {
+            manifest: Manifest { main_class },
+            entries,
+        }
+    }
+
+    pub fn as_binary(&self) -> Vec<u8> {
+        let mut binary = Vec::new();
+        binary.extend_from_slice(&PACKAGE_MAGIC);
+        binary.push(PACKAGE_VERSION);
+        write_bytes(&mut binary, self.manifest.main_class.as_bytes());
+        binary.extend_from_slice(&(self.entries.len() as u64).to_le_bytes());
+        for entry in &self.entries {
+            write_bytes(&mut binary, entry.path.as_bytes());
+            write_bytes(&mut binary, &entry.data);
+        }
+        binary
+    }
+}
+
+impl From<&[u8]> for Package {
+    fn from(binary: &[u8]) -> Self {
+        Package::from_binary(binary)
+    }
+}
+
+fn read_u64(binary: &[u8], index: &mut usize) -> u64 {
+    let value = u64::from_le_bytes([
+        binary[*index], binary[*index + 1], binary[*index + 2], binary[*index + 3],
+        binary[*index + 4], binary[*index + 5], binary[*index + 6], binary[*index + 7]
+    ]);
+    *index += size_of::<u64>();
+    value
+}
+
+fn read_bytes<'a>(binary: &'a [u8], index: &mut usize) -> &'a [u8] {
+    let size = read_u64(binary, index) as usize;
+    let bytes = &binary[*index..(*index + size)];
+    *index += size;
+    bytes
+}
+
+fn write_bytes(binary: &mut Vec<u8>, bytes: &[u8]) {
+    binary.extend_from_slice(&(bytes.len() as u64).to_le_bytes());
+    binary.extend_from_slice(bytes);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_package_into_binary_and_back() {
+        let mut package = Package::new("main::Main");
+        package.add_entry("main/Main.class", vec![0, 0, 1, 2, 3]);
+        package.add_entry("std/console/Console.so", vec![0x7f, b'E', b'L', b'F']);
+
+        let binary = package.as_binary();
+        assert!(Package::is_package(&binary));
+
+        let loaded = Package::from(binary.as_slice());
+        assert_eq!(loaded, package);
+        assert_eq!(loaded.get("main/Main.class"), Some([0, 0, 1, 2, 3].as_slice()));
+        assert!(loaded.get("main/Other.class").is_none());
+    }
+
+    #[test]
+    fn test_add_entry_replaces_existing_path() {
+        let mut package = Package::new("main::Main");
+        package.add_entry("main/Main.class", vec![1]);
+        package.add_entry("main/Main.class", vec![2]);
+
+        assert_eq!(package.entries.len(), 1);
+        assert_eq!(package.get("main/Main.class"), Some([2].as_slice()));
+    }
+}
@@ -3,3 +3,4 @@ mod compiler_utils;
 pub mod pre_compilation;
 
 pub use compiler::Compiler as Compiler;
+pub use compiler::OutputFile;
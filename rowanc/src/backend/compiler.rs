@@ -1,16 +1,16 @@
-use std::{collections::HashMap, io::Write, path::PathBuf};
+use std::collections::HashMap;
 use std::cmp::Ordering;
 use either::Either;
 use itertools::Itertools;
 use rowan_shared::{bytecode::compiled::Bytecode, classfile::{Member, SignatureEntry, VTable, VTableEntry}, TypeTag};
-use rowan_shared::classfile::{SignatureIndex, StaticMethods};
+use rowan_shared::classfile::{ClassKind, LineEntry, SignatureIndex, StaticMethods, StringIndex};
 use crate::{trees::ir, trees::ir::{Class, Constant, Expression, File, Literal, Method, Parameter, Pattern, Statement, TopLevelStatement}, backend::compiler_utils::Frame};
 use crate::backend::compiler_utils::partial_interface::PartialInterface;
 use crate::backend::compiler_utils::partial_interface_impl::PartialInterfaceImpl;
-use crate::trees::ir::{ClosureParameter, IfExpression, ParentDec, Trait};
+use crate::trees::ir::{CatchClause, ClosureParameter, IfExpression, MatchExpression, ParentDec, Trait};
 use crate::trees::{BinaryOperator, PathName, Type, UnaryOperator, Text, Annotation, Span, Visibility};
 use crate::trees::ir::TraitImpl;
-use super::compiler_utils::{ClassMap, partial_class::{PartialClass, StaticMember}, CurrentCompilationUnit};
+use super::compiler_utils::{ClassMap, partial_class::{PartialClass, StaticMember}, CurrentCompilationUnit, FinallyContext, SourceFile, TryContext};
 
 
 
@@ -21,12 +21,27 @@ fn create_stdlib() -> ClassMap {
     object.set_name("core::Object");
     let functions = vec![
         VTableEntry::default(),
+        VTableEntry::default(),
+        VTableEntry::default(),
+        VTableEntry::default(),
+        VTableEntry::default(),
+        VTableEntry::default(),
     ];
     let names = vec![
         "core::Object::downcast",
+        "core::Object::try-downcast",
+        "core::Object::tick",
+        "core::Object::ready",
+        "core::Object::add-child",
+        "core::Object::remove-child",
     ];
     let signatures = vec![
         SignatureEntry::new(vec![TypeTag::Object, TypeTag::Object]),
+        SignatureEntry::new(vec![TypeTag::Object, TypeTag::Object]),
+        SignatureEntry::new(vec![TypeTag::Void, TypeTag::Object, TypeTag::F64]),
+        SignatureEntry::new(vec![TypeTag::Void, TypeTag::Object]),
+        SignatureEntry::new(vec![TypeTag::Void, TypeTag::Object, TypeTag::Object]),
+        SignatureEntry::new(vec![TypeTag::Void, TypeTag::Object, TypeTag::Object]),
     ];
     let vtable = VTable::new(functions);
     object.add_vtable(&vec![String::from("core"), String::from("Object")], vtable, &names, &signatures);
@@ -545,6 +560,94 @@ fn create_stdlib() -> ClassMap {
     let index = classes.insert(vec![String::from("Arrayf64")], array);
     classes.add_alias(vec![String::from("core"), String::from("Arrayf64")], index);
 
+    let mut exception = PartialClass::new();
+    exception.set_name("core::Exception");
+    exception.set_parent("core::Object");
+    let functions = vec![
+        VTableEntry::default(),
+        VTableEntry::default(),
+        VTableEntry::default(),
+        VTableEntry::default(),
+    ];
+    let names = vec![
+        "core::Exception::init",
+        "core::Exception::fill-in-stack-trace",
+        "core::Exception::print-stack-trace",
+        "core::Exception::new",
+    ];
+    let signatures = vec![
+        SignatureEntry::new(vec![TypeTag::Void, TypeTag::Object, TypeTag::Object]),
+        SignatureEntry::new(vec![TypeTag::Void, TypeTag::Object]),
+        SignatureEntry::new(vec![TypeTag::Void, TypeTag::Object]),
+        SignatureEntry::new(vec![TypeTag::Object, TypeTag::Object, TypeTag::Object]),
+    ];
+    let vtable = VTable::new(functions);
+
+    exception.add_vtable(&vec![String::from("core"), String::from("Exception")], vtable, &names, &signatures);
+    exception.add_member(Member {
+        name: 0,
+        type_tag: TypeTag::Object,
+    }, "message");
+    exception.add_member(Member {
+        name: 0,
+        type_tag: TypeTag::U64,
+    }, "stack-length");
+    exception.add_member(Member {
+        name: 0,
+        type_tag: TypeTag::U64,
+    }, "stack-capacity");
+    exception.add_member(Member {
+        name: 0,
+        type_tag: TypeTag::U64,
+    }, "stack-trace-pointer");
+    exception.make_not_printable();
+    let index = classes.insert(vec![String::from("core"), String::from("Exception")], exception);
+    classes.add_alias(vec![String::from("Exception")], index);
+
+    let mut index_out_of_bounds = PartialClass::new();
+    index_out_of_bounds.set_name("core::IndexOutOfBounds");
+    index_out_of_bounds.set_parent("core::Exception");
+    let functions = vec![
+        VTableEntry::default(),
+        VTableEntry::default(),
+    ];
+    let names = vec![
+        "core::IndexOutOfBounds::init",
+        "core::IndexOutOfBounds::new",
+    ];
+    let signatures = vec![
+        SignatureEntry::new(vec![TypeTag::Void, TypeTag::Object, TypeTag::U64, TypeTag::U64]),
+        SignatureEntry::new(vec![TypeTag::Object, TypeTag::Object, TypeTag::U64, TypeTag::U64]),
+    ];
+    let vtable = VTable::new(functions);
+
+    index_out_of_bounds.add_vtable(&vec![String::from("core"), String::from("IndexOutOfBounds")], vtable, &names, &signatures);
+    index_out_of_bounds.make_not_printable();
+    let index = classes.insert(vec![String::from("core"), String::from("IndexOutOfBounds")], index_out_of_bounds);
+    classes.add_alias(vec![String::from("IndexOutOfBounds")], index);
+
+    let mut null_pointer = PartialClass::new();
+    null_pointer.set_name("core::NullPointerException");
+    null_pointer.set_parent("core::Exception");
+    let functions = vec![
+        VTableEntry::default(),
+        VTableEntry::default(),
+    ];
+    let names = vec![
+        "core::NullPointerException::init",
+        "core::NullPointerException::new",
+    ];
+    let signatures = vec![
+        SignatureEntry::new(vec![TypeTag::Void, TypeTag::Object]),
+        SignatureEntry::new(vec![TypeTag::Object, TypeTag::Object]),
+    ];
+    let vtable = VTable::new(functions);
+
+    null_pointer.add_vtable(&vec![String::from("core"), String::from("NullPointerException")], vtable, &names, &signatures);
+    null_pointer.make_not_printable();
+    let index = classes.insert(vec![String::from("core"), String::from("NullPointerException")], null_pointer);
+    classes.add_alias(vec![String::from("NullPointerException")], index);
+
     let mut array = PartialClass::new();
     array.set_name("core::Arrayobject");
     let functions = vec![
@@ -577,12 +680,36 @@ pub enum CompilerError {
 }
 
 
+/// A file produced by the compiler, placed at a `/` separated path relative to the output root
+pub struct OutputFile {
+    pub path: String,
+    pub bytes: Vec<u8>,
+}
+
+impl OutputFile {
+    fn new(path: String, bytes: Vec<u8>) -> Self {
+        OutputFile { path, bytes }
+    }
+
+    pub fn is_class_file(&self) -> bool {
+        self.path.ends_with(".class")
+    }
+}
+
 pub struct Compiler {
     scopes: Vec<Frame>,
     pub(crate) classes: ClassMap,
     current_block: u64,
     method_returned: bool,
     current_block_returned: bool,
+    /// The try statements that enclose the code being compiled, innermost last
+    try_stack: Vec<TryContext>,
+    /// The instruction indices of the method being compiled paired with the spans they came from
+    lines: Vec<(usize, Span)>,
+    /// The source files, keyed by their module path
+    sources: HashMap<Vec<String>, SourceFile>,
+    /// The module path of the class being compiled
+    current_source: Vec<String>,
     current_type_args: HashMap<String, TypeTag>,
     current_module: Vec<String>,
     active_imports: HashMap<String, Vec<String>>,
@@ -608,6 +735,10 @@ impl Compiler {
             active_imports: HashMap::new(),
             imports_to_change: HashMap::new(),
             current_block_returned: false,
+            try_stack: Vec::new(),
+            lines: Vec::new(),
+            sources: HashMap::new(),
+            current_source: Vec::new(),
             functions: HashMap::new(),
             closures_under_path: HashMap::new(),
             interfaces: HashMap::new(),
@@ -616,6 +747,62 @@ impl Compiler {
         }
     }
 
+    pub fn add_source(&mut self, module: Vec<String>, name: impl AsRef<str>, contents: &str) {
+        self.sources.insert(module, SourceFile::new(name, contents));
+    }
+
+    /// Records that the instructions starting at `index` come from `span`
+    fn mark_span(&mut self, span: Span, index: usize) {
+        if span == Span::new(0, 0) {
+            return;
+        }
+        self.lines.push((index, span));
+    }
+
+    /// Moves the spans recorded since `start` to account for their output being placed at `offset`
+    fn shift_spans(&mut self, start: usize, end: usize, offset: usize) {
+        for (index, _) in self.lines[start..end].iter_mut() {
+            *index += offset;
+        }
+    }
+
+    /// Converts the bytecode into binary and the recorded spans into a line table
+    fn assemble(&mut self, partial_file: &mut CurrentCompilationUnit, bytecode: Vec<Bytecode>) -> (Vec<u8>, Vec<LineEntry>) {
+        let mut offsets = Vec::with_capacity(bytecode.len() + 1);
+        let mut binary = Vec::new();
+        for code in bytecode {
+            offsets.push(binary.len() as u64);
+            binary.extend(code.into_binary());
+        }
+        offsets.push(binary.len() as u64);
+
+        let mut spans = std::mem::take(&mut self.lines);
+        spans.sort_by_key(|(index, _)| *index);
+
+        let Some(source) = self.sources.get(&self.current_source) else {
+            return (binary, Vec::new());
+        };
+        let file = partial_file.add_string(&source.name);
+        let mut lines: Vec<LineEntry> = Vec::new();
+        for (index, span) in spans {
+            let (line, column) = source.position(span.start);
+            let entry = LineEntry {
+                bytecode_offset: offsets[index],
+                file,
+                line,
+                column,
+            };
+            match lines.last_mut() {
+                // A later span for the same instruction is more specific
+                Some(last) if last.bytecode_offset == entry.bytecode_offset => *last = entry,
+                Some(last) if last.line == line && last.column == column => {}
+                _ => lines.push(entry),
+            }
+        }
+
+        (binary, lines)
+    }
+
     fn increment_block(&mut self) {
         self.current_block += 1;
     }
@@ -850,6 +1037,7 @@ impl Compiler {
             &closure_path,
             format!("std::function::{closure_name}::call"),
             &[0],
+            Vec::new(),
             false,
         ).unwrap();
 
@@ -983,6 +1171,7 @@ impl Compiler {
             &closure_path,
             format!("std::function::{closure_name}::call"),
             &[0],
+            Vec::new(),
             false,
         ).unwrap();
 
@@ -994,11 +1183,63 @@ impl Compiler {
     }
 
 
+    /// Tuples are objects of a class with a field for each element, named after its position.
+    /// The class is made the first time a tuple with the same layout is needed.
+    fn tuple_class(&mut self, tys: &[Type]) -> Vec<String> {
+        let mut tuple_name = String::from("Tuple");
+        let tags = tys.iter().map(|ty| self.convert_type(ty)).collect::<Vec<_>>();
+        for tag in tags.iter() {
+            let tag_name = match tag {
+                TypeTag::I8 => "i8",
+                TypeTag::U8 => "u8",
+                TypeTag::I16 => "i16",
+                TypeTag::U16 => "u16",
+                TypeTag::I32 => "i32",
+                TypeTag::U32 => "u32",
+                TypeTag::I64 => "i64",
+                TypeTag::U64 => "u64",
+                TypeTag::F32 => "f32",
+                TypeTag::F64 => "f64",
+                _ => "object",
+            };
+            tuple_name.push_str(tag_name);
+        }
+        let path = vec![
+            String::from("std"),
+            String::from("tuple"),
+            tuple_name,
+        ];
+        if self.classes.contains_key(&path) {
+            return path;
+        }
+
+        let mut partial_class = PartialClass::new();
+        partial_class.set_name(&path.join("::"));
+
+        let object_class = self.classes.get(&vec!["core".to_string(), "Object".to_string()]).expect("Object not added to known classes");
+        let vtables = object_class.get_vtables(&[
+            String::from("core"),
+            String::from("Object"),
+        ]);
+        let (vtable, names, signatures) = &vtables[0];
+        partial_class.add_vtable(&vec![String::from("core"), String::from("Object")], vtable.clone(), names, signatures);
+        partial_class.set_parent("core::Object");
+
+        for (i, tag) in tags.into_iter().enumerate() {
+            partial_class.add_member(Member::new(tag), i.to_string());
+        }
+
+        self.classes.insert(path.clone(), partial_class);
+
+        path
+    }
+
     /// files should be sorted in a way that means we don't need to do each file incrementally
+    /// Compiles the files into class files and the headers for their native methods
     pub fn compile_files(
         mut self, 
         files: Vec<File>,
-    ) -> Result<(), CompilerError> {
+    ) -> Result<Vec<OutputFile>, CompilerError> {
 
         let mut all_classes = Vec::new();
         let mut all_interfaces = Vec::new();
@@ -1007,6 +1248,7 @@ impl Compiler {
         for file in files {
             let File { path, content, .. } = file;
             self.current_module = path.segments.into_iter().map(|x| x.to_string()).collect();
+            self.current_source = self.current_module.clone();
             let mut content = content;
 
             content.sort_by(|a, b| {
@@ -1021,25 +1263,34 @@ impl Compiler {
                 }
             });
 
-            let (mut classes, mut interfaces, mut interface_impls) = self.load_parts(content)?;
+            let (classes, interfaces, interface_impls) = self.load_parts(content)?;
 
-            all_classes.append(&mut classes);
-            all_interfaces.append(&mut interfaces);
-            all_interface_impls.append(&mut interface_impls);
+            let module = &self.current_module;
+            all_classes.extend(classes.into_iter().map(|(class, type_args)| (class, type_args, module.clone())));
+            all_interfaces.extend(interfaces.into_iter().map(|(interface, type_args)| (interface, type_args, module.clone())));
+            all_interface_impls.extend(interface_impls.into_iter().map(|(r#impl, type_args)| (r#impl, type_args, module.clone())));
         }
 
         self.alter_imports_if_needed();
 
-        for (interface, type_args) in all_interfaces {
+        for (interface, type_args, module) in all_interfaces {
+            self.current_module = module.clone();
+            self.current_source = module;
             self.compile_interface(interface, type_args)?;
         }
-        for (class, type_args) in all_classes {
+        for (class, type_args, module) in all_classes {
+            self.current_module = module.clone();
+            self.current_source = module;
             self.compile_class(class, type_args)?;
         }
-        for (r#impl, type_args) in all_interface_impls {
+        for (r#impl, type_args, module) in all_interface_impls {
+            self.current_module = module.clone();
+            self.current_source = module;
             self.compile_interface_impl(r#impl, type_args)?;
         }
 
+        let mut outputs = Vec::new();
+
         for (path, file) in self.classes.into_iter() {
             /*if file.is_printable() {
                 println!("Path: {}", path.join("/"));
@@ -1048,49 +1299,16 @@ impl Compiler {
 
             if let Some((file, native_definitions)) = file.create_class_file() {
                 if !native_definitions.is_empty() {
-                    let path = format!("output/{}.h", path.join("/"));
                     let header = native_definitions.as_c_header();
-                    let bytes = header.as_bytes();
-                    let path = PathBuf::from(path);
-                    if let Some(parents) = path.parent() {
-                        let _ = std::fs::create_dir_all(parents);
-                    }
-                    let _ = std::fs::remove_file(&path);
-                    let mut file = std::fs::File::create(path).unwrap();
-                    file.write_all(&bytes).unwrap();
+                    outputs.push(OutputFile::new(format!("{}.h", path.join("/")), header.into_bytes()));
                 }
-                let path = format!("output/{}.class", path.join("/"));
-                let bytes = file.as_binary();
-                let path = PathBuf::from(path);
-                if let Some(parents) = path.parent() {
-                    let _ = std::fs::create_dir_all(parents);
-                }
-                let _ = std::fs::remove_file(&path);
-                let mut file = std::fs::File::create(path).unwrap();
-                file.write_all(&bytes).unwrap();
+                outputs.push(OutputFile::new(format!("{}.class", path.join("/")), file.as_binary()));
             }
         }
 
         for (path, file) in self.interfaces.into_iter() {
             let file = file.create_interface_file();
-            let mut file_path = PathBuf::new();
-            file_path.push("output");
-            let path_len = path.len();
-            for (i, item) in path.into_iter().enumerate() {
-                if i < path_len - 1 {
-                    file_path.push(item);
-                } else {
-                    file_path.push(format!("{}.class", item));
-                }
-            }
-
-            let bytes = file.as_binary();
-            if let Some(parents) = file_path.parent() {
-                let _ = std::fs::create_dir_all(parents);
-            }
-            let _ = std::fs::remove_file(&file_path);
-            let mut file = std::fs::File::create(file_path).unwrap();
-            file.write_all(&bytes).unwrap();
+            outputs.push(OutputFile::new(format!("{}.class", path.join("/")), file.as_binary()));
         }
 
         for (path, impls) in self.interface_impls.into_iter() {
@@ -1100,24 +1318,13 @@ impl Compiler {
                 let last_path = path.last().unwrap();
                 let last_trait = r#trait.last().unwrap();
 
-                let last_file = format!("{last_trait}{last_path}.class");
-                let mut file_path = PathBuf::new();
-                file_path.push("output");
-                for item in path[0..(path.len() - 1)].iter() {
-                    file_path.push(item);
-                }
-                file_path.push(last_file);
+                let mut file_path = path[0..(path.len() - 1)].to_vec();
+                file_path.push(format!("{last_trait}{last_path}.class"));
 
-                let bytes = file.as_binary();
-                if let Some(parents) = file_path.parent() {
-                    let _ = std::fs::create_dir_all(parents);
-                }
-                let _ = std::fs::remove_file(&file_path);
-                let mut file = std::fs::File::create(file_path).unwrap();
-                file.write_all(&bytes).unwrap();
+                outputs.push(OutputFile::new(file_path.join("/"), file.as_binary()));
             }
         }
-        Ok(())
+        Ok(outputs)
     }
 
     fn load_parts<'a>(&mut self, content: Vec<TopLevelStatement<'a>>) -> Result<(Vec<(Class<'a>, HashMap<String, TypeTag>)>, Vec<(Trait<'a>, HashMap<String, TypeTag>)>, Vec<(TraitImpl<'a>, HashMap<String, TypeTag>)>), CompilerError> {
@@ -1157,11 +1364,13 @@ impl Compiler {
     fn load_class_part<'a>(&mut self, class: Class<'a>) -> Result<Vec<(Class<'a>, HashMap<String, TypeTag>)>, CompilerError> {
         let Class {
             name,
+            kind,
             type_params,
             parent,
             members,
             methods,
             static_members,
+            signals,
             span,
 
         } = class.clone();
@@ -1172,7 +1381,7 @@ impl Compiler {
 
         if type_params.is_empty() {
             let new_parents = self.create_new_parent(&parent);
-            let name = self.load_class_part_inner(&class_name, &new_parents, &methods, &members, &static_members)?;
+            let name = self.load_class_part_inner(&class_name, kind, &new_parents, &methods, &members, &static_members, &signals)?;
             classes_to_compile.push((class, HashMap::new()));
         } else {
             let mut name_order = Vec::new();
@@ -1221,13 +1430,15 @@ impl Compiler {
                 let mut new_path = class_name.clone();
                 new_path.last_mut().unwrap().push_str(&modifier_string);
 
-                let name = self.load_class_part_inner(&new_path, &new_parents, &methods, &members, &static_members)?;
+                let name = self.load_class_part_inner(&new_path, kind, &new_parents, &methods, &members, &static_members, &signals)?;
                 classes_to_compile.push((Class {
                     name: Text::Owned(name),
+                    kind,
                     parent,
                     members: members.clone(),
                     methods: methods.clone(),
                     static_members: static_members.clone(),
+                    signals: signals.clone(),
                     type_params: Vec::new(),
                     span,
                 }, self.current_type_args.clone()));
@@ -1240,10 +1451,12 @@ impl Compiler {
     fn load_class_part_inner(
         &mut self,
         name: &Vec<String>,
+        kind: ClassKind,
         parent: &Option<ParentDec>,
         methods: &Vec<Method>,
         members: &Vec<ir::Member>,
         static_members: &Vec<ir::StaticMember>,
+        signals: &Vec<ir::Signal>,
     ) -> Result<String, CompilerError> {
         for method in methods.iter() {
             let Method {
@@ -1268,7 +1481,7 @@ impl Compiler {
         let mut partial_class = PartialClass::new();
         let path_name = name.join("::");
         partial_class.set_name(&path_name);
-
+        partial_class.set_kind(kind);
 
         parent.as_ref().map(|parent| {
             let path = self.add_path_if_needed(parent.name.clone().to_string()).join("::");
@@ -1319,6 +1532,14 @@ impl Compiler {
             partial_class.add_member(member, name);
         });
 
+        for signal in signals.iter() {
+            let mut types = vec![TypeTag::Void];
+            types.extend(signal.parameters.iter().map(|ty| self.convert_type(ty)));
+            let mut signal_name = name.clone();
+            signal_name.push(signal.name.to_string());
+            partial_class.add_signal(signal_name.join("::"), signal.is_static, SignatureEntry::new(types));
+        }
+
         let mut static_init_bytecode = Vec::new();
         static_init_bytecode.push(Bytecode::StartBlock(0));
 
@@ -1353,11 +1574,9 @@ impl Compiler {
 
         static_init_bytecode.push(Bytecode::ReturnVoid);
 
-        let static_init_bytecode = static_init_bytecode.into_iter().flat_map(|code| {
-            code.into_binary()
-        }).collect::<Vec<_>>();
+        let (static_init_bytecode, lines) = self.assemble(&mut CurrentCompilationUnit::Class(&mut partial_class), static_init_bytecode);
 
-        partial_class.attach_static_init_bytecode(static_init_bytecode).expect("attaching bytecode error");
+        partial_class.attach_static_init_bytecode(static_init_bytecode, lines).expect("attaching bytecode error");
 
         self.classes.insert(class_name.clone(), partial_class);
 
@@ -2152,8 +2371,11 @@ impl Compiler {
 
         for method in methods {
             self.method_returned = false;
+            self.current_block_returned = false;
+            self.lines.clear();
             let Method {
                 name,
+                annotations,
                 parameters,
                 body,
                 is_native,
@@ -2179,19 +2401,32 @@ impl Compiler {
                 bytecode.push(Bytecode::ReturnVoid);
             }
 
-            let bytecode = bytecode.into_iter().flat_map(|code| {
-                code.into_binary()
-            }).collect::<Vec<_>>();
+            let (bytecode, lines) = self.assemble(partial_file, bytecode);
 
             if is_static {
+                let responds_to = annotations.iter()
+                    .find(|annotation| annotation.name == "Listen")
+                    .map(|annotation| {
+                        let (current_class, signal) = match annotation.parameters.as_slice() {
+                            [signal] => (class_name.clone(), signal),
+                            [class, signal] => (self.add_path_if_needed(class.to_string()), signal),
+                            _ => unreachable!("Listen takes a signal or a class and a signal"),
+                        };
+                        let (_, signal_name) = self.find_signal(current_class, signal);
+                        signal_name
+                    });
                 let mut class_name = partial_file.get_class_name();
                 class_name.push(name.to_string());
                 let name = class_name.join("::");
-                partial_file.add_static_method(name, bytecode, *is_native);
+                partial_file.add_static_method(name, bytecode, lines, *is_native, responds_to);
             } else {
                 //println!("{}", name);
+                let is_override = annotations.iter()
+                    .any(|annotation| annotation.name == "Override");
                 let path_name = if name.contains("::") {
                     name.to_string()
+                } else if let Some(path_name) = is_override.then(|| partial_file.get_overridden_method_name(name)).flatten() {
+                    path_name
                 } else {
                     let mut path_name = class_name.clone();
                     path_name.push(name.to_string());
@@ -2212,7 +2447,7 @@ impl Compiler {
                     method_name.join("::")
                 };
 
-                partial_file.attach_bytecode(&method_class_name, method_name, bytecode, *is_native).expect("Handle partial class error");
+                partial_file.attach_bytecode(&method_class_name, method_name, bytecode, lines, *is_native).expect("Handle partial class error");
             }
 
             self.pop_scope();
@@ -2260,11 +2495,21 @@ impl Compiler {
         
         for statement in body {
             // println!("{:#?}", statement);
+            self.mark_span(statement.get_span(), output.len());
             match statement {
                 Statement::Expression(expr, _) => {
                     self.compile_expression(class_name, partial_class, &expr, output, false)?;
+                    // An unused result would pile up on the stack every time a loop runs the statement
+                    match expr {
+                        Expression::Call { annotation, .. } |
+                        Expression::StaticCall { annotation, .. } |
+                        Expression::MatchExpression(MatchExpression { ty: annotation, .. }, _) if *annotation != Type::Void => {
+                            output.push(Bytecode::Pop);
+                        }
+                        _ => {}
+                    }
                 }
-                Statement::Let { bindings, value, .. } => {
+                Statement::Let { bindings, ty, value, .. } => {
                     self.compile_expression(class_name, partial_class, &value, output, false)?;
                     match bindings {
                         Pattern::Variable(var, _, _) => {
@@ -2272,7 +2517,16 @@ impl Compiler {
                             output.push(Bytecode::StoreLocal(index));
 
                         }
-                        _ => todo!("let bindings"),
+                        _ => {
+                            let value_name = format!("%let{}-value", self.current_block);
+                            let index = self.bind_variable(&value_name);
+                            output.push(Bytecode::StoreLocal(index));
+                            let mut failures = Vec::new();
+                            self.compile_pattern(class_name, partial_class, bindings, &value_name, ty, &mut failures, output)?;
+                            if !failures.is_empty() {
+                                unreachable!("the typechecker rejects refutable let bindings");
+                            }
+                        }
                     }
                 }
                 Statement::While { test, body, .. } => {
@@ -2281,6 +2535,8 @@ impl Compiler {
                     let while_test_block = self.current_block;
                     output.push(Bytecode::StartBlock(while_test_block));
                     self.compile_expression(class_name, partial_class, test, output, false)?;
+                    // The body can contain blocks of its own, so the exit is filled in after compiling it
+                    let test_index = output.len();
                     output.push(Bytecode::If(1, 2));
                     self.increment_block();
                     self.compile_block(class_name, partial_class, body, output)?;
@@ -2288,36 +2544,84 @@ impl Compiler {
                     output.push(Bytecode::Goto(while_loop_block));
                     self.increment_block();
                     let exit_block = self.current_block;
+                    output[test_index] = Bytecode::If(1, (exit_block - while_test_block) as i64);
                     output.push(Bytecode::StartBlock(exit_block));
                 }
-                Statement::Assignment { target, value, .. } => {
-                    match target {
-                        Expression::Variable(name, _,  _) => {
-                            let var_index = self.get_variable(name).expect("report unbound variable");
-                            self.compile_expression(class_name, partial_class, value, output, false)?;
-                            output.push(Bytecode::StoreLocal(var_index));
-                        }
-                        Expression::BinaryOperation { operator: BinaryOperator::Index, .. } => {
-                            let defer = match self.compile_expression(class_name, partial_class, target, output, true) {
-                                Ok(defer) => defer,
-                                Err(err) => return Err(err),
-                            };
-                            self.compile_expression(class_name, partial_class, value, output, false)?;
-                            if let Some(defer) = defer {
-                                defer(output);
-                            }
-                        }
-                        Expression::MemberAccess {
-                             ..
-                        } => {
-                            let defer = self.compile_member_set(class_name, partial_class, target, output)?;
-                            self.compile_expression(class_name, partial_class, value, output, false)?;
-                            if let Some(defer) = defer {
-                                defer(output);
-                            }
-                        }
-                        _ => todo!("lhs assignment")
+                Statement::With { expr, bindings, bindings_type, block, span } => {
+                    self.compile_with(class_name, partial_class, expr, bindings, bindings_type, block, *span, output)?;
+                }
+                Statement::For { bindings, bindings_type, iterable, body, span, .. } => {
+                    self.compile_for(class_name, partial_class, bindings, bindings_type, iterable, body, *span, output)?;
+                }
+                Statement::Assignment { target, operator: None, value, .. } => {
+                    self.compile_assignment(class_name, partial_class, target, value, output)?;
+                }
+                Statement::Assignment { target, operator: Some(operator), value, span } => {
+                    self.compile_compound_assignment(class_name, partial_class, target, operator, value, *span, output)?;
+                }
+                Statement::Emit { object, signal, args, .. } => {
+                    // Rev is used because otherwise arguments are not loaded from left to right
+                    for (i, arg) in args.iter().enumerate().rev() {
+                        self.compile_expression(class_name, partial_class, arg, output, false)?;
+                        self.bind_variable(format!("arg{i}"));
+                    }
+
+                    for i in 1..=args.len() { // 1..len for leaving space for object
+                        self.get_variable(format!("arg{}", i - 1));
+                        output.push(Bytecode::StoreArgument(i as u8));
+                    }
+
+                    self.compile_expression(class_name, partial_class, object, output, false)?;
+                    output.push(Bytecode::StoreArgument(0));
+
+                    let (declaring_class, signal_name) = self.get_signal_name(class_name, object, signal);
+                    let declaring_class = partial_class.add_string(declaring_class);
+                    let signal_name = partial_class.add_string(signal_name);
+                    output.push(Bytecode::EmitSignal(declaring_class, signal_name));
+                }
+                Statement::EmitStatic { signal, args, .. } => {
+                    // Rev is used because otherwise arguments are not loaded from left to right
+                    for (i, arg) in args.iter().enumerate().rev() {
+                        self.compile_expression(class_name, partial_class, arg, output, false)?;
+                        self.bind_variable(format!("arg{i}"));
+                    }
+
+                    for i in 0..args.len() {
+                        self.get_variable(format!("arg{}", i));
+                        output.push(Bytecode::StoreArgument(i as u8));
                     }
+
+                    let (declaring_class, signal_name) = self.get_static_signal_name(class_name, signal);
+                    let declaring_class = partial_class.add_string(declaring_class);
+                    let signal_name = partial_class.add_string(signal_name);
+                    output.push(Bytecode::EmitStaticSignal(declaring_class, signal_name));
+                }
+                Statement::Connect { source, signal, target, method, .. } => {
+                    let (signal_name, vtable_class_name, method_name) = self.compile_connection(
+                        class_name,
+                        partial_class,
+                        source,
+                        signal,
+                        target,
+                        method,
+                        output
+                    )?;
+                    output.push(Bytecode::ConnectSignal(signal_name, vtable_class_name, method_name));
+                }
+                Statement::Disconnect { source, signal, target, method, .. } => {
+                    let (signal_name, vtable_class_name, method_name) = self.compile_connection(
+                        class_name,
+                        partial_class,
+                        source,
+                        signal,
+                        target,
+                        method,
+                        output
+                    )?;
+                    output.push(Bytecode::DisconnectSignal(signal_name, vtable_class_name, method_name));
+                }
+                Statement::Try { body, catches, finally, .. } => {
+                    self.compile_try(class_name, partial_class, body, catches, finally.as_ref(), output)?;
                 }
                 _ => unimplemented!("compile_block statement: {:?}", statement),
             }
@@ -2328,82 +2632,482 @@ impl Compiler {
         Ok(())
     }
 
-    fn compile_expression<'a>(
+    /// Lowers a try statement onto exception handlers.
+    /// The handler for the first catch is registered last so that it gets checked first.
+    /// A finally block registers a handler for every exception so that it can run before the exception is rethrown.
+    /// Every way of leaving the try, including returns, goes through the finally block.
+    fn compile_try(
         &mut self,
         class_name: &Vec<String>,
         partial_class: &mut CurrentCompilationUnit,
-        expr: &'a Expression,
+        body: &Vec<Statement>,
+        catches: &Vec<CatchClause>,
+        finally: Option<&Vec<Statement>>,
+        output: &mut Vec<Bytecode>
+    ) -> Result<(), CompilerError> {
+        let start_block = self.current_block;
+        self.current_block_returned = false;
+        let catch_classes = catches.iter()
+            .map(|catch| {
+                let Type::Object(name, _) = &catch.ty else {
+                    unreachable!("the typechecker only allows exceptions to be caught")
+                };
+                self.add_path_if_needed(name.to_string()).join("::")
+            })
+            .collect::<Vec<_>>();
+
+        // The gotos to the exit block, whether they are in the finally block and the blocks they are in
+        let mut exits = Vec::new();
+
+        // The finally block is compiled first so that returns in the body know which block to go to
+        let mut finally_output = Vec::new();
+        let finally_lines_start = self.lines.len();
+        let finally = match finally {
+            Some(finally) => {
+                self.increment_block();
+                let context = FinallyContext {
+                    block: self.current_block,
+                    exception: self.bind_variable(format!("%try{start_block}-exception")),
+                    rethrow: self.bind_variable(format!("%try{start_block}-rethrow")),
+                    value: self.bind_variable(format!("%try{start_block}-value")),
+                    returning_value: self.bind_variable(format!("%try{start_block}-returning-value")),
+                    returning_void: self.bind_variable(format!("%try{start_block}-returning-void")),
+                };
+                self.compile_block(class_name, partial_class, finally, &mut finally_output)?;
+                if !self.current_block_returned {
+                    self.compile_finally_dispatch(partial_class, &context, &mut finally_output, &mut exits);
+                }
+                self.current_block_returned = false;
+                Some(context)
+            }
+            None => None,
+        };
+        let finally_lines_end = self.lines.len();
+        let exception_class = String::from("core::Exception");
+
+        // The offsets of the handlers are filled in once their blocks have been compiled
+        let mut handlers = Vec::new();
+        let mut registrations = Vec::new();
+        if finally.is_some() {
+            handlers.push(exception_class.clone());
+        }
+        handlers.extend(catch_classes.iter().rev().cloned());
+        for class in handlers.iter() {
+            let index = partial_class.add_string(class);
+            registrations.push(output.len());
+            output.push(Bytecode::RegisterException(index, 0));
+        }
+
+        self.increment_block();
+        output.push(Bytecode::Goto((self.current_block - start_block) as i64));
+        self.try_stack.push(TryContext {
+            handlers,
+            finally: finally.clone(),
+        });
+        self.compile_block(class_name, partial_class, body, output)?;
+        if !self.current_block_returned {
+            self.compile_leave_try(partial_class, output, &mut exits);
+        }
+        self.current_block_returned = false;
+
+        for (i, catch) in catches.iter().enumerate() {
+            self.increment_block();
+            let handler_block = self.current_block;
+            let registration = registrations[registrations.len() - 1 - i];
+            let Bytecode::RegisterException(index, _) = output[registration] else {
+                unreachable!("registrations only point to RegisterException");
+            };
+            output[registration] = Bytecode::RegisterException(index, (handler_block - start_block) as i64);
+
+            output.push(Bytecode::StartBlock(handler_block));
+            self.push_scope();
+            let binding = self.bind_variable(&catch.binding);
+            output.push(Bytecode::StoreLocal(binding));
+            // Catching an exception only removes the handlers registered after the one that caught it
+            for class in catch_classes.iter().skip(i + 1) {
+                let index = partial_class.add_string(class);
+                output.push(Bytecode::UnregisterException(index));
+            }
+            let context = self.try_stack.last_mut().expect("try context was just pushed");
+            context.handlers.truncate(if finally.is_some() { 1 } else { 0 });
+
+            output.push(Bytecode::Goto(1));
+            self.increment_block();
+            self.compile_block(class_name, partial_class, &catch.body, output)?;
+            if !self.current_block_returned {
+                self.compile_leave_try(partial_class, output, &mut exits);
+            }
+            self.current_block_returned = false;
+            self.pop_scope();
+        }
+        self.try_stack.pop();
+
+        if let Some(finally) = &finally {
+            self.increment_block();
+            let handler_block = self.current_block;
+            let index = partial_class.add_string(&exception_class);
+            output[registrations[0]] = Bytecode::RegisterException(index, (handler_block - start_block) as i64);
+
+            output.push(Bytecode::StartBlock(handler_block));
+            output.push(Bytecode::StoreLocal(finally.exception));
+            self.compile_finally_flags(finally, true, false, false, output);
+            output.push(Bytecode::Goto(finally.block as i64 - handler_block as i64));
+
+            let offset = output.len();
+            for (index, in_finally, _) in exits.iter_mut() {
+                if *in_finally {
+                    *index += offset;
+                }
+            }
+            self.shift_spans(finally_lines_start, finally_lines_end, offset);
+            output.extend(finally_output);
+        }
+
+        self.increment_block();
+        let exit_block = self.current_block;
+        for (index, _, block) in exits {
+            output[index] = Bytecode::Goto(exit_block as i64 - block as i64);
+        }
+        output.push(Bytecode::StartBlock(exit_block));
+
+        Ok(())
+    }
+
+    /// Leaves the innermost try normally by unregistering its handlers and going through its finally block
+    fn compile_leave_try(
+        &mut self,
+        partial_class: &mut CurrentCompilationUnit,
         output: &mut Vec<Bytecode>,
-        lhs : bool,
-    ) -> Result<Option<Box<dyn Fn(&mut Vec<Bytecode>) + 'a>>, CompilerError> {
-        // println!("lhs {}", lhs);
-        // println!("Expression: {:#?}", expr);
-        match expr {
-            Expression::Variable(var, _, span) => {
-                let index = self.get_variable(var)
-                    .ok_or(CompilerError::UnboundIdentifer(var.clone().to_string(), span.start, span.end))?;
-                output.push(Bytecode::LoadLocal(index));
-                
+        exits: &mut Vec<(usize, bool, u64)>,
+    ) {
+        let context = self.try_stack.last().expect("leaving a try outside of a try");
+        for class in context.handlers.iter().rev() {
+            let index = partial_class.add_string(class);
+            output.push(Bytecode::UnregisterException(index));
+        }
+        match &context.finally {
+            Some(finally) => {
+                self.compile_finally_flags(finally, false, false, false, output);
+                output.push(Bytecode::Goto(finally.block as i64 - self.current_block as i64));
             }
-            Expression::Literal(lit) => {
-                match lit {
-                    Literal::Constant(constant) => {
-                        match constant {
-                            Constant::Bool(val, span) => {
-                                if *val {
-                                    output.push(Bytecode::LoadU8(1));
-                                } else {
-                                    output.push(Bytecode::LoadU8(0));
-                                }
-                            }
-                            Constant::Character(char_str, span) => {
-                                let chr = match char_str.as_str() {
-                                    "\\n" => '\n',
-                                    "\\t" => '\t',
-                                    "\\r" => '\r',
-                                    "\\\\" => '\\',
-                                    x if x.contains("\\x") => {
-                                        x.parse::<char>().expect("Char parse error")
-                                    }
-                                    x if x.chars().count() == 1 => {
-                                        x.chars().next().unwrap()
-                                    }
-                                    x => return Err(CompilerError::MalformedCharacter(x.to_string(), span.start, span.end)),
-                                };
+            None => {
+                exits.push((output.len(), false, self.current_block));
+                output.push(Bytecode::Goto(0));
+            }
+        }
+    }
 
-                                let value = chr as u32;
+    /// Decides what to do at the end of a finally block from how it was entered
+    fn compile_finally_dispatch(
+        &mut self,
+        partial_class: &mut CurrentCompilationUnit,
+        finally: &FinallyContext,
+        output: &mut Vec<Bytecode>,
+        exits: &mut Vec<(usize, bool, u64)>,
+    ) {
+        // These returns only happen if the try returned, so they don't count as the method returning
+        let method_returned = self.method_returned;
+        output.push(Bytecode::LoadLocal(finally.rethrow));
+        output.push(Bytecode::If(1, 2));
+        self.increment_block();
+        output.push(Bytecode::StartBlock(self.current_block));
+        output.push(Bytecode::LoadLocal(finally.exception));
+        output.push(Bytecode::Throw);
+
+        self.increment_block();
+        output.push(Bytecode::StartBlock(self.current_block));
+        output.push(Bytecode::LoadLocal(finally.returning_value));
+        output.push(Bytecode::If(1, 2));
+        self.increment_block();
+        output.push(Bytecode::StartBlock(self.current_block));
+        output.push(Bytecode::LoadLocal(finally.value));
+        self.compile_return(partial_class, true, output);
+
+        self.increment_block();
+        output.push(Bytecode::StartBlock(self.current_block));
+        output.push(Bytecode::LoadLocal(finally.returning_void));
+        output.push(Bytecode::If(1, 2));
+        self.increment_block();
+        output.push(Bytecode::StartBlock(self.current_block));
+        self.compile_return(partial_class, false, output);
+        self.method_returned = method_returned;
 
-                                output.push(Bytecode::LoadU32(value));
-                            }
-                            Constant::String(string, _) => {
-                                let string = string.replace("\\n", "\n");
-                                let string = string.replace("\\r", "\r");
-                                let string = string.replace("\\t", "\t");
-                                let string = string.replace("\\\\", "\\");
+        self.increment_block();
+        output.push(Bytecode::StartBlock(self.current_block));
+        exits.push((output.len(), true, self.current_block));
+        output.push(Bytecode::Goto(0));
+    }
 
-                                let string_ref = partial_class.add_string(string);
-                                output.push(Bytecode::GetStrRef(string_ref));
-                            }
-                            Constant::Float(value, ty, _) => {
-                                match ty {
-                                    Type::F32 => {
-                                        let value = value.parse::<f32>().expect("malformed f32");
-                                        output.push(Bytecode::LoadF32(value));
-                                    }
-                                    Type::F64 => {
-                                        let value = value.parse::<f64>().expect("malformed f64");
-                                        output.push(Bytecode::LoadF64(value));
-                                    }
-                                    _ => unreachable!("float literal"),
-                                }
-                            }
-                            Constant::Integer(value, ty, _) => {
-                                match ty {
-                                    Type::F32 => {
-                                        let value = value.parse::<f32>().expect("malformed f32");
-                                        output.push(Bytecode::LoadF32(value));
-                                    }
-                                    Type::F64 => {
+    fn compile_finally_flags(
+        &self,
+        finally: &FinallyContext,
+        rethrow: bool,
+        returning_value: bool,
+        returning_void: bool,
+        output: &mut Vec<Bytecode>,
+    ) {
+        for (flag, value) in [
+            (finally.rethrow, rethrow),
+            (finally.returning_value, returning_value),
+            (finally.returning_void, returning_void),
+        ] {
+            output.push(Bytecode::LoadU8(value as u8));
+            output.push(Bytecode::StoreLocal(flag));
+        }
+    }
+
+    /// Returns from the method with the value on the stack if there is one.
+    /// Inside of a try with a finally block, the handlers get unregistered and the finally block runs before returning.
+    fn compile_return(&mut self, partial_class: &mut CurrentCompilationUnit, has_value: bool, output: &mut Vec<Bytecode>) {
+        let mut handlers = Vec::new();
+        let mut finally = None;
+        for context in self.try_stack.iter().rev() {
+            handlers.extend(context.handlers.iter().rev().cloned());
+            if let Some(context) = &context.finally {
+                finally = Some(context.clone());
+                break;
+            }
+        }
+        let Some(finally) = finally else {
+            // Any handlers that are still registered go away with the frame
+            self.method_returned = true;
+            if has_value {
+                output.push(Bytecode::Return);
+            } else {
+                output.push(Bytecode::ReturnVoid);
+            }
+            return;
+        };
+
+        if has_value {
+            output.push(Bytecode::StoreLocal(finally.value));
+        }
+        for class in handlers {
+            let index = partial_class.add_string(class);
+            output.push(Bytecode::UnregisterException(index));
+        }
+        self.compile_finally_flags(&finally, false, has_value, !has_value, output);
+        output.push(Bytecode::Goto(finally.block as i64 - self.current_block as i64));
+    }
+
+    /// Gets the path of the class of an object that is sending or receiving a signal
+    fn get_object_class_path(&self, class_name: &Vec<String>, object: &Expression) -> Vec<String> {
+        match object.get_type() {
+            Either::Right(()) => class_name.clone(),
+            Either::Left(Type::Object(name, _)) => self.add_path_if_needed(name.to_string()),
+            Either::Left(ty) => todo!("signals on objects of type {:?}", ty),
+        }
+    }
+
+    /// Finds the class that declares a signal and returns the class name and the full name of the signal
+    fn get_signal_name(&self, class_name: &Vec<String>, object: &Expression, signal: &PathName) -> (String, String) {
+        let signal = signal.segments.last().unwrap();
+        let current_class = self.get_object_class_path(class_name, object);
+        self.find_signal(current_class, signal)
+    }
+
+    /// Gets the declaring class and full name of a static signal.
+    /// A signal without a class path refers to the current class.
+    fn get_static_signal_name(&mut self, class_name: &Vec<String>, signal: &PathName) -> (String, String) {
+        let signal_name = signal.segments.last().unwrap();
+        let current_class = if signal.segments.len() == 1 {
+            class_name.clone()
+        } else {
+            let class = signal.segments[signal.segments.len() - 2].to_string();
+            self.add_path_if_needed(class)
+        };
+        self.find_signal(current_class, signal_name)
+    }
+
+    /// Walks up the parents of a class until it finds the class that declares the signal
+    fn find_signal(&self, mut current_class: Vec<String>, signal: &str) -> (String, String) {
+        loop {
+            let mut signal_name = current_class.clone();
+            signal_name.push(signal.to_string());
+            let signal_name = signal_name.join("::");
+
+            let partial_class = self.classes.get(&current_class)
+                .expect(&format!("unable to find class: {}", current_class.join("::")));
+            if partial_class.contains_signal(&signal_name) {
+                return (current_class.join("::"), signal_name);
+            }
+            let Some(parent_path) = partial_class.get_parent_name() else {
+                panic!("Unable to find signal {}", signal);
+            };
+            current_class = parent_path;
+        }
+    }
+
+    /// Pushes the emitting object and then the receiving object onto the stack.
+    /// This returns the signal name, the class name of the method's vtable, and the method name
+    fn compile_connection(
+        &mut self,
+        class_name: &Vec<String>,
+        partial_class: &mut CurrentCompilationUnit,
+        source: &Expression,
+        signal: &PathName,
+        target: &Expression,
+        method: &PathName,
+        output: &mut Vec<Bytecode>,
+    ) -> Result<(StringIndex, StringIndex, StringIndex), CompilerError> {
+        self.compile_expression(class_name, partial_class, source, output, false)?;
+        self.compile_expression(class_name, partial_class, target, output, false)?;
+
+        let (_, signal_name) = self.get_signal_name(class_name, source, signal);
+        let signal_name = partial_class.add_string(signal_name);
+
+        let ty = self.get_object_class_path(class_name, target);
+        let name = method.segments.last().unwrap();
+
+        if ty == partial_class.get_class_name() {
+            let mut method_name = class_name.clone();
+            method_name.push(name.to_string());
+
+            let method_name = method_name.join("::");
+            let (vtable, method_name) = match partial_class.get_vtable(&method_name) {
+                Ok(vtable) => (vtable, method_name),
+                Err(e) => {
+                    let mut current_partial_class: &PartialClass = match partial_class {
+                        CurrentCompilationUnit::Class(class) => *class,
+                        _ => unreachable!(),
+                    };
+                    let mut vtable = None;
+                    while let Some(parent_path) = current_partial_class.get_parent_name() {
+                        let mut method_name = parent_path.clone();
+                        method_name.push(name.to_string());
+                        match partial_class.get_vtable(method_name.join("::")) {
+                            Ok(vtbl) => {
+                                vtable = Some((vtbl, method_name.join("::")));
+                                break;
+                            }
+                            _ => {}
+                        }
+                        current_partial_class = self.classes.get(&parent_path).unwrap();
+                    }
+
+                    match vtable {
+                        Some(vtable) => vtable,
+                        None => panic!("Unable to find vtable {:?}", e),
+                    }
+                }
+            };
+            let method_entry = partial_class.get_method_entry(&method_name).expect("add proper handling of missing method");
+
+            let class_name = partial_class.index_string_table(vtable.sub_class_name).to_string();
+            let vtable_class_name = partial_class.add_string(class_name);
+
+            let method_name = partial_class.index_string_table(method_entry.name).to_string();
+            let method_name = partial_class.add_string(method_name);
+
+            Ok((signal_name, vtable_class_name, method_name))
+        } else {
+            let class = self.classes.get(&ty).expect(&format!("unable to find class: {}", ty.join("::")));
+            let mut method_path = ty.clone();
+            method_path.push(name.to_string());
+
+            let mut method_path = method_path.join("::");
+            let mut vtable = class.get_vtable(&method_path);
+            let mut current_class = class;
+            while vtable.is_err() {
+                // Here we try to find the method in the vtables of the parents
+                let Some(parent_path) = current_class.get_parent_name() else {
+                    break;
+                };
+                let mut parent_method_path = parent_path.clone();
+                parent_method_path.push(name.to_string());
+                method_path = parent_method_path.join("::");
+                vtable = class.get_vtable(&method_path);
+                current_class = self.classes.get(&parent_path).unwrap();
+            }
+            let vtable = vtable.expect("Unable to find vtable");
+            let method_entry = class.get_method_entry(&method_path).expect("add proper handling of missing method");
+
+            let class_name = class.index_string_table(vtable.sub_class_name);
+            let vtable_class_name = partial_class.add_string(class_name);
+
+            let method_name = class.index_string_table(method_entry.name);
+            let method_name = partial_class.add_string(method_name);
+
+            Ok((signal_name, vtable_class_name, method_name))
+        }
+    }
+
+    fn compile_expression<'a>(
+        &mut self,
+        class_name: &Vec<String>,
+        partial_class: &mut CurrentCompilationUnit,
+        expr: &'a Expression,
+        output: &mut Vec<Bytecode>,
+        lhs : bool,
+    ) -> Result<Option<Box<dyn Fn(&mut Vec<Bytecode>) + 'a>>, CompilerError> {
+        // println!("lhs {}", lhs);
+        // println!("Expression: {:#?}", expr);
+        match expr {
+            Expression::Variable(var, _, span) => {
+                let index = self.get_variable(var)
+                    .ok_or(CompilerError::UnboundIdentifer(var.clone().to_string(), span.start, span.end))?;
+                output.push(Bytecode::LoadLocal(index));
+                
+            }
+            Expression::Literal(lit) => {
+                match lit {
+                    Literal::Constant(constant) => {
+                        match constant {
+                            Constant::Bool(val, span) => {
+                                if *val {
+                                    output.push(Bytecode::LoadU8(1));
+                                } else {
+                                    output.push(Bytecode::LoadU8(0));
+                                }
+                            }
+                            Constant::Character(char_str, span) => {
+                                let chr = match char_str.as_str() {
+                                    "\\n" => '\n',
+                                    "\\t" => '\t',
+                                    "\\r" => '\r',
+                                    "\\\\" => '\\',
+                                    x if x.contains("\\x") => {
+                                        x.parse::<char>().expect("Char parse error")
+                                    }
+                                    x if x.chars().count() == 1 => {
+                                        x.chars().next().unwrap()
+                                    }
+                                    x => return Err(CompilerError::MalformedCharacter(x.to_string(), span.start, span.end)),
+                                };
+
+                                let value = chr as u32;
+
+                                output.push(Bytecode::LoadU32(value));
+                            }
+                            Constant::String(string, _) => {
+                                let string = string.replace("\\n", "\n");
+                                let string = string.replace("\\r", "\r");
+                                let string = string.replace("\\t", "\t");
+                                let string = string.replace("\\\\", "\\");
+
+                                let string_ref = partial_class.add_string(string);
+                                output.push(Bytecode::GetStrRef(string_ref));
+                            }
+                            Constant::Float(value, ty, _) => {
+                                match ty {
+                                    Type::F32 => {
+                                        let value = value.parse::<f32>().expect("malformed f32");
+                                        output.push(Bytecode::LoadF32(value));
+                                    }
+                                    Type::F64 => {
+                                        let value = value.parse::<f64>().expect("malformed f64");
+                                        output.push(Bytecode::LoadF64(value));
+                                    }
+                                    _ => unreachable!("float literal"),
+                                }
+                            }
+                            Constant::Integer(value, ty, _) => {
+                                match ty {
+                                    Type::F32 => {
+                                        let value = value.parse::<f32>().expect("malformed f32");
+                                        output.push(Bytecode::LoadF32(value));
+                                    }
+                                    Type::F64 => {
                                         let value = value.parse::<f64>().expect("malformed f64");
                                         output.push(Bytecode::LoadF64(value));
                                     }
@@ -2476,12 +3180,51 @@ impl Compiler {
                             output.push(Bytecode::ArraySet(type_tag));
                         }
                     }
+                    Literal::Tuple(exprs, Type::Tuple(tys, _), _) => {
+                        let tuple_class = self.tuple_class(tys);
+                        let class = self.classes.get(&tuple_class).expect("tuple class was just created");
+                        let offsets = (0..exprs.len())
+                            .map(|i| class.get_member_offset(i.to_string().as_str()))
+                            .collect::<Vec<_>>();
+                        let class_ref = partial_class.add_string(tuple_class.join("::"));
+                        let parent_ref = partial_class.add_string("");
+                        output.push(Bytecode::NewObject(class_ref));
+                        for (expr, (offset, type_tag)) in exprs.iter().zip(offsets) {
+                            output.push(Bytecode::Dup);
+                            self.compile_expression(class_name, partial_class, expr, output, lhs)?;
+                            output.push(Bytecode::SetField(class_ref, parent_ref, offset, type_tag));
+                        }
+                    }
                     _ => todo!("all other literals")
                 }
             }
             Expression::This(_) => {
                 output.push(Bytecode::LoadLocal(0));
             }
+            Expression::BinaryOperation { operator: operator @ (BinaryOperator::Eq | BinaryOperator::Ne), left, right, span }
+                if self.is_value_type(&left.get_type()) => {
+                // Records and variants are compared by their fields through their equals method
+                let Either::Left(ty) = left.get_type() else {
+                    unreachable!("value types are always known");
+                };
+                let call = Expression::Call {
+                    name: Box::new(Expression::MemberAccess {
+                        object: left.clone(),
+                        field: PathName::new(vec![Text::Borrowed("equals")], *span),
+                        span: *span,
+                        annotation: Type::Function(vec![ty], Box::new(Type::Boolean), *span),
+                    }),
+                    type_args: Vec::new(),
+                    args: vec![right.as_ref().clone()],
+                    span: *span,
+                    annotation: Type::Boolean,
+                };
+                self.compile_call_expression(class_name, partial_class, &call, output, lhs)?;
+                if *operator == BinaryOperator::Ne {
+                    output.push(Bytecode::LoadU8(0));
+                    output.push(Bytecode::EqualUnsigned);
+                }
+            }
             Expression::BinaryOperation { operator, left, right, span } => {
                 self.compile_expression(class_name, partial_class, left.as_ref(), output, lhs)?;
                 self.compile_expression(class_name, partial_class, right.as_ref(), output, lhs)?;
@@ -2535,6 +3278,25 @@ impl Compiler {
                     (Either::Left(lhs), BinaryOperator::Ne, Either::Left(rhs)) if lhs.is_signed() && rhs.is_signed() => {
                         output.push(Bytecode::NotEqualSigned)
                     }
+                    (Either::Left(lhs), BinaryOperator::Eq, Either::Left(rhs)) if lhs.is_float() || rhs.is_float() => {
+                        output.push(Bytecode::EqualFloat)
+                    }
+                    (Either::Left(lhs), BinaryOperator::Ne, Either::Left(rhs)) if lhs.is_float() || rhs.is_float() => {
+                        output.push(Bytecode::NotEqualFloat)
+                    }
+                    (Either::Left(Type::Boolean | Type::Char), BinaryOperator::Eq, _) => {
+                        output.push(Bytecode::EqualUnsigned)
+                    }
+                    (Either::Left(Type::Boolean | Type::Char), BinaryOperator::Ne, _) => {
+                        output.push(Bytecode::NotEqualUnsigned)
+                    }
+                    // Everything else that is an object is compared by identity
+                    (_, BinaryOperator::Eq, _) => {
+                        output.push(Bytecode::EqualUnsigned)
+                    }
+                    (_, BinaryOperator::Ne, _) => {
+                        output.push(Bytecode::NotEqualUnsigned)
+                    }
                     (Either::Left(lhs), BinaryOperator::Lt, Either::Left(rhs)) if lhs.is_unsigned() && rhs.is_unsigned() => {
                         output.push(Bytecode::LessUnsigned)
                     }
@@ -2599,6 +3361,24 @@ impl Compiler {
                             output.push(Bytecode::ArrayGet(type_tag));
                         }
                     }
+                    (Either::Left(lhs), BinaryOperator::BitAnd, _) if lhs.is_integer() || lhs == Type::Boolean => {
+                        output.push(Bytecode::And)
+                    }
+                    (Either::Left(lhs), BinaryOperator::BitOr, _) if lhs.is_integer() || lhs == Type::Boolean => {
+                        output.push(Bytecode::Or)
+                    }
+                    (Either::Left(lhs), BinaryOperator::BitXor, _) if lhs.is_integer() || lhs == Type::Boolean => {
+                        output.push(Bytecode::Xor)
+                    }
+                    (Either::Left(lhs), BinaryOperator::Shl, _) if lhs.is_integer() => {
+                        output.push(Bytecode::Shl)
+                    }
+                    (Either::Left(lhs), BinaryOperator::Shr, _) if lhs.is_signed() => {
+                        output.push(Bytecode::AShr)
+                    }
+                    (Either::Left(lhs), BinaryOperator::Shr, _) if lhs.is_unsigned() => {
+                        output.push(Bytecode::LShr)
+                    }
                     (l, op, r) => todo!("binary operator {:?}: ({:?}: {:?}) ({:?}: {:?}) spanned: {:?}", op, left, l, right, r, span),
                 }
                 
@@ -2613,16 +3393,21 @@ impl Compiler {
                     UnaryOperator::Not => {
                         output.push(Bytecode::Not);
                     }
+                    UnaryOperator::BitNot => {
+                        output.push(Bytecode::Not);
+                    }
                     _ => unreachable!("try operator"),
                 }
             }
             Expression::Parenthesized(expr, _) => {
                 self.compile_expression(class_name, partial_class, expr.as_ref(), output, lhs)?;
             }
-            Expression::Call { .. } => {
+            Expression::Call { span, .. } => {
                 self.compile_call_expression(class_name, partial_class, expr, output, lhs)?;
+                // The invoke is the last instruction of the call
+                self.mark_span(*span, output.len() - 1);
             }
-            Expression::StaticCall { name, type_args, args, .. } => {
+            Expression::StaticCall { name, type_args, args, span, .. } => {
 
                 for (i, arg) in args.iter().enumerate().rev() {
                     self.compile_expression(class_name, partial_class, arg, output, lhs)?;
@@ -2635,15 +3420,18 @@ impl Compiler {
                 }
 
                 let method_name = name.segments.last().unwrap();
-                let method_class = name.segments.iter().rev().skip(1).next().unwrap();
-                let method_class = method_class.to_string();
+                let method_class = name.segments[..name.segments.len() - 1].iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join("::");
 
                 let mut path = self.add_path_if_needed(method_class);
                 let method_class = partial_class.add_string(path.join("::"));
                 path.push(method_name.to_string());
 
                 let method_name = partial_class.add_string(path.join("::"));
-                
+
+                self.mark_span(*span, output.len());
                 output.push(Bytecode::InvokeStatic(method_class, method_name));
             }
             Expression::MemberAccess {
@@ -2651,7 +3439,7 @@ impl Compiler {
             } => {
                 self.compile_member_get(class_name, partial_class, expr, output)?;
             }
-            Expression::New(ty, arr_size, _) => {
+            Expression::New(ty, arr_size, span) => {
                 if let Some(arr_size) = arr_size {
                     let name = match ty {
                         Type::I8 => TypeTag::I8,
@@ -2669,13 +3457,14 @@ impl Compiler {
                             if self.classes.contains_key(&path) {
                                 TypeTag::Object
                             } else {
-                                *self.current_type_args.get(name.as_str()).unwrap()
+                                *self.current_type_args.get(name.as_str()).unwrap_or(&TypeTag::Object)
                             }
                         },
                         Type::Native => unreachable!("new array can't have native"),
                         _ => TypeTag::Object,
                     };
                     self.compile_expression(class_name, partial_class, arr_size.as_ref(), output, lhs)?;
+                    self.mark_span(*span, output.len());
                     output.push(Bytecode::CreateArray(name));
                 } else {
                     let name = match ty {
@@ -2699,7 +3488,7 @@ impl Compiler {
                                         if self.classes.contains_key(&path) {
                                             "object"
                                         } else {
-                                            match self.current_type_args.get(ty.as_str()).unwrap() {
+                                            match self.current_type_args.get(ty.as_str()).unwrap_or(&TypeTag::Object) {
                                                 TypeTag::I8 | TypeTag::U8 => "8",
                                                 TypeTag::I16 | TypeTag::U16 => "16",
                                                 TypeTag::I32 | TypeTag::U32 => "32",
@@ -2730,24 +3519,28 @@ impl Compiler {
 
                     let string_ref = partial_class.add_string(name.join("::"));
 
+                    self.mark_span(*span, output.len());
                     output.push(Bytecode::NewObject(string_ref));
+                    self.compile_default_constructor(&name, partial_class, output);
                 }
             }
             Expression::IfExpression(if_expr, _) => {
                 self.compile_if_expression(class_name, partial_class, if_expr, output, lhs)?;
             }
+            Expression::MatchExpression(match_expr, _) => {
+                self.compile_match_expression(class_name, partial_class, match_expr, output, lhs)?;
+            }
             Expression::Return(value, _) => {
-                self.method_returned = true;
-                let result = value.as_ref().map(|expr| {
-                    self.compile_expression(class_name, partial_class, expr.as_ref(), output, lhs)
-                });
-
-                if let Some(result) = result {
-                    let _ = result?;
-                    output.push(Bytecode::Return)
-                } else {
-                    output.push(Bytecode::ReturnVoid)
+                if let Some(value) = value {
+                    self.compile_expression(class_name, partial_class, value.as_ref(), output, lhs)?;
                 }
+                self.compile_return(partial_class, value.is_some(), output);
+                self.current_block_returned = true;
+            }
+            Expression::Throw(exception, span) => {
+                self.compile_expression(class_name, partial_class, exception.as_ref(), output, lhs)?;
+                self.mark_span(*span, output.len());
+                output.push(Bytecode::Throw);
                 self.current_block_returned = true;
             }
             Expression::Closure { params, return_type, body, captures, span, .. } => {
@@ -2773,6 +3566,7 @@ impl Compiler {
         Ok(None)
     }
 
+    /// The branches can contain blocks of their own, so the jumps past them are filled in once they have been compiled
     fn compile_if_expression(
         &mut self,
         class_name: &Vec<String>,
@@ -2781,54 +3575,530 @@ impl Compiler {
         output: &mut Vec<Bytecode>,
         lhs: bool,
     ) -> Result<(), CompilerError> {
-        match expr {
-            IfExpression { condition, then_branch, else_branch: None, ..} => {
-                self.compile_expression(class_name, partial_class, condition.as_ref(), output, lhs)?;
-                output.push(Bytecode::If(1, 2));
-                self.increment_block();
-                self.compile_block(class_name, partial_class, then_branch, output)?;
-                self.increment_block();
-                if !self.current_block_returned {
-                    output.push(Bytecode::Goto(1));
+        let IfExpression { condition, then_branch, else_branch, .. } = expr;
+        self.compile_expression(class_name, partial_class, condition.as_ref(), output, lhs)?;
+        let test_block = self.current_block;
+        let test_index = output.len();
+        output.push(Bytecode::If(1, 2));
+        self.increment_block();
+        self.compile_block(class_name, partial_class, then_branch, output)?;
+
+        // The gotos to the exit block and the blocks they are in
+        let mut exits = Vec::new();
+        if !self.current_block_returned {
+            exits.push((output.len(), self.current_block));
+            output.push(Bytecode::Goto(1));
+        }
+        self.current_block_returned = false;
+        self.increment_block();
+        output[test_index] = Bytecode::If(1, (self.current_block - test_block) as i64);
+
+        if let Some(else_branch) = else_branch {
+            match else_branch {
+                Either::Left(else_branch) => {
+                    let block = self.current_block;
+                    output.push(Bytecode::StartBlock(block));
+                    self.compile_if_expression(class_name, partial_class, else_branch.as_ref(), output, lhs)?;
+                }
+                Either::Right(else_branch) => {
+                    self.compile_block(class_name, partial_class, else_branch, output)?;
                 }
-                self.current_block_returned = false;
-                let block = self.current_block;
-                output.push(Bytecode::StartBlock(block));
             }
-            IfExpression { condition, then_branch, else_branch: Some(Either::Right(else_branch)), ..} => {
-                self.compile_expression(class_name, partial_class, condition.as_ref(), output, lhs)?;
-                output.push(Bytecode::If(1, 2));
-                self.increment_block();
-                self.compile_block(class_name, partial_class, then_branch, output)?;
-                output.push(Bytecode::Goto(2));
-                self.increment_block();
-                self.compile_block(class_name, partial_class, else_branch, output)?;
-                if !self.current_block_returned {
+            if !self.current_block_returned {
+                exits.push((output.len(), self.current_block));
+                output.push(Bytecode::Goto(1));
+            }
+            self.current_block_returned = false;
+            self.increment_block();
+        }
+
+        let exit_block = self.current_block;
+        for (index, block) in exits {
+            output[index] = Bytecode::Goto((exit_block - block) as i64);
+        }
+        output.push(Bytecode::StartBlock(exit_block));
+        Ok(())
+    }
+
+    fn compile_assignment<'a>(
+        &mut self,
+        class_name: &Vec<String>,
+        partial_class: &mut CurrentCompilationUnit,
+        target: &Expression<'a>,
+        value: &Expression<'a>,
+        output: &mut Vec<Bytecode>,
+    ) -> Result<(), CompilerError> {
+        match target {
+            Expression::Variable(name, _,  _) => {
+                let var_index = self.get_variable(name).expect("report unbound variable");
+                self.compile_expression(class_name, partial_class, value, output, false)?;
+                output.push(Bytecode::StoreLocal(var_index));
+            }
+            Expression::BinaryOperation { operator: BinaryOperator::Index, .. } => {
+                let defer = match self.compile_expression(class_name, partial_class, target, output, true) {
+                    Ok(defer) => defer,
+                    Err(err) => return Err(err),
+                };
+                self.compile_expression(class_name, partial_class, value, output, false)?;
+                if let Some(defer) = defer {
+                    defer(output);
+                }
+            }
+            Expression::MemberAccess {
+                 ..
+            } => {
+                let defer = self.compile_member_set(class_name, partial_class, target, output)?;
+                self.compile_expression(class_name, partial_class, value, output, false)?;
+                if let Some(defer) = defer {
+                    defer(output);
+                }
+            }
+            _ => unreachable!("the typechecker rejects other assignment targets")
+        }
+        Ok(())
+    }
+
+    /// `target op= value` is compiled as `target = target op value`.
+    /// The object or array and index of the target are stored in hidden locals first so that they are only evaluated once
+    fn compile_compound_assignment<'a>(
+        &mut self,
+        class_name: &Vec<String>,
+        partial_class: &mut CurrentCompilationUnit,
+        target: &Expression<'a>,
+        operator: &BinaryOperator,
+        value: &Expression<'a>,
+        span: Span,
+        output: &mut Vec<Bytecode>,
+    ) -> Result<(), CompilerError> {
+        let target = match target {
+            Expression::MemberAccess { object, field, span, annotation } => {
+                let object = self.compile_assignment_operand(class_name, partial_class, object, "object", output)?;
+                Expression::MemberAccess {
+                    object: Box::new(object),
+                    field: field.clone(),
+                    span: *span,
+                    annotation: annotation.clone(),
+                }
+            }
+            Expression::BinaryOperation { operator: BinaryOperator::Index, left, right, span } => {
+                let left = self.compile_assignment_operand(class_name, partial_class, left, "array", output)?;
+                let right = self.compile_assignment_operand(class_name, partial_class, right, "index", output)?;
+                Expression::BinaryOperation {
+                    operator: BinaryOperator::Index,
+                    left: Box::new(left),
+                    right: Box::new(right),
+                    span: *span,
+                }
+            }
+            target => target.clone(),
+        };
+        let value = Expression::BinaryOperation {
+            operator: operator.clone(),
+            left: Box::new(target.clone()),
+            right: Box::new(value.clone()),
+            span,
+        };
+        self.compile_assignment(class_name, partial_class, &target, &value, output)
+    }
+
+    /// Evaluates part of an assignment target into a hidden local unless reading it again has no effects
+    fn compile_assignment_operand<'a>(
+        &mut self,
+        class_name: &Vec<String>,
+        partial_class: &mut CurrentCompilationUnit,
+        operand: &Expression<'a>,
+        role: &str,
+        output: &mut Vec<Bytecode>,
+    ) -> Result<Expression<'a>, CompilerError> {
+        match operand {
+            Expression::Variable(..)
+            | Expression::This(_)
+            | Expression::ClassAccess { .. }
+            | Expression::Literal(Literal::Constant(_)) => Ok(operand.clone()),
+            _ => {
+                let Either::Left(ty) = operand.get_type() else {
+                    unreachable!("only self has no type");
+                };
+                self.compile_expression(class_name, partial_class, operand, output, false)?;
+                let name = format!("%assign{}-{role}", self.current_block);
+                let index = self.bind_variable(&name);
+                output.push(Bytecode::StoreLocal(index));
+                Ok(Expression::Variable(Text::Owned(name), ty, Span::new(0, 0)))
+            }
+        }
+    }
+
+    /// Calls the constructor of the object that `new X()` just created if it has one that takes no arguments.
+    fn compile_default_constructor(
+        &mut self,
+        class_path: &Vec<String>,
+        partial_class: &mut CurrentCompilationUnit,
+        output: &mut Vec<Bytecode>,
+    ) {
+        let Some(class) = self.classes.get(class_path) else {
+            return;
+        };
+        let mut method_path = class_path.clone();
+        method_path.push(String::from("new"));
+        let method_path = method_path.join("::");
+        let (Ok(vtable), Ok(method_entry)) = (class.get_vtable(&method_path), class.get_method_entry(&method_path)) else {
+            return;
+        };
+        // The return type and the object itself
+        if class.get_signature(method_entry.signature).types.len() != 2 {
+            return;
+        }
+
+        let vtable_class_name = partial_class.add_string(class.index_string_table(vtable.sub_class_name));
+        let method_name = partial_class.add_string(class.index_string_table(method_entry.name));
+        output.push(Bytecode::StoreArgument(0));
+        output.push(Bytecode::InvokeVirt(vtable_class_name, method_name));
+    }
+
+    /// Binds the value of a with statement and runs its block.
+    /// The value is closed in a finally block so that it happens however the block is left
+    fn compile_with<'a>(
+        &mut self,
+        class_name: &Vec<String>,
+        partial_class: &mut CurrentCompilationUnit,
+        expr: &Expression<'a>,
+        bindings: &Pattern,
+        bindings_type: &Type<'a>,
+        block: &Vec<Statement>,
+        span: Span,
+        output: &mut Vec<Bytecode>,
+    ) -> Result<(), CompilerError> {
+        let value_name = format!("%with{}-value", self.current_block);
+        self.compile_expression(class_name, partial_class, expr, output, false)?;
+        let value = self.bind_variable(&value_name);
+        output.push(Bytecode::StoreLocal(value));
+
+        self.push_scope();
+        let mut failures = Vec::new();
+        self.compile_pattern(class_name, partial_class, bindings, &value_name, bindings_type, &mut failures, output)?;
+        if !failures.is_empty() {
+            unreachable!("the typechecker rejects refutable with bindings");
+        }
+        let close = Expression::Call {
+            name: Box::new(Expression::MemberAccess {
+                object: Box::new(Expression::Variable(Text::Owned(value_name), bindings_type.clone(), span)),
+                field: PathName::new(vec![Text::Borrowed("close")], span),
+                span,
+                annotation: Type::Function(Vec::new(), Box::new(Type::Void), span),
+            }),
+            type_args: Vec::new(),
+            args: Vec::new(),
+            span,
+            annotation: Type::Void,
+        };
+        let finally = vec![Statement::Expression(close, span)];
+        self.compile_try(class_name, partial_class, block, &Vec::new(), Some(&finally), output)?;
+        self.pop_scope();
+        Ok(())
+    }
+
+    /// Arrays are walked with a hidden index, anything else is driven through `std::iter::Iterator`
+    fn compile_for<'a>(
+        &mut self,
+        class_name: &Vec<String>,
+        partial_class: &mut CurrentCompilationUnit,
+        bindings: &Pattern,
+        bindings_type: &Type<'a>,
+        iterable: &Expression<'a>,
+        body: &Vec<Statement>,
+        span: Span,
+        output: &mut Vec<Bytecode>,
+    ) -> Result<(), CompilerError> {
+        let start_block = self.current_block;
+        let iterable_type = iterable.get_type().left().unwrap_or(Type::Void);
+        let iterable_name = format!("%for{start_block}-iterable");
+        let index_name = format!("%for{start_block}-index");
+        let element_name = format!("%for{start_block}-element");
+
+        self.compile_expression(class_name, partial_class, iterable, output, false)?;
+        let iterable_local = self.bind_variable(&iterable_name);
+        output.push(Bytecode::StoreLocal(iterable_local));
+
+        let iterable_var = Expression::Variable(Text::Owned(iterable_name), iterable_type.clone(), span);
+        let method = |name: &str, ret: Type<'a>| Expression::Call {
+            name: Box::new(Expression::MemberAccess {
+                object: Box::new(iterable_var.clone()),
+                field: PathName::new(vec![Text::Owned(name.to_string())], span),
+                span,
+                annotation: Type::Function(Vec::new(), Box::new(ret.clone()), span),
+            }),
+            type_args: Vec::new(),
+            args: Vec::new(),
+            span,
+            annotation: ret,
+        };
+        let (test, element, index) = match &iterable_type {
+            Type::Array(..) => {
+                output.push(Bytecode::LoadU64(0));
+                let index = self.bind_variable(&index_name);
+                output.push(Bytecode::StoreLocal(index));
+                let index_var = Expression::Variable(Text::Owned(index_name), Type::U64, span);
+                let test = Expression::BinaryOperation {
+                    operator: BinaryOperator::Lt,
+                    left: Box::new(index_var.clone()),
+                    right: Box::new(method("len", Type::U64)),
+                    span,
+                };
+                let element = Expression::BinaryOperation {
+                    operator: BinaryOperator::Index,
+                    left: Box::new(iterable_var.clone()),
+                    right: Box::new(index_var),
+                    span,
+                };
+                (test, element, Some(index))
+            }
+            _ => (method("has-next", Type::Boolean), method("next", bindings_type.clone()), None),
+        };
+
+        output.push(Bytecode::Goto(1));
+        self.increment_block();
+        let test_block = self.current_block;
+        output.push(Bytecode::StartBlock(test_block));
+        self.compile_expression(class_name, partial_class, &test, output, false)?;
+        // The body can contain blocks of its own, so the exit is filled in after compiling it
+        let test_index = output.len();
+        output.push(Bytecode::If(1, 2));
+        self.increment_block();
+        let block = self.current_block;
+        output.push(Bytecode::StartBlock(block));
+
+        self.push_scope();
+        self.compile_expression(class_name, partial_class, &element, output, false)?;
+        let element = self.bind_variable(&element_name);
+        output.push(Bytecode::StoreLocal(element));
+        if let Some(index) = index {
+            output.push(Bytecode::LoadLocal(index));
+            output.push(Bytecode::LoadU64(1));
+            output.push(Bytecode::AddInt);
+            output.push(Bytecode::StoreLocal(index));
+        }
+        // Elements that don't match the bindings are skipped
+        let mut failures = Vec::new();
+        self.compile_pattern(class_name, partial_class, bindings, &element_name, bindings_type, &mut failures, output)?;
+        output.push(Bytecode::Goto(1));
+        self.increment_block();
+        self.compile_block(class_name, partial_class, body, output)?;
+        if !self.current_block_returned {
+            output.push(Bytecode::Goto(test_block as i64 - self.current_block as i64));
+        }
+        self.current_block_returned = false;
+        self.pop_scope();
+        for (index, block) in failures {
+            output[index] = Bytecode::If(1, test_block as i64 - block as i64);
+        }
+
+        self.increment_block();
+        let exit_block = self.current_block;
+        output[test_index] = Bytecode::If(1, (exit_block - test_block) as i64);
+        output.push(Bytecode::StartBlock(exit_block));
+        Ok(())
+    }
+
+    /// Lowers a match expression onto a chain of tests with a block after each test.
+    /// A failed test goes to the first block of the next arm, which is the exit block for the last arm
+    /// since the typechecker makes sure that the arms are exhaustive.
+    /// Arms that don't return store their value in a local that is loaded in the exit block.
+    fn compile_match_expression(
+        &mut self,
+        class_name: &Vec<String>,
+        partial_class: &mut CurrentCompilationUnit,
+        expr: &MatchExpression,
+        output: &mut Vec<Bytecode>,
+        lhs: bool,
+    ) -> Result<(), CompilerError> {
+        let start_block = self.current_block;
+        // Matching on a tuple literal keeps each of its values in a local instead of creating the tuple,
+        // unless an arm binds the whole tuple
+        let splittable = expr.arms.iter()
+            .all(|arm| matches!(arm.pattern, Pattern::Tuple(..) | Pattern::WildCard(_)));
+        let values = match expr.value.as_ref() {
+            Expression::Literal(Literal::Tuple(exprs, Type::Tuple(tys, _), _)) if splittable => {
+                let mut values = Vec::new();
+                for (i, (value, ty)) in exprs.iter().zip(tys.iter()).enumerate() {
+                    self.compile_expression(class_name, partial_class, value, output, lhs)?;
+                    let name = format!("%match{start_block}-value-{i}");
+                    let index = self.bind_variable(&name);
+                    output.push(Bytecode::StoreLocal(index));
+                    values.push((name, ty.clone()));
+                }
+                Some(values)
+            }
+            _ => None,
+        };
+        let value_name = format!("%match{start_block}-value");
+        if values.is_none() {
+            self.compile_expression(class_name, partial_class, expr.value.as_ref(), output, lhs)?;
+            let value = self.bind_variable(&value_name);
+            output.push(Bytecode::StoreLocal(value));
+        }
+        let value_type = expr.value.get_type().left().unwrap_or(Type::Void);
+        let result = if expr.ty != Type::Void {
+            Some(self.bind_variable(format!("%match{start_block}-result")))
+        } else {
+            None
+        };
+
+        // The gotos to the exit block and the blocks they are in
+        let mut exits = Vec::new();
+        for arm in expr.arms.iter() {
+            self.push_scope();
+            // The tests that go to the next arm and the blocks they are in
+            let mut failures = Vec::new();
+            match (&values, &arm.pattern) {
+                (Some(values), Pattern::Tuple(patterns, _)) => {
+                    for ((name, ty), pattern) in values.iter().zip(patterns.iter()) {
+                        self.compile_pattern(class_name, partial_class, pattern, name, ty, &mut failures, output)?;
+                    }
+                }
+                (Some(_), Pattern::WildCard(_)) => {}
+                (Some(_), _) => unreachable!("tuple literals are only split when no arm binds the whole tuple"),
+                (None, pattern) => {
+                    self.compile_pattern(class_name, partial_class, pattern, &value_name, &value_type, &mut failures, output)?;
+                }
+            }
+            match &arm.value {
+                Either::Left(value) => {
+                    self.compile_expression(class_name, partial_class, value, output, lhs)?;
+                    if let (Some(result), false) = (result, self.current_block_returned) {
+                        output.push(Bytecode::StoreLocal(result));
+                    }
+                }
+                Either::Right(body) => {
                     output.push(Bytecode::Goto(1));
+                    self.increment_block();
+                    self.compile_block(class_name, partial_class, body, output)?;
                 }
-                self.current_block_returned = false;
-                let block = self.current_block;
-                output.push(Bytecode::StartBlock(block));
             }
-            IfExpression { condition, then_branch, else_branch: Some(Either::Left(else_branch)), ..} => {
-                self.compile_expression(class_name, partial_class, condition.as_ref(), output, lhs)?;
-                output.push(Bytecode::If(1, 2));
-                self.increment_block();
-                self.compile_block(class_name, partial_class, then_branch, output)?;
-                let mut temp_output = Vec::new();
-                let then_block = self.current_block;
-                self.compile_if_expression(class_name, partial_class, else_branch.as_ref(), &mut temp_output, lhs)?;
-                let escape_block = self.current_block;
-                if !self.current_block_returned {
-                    output.push(Bytecode::Goto((escape_block - then_block) as i64));
+            if !self.current_block_returned {
+                exits.push((output.len(), self.current_block));
+                output.push(Bytecode::Goto(1));
+            }
+            self.current_block_returned = false;
+            self.pop_scope();
+
+            self.increment_block();
+            let next_block = self.current_block;
+            for (index, block) in failures {
+                output[index] = Bytecode::If(1, (next_block - block) as i64);
+            }
+            output.push(Bytecode::StartBlock(next_block));
+        }
+
+        let exit_block = self.current_block;
+        for (index, block) in exits {
+            output[index] = Bytecode::Goto((exit_block - block) as i64);
+        }
+        if let Some(result) = result {
+            output.push(Bytecode::LoadLocal(result));
+        }
+        Ok(())
+    }
+
+    /// Tests the value in the local `name` against a pattern and binds the variables in the pattern.
+    /// Every test is followed by a new block and the index of the test is added to `failures` to be filled in later.
+    /// Destructured fields are stored in locals named after the local they came from.
+    fn compile_pattern(
+        &mut self,
+        class_name: &Vec<String>,
+        partial_class: &mut CurrentCompilationUnit,
+        pattern: &Pattern,
+        name: &str,
+        ty: &Type,
+        failures: &mut Vec<(usize, u64)>,
+        output: &mut Vec<Bytecode>,
+    ) -> Result<(), CompilerError> {
+        let local = self.get_variable(name).expect("pattern value should have been bound");
+        match pattern {
+            Pattern::WildCard(_) => return Ok(()),
+            Pattern::Variable(var, _, _) => {
+                output.push(Bytecode::LoadLocal(local));
+                let index = self.bind_variable(var);
+                output.push(Bytecode::StoreLocal(index));
+                return Ok(());
+            }
+            Pattern::Constant(Constant::String(..)) => unreachable!("the typechecker rejects string patterns"),
+            Pattern::Constant(constant) => {
+                let span = constant.get_span();
+                let test = Expression::BinaryOperation {
+                    operator: BinaryOperator::Eq,
+                    left: Box::new(Expression::Variable(Text::Owned(name.to_string()), ty.clone(), span)),
+                    right: Box::new(Expression::Literal(Literal::Constant(constant.clone()))),
+                    span,
+                };
+                self.compile_expression(class_name, partial_class, &test, output, false)?;
+            }
+            Pattern::Tuple(patterns, span) => {
+                let Type::Tuple(tys, _) = ty else {
+                    unreachable!("tuple patterns always match a tuple type");
+                };
+                for (i, (pattern, field_type)) in patterns.iter().zip(tys.iter()).enumerate() {
+                    if let Pattern::WildCard(_) = pattern {
+                        continue;
+                    }
+                    let access = Expression::MemberAccess {
+                        object: Box::new(Expression::Variable(Text::Owned(name.to_string()), ty.clone(), *span)),
+                        field: PathName::new(vec![Text::Owned(i.to_string())], *span),
+                        span: *span,
+                        annotation: field_type.clone(),
+                    };
+                    self.compile_expression(class_name, partial_class, &access, output, false)?;
+                    let field_name = format!("{name}-{i}");
+                    let index = self.bind_variable(&field_name);
+                    output.push(Bytecode::StoreLocal(index));
+                    self.compile_pattern(class_name, partial_class, pattern, &field_name, field_type, failures, output)?;
                 }
-                self.current_block_returned = false;
-                output.extend(temp_output);
+                return Ok(());
+            }
+            Pattern::Class { ty: class_type, fields, span } => {
+                // A value of the matched type is always an instance of its own class
+                if class_type != ty {
+                    let Type::Object(class, _) = class_type else {
+                        unreachable!("class patterns always have an object type");
+                    };
+                    let class = self.add_path_if_needed(class.to_string());
+                    let class = partial_class.add_string(class.join("::"));
+                    self.mark_span(*span, output.len());
+                    output.push(Bytecode::LoadLocal(local));
+                    output.push(Bytecode::IsA(class));
+                    self.compile_pattern_test(failures, output);
+                }
+                for (i, (field, field_type, pattern)) in fields.iter().enumerate() {
+                    if let Pattern::WildCard(_) = pattern {
+                        continue;
+                    }
+                    let access = Expression::MemberAccess {
+                        object: Box::new(Expression::Variable(Text::Owned(name.to_string()), class_type.clone(), *span)),
+                        field: PathName::new(vec![field.clone()], *span),
+                        span: *span,
+                        annotation: field_type.clone(),
+                    };
+                    self.compile_expression(class_name, partial_class, &access, output, false)?;
+                    let field_name = format!("{name}-{i}");
+                    let index = self.bind_variable(&field_name);
+                    output.push(Bytecode::StoreLocal(index));
+                    self.compile_pattern(class_name, partial_class, pattern, &field_name, field_type, failures, output)?;
+                }
+                return Ok(());
             }
         }
+        self.compile_pattern_test(failures, output);
         Ok(())
     }
 
+    /// Branches on the result of a pattern test, the failing side is filled in once the next arm is known
+    fn compile_pattern_test(&mut self, failures: &mut Vec<(usize, u64)>, output: &mut Vec<Bytecode>) {
+        failures.push((output.len(), self.current_block));
+        output.push(Bytecode::If(1, 1));
+        self.increment_block();
+        let block = self.current_block;
+        output.push(Bytecode::StartBlock(block));
+    }
+
     fn compile_member_get<'a>(
         &mut self,
         class_name: &Vec<String>,
@@ -2900,6 +4170,7 @@ impl Compiler {
                 }
                 self.add_path_if_needed(string_name)
             }
+            Either::Left(Type::Tuple(tys, _)) => self.tuple_class(&tys),
             Either::Right(()) => {
                 class_name.clone()
             }
@@ -3009,11 +4280,13 @@ impl Compiler {
             _ => todo!("report error about method output not being an object: {:?} {:?}", object, field),
         };
 
-        let this_class = match partial_class {
-            CurrentCompilationUnit::Class(class) => class,
-            _ => unreachable!(),
+        let class = match self.classes.get(&name) {
+            Some(class) => class,
+            _ => match partial_class {
+                CurrentCompilationUnit::Class(class) => class,
+                _ => unreachable!("it is impossible to get from something other than a class")
+            },
         };
-        let class = self.classes.get(&name).unwrap_or(this_class);
         let (class_name, parent_name) = if class.contains_field(field.to_string().as_str()) {
             (class.get_class_name(), Vec::new())
         } else {
@@ -3042,6 +4315,17 @@ impl Compiler {
         })))
     }
 
+    /// Checks if a type is a record, union or variant that should be compared by value
+    fn is_value_type(&mut self, ty: &Either<Type, ()>) -> bool {
+        let Either::Left(Type::Object(name, _)) = ty else {
+            return false;
+        };
+        let path = self.add_path_if_needed(name.to_string());
+        self.classes.get(&path)
+            .map(|class| class.get_kind().is_value() || class.get_kind() == ClassKind::Union)
+            .unwrap_or(false)
+    }
+
     fn compile_call_expression<'a>(
         &mut self,
         class_name: &Vec<String>,
@@ -3073,7 +4357,7 @@ impl Compiler {
                                     if self.classes.contains_key(&path) {
                                         Text::Borrowed("Arrayobject")
                                     } else {
-                                        match self.current_type_args.get(ty.as_str()).unwrap() {
+                                        match self.current_type_args.get(ty.as_str()).unwrap_or(&TypeTag::Object) {
                                             TypeTag::I8 | TypeTag::U8 => Text::Borrowed("Array8"),
                                             TypeTag::I16 | TypeTag::U16 => Text::Borrowed("Array16"),
                                             TypeTag::I32 | TypeTag::U32 => Text::Borrowed("Array32"),
@@ -3111,7 +4395,7 @@ impl Compiler {
                                         if self.classes.contains_key(&path) {
                                             "object"
                                         } else {
-                                            match self.current_type_args.get(ty.as_str()).unwrap() {
+                                            match self.current_type_args.get(ty.as_str()).unwrap_or(&TypeTag::Object) {
                                                 TypeTag::I8 | TypeTag::U8 => "8",
                                                 TypeTag::I16 | TypeTag::U16 => "16",
                                                 TypeTag::I32 | TypeTag::U32 => "32",
@@ -3158,7 +4442,7 @@ impl Compiler {
                                             if self.classes.contains_key(&path) {
                                                 Text::Borrowed("Arrayobject")
                                             } else {
-                                                match self.current_type_args.get(ty.as_str()).unwrap() {
+                                                match self.current_type_args.get(ty.as_str()).unwrap_or(&TypeTag::Object) {
                                                     TypeTag::I8 | TypeTag::U8 => Text::Borrowed("Array8"),
                                                     TypeTag::I16 | TypeTag::U16 => Text::Borrowed("Array16"),
                                                     TypeTag::I32 | TypeTag::U32 => Text::Borrowed("Array32"),
@@ -3193,7 +4477,7 @@ impl Compiler {
                                                 if self.classes.contains_key(&path) {
                                                     "object"
                                                 } else {
-                                                    match self.current_type_args.get(ty.as_str()).unwrap() {
+                                                    match self.current_type_args.get(ty.as_str()).unwrap_or(&TypeTag::Object) {
                                                         TypeTag::I8 | TypeTag::U8 => "8",
                                                         TypeTag::I16 | TypeTag::U16 => "16",
                                                         TypeTag::I32 | TypeTag::U32 => "32",
@@ -3217,6 +4501,25 @@ impl Compiler {
 
                             break 'setup_args (field, annotation);
                         }
+                        Expression::New(Type::Object(ty, _), None, _) => {
+                            // Rev is used because otherwise arguments are not loaded from left to right
+                            for (i, arg) in args.iter().enumerate().rev() {
+                                self.compile_expression(class_name, partial_class, arg, output, lhs)?;
+                                self.bind_variable(format!("arg{i}"));
+                            }
+
+                            for i in 1..=args.len() { // 1..len for leaving space for object
+                                self.get_variable(format!("arg{}", i - 1));
+                                output.push(Bytecode::StoreArgument(i as u8));
+                            }
+
+                            self.compile_expression(class_name, partial_class, object.as_ref(), output, lhs)?;
+                            output.push(Bytecode::StoreArgument(0));
+
+                            let path = self.add_path_if_needed(ty.to_string());
+
+                            break 'setup_args (field, path);
+                        }
                         Expression::Variable(value, Type::Function(function_args, return_ty, ..), ..) => {
                             self.compile_expression(class_name, partial_class, object.as_ref(), output, lhs)?;
                             for (i, arg) in args.iter().enumerate() {
@@ -3298,7 +4601,7 @@ impl Compiler {
 
         let name = name.segments.last().unwrap();
 
-        if name.as_str() == "downcast" || name.as_str() == "downcast-contents" {
+        if name.as_str() == "downcast" || name.as_str() == "try-downcast" || name.as_str() == "downcast-contents" {
             assert_eq!(type_args.len(), 1, "Downcast only takes one type argument");
             let ty = match type_args.first().unwrap() {
                 Type::Array(ty, _) => {
@@ -3315,7 +4618,7 @@ impl Compiler {
                             if self.classes.contains_key(&path) {
                                 Text::Borrowed("Arrayobject")
                             } else {
-                                match self.current_type_args.get(ty.as_str()).unwrap() {
+                                match self.current_type_args.get(ty.as_str()).unwrap_or(&TypeTag::Object) {
                                     TypeTag::I8 | TypeTag::U8 => Text::Borrowed("Array8"),
                                     TypeTag::I16 | TypeTag::U16 => Text::Borrowed("Array16"),
                                     TypeTag::I32 | TypeTag::U32 => Text::Borrowed("Array32"),
@@ -3338,7 +4641,7 @@ impl Compiler {
             let class_symbol = partial_class.add_string(path.join("::"));
 
             output.push(Bytecode::LoadSymbol(class_symbol));
-            output.push(Bytecode::StoreArgument(args.len() as u8));
+            output.push(Bytecode::StoreArgument(args.len() as u8 + 1));
         }
 
         //println!("{ty:?} {class_name:?}");
@@ -3381,9 +4684,9 @@ impl Compiler {
             };
             let method_entry = partial_class.get_method_entry(&method_name).expect("add proper handling of missing method");
 
-            //println!("{}", partial_class.index_string_table(vtable.class_name));
+            //println!("{}", partial_class.index_string_table(vtable.sub_class_name));
 
-            let class_name = partial_class.index_string_table(vtable.class_name);
+            let class_name = partial_class.index_string_table(vtable.sub_class_name);
             let class_name = class_name.to_string();
             let vtable_class_name = partial_class.add_string(class_name);
 
@@ -3399,12 +4702,27 @@ impl Compiler {
             let mut field_path = self.add_path_if_needed(class_name_path.join("::"));
             field_path.push(name.to_string());
 
-            if let Ok(vtable) = class.get_vtable(field_path.join("::")) {
-                let method_entry = class.get_method_entry(field_path.join("::")).expect("add proper handling of missing method");
+            let mut method_path = field_path.join("::");
+            let mut vtable = class.get_vtable(&method_path);
+            let mut current_class = class;
+            while vtable.is_err() {
+                // Here we try to find the method in the vtables of the parents
+                let Some(parent_path) = current_class.get_parent_name() else {
+                    break;
+                };
+                let mut parent_method_path = parent_path.clone();
+                parent_method_path.push(name.to_string());
+                method_path = parent_method_path.join("::");
+                vtable = class.get_vtable(&method_path);
+                current_class = self.classes.get(&parent_path).unwrap();
+            }
+
+            if let Ok(vtable) = vtable {
+                let method_entry = class.get_method_entry(&method_path).expect("add proper handling of missing method");
 
-                //println!("{}", class.index_string_table(vtable.class_name));
+                //println!("{}", class.index_string_table(vtable.sub_class_name));
 
-                let class_name = class.index_string_table(vtable.class_name);
+                let class_name = class.index_string_table(vtable.sub_class_name);
                 let vtable_class_name = partial_class.add_string(class_name);
 
                 let method_name = class.index_string_table(method_entry.name);
@@ -3431,6 +4749,17 @@ impl Compiler {
                     break
                 }
             }
+        } else if let Some(interface) = self.interfaces.get(&ty) {
+            // Values typed by a trait, like those of bounded type parameters, are called through the trait
+            let mut method_path = ty.clone();
+            method_path.push(name.to_string());
+            let method_entry = interface.get_method_entry(method_path.join("::"))
+                .expect("add proper handling of missing method");
+
+            let interface_name = partial_class.add_string(ty.join("::"));
+            let method_name = partial_class.add_string(interface.index_string_table(method_entry.name));
+
+            output.push(Bytecode::InvokeInterface(interface_name, method_name));
         } else {
             panic!("Classes are in a bad order of compiling {ty:?} {class_name:?}");
         }
@@ -3535,6 +4864,7 @@ impl Compiler {
                         span: Span::new(0,0),
                         annotation: ty.clone(),
                     },
+                    operator: None,
                     value: Expression::Variable(capture.clone(), ty.clone() ,Span::new(0,0)),
                     span: Span::new(0, 0),
                 });
@@ -3581,6 +4911,7 @@ impl Compiler {
 
         let class = Class {
             name: Text::Owned(format!("Closure{closure_number}")),
+            kind: ClassKind::Class,
             parent: Some(ParentDec {
                 name: Text::Owned(closure_name.clone()),
                 type_args: Vec::new(),
@@ -3590,6 +4921,7 @@ impl Compiler {
             members,
             methods,
             static_members: vec![],
+            signals: vec![],
             type_params: vec![],
             span,
         };
@@ -3598,6 +4930,8 @@ impl Compiler {
         let current_block: u64 = self.current_block;
         let method_returned: bool = self.method_returned;
         let current_block_returned: bool = self.current_block_returned;
+        let try_stack = std::mem::take(&mut self.try_stack);
+        let lines = std::mem::take(&mut self.lines);
 
         let mut results = self.load_class_part(class)?;
         let Some((class, type_args)) = results.pop() else {
@@ -3609,6 +4943,8 @@ impl Compiler {
         self.current_block = current_block;
         self.method_returned = method_returned;
         self.current_block_returned = current_block_returned;
+        self.try_stack = try_stack;
+        self.lines = lines;
 
         let path = self.add_path_if_needed(format!("Closure{closure_number}"));
 
@@ -4,7 +4,7 @@ pub mod partial_interface_impl;
 
 use std::collections::HashMap;
 use std::io::BufRead;
-use rowan_shared::classfile::{StringIndex, VTable, VTableEntry};
+use rowan_shared::classfile::{LineEntry, StringIndex, VTable, VTableEntry};
 use crate::backend::compiler_utils::partial_class::PartialClass;
 use crate::backend::compiler_utils::partial_interface::PartialInterface;
 use crate::backend::compiler_utils::partial_interface_impl::PartialInterfaceImpl;
@@ -145,6 +145,52 @@ impl Frame {
 }
 
 
+/// A source file that methods are compiled from, used to turn spans into line numbers
+pub struct SourceFile {
+    /// The name written into the line tables
+    pub name: String,
+    /// The byte offset of the start of each line
+    line_starts: Vec<usize>,
+}
+
+impl SourceFile {
+    pub fn new(name: impl AsRef<str>, contents: &str) -> Self {
+        let mut line_starts = vec![0];
+        line_starts.extend(contents.match_indices('\n').map(|(index, _)| index + 1));
+        SourceFile {
+            name: name.as_ref().to_string(),
+            line_starts,
+        }
+    }
+
+    /// Converts a byte offset into a line and column, both starting at 1
+    pub fn position(&self, offset: usize) -> (u64, u64) {
+        let line = self.line_starts.partition_point(|start| *start <= offset) - 1;
+        (line as u64 + 1, (offset - self.line_starts[line]) as u64 + 1)
+    }
+}
+
+/// The locals a finally block uses to know how it was entered
+#[derive(Clone)]
+pub struct FinallyContext {
+    /// The first block of the finally block
+    pub block: u64,
+    /// The exception to rethrow once the finally block is done
+    pub exception: VarLocation,
+    pub rethrow: VarLocation,
+    /// The value to return once the finally block is done
+    pub value: VarLocation,
+    pub returning_value: VarLocation,
+    pub returning_void: VarLocation,
+}
+
+/// A try statement that is being compiled
+pub struct TryContext {
+    /// The classes of the exception handlers that are currently registered, from oldest to newest
+    pub handlers: Vec<String>,
+    pub finally: Option<FinallyContext>,
+}
+
 
 pub enum CurrentCompilationUnit<'a> {
     Class(&'a mut PartialClass),
@@ -165,12 +211,13 @@ impl<'a> CurrentCompilationUnit<'a> {
         &mut self, 
         name: impl AsRef<str>,
         bytecode: B,
+        lines: Vec<LineEntry>,
         is_native: bool,
-        
+        responds_to: Option<String>,
     ) {
         match self {
             CurrentCompilationUnit::Class(class) => {
-                class.add_static_method(name.as_ref(), bytecode.as_ref(), is_native);
+                class.add_static_method(name.as_ref(), bytecode.as_ref(), lines, is_native, responds_to);
             }
             CurrentCompilationUnit::Interface(_) | CurrentCompilationUnit::InterfaceImpl(_) => {
                 unreachable!("Interfaces and InterfaceImpls do not support static methods")
@@ -192,6 +239,15 @@ impl<'a> CurrentCompilationUnit<'a> {
         }
     }
     
+    pub fn get_overridden_method_name(&self, method_name: &str) -> Option<String> {
+        match self {
+            CurrentCompilationUnit::Class(class) => {
+                class.get_overridden_method_name(method_name)
+            }
+            CurrentCompilationUnit::Interface(_) | CurrentCompilationUnit::InterfaceImpl(_) => None,
+        }
+    }
+
     pub fn index_string_table(&self, index: StringIndex) -> &str {
         match self {
             CurrentCompilationUnit::Class(class) => {
@@ -211,17 +267,18 @@ impl<'a> CurrentCompilationUnit<'a> {
         method_class_name: &Vec<String>,
         method_name: impl AsRef<str>,
         bytecode: impl AsRef<[u8]>,
+        lines: Vec<LineEntry>,
         is_native: bool,
     ) -> PartialClassResult<()> {
         match self {
             CurrentCompilationUnit::Class(class) => {
-                class.attach_bytecode(method_class_name, method_name, bytecode, is_native)
+                class.attach_bytecode(method_class_name, method_name, bytecode, lines, is_native)
             }
             CurrentCompilationUnit::Interface(interface) => {
-                Ok(interface.attach_bytecode(method_name.as_ref(), bytecode.as_ref()))
+                Ok(interface.attach_bytecode(method_name.as_ref(), bytecode.as_ref(), lines))
             }
             CurrentCompilationUnit::InterfaceImpl(r#impl) => {
-                Ok(r#impl.attach_bytecode(method_name.as_ref(), bytecode.as_ref()))
+                Ok(r#impl.attach_bytecode(method_name.as_ref(), bytecode.as_ref(), lines))
             }
         }
     }
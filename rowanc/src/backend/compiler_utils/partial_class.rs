@@ -1,5 +1,5 @@
 use std::collections::HashMap;
-use rowan_shared::classfile::{BytecodeEntry, BytecodeIndex, ClassFile, Member, SignatureEntry, SignatureIndex, StaticMethods, StringEntry, StringIndex, VTable, VTableEntry};
+use rowan_shared::classfile::{BytecodeEntry, BytecodeIndex, ClassFile, ClassKind, LineEntry, Member, SignatureEntry, SignatureIndex, Signal, StaticMethods, StringEntry, StringIndex, VTable, VTableEntry};
 use rowan_shared::TypeTag;
 use crate::backend::Compiler;
 use crate::backend::compiler_utils::{PartialClassError, PartialClassResult};
@@ -39,10 +39,14 @@ pub struct PartialClass {
     name: StringIndex,
     /// Parent class names
     parent: StringIndex,
+    /// What kind of declaration the class came from
+    kind: ClassKind,
     /// Virtual tables
     vtables: Vec<VTable>,
     /// Members and their types
     members: Vec<Member>,
+    /// Signals the class can emit
+    signals: Vec<Signal>,
     /// Static methods and their entry
     static_methods: Vec<VTableEntry>,
     /// Static members
@@ -98,6 +102,10 @@ impl PartialClass {
         &self.bytecode_table[(index - 1) as usize]
     }
 
+    pub fn get_signature(&self, index: SignatureIndex) -> &SignatureEntry {
+        &self.signature_table[index as usize]
+    }
+
     pub fn get_vtable(&self, method_name: impl AsRef<str>) -> Result<&VTable, PartialClassError> {
         //println!("{}: {:#?}", method_name.as_ref(), self.method_to_class);
 
@@ -127,6 +135,18 @@ impl PartialClass {
         Err(PartialClassError::VTableNotNotFound(method_name.to_string()))
     }
 
+    /// Finds the full path of an inherited method from its bare name.
+    /// This is used to resolve methods annotated with `@Override` that don't use a path.
+    pub fn get_overridden_method_name(&self, method_name: &str) -> Option<String> {
+        let mut candidates = self.method_to_class.keys()
+            .filter(|name| name.contains("::") && name.split("::").last() == Some(method_name));
+        let candidate = candidates.next()?;
+        if candidates.next().is_some() {
+            return None;
+        }
+        Some(candidate.clone())
+    }
+
     pub fn get_method_entry(&self, method_name: impl AsRef<str>) -> Result<VTableEntry, PartialClassError> {
         //println!("{}: {:#?}", method_name.as_ref(), self.method_to_function);
         let vtable = self.get_vtable(method_name.as_ref())?;
@@ -144,8 +164,10 @@ impl PartialClass {
         PartialClass {
             name: 0,
             parent: 0,
+            kind: ClassKind::Class,
             vtables: Vec::new(),
             members: Vec::new(),
+            signals: Vec::new(),
             static_methods: Vec::new(),
             static_members: Vec::new(),
             static_init: 0,
@@ -173,17 +195,20 @@ impl PartialClass {
             return None;
         }
         let class_name = self.get_class_name().join("::");
-        Some((ClassFile::new_from_parts(
+        let mut class_file = ClassFile::new_from_parts(
             self.name,
             self.parent,
             self.vtables,
             self.members,
+            self.signals,
             StaticMethods::new(self.static_methods),
             self.static_members.into_iter().map(Into::<Member>::into).collect(),
             self.static_init,
             self.bytecode_table,
             self.string_table,
-            self.signature_table),
+            self.signature_table);
+        class_file.kind = self.kind;
+        Some((class_file,
               NativeAttributes::new(class_name, self.native_member_sizes, self.native_functions),
         ))
     }
@@ -200,6 +225,14 @@ impl PartialClass {
         self.static_method_to_signature = map;
     }
 
+    pub fn set_kind(&mut self, kind: ClassKind) {
+        self.kind = kind;
+    }
+
+    pub fn get_kind(&self) -> ClassKind {
+        self.kind
+    }
+
     pub fn set_parent(&mut self, name: &str) {
         let index = self.add_string(name);
         self.parent = index;
@@ -278,9 +311,12 @@ impl PartialClass {
         &mut self,
         method_name: impl AsRef<str>,
         code: B,
+        lines: Vec<LineEntry>,
         is_native: bool,
+        responds_to: Option<String>,
     ) {
         let name_index = self.add_string(method_name.as_ref());
+        let responds_to = responds_to.map(|signal| self.add_string(signal)).unwrap_or(0);
         let signature_index = self.static_method_to_signature.get(method_name.as_ref()).unwrap();
 
         if is_native {
@@ -295,6 +331,7 @@ impl PartialClass {
 
             self.static_methods.push(VTableEntry {
                 name: name_index,
+                responds_to,
                 signature: *signature_index,
                 bytecode: -1,
             });
@@ -302,11 +339,12 @@ impl PartialClass {
             return
         }
 
-        self.bytecode_table.push(BytecodeEntry::new(code.as_ref()));
+        self.bytecode_table.push(BytecodeEntry::with_lines(code.as_ref(), lines));
         let bytecode_index = self.bytecode_table.len() as BytecodeIndex;
 
         self.static_methods.push(VTableEntry {
             name: name_index,
+            responds_to,
             signature: *signature_index,
             bytecode: bytecode_index,
         })
@@ -325,6 +363,23 @@ impl PartialClass {
         self.members.push(member);
     }
 
+    pub fn add_signal<S: AsRef<str>>(&mut self, name: S, is_static: bool, signature: SignatureEntry) {
+        let name = self.add_string(name.as_ref());
+        let signature_index = self.signature_table.len() as SignatureIndex;
+        self.signature_table.push(signature);
+
+        self.signals.push(Signal::new(name, is_static, signature_index));
+    }
+
+    pub fn contains_signal(&self, signal: &str) -> bool {
+        for sig in self.signals.iter() {
+            if signal == self.index_string_table(sig.name) {
+                return true;
+            }
+        }
+        false
+    }
+
     pub fn add_static_member<S: AsRef<str>>(&mut self, mut member: StaticMember, member_name: S) {
         member.name = self.add_string(member_name);
 
@@ -336,6 +391,7 @@ impl PartialClass {
         class_name: &[String],
         method_name: impl AsRef<str>,
         code: B,
+        lines: Vec<LineEntry>,
         is_native: bool,
     ) -> PartialClassResult<()> {
         //println!("{:?}", class_name);
@@ -373,7 +429,7 @@ impl PartialClass {
             return Ok(());
         }
 
-        self.bytecode_table.push(BytecodeEntry::new(code.as_ref()));
+        self.bytecode_table.push(BytecodeEntry::with_lines(code.as_ref(), lines));
         let bytecode_index = self.bytecode_table.len();
 
         self.vtables[vtable_index].functions[method_index].bytecode = bytecode_index as u64 as BytecodeIndex;
@@ -383,8 +439,9 @@ impl PartialClass {
     pub fn attach_static_init_bytecode<B: AsRef<[u8]>>(
         &mut self,
         code: B,
+        lines: Vec<LineEntry>,
     ) -> PartialClassResult<()> {
-        self.bytecode_table.push(BytecodeEntry::new(code.as_ref()));
+        self.bytecode_table.push(BytecodeEntry::with_lines(code.as_ref(), lines));
         let bytecode_index = self.bytecode_table.len();
 
         self.static_init = bytecode_index as u64 as BytecodeIndex;
@@ -396,7 +453,10 @@ impl PartialClass {
         Vec<String>,
         Vec<SignatureEntry>)> {
 
-        let vtable_indices = self.class_to_vtable.get(class_name).unwrap();
+        // Classes without methods of their own, like a union with no methods, have no vtable
+        let Some(vtable_indices) = self.class_to_vtable.get(class_name) else {
+            return Vec::new();
+        };
 
         let mut output = Vec::new();
         for vtable_index in vtable_indices {
@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 use itertools::Itertools;
-use rowan_shared::classfile::{BytecodeEntry, BytecodeIndex, SignatureEntry, SignatureIndex, StringEntry, StringIndex, VTable, VTableEntry};
+use rowan_shared::classfile::{BytecodeEntry, BytecodeIndex, LineEntry, SignatureEntry, SignatureIndex, StringEntry, StringIndex, VTable, VTableEntry};
 use rowan_shared::interfacefile::InterfaceFile;
 
 #[derive(Debug, Clone)]
@@ -106,6 +106,7 @@ impl PartialInterface {
             self.signature_table.push(sig.clone());
             self.vtable.functions.push(VTableEntry {
                 name,
+                responds_to: 0,
                 signature: index,
                 bytecode: 0,
             });
@@ -117,10 +118,11 @@ impl PartialInterface {
     pub fn attach_bytecode<B: AsRef<[u8]>>(
         &mut self, 
         method_name: impl AsRef<str>,
-        bytecode: B
+        bytecode: B,
+        lines: Vec<LineEntry>,
     ) {
         let index = self.method_to_function.get(method_name.as_ref()).unwrap();
-        self.bytecode_table.push(BytecodeEntry::new(bytecode.as_ref()));
+        self.bytecode_table.push(BytecodeEntry::with_lines(bytecode.as_ref(), lines));
         let bytecode_index = self.bytecode_table.len() as BytecodeIndex;
         self.vtable.functions[*index].bytecode = bytecode_index;
     }
@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 use std::io::BufRead;
-use rowan_shared::classfile::{BytecodeEntry, BytecodeIndex, SignatureEntry, SignatureIndex, StringEntry, StringIndex, VTable, VTableEntry};
+use rowan_shared::classfile::{BytecodeEntry, BytecodeIndex, LineEntry, SignatureEntry, SignatureIndex, StringEntry, StringIndex, VTable, VTableEntry};
 use rowan_shared::interfaceimplfile::InterfaceImplFile;
 
 #[derive(Debug, Clone)]
@@ -121,6 +121,7 @@ impl PartialInterfaceImpl {
             self.signature_table.push(sig.clone());
             self.vtable.functions.push(VTableEntry {
                 name,
+                responds_to: 0,
                 signature: index,
                 bytecode: 0,
             });
@@ -132,10 +133,11 @@ impl PartialInterfaceImpl {
     pub fn attach_bytecode<B: AsRef<[u8]>>(
         &mut self,
         method_name: impl AsRef<str>,
-        bytecode: B
+        bytecode: B,
+        lines: Vec<LineEntry>,
     ) {
         let index = self.method_to_function.get(method_name.as_ref()).unwrap();
-        self.bytecode_table.push(BytecodeEntry::new(bytecode.as_ref()));
+        self.bytecode_table.push(BytecodeEntry::with_lines(bytecode.as_ref(), lines));
         let bytecode_index = self.bytecode_table.len() as BytecodeIndex;
         self.vtable.functions[*index].bytecode = bytecode_index;
     }
@@ -1,6 +1,6 @@
 use std::collections::{HashMap, HashSet};
 use either::Either;
-use crate::trees::ir::{Class, ClosureParameter, Expression, File, IfExpression, Method, Parameter, Pattern, Statement, TopLevelStatement, Trait, TraitImpl};
+use crate::trees::ir::{Class, ClosureParameter, Expression, File, IfExpression, MatchExpression, Method, Parameter, Pattern, Statement, TopLevelStatement, Trait, TraitImpl};
 use crate::trees::{PathName, Span, Text, Type};
 
 pub struct BoxClosureCapture<> {}
@@ -90,10 +90,12 @@ impl<'boxing> BoxClosureCapture<> {
     fn box_class(&mut self, class: Class<'boxing>) -> Class<'boxing> {
         let Class {
             name,
+            kind,
             parent,
             members,
             methods,
             static_members,
+            signals,
             type_params,
             span
         } = class;
@@ -104,7 +106,7 @@ impl<'boxing> BoxClosureCapture<> {
             new_methods.push(self.box_method(method));
         }
 
-        Class { name, parent, members, methods: new_methods, static_members, type_params, span }
+        Class { name, kind, parent, members, methods: new_methods, static_members, signals, type_params, span }
     }
 
     fn box_method(&mut self, method: Method<'boxing>) -> Method<'boxing> {
@@ -148,18 +150,39 @@ impl<'boxing> BoxClosureCapture<> {
                 Statement::While { test, body, .. } => {
                     self.find_closure(test) || self.find_closure_body(body)
                 }
+                Statement::For { iterable, body, .. } => {
+                    self.find_closure(iterable) || self.find_closure_body(body)
+                }
+                Statement::With { expr, block, .. } => {
+                    self.find_closure(expr) || self.find_closure_body(block)
+                }
                 Statement::Const { value, .. } => {
                     self.find_closure(value)
                 }
                 Statement::Assignment { target, value, .. } => {
                     self.find_closure(target) || self.find_closure(value)
                 }
-                _ => todo!("finding closure for remaining statements")
+                Statement::Emit { object, args, .. } => {
+                    self.find_closure(object) || args.iter_mut()
+                        .any(|arg| self.find_closure(arg))
+                }
+                Statement::EmitStatic { args, .. } => {
+                    args.iter_mut()
+                        .any(|arg| self.find_closure(arg))
+                }
+                Statement::Connect { source, target, .. } | Statement::Disconnect { source, target, .. } => {
+                    self.find_closure(source) || self.find_closure(target)
+                }
+                Statement::Try { body, catches, finally, .. } => {
+                    self.find_closure_body(body)
+                        || catches.iter_mut().any(|catch| self.find_closure_body(&mut catch.body))
+                        || finally.as_mut().is_some_and(|finally| self.find_closure_body(finally))
+                }
             };
             if found_closure {
                 println!("found closure");
                 loop {
-                    let mut stmts: Vec<Statement> = self.box_primitives(index, &mut body);
+                    let mut stmts: Vec<Statement> = self.box_primitives(&mut index, &mut body);
                     let is_stmts_empty = stmts.is_empty();
                     index += stmts.len();
                     println!();
@@ -205,6 +228,9 @@ impl<'boxing> BoxClosureCapture<> {
                     false
                 }
             }
+            Expression::Throw(expr, _) => {
+                self.find_closure(expr.as_mut())
+            }
             Expression::Call{ name, args, .. } => {
                 self.find_closure(name.as_mut()) || args.iter_mut()
                     .any(|arg| self.find_closure(arg))
@@ -216,6 +242,9 @@ impl<'boxing> BoxClosureCapture<> {
             Expression::IfExpression(if_expr, ..) => {
                 self.find_closure_if(if_expr)
             }
+            Expression::MatchExpression(match_expr, ..) => {
+                self.find_closure_match(match_expr)
+            }
             Expression::Closure { body, .. } => {
                 true || self.find_closure_body(body)
             }
@@ -241,6 +270,18 @@ impl<'boxing> BoxClosureCapture<> {
         test_result || then_result || else_result || test_result
     }
 
+    fn find_closure_match(&mut self, match_expr: &mut MatchExpression<'boxing>) -> bool {
+        let MatchExpression {
+            value,
+            arms,
+            ..
+        } = match_expr;
+        self.find_closure(value.as_mut()) || arms.iter_mut().any(|arm| match &mut arm.value {
+            Either::Left(expr) => self.find_closure(expr),
+            Either::Right(body) => self.find_closure_body(body),
+        })
+    }
+
     fn find_closure_body(&mut self, body: &mut Vec<Statement<'boxing>>) -> bool {
         for statement in body.iter_mut() {
             match statement {
@@ -268,26 +309,67 @@ impl<'boxing> BoxClosureCapture<> {
                         return true;
                     }
                 }
+                Statement::For { iterable, body, .. } => {
+                    let result = self.find_closure(iterable) || self.find_closure_body(body);
+                    if result {
+                        return true;
+                    }
+                }
+                Statement::With { expr, block, .. } => {
+                    let result = self.find_closure(expr) || self.find_closure_body(block);
+                    if result {
+                        return true;
+                    }
+                }
                 Statement::Expression(expr, ..) => {
                     let result = self.find_closure(expr);
                     if result {
                         return true;
                     }
                 }
-                _ => todo!("finding closure for remaining statements"),
+                Statement::Emit { object, args, .. } => {
+                    let result = self.find_closure(object) || args.iter_mut()
+                        .any(|arg| self.find_closure(arg));
+                    if result {
+                        return true;
+                    }
+                }
+                Statement::EmitStatic { args, .. } => {
+                    let result = args.iter_mut()
+                        .any(|arg| self.find_closure(arg));
+                    if result {
+                        return true;
+                    }
+                }
+                Statement::Connect { source, target, .. } | Statement::Disconnect { source, target, .. } => {
+                    let result = self.find_closure(source) || self.find_closure(target);
+                    if result {
+                        return true;
+                    }
+                }
+                Statement::Try { body, catches, finally, .. } => {
+                    let result = self.find_closure_body(body)
+                        || catches.iter_mut().any(|catch| self.find_closure_body(&mut catch.body))
+                        || finally.as_mut().is_some_and(|finally| self.find_closure_body(finally));
+                    if result {
+                        return true;
+                    }
+                }
             }
         }
         false
     }
 
     /// TODO: fix this to handle nested closures
-    fn box_primitives<'input>(&mut self, index: usize, stmts: &mut Vec<Statement<'boxing>>) -> Vec<Statement<'boxing>> {
+    /// Boxes the mutated primitives that the closure in `stmts[*index]` captures.
+    /// Rebindings that have to come right after a binding are inserted into `stmts`, which moves `index` along with the closure.
+    fn box_primitives<'input>(&mut self, index: &mut usize, stmts: &mut Vec<Statement<'boxing>>) -> Vec<Statement<'boxing>> {
         println!("boxing primitives");
         let mut prepend_statements = Vec::new();
         let mut indices = Vec::new();
 
         let mut found_captures = {
-            let the_closure = self.get_closure(&mut stmts[index]);
+            let the_closure = self.get_closure(&mut stmts[*index]);
             let Some(Expression::Closure {
                          params,
                          captures,
@@ -338,7 +420,7 @@ impl<'boxing> BoxClosureCapture<> {
             }
 
 
-            for i in (0..index).rev() {
+            for i in (0..*index).rev() {
                 match &stmts[i] {
                     Statement::Let { bindings, .. } => {
                         if self.is_bound(bindings, &found_captures) {
@@ -357,56 +439,70 @@ impl<'boxing> BoxClosureCapture<> {
         };
 
         println!("indices: {:?}", indices);
-        for index in indices.into_iter().rev() {
-            match &mut stmts[index] {
+        let mut rebindings = Vec::new();
+        for binding_index in indices.into_iter().rev() {
+            let mut statements = Vec::new();
+            match &mut stmts[binding_index] {
                 Statement::Let { bindings, ty, value, .. } => {
-                    self.modify_binding(bindings, ty, value, &mut found_captures);
+                    self.modify_binding(bindings, ty, value, &mut found_captures, &mut statements);
                 }
                 Statement::Const { bindings, ty, value, .. } => {
-                    self.modify_binding(bindings, ty, value, &mut found_captures);
+                    self.modify_binding(bindings, ty, value, &mut found_captures, &mut statements);
                 }
                 _ => unreachable!("only be let or const"),
             }
+            if !statements.is_empty() {
+                rebindings.push((binding_index, statements));
+            }
+        }
+        // Inserting from the back keeps the indices of the earlier bindings valid
+        for (binding_index, statements) in rebindings.into_iter().rev() {
+            *index += statements.len();
+            stmts.splice(binding_index + 1..binding_index + 1, statements);
         }
 
         for (var, (mutated, ty)) in found_captures {
             if !mutated {
                 continue;
             }
-            let string = match &ty {
-                Type::U8 => Text::Borrowed("U8"),
-                Type::U16 => Text::Borrowed("U16"),
-                Type::U32 => Text::Borrowed("U32"),
-                Type::U64 => Text::Borrowed("U64"),
-                Type::I8 => Text::Borrowed("I8"),
-                Type::I16 => Text::Borrowed("I16"),
-                Type::I32 => Text::Borrowed("I32"),
-                Type::I64 => Text::Borrowed("I64"),
-                Type::F32 => Text::Borrowed("F32"),
-                Type::F64 => Text::Borrowed("F64"),
-                _ => unreachable!("unsupported type, can only be primitive"),
-            };
-            let bindings = Pattern::Variable(Text::Owned(var.clone()), false, Span::new(0, 0));
-            let let_type = Type::Object(string.clone(), Span::new(0, 0));
-            let value = Expression::StaticCall {
-                name: PathName::new(vec![string.clone(), Text::Borrowed("create")], Span::new(0, 0)),
-                type_args: vec![],
-                args: vec![Expression::Variable(Text::Owned(var), ty, Span::new(0, 0))],
-                annotation: Type::Object(string, Span::new(0,0)),
-                span: Span::new(0, 0),
-            };
-            let statement = Statement::Let {
-                bindings,
-                ty: let_type,
-                value,
-                span: Span::new(0, 0),
-            };
-            prepend_statements.push(statement);
+            prepend_statements.push(Self::box_rebinding(var, ty));
         }
 
         prepend_statements
     }
 
+    /// Shadows a captured primitive with a box of it, so that the closure and the method share one value
+    fn box_rebinding(var: String, ty: Type<'boxing>) -> Statement<'boxing> {
+        let string = match &ty {
+            Type::U8 => Text::Borrowed("U8"),
+            Type::U16 => Text::Borrowed("U16"),
+            Type::U32 => Text::Borrowed("U32"),
+            Type::U64 => Text::Borrowed("U64"),
+            Type::I8 => Text::Borrowed("I8"),
+            Type::I16 => Text::Borrowed("I16"),
+            Type::I32 => Text::Borrowed("I32"),
+            Type::I64 => Text::Borrowed("I64"),
+            Type::F32 => Text::Borrowed("F32"),
+            Type::F64 => Text::Borrowed("F64"),
+            _ => unreachable!("unsupported type, can only be primitive"),
+        };
+        let bindings = Pattern::Variable(Text::Owned(var.clone()), false, Span::new(0, 0));
+        let let_type = Type::Object(string.clone(), Span::new(0, 0));
+        let value = Expression::StaticCall {
+            name: PathName::new(vec![string.clone(), Text::Borrowed("create")], Span::new(0, 0)),
+            type_args: vec![],
+            args: vec![Expression::Variable(Text::Owned(var), ty, Span::new(0, 0))],
+            annotation: Type::Object(string, Span::new(0,0)),
+            span: Span::new(0, 0),
+        };
+        Statement::Let {
+            bindings,
+            ty: let_type,
+            value,
+            span: Span::new(0, 0),
+        }
+    }
+
     fn get_capture(
         &self,
         stmt: &Statement<'boxing>,
@@ -416,17 +512,11 @@ impl<'boxing> BoxClosureCapture<> {
     ) {
         match stmt {
             Statement::Let { bindings, value, .. } => {
-                let Pattern::Variable(var, _, _) = bindings else {
-                    todo!("support additional patterns")
-                };
-                bound_vars.insert(var.to_string());
+                Self::bind_pattern(bindings, bound_vars);
                 self.get_capture_expression(value, bound_vars, captures, false, ordering);
             }
             Statement::Const { bindings, value, .. } => {
-                let Pattern::Variable(var, _, _) = bindings else {
-                    todo!("support additional patterns")
-                };
-                bound_vars.insert(var.to_string());
+                Self::bind_pattern(bindings, bound_vars);
                 self.get_capture_expression(value, bound_vars, captures, false, ordering);
             }
             Statement::Assignment { target, value, .. } => {
@@ -439,10 +529,54 @@ impl<'boxing> BoxClosureCapture<> {
                     self.get_capture(stmt, bound_vars, captures, ordering);
                 }
             }
+            Statement::For { bindings, iterable, body, .. } => {
+                self.get_capture_expression(iterable, bound_vars, captures, false, ordering);
+                Self::bind_pattern(bindings, bound_vars);
+                for stmt in body {
+                    self.get_capture(stmt, bound_vars, captures, ordering);
+                }
+            }
+            Statement::With { bindings, expr, block, .. } => {
+                self.get_capture_expression(expr, bound_vars, captures, false, ordering);
+                Self::bind_pattern(bindings, bound_vars);
+                for stmt in block {
+                    self.get_capture(stmt, bound_vars, captures, ordering);
+                }
+            }
             Statement::Expression(expr, ..) => {
                 self.get_capture_expression(expr, bound_vars, captures, false, ordering);
             }
-            _ => todo!("remaining statements of get_capture"),
+            Statement::Emit { object, args, .. } => {
+                self.get_capture_expression(object, bound_vars, captures, false, ordering);
+                for arg in args {
+                    self.get_capture_expression(arg, bound_vars, captures, false, ordering);
+                }
+            }
+            Statement::EmitStatic { args, .. } => {
+                for arg in args {
+                    self.get_capture_expression(arg, bound_vars, captures, false, ordering);
+                }
+            }
+            Statement::Connect { source, target, .. } | Statement::Disconnect { source, target, .. } => {
+                self.get_capture_expression(source, bound_vars, captures, false, ordering);
+                self.get_capture_expression(target, bound_vars, captures, false, ordering);
+            }
+            Statement::Try { body, catches, finally, .. } => {
+                for stmt in body {
+                    self.get_capture(stmt, bound_vars, captures, ordering);
+                }
+                for catch in catches {
+                    bound_vars.insert(catch.binding.to_string());
+                    for stmt in catch.body.iter() {
+                        self.get_capture(stmt, bound_vars, captures, ordering);
+                    }
+                }
+                if let Some(finally) = finally {
+                    for stmt in finally {
+                        self.get_capture(stmt, bound_vars, captures, ordering);
+                    }
+                }
+            }
         }
     }
 
@@ -492,6 +626,9 @@ impl<'boxing> BoxClosureCapture<> {
                     self.get_capture_expression(expr, bound_vars, captures, false, ordering);
                 }
             }
+            Expression::Throw(expr, _) => {
+                self.get_capture_expression(expr, bound_vars, captures, false, ordering);
+            }
             Expression::New(_, expr, _) => {
                 if let Some(expr) = expr {
                     self.get_capture_expression(expr, bound_vars, captures, false, ordering);
@@ -511,6 +648,24 @@ impl<'boxing> BoxClosureCapture<> {
             Expression::IfExpression(if_expr,..) => {
                 self.get_capture_expression_if(if_expr, bound_vars, captures, ordering);
             }
+            Expression::MatchExpression(MatchExpression { value, arms, .. }, ..) => {
+                self.get_capture_expression(value.as_ref(), bound_vars, captures, false, ordering);
+                for arm in arms {
+                    // Variables bound by a pattern are only visible in its arm
+                    let mut arm_bindings = bound_vars.clone();
+                    Self::bind_pattern(&arm.pattern, &mut arm_bindings);
+                    match &arm.value {
+                        Either::Left(expr) => {
+                            self.get_capture_expression(expr, &mut arm_bindings, captures, false, ordering);
+                        }
+                        Either::Right(body) => {
+                            for stmt in body {
+                                self.get_capture(stmt, &mut arm_bindings, captures, ordering);
+                            }
+                        }
+                    }
+                }
+            }
             Expression::Literal(..) => {}
             Expression::MemberAccess { object, .. } => {
                 self.get_capture_expression(object.as_ref(), bound_vars, captures, false, ordering);
@@ -557,6 +712,7 @@ impl<'boxing> BoxClosureCapture<> {
         ty: &mut Type,
         value: &mut Expression<'boxing>,
         bound_vars: &mut HashMap<String, (bool, Type<'boxing>)>,
+        rebindings: &mut Vec<Statement<'boxing>>,
     ) {
         match (pattern, ty, value) {
             (Pattern::Variable(var, ..), ty, value) => {
@@ -764,30 +920,51 @@ impl<'boxing> BoxClosureCapture<> {
                     _ => {}
                 }
             }
-            (Pattern::Tuple(..), _, _) => todo!("handle tuple pattern, this might need adding a new binding after this one"),
+            (Pattern::Tuple(..), _, _) => {
+                let mut vars = HashSet::new();
+                Self::bind_pattern(pattern, &mut vars);
+                // Boxing one part of a tuple needs a binding of its own after this one
+                let mut vars = vars.into_iter().collect::<Vec<_>>();
+                vars.sort();
+                for var in vars {
+                    let Some((mutated, ty)) = bound_vars.remove(var.as_str()) else {
+                        continue;
+                    };
+                    // Like with a single binding, only primitives that are mutated need to be boxed
+                    if mutated && (ty.is_integer() || ty.is_float()) {
+                        rebindings.push(Self::box_rebinding(var, ty));
+                    }
+                }
+            }
             _ => {}
         }
     }
 
-    fn get_param_set(&self, params: &[ClosureParameter<'boxing>]) -> HashSet<String> {
-        let mut set = HashSet::new();
-        fn bind_pattern<'boxing>(pattern: &Pattern<'boxing>, set: &mut HashSet<String>) {
-            match pattern {
-                Pattern::Variable(var, ..) => {
-                    set.insert(var.to_string());
+    fn bind_pattern(pattern: &Pattern<'boxing>, set: &mut HashSet<String>) {
+        match pattern {
+            Pattern::Variable(var, ..) => {
+                set.insert(var.to_string());
+            }
+            Pattern::Tuple(patterns, ..) => {
+                for pattern in patterns {
+                    Self::bind_pattern(pattern, set);
                 }
-                Pattern::Tuple(patterns, ..) => {
-                    for pattern in patterns {
-                        bind_pattern(pattern, set);
-                    }
+            }
+            Pattern::Class { fields, .. } => {
+                for (_, _, pattern) in fields {
+                    Self::bind_pattern(pattern, set);
                 }
-                _ => {}
             }
+            _ => {}
         }
+    }
+
+    fn get_param_set(&self, params: &[ClosureParameter<'boxing>]) -> HashSet<String> {
+        let mut set = HashSet::new();
         for param in params {
             match &param.parameter {
                 Parameter::Pattern { name, .. } => {
-                    bind_pattern(name, &mut set);
+                    Self::bind_pattern(name, &mut set);
                 }
                 Parameter::This(..) => {
                     unreachable!("invalid closure parameter")
@@ -829,10 +1006,83 @@ impl<'boxing> BoxClosureCapture<> {
                 }
                 None
             }
+            Statement::For { iterable, body, .. } => {
+                {
+                    let result = self.get_closure_expression(iterable);
+                    if result.is_some() {
+                        return result;
+                    }
+                }
+                for stmt in body.iter_mut() {
+                    if let Some(expr) = self.get_closure(stmt) {
+                        return Some(expr);
+                    }
+                }
+                None
+            }
+            Statement::With { expr, block, .. } => {
+                {
+                    let result = self.get_closure_expression(expr);
+                    if result.is_some() {
+                        return result;
+                    }
+                }
+                for stmt in block.iter_mut() {
+                    if let Some(expr) = self.get_closure(stmt) {
+                        return Some(expr);
+                    }
+                }
+                None
+            }
             Statement::Expression(expr, ..) => {
                 self.get_closure_expression(expr)
             }
-            _ => todo!("getting closure for remaining statements"),
+            Statement::Emit { object, args, .. } => {
+                {
+                    let result = self.get_closure_expression(object);
+                    if result.is_some() {
+                        return result;
+                    }
+                }
+                for arg in args {
+                    let arg_result = self.get_closure_expression(arg);
+                    if arg_result.is_some() {
+                        return arg_result;
+                    }
+                }
+                None
+            }
+            Statement::EmitStatic { args, .. } => {
+                for arg in args {
+                    let arg_result = self.get_closure_expression(arg);
+                    if arg_result.is_some() {
+                        return arg_result;
+                    }
+                }
+                None
+            }
+            Statement::Connect { source, target, .. } | Statement::Disconnect { source, target, .. } => {
+                if let Some(source) = self.get_closure_expression(source) {
+                    Some(source)
+                } else if let Some(target) = self.get_closure_expression(target) {
+                    Some(target)
+                } else {
+                    None
+                }
+            }
+            Statement::Try { body, catches, finally, .. } => {
+                let bodies = std::iter::once(body)
+                    .chain(catches.iter_mut().map(|catch| &mut catch.body))
+                    .chain(finally.iter_mut());
+                for body in bodies {
+                    for stmt in body.iter_mut() {
+                        if let Some(expr) = self.get_closure(stmt) {
+                            return Some(expr);
+                        }
+                    }
+                }
+                None
+            }
         }
     }
 
@@ -879,6 +1129,28 @@ impl<'boxing> BoxClosureCapture<> {
             Expression::IfExpression(if_expr, ..) => {
                 self.get_closure_expression_if(if_expr)
             }
+            Expression::MatchExpression(MatchExpression { value, arms, .. }, ..) => {
+                if let Some(expr) = self.get_closure_expression(value.as_mut()) {
+                    return Some(expr);
+                }
+                for arm in arms.iter_mut() {
+                    match &mut arm.value {
+                        Either::Left(expr) => {
+                            if let Some(expr) = self.get_closure_expression(expr) {
+                                return Some(expr);
+                            }
+                        }
+                        Either::Right(body) => {
+                            for stmt in body.iter_mut() {
+                                if let Some(expr) = self.get_closure(stmt) {
+                                    return Some(expr);
+                                }
+                            }
+                        }
+                    }
+                }
+                None
+            }
             Expression::UnaryOperation { operand, ..} => {
                 self.get_closure_expression(operand.as_mut())
             }
@@ -899,6 +1171,9 @@ impl<'boxing> BoxClosureCapture<> {
             Expression::Return(None, ..) => {
                 None
             }
+            Expression::Throw(expr, ..) => {
+                self.get_closure_expression(expr.as_mut())
+            }
             Expression::Variable(..) => None,
             Expression::New(..) => None,
             Expression::MemberAccess { .. } => None,
@@ -953,6 +1228,9 @@ impl<'boxing> BoxClosureCapture<> {
                 }
                 false
             }
+            Pattern::Class { fields, .. } => {
+                fields.iter().any(|(_, _, pattern)| self.is_bound(pattern, captures))
+            }
             _ => false,
         }
     }
@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 use either::Either;
-use crate::trees::ir::{Class, Expression, File, IfExpression, Method, Parameter, Pattern, Statement, TopLevelStatement, Trait, TraitImpl};
+use crate::trees::ir::{Class, Expression, File, IfExpression, MatchExpression, Method, Parameter, Pattern, Statement, TopLevelStatement, Trait, TraitImpl};
 use crate::trees::{PathName, Span, Text, Type};
 
 pub struct Frame<'fix> {
@@ -199,10 +199,12 @@ impl<'fix> FixTypesAfterBoxing<'fix> {
     fn fix_class(&mut self, class: Class<'fix>) -> Class<'fix> {
         let Class {
             name, 
+            kind,
             parent, 
             members, 
             methods, 
             static_members, 
+            signals, 
             type_params, 
             span
         } = class;
@@ -251,10 +253,12 @@ impl<'fix> FixTypesAfterBoxing<'fix> {
         
         Class {
             name,
+            kind,
             parent,
             members,
             methods,
             static_members,
+            signals,
             type_params,
             span
         }
@@ -285,7 +289,43 @@ impl<'fix> FixTypesAfterBoxing<'fix> {
                     self.fix_expr(test);
                     self.fix_body(body);
                 }
-                _ => todo!("complete remaining statements in fix_types_after_boxing"),
+                Statement::For { bindings, bindings_type, iterable, body, .. } => {
+                    self.fix_expr(iterable);
+                    self.bind_vars(bindings, bindings_type);
+                    self.fix_body(body);
+                }
+                Statement::With { bindings, bindings_type, expr, block, .. } => {
+                    self.fix_expr(expr);
+                    self.bind_vars(bindings, bindings_type);
+                    self.fix_body(block);
+                }
+                Statement::Emit { object, args, .. } => {
+                    self.fix_expr(object);
+                    for arg in args {
+                        self.fix_expr(arg);
+                    }
+                }
+                Statement::EmitStatic { args, .. } => {
+                    for arg in args {
+                        self.fix_expr(arg);
+                    }
+                }
+                Statement::Connect { source, target, .. } | Statement::Disconnect { source, target, .. } => {
+                    self.fix_expr(source);
+                    self.fix_expr(target);
+                }
+                Statement::Try { body, catches, finally, .. } => {
+                    self.fix_body(body);
+                    for catch in catches {
+                        self.push_frame();
+                        self.bind_variable(catch.binding.as_str(), catch.ty.clone());
+                        self.fix_body(&mut catch.body);
+                        self.pop_frame();
+                    }
+                    if let Some(finally) = finally {
+                        self.fix_body(finally);
+                    }
+                }
             }
         }
     }
@@ -363,6 +403,9 @@ impl<'fix> FixTypesAfterBoxing<'fix> {
                     self.fix_expr(expr.as_mut());
                 }
             }
+            Expression::Throw(expr, ..) => {
+                self.fix_expr(expr.as_mut());
+            }
             Expression::New(_, array_size, _) => {
                 if let Some(array_size) = array_size {
                     self.fix_expr(array_size.as_mut());
@@ -371,6 +414,9 @@ impl<'fix> FixTypesAfterBoxing<'fix> {
             Expression::IfExpression(if_expr, ..) => {
                 self.fix_expr_if(if_expr);
             }
+            Expression::MatchExpression(match_expr, ..) => {
+                self.fix_expr_match(match_expr);
+            }
             e => todo!("remaining expression in fix_types_after_boxing {e:?}"),
         }
     }
@@ -380,9 +426,40 @@ impl<'fix> FixTypesAfterBoxing<'fix> {
             (Pattern::Variable(var, ..), ty) => {
                 self.bind_variable(var.as_str(), ty.clone());
             }
+            (Pattern::Tuple(patterns, ..), Type::Tuple(tys, ..)) => {
+                for (pattern, ty) in patterns.iter().zip(tys.iter()) {
+                    self.bind_vars(pattern, ty);
+                }
+            }
+            (Pattern::Class { fields, .. }, _) => {
+                for (_, ty, pattern) in fields {
+                    self.bind_vars(pattern, ty);
+                }
+            }
+            (Pattern::WildCard(..) | Pattern::Constant(..), _) => {}
             _ => todo!("complete remaining pattern bindings in fix_types_after_boxing")
         }
     }
+
+    fn fix_expr_match(&mut self, expr: &mut MatchExpression<'fix>) {
+        let MatchExpression {
+            value,
+            arms,
+            ..
+        } = expr;
+
+        self.fix_expr(value.as_mut());
+        let ty = value.get_type().left().unwrap_or(Type::Void);
+        for arm in arms {
+            self.push_frame();
+            self.bind_vars(&arm.pattern, &ty);
+            match &mut arm.value {
+                Either::Left(expr) => self.fix_expr(expr),
+                Either::Right(body) => self.fix_body(body),
+            }
+            self.pop_frame();
+        }
+    }
     
     fn fix_expr_if(&mut self, expr: &mut IfExpression<'fix>) {
         let IfExpression {
@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 use either::Either;
-use crate::trees::ir::{Class, Expression, File, IfExpression, Literal, Method, Parameter, Statement, TopLevelStatement, Trait, TraitImpl};
+use crate::trees::ir::{CatchClause, Class, Expression, File, IfExpression, Literal, MatchArm, MatchExpression, Method, Parameter, Pattern, Statement, TopLevelStatement, Trait, TraitImpl};
 use crate::trees::{PathName, Text, Type};
 use crate::trees::ir::ClosureParameter;
 
@@ -14,6 +14,7 @@ impl InlineImports {
     pub fn new() -> Self {
 
         let mut imports = HashMap::new();
+        imports.insert("Object".to_string(), "core::Object".to_string());
         imports.insert("Printer".to_string(), "core::Printer".to_string());
         imports.insert("String".to_string(), "core::String".to_string());
         imports.insert("StringBuffer".to_string(), "core::StringBuffer".to_string());
@@ -28,6 +29,9 @@ impl InlineImports {
         imports.insert("I64".to_string(), "core::I64".to_string());
         imports.insert("F32".to_string(), "core::F32".to_string());
         imports.insert("F64".to_string(), "core::F64".to_string());
+        imports.insert("Exception".to_string(), "core::Exception".to_string());
+        imports.insert("IndexOutOfBounds".to_string(), "core::IndexOutOfBounds".to_string());
+        imports.insert("NullPointerException".to_string(), "core::NullPointerException".to_string());
 
         Self {
             imports,
@@ -83,17 +87,23 @@ impl InlineImports {
     fn inline_class<'inline>(&mut self, class: Class<'inline>) -> Class<'inline> {
         let Class {
             name,
+            kind,
             parent,
             members,
             methods,
             static_members,
+            signals,
             type_params,
             span
         } = class;
 
         let parent = parent.map(|mut decl| {
-            let path = self.imports.get(decl.name.as_str()).unwrap();
-            decl.name = Text::Owned(path.to_string());
+            // Parents that are not imported are declared in the same file
+            let path = match self.imports.get(decl.name.as_str()) {
+                Some(path) => path.to_string(),
+                None => format!("{}::{}", self.current_path, decl.name),
+            };
+            decl.name = Text::Owned(path);
             decl
         });
 
@@ -105,12 +115,23 @@ impl InlineImports {
 
         let methods = new_methods;
 
+        let signals = signals.into_iter()
+            .map(|mut signal| {
+                signal.parameters = signal.parameters.into_iter()
+                    .map(|ty| self.inline_type(ty))
+                    .collect();
+                signal
+            })
+            .collect();
+
         Class {
             name,
+            kind,
             parent,
             members,
             methods,
             static_members,
+            signals,
             type_params,
             span,
         }
@@ -303,6 +324,7 @@ impl InlineImports {
             }
             Statement::Assignment {
                 target,
+                operator,
                 value,
                 span
             } => {
@@ -311,6 +333,7 @@ impl InlineImports {
 
                 Statement::Assignment {
                     target,
+                    operator,
                     value,
                     span,
                 }
@@ -335,7 +358,139 @@ impl InlineImports {
                     span
                 }
             }
-            _ => todo!("inline remaining statements")
+            Statement::For {
+                label,
+                bindings,
+                bindings_type,
+                iterable,
+                body,
+                span
+            } => {
+                let bindings_type = self.inline_type(bindings_type);
+                let iterable = self.inline_expression(iterable);
+                let body = self.inline_body(body);
+
+                Statement::For {
+                    label,
+                    bindings,
+                    bindings_type,
+                    iterable,
+                    body,
+                    span
+                }
+            }
+            Statement::With {
+                bindings,
+                bindings_type,
+                expr,
+                block,
+                span
+            } => {
+                let bindings_type = self.inline_type(bindings_type);
+                let expr = self.inline_expression(expr);
+                let block = self.inline_body(block);
+
+                Statement::With {
+                    bindings,
+                    bindings_type,
+                    expr,
+                    block,
+                    span
+                }
+            }
+            Statement::Emit {
+                object,
+                signal,
+                args,
+                span
+            } => {
+                let object = self.inline_expression(object);
+                let args = args.into_iter()
+                    .map(|arg| self.inline_expression(arg))
+                    .collect();
+
+                Statement::Emit {
+                    object,
+                    signal,
+                    args,
+                    span
+                }
+            }
+            Statement::EmitStatic {
+                signal,
+                args,
+                span
+            } => {
+                let args = args.into_iter()
+                    .map(|arg| self.inline_expression(arg))
+                    .collect();
+
+                Statement::EmitStatic {
+                    signal,
+                    args,
+                    span
+                }
+            }
+            Statement::Connect {
+                source,
+                signal,
+                target,
+                method,
+                span
+            } => {
+                let source = self.inline_expression(source);
+                let target = self.inline_expression(target);
+
+                Statement::Connect {
+                    source,
+                    signal,
+                    target,
+                    method,
+                    span
+                }
+            }
+            Statement::Disconnect {
+                source,
+                signal,
+                target,
+                method,
+                span
+            } => {
+                let source = self.inline_expression(source);
+                let target = self.inline_expression(target);
+
+                Statement::Disconnect {
+                    source,
+                    signal,
+                    target,
+                    method,
+                    span
+                }
+            }
+            Statement::Try {
+                body,
+                catches,
+                finally,
+                span
+            } => {
+                let body = self.inline_body(body);
+                let catches = catches.into_iter()
+                    .map(|catch| {
+                        let CatchClause { binding, ty, body, span } = catch;
+                        let ty = self.inline_type(ty);
+                        let body = self.inline_body(body);
+                        CatchClause::new(binding, ty, body, span)
+                    })
+                    .collect();
+                let finally = finally.map(|finally| self.inline_body(finally));
+
+                Statement::Try {
+                    body,
+                    catches,
+                    finally,
+                    span
+                }
+            }
         }
     }
 
@@ -400,6 +555,21 @@ impl InlineImports {
                 }
                 let args = new_args;
                 let annotation = self.inline_type(annotation);
+                let name = match self.imports.get(name.segments[0].as_str()) {
+                    Some(path) => {
+                        let PathName { segments, span } = name;
+                        let segments = path.split("::")
+                            .map(String::from)
+                            .map(Text::Owned)
+                            .chain(segments.into_iter().skip(1))
+                            .collect::<Vec<_>>();
+                        PathName {
+                            segments,
+                            span,
+                        }
+                    }
+                    None => name,
+                };
 
                 Expression::StaticCall {
                     name,
@@ -508,6 +678,10 @@ impl InlineImports {
                 let if_expr = self.inline_if_expr(if_expr);
                 Expression::IfExpression(if_expr, span)
             }
+            Expression::MatchExpression(match_expr, span) => {
+                let match_expr = self.inline_match_expr(match_expr);
+                Expression::MatchExpression(match_expr, span)
+            }
             Expression::UnaryOperation {
                 operator,
                 operand,
@@ -543,6 +717,10 @@ impl InlineImports {
                 });
                 Expression::Return(expr, span)
             }
+            Expression::Throw(expr, span) => {
+                let expr = Box::new(self.inline_expression(*expr));
+                Expression::Throw(expr, span)
+            }
             Expression::New(ty, array_size, span) => {
                 let ty = self.inline_type(ty);
                 let array_size = array_size.map(|array_size| {
@@ -604,10 +782,73 @@ impl InlineImports {
         }
     }
 
+    fn inline_match_expr<'inline>(&mut self, match_expr: MatchExpression<'inline>) -> MatchExpression<'inline> {
+        let MatchExpression {
+            value,
+            arms,
+            ty,
+            start,
+            end
+        } = match_expr;
+
+        let value = Box::new(self.inline_expression(*value));
+        let ty = self.inline_type(ty);
+
+        let mut new_arms = Vec::new();
+        for arm in arms {
+            let MatchArm {
+                pattern,
+                value,
+                start,
+                end
+            } = arm;
+            let pattern = self.inline_pattern(pattern);
+            let value = match value {
+                Either::Left(expr) => Either::Left(self.inline_expression(expr)),
+                Either::Right(body) => Either::Right(self.inline_body(body)),
+            };
+            new_arms.push(MatchArm::new(pattern, value, start, end));
+        }
+        let arms = new_arms;
+
+        MatchExpression {
+            value,
+            arms,
+            ty,
+            start,
+            end,
+        }
+    }
+
+    fn inline_pattern<'inline>(&mut self, pattern: Pattern<'inline>) -> Pattern<'inline> {
+        match pattern {
+            Pattern::Tuple(patterns, span) => {
+                let patterns = patterns.into_iter()
+                    .map(|pattern| self.inline_pattern(pattern))
+                    .collect();
+                Pattern::Tuple(patterns, span)
+            }
+            Pattern::Class { ty, fields, span } => {
+                let ty = self.inline_type(ty);
+                let mut new_fields = Vec::new();
+                for (name, ty, pattern) in fields {
+                    let ty = self.inline_type(ty);
+                    let pattern = self.inline_pattern(pattern);
+                    new_fields.push((name, ty, pattern));
+                }
+                Pattern::Class { ty, fields: new_fields, span }
+            }
+            pattern => pattern,
+        }
+    }
+
     fn inline_type<'inline>(&mut self, r#type: Type<'inline>) -> Type<'inline> {
         match r#type {
             Type::Object(name, span) => {
-                let name = if let Some(path) = self.imports.get(name.as_str()) {
+                // Types the typechecker named by their full path are left alone
+                let name = if name.contains("::") {
+                    name.to_string()
+                } else if let Some(path) = self.imports.get(name.as_str()) {
                     path.clone()
                 } else {
                     format!("{}::{}", self.current_path, name)
@@ -1,4 +1,5 @@
 use either::Either;
+use rowan_shared::classfile::ClassKind;
 use crate::trees::*;
 
 pub struct IRConverter {}
@@ -24,8 +25,19 @@ impl<'convert> IRConverter {
     ) -> Result<Vec<ir::TopLevelStatement<'convert>>, ()> {
         let mut output = Vec::new();
         for statement in statements {
-            let statement = self.convert_toplevel_statement(statement)?;
-            output.push(statement);
+            match statement {
+                ast::TopLevelStatement::Record(record) => {
+                    output.push(ir::TopLevelStatement::Class(self.convert_record(record)?));
+                }
+                ast::TopLevelStatement::Union(union) => {
+                    let classes = self.convert_union(union)?;
+                    output.extend(classes.into_iter().map(ir::TopLevelStatement::Class));
+                }
+                statement => {
+                    let statement = self.convert_toplevel_statement(statement)?;
+                    output.push(statement);
+                }
+            }
         }
         Ok(output)
     }
@@ -92,6 +104,9 @@ impl<'convert> IRConverter {
                 
                 ir::TopLevelStatement::TraitImpl(r#impl)
             }
+            ast::TopLevelStatement::Record(_) | ast::TopLevelStatement::Union(_) => {
+                unreachable!("records and unions are converted in convert_toplevel_statements")
+            }
         };
 
         Ok(result)
@@ -104,6 +119,7 @@ impl<'convert> IRConverter {
             members,
             methods,
             static_members,
+            signals,
             type_params,
             span
         } = class;
@@ -122,6 +138,9 @@ impl<'convert> IRConverter {
         let static_members = static_members.into_iter()
             .map(|sm| self.convert_static_member(sm))
             .collect::<Result<Vec<_>, _>>()?;
+        let signals = signals.into_iter()
+            .map(|s| self.convert_signal(s))
+            .collect::<Result<Vec<_>, _>>()?;
 
         let type_params = type_params.into_iter()
             .map(|tp| self.convert_type_param(tp))
@@ -129,15 +148,276 @@ impl<'convert> IRConverter {
 
         Ok(ir::Class {
             name,
+            kind: ClassKind::Class,
             parent,
             members,
             methods,
             static_members,
+            signals,
+            type_params,
+            span
+        })
+    }
+
+    fn convert_record(&mut self, record: ast::Record<'convert>) -> Result<ir::Class<'convert>, ()> {
+        let ast::Record {
+            name,
+            members,
+            methods,
+            type_params,
+            span
+        } = record;
+
+        let members = members.into_iter()
+            .map(|m| self.convert_member(m))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut methods = methods.into_iter()
+            .map(|m| self.convert_method(m))
+            .collect::<Result<Vec<_>, _>>()?;
+        let type_params = type_params.into_iter()
+            .map(|tp| self.convert_type_param(tp))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        methods.extend(Self::record_methods(&name, &members, &type_params, None, span));
+
+        Ok(ir::Class {
+            name,
+            kind: ClassKind::Record,
+            parent: None,
+            members,
+            methods,
+            static_members: Vec::new(),
+            signals: Vec::new(),
             type_params,
             span
         })
     }
 
+    /// A union becomes an abstract class with a child class for each of its variants
+    fn convert_union(&mut self, union: ast::Union<'convert>) -> Result<Vec<ir::Class<'convert>>, ()> {
+        let ast::Union {
+            name,
+            variants,
+            methods,
+            type_params,
+            span
+        } = union;
+
+        let mut methods = methods.into_iter()
+            .map(|m| self.convert_method(m))
+            .collect::<Result<Vec<_>, _>>()?;
+        let type_params = type_params.into_iter()
+            .map(|tp| self.convert_type_param(tp))
+            .collect::<Result<Vec<_>, _>>()?;
+        // Every value of a union is one of its variants, which override this
+        methods.push(ir::Method {
+            name: Text::Borrowed("equals"),
+            is_native: false,
+            annotations: Vec::new(),
+            visibility: Visibility::Public,
+            type_params: Vec::new(),
+            parameters: vec![
+                ir::Parameter::This(false, span),
+                ir::Parameter::Pattern {
+                    name: ir::Pattern::Variable(Text::Borrowed("other"), false, span),
+                    ty: Self::value_type(&name, &type_params, span),
+                    span,
+                },
+            ],
+            return_type: Type::Boolean,
+            body: vec![ir::Statement::Expression(
+                ir::Expression::Return(Some(Box::new(ir::Expression::Literal(ir::Literal::Constant(ir::Constant::Bool(false, span))))), span),
+                span,
+            )],
+            span,
+        });
+
+        let mut classes = Vec::with_capacity(variants.len() + 1);
+        for variant in variants {
+            let ast::Variant { name: variant_name, fields, span } = variant;
+            let members = fields.into_iter()
+                .map(|m| self.convert_member(m))
+                .collect::<Result<Vec<_>, _>>()?;
+            let methods = Self::record_methods(&variant_name, &members, &type_params, Some(&name), span);
+            let parent = ir::ParentDec {
+                name: name.clone(),
+                type_args: type_params.iter()
+                    .map(|param| Type::Object(param.name.clone(), param.span))
+                    .collect(),
+                type_params: Vec::new(),
+                span,
+            };
+
+            classes.push(ir::Class {
+                name: variant_name,
+                kind: ClassKind::Variant,
+                parent: Some(parent),
+                members,
+                methods,
+                static_members: Vec::new(),
+                signals: Vec::new(),
+                type_params: type_params.clone(),
+                span
+            });
+        }
+
+        classes.insert(0, ir::Class {
+            name,
+            kind: ClassKind::Union,
+            parent: None,
+            members: Vec::new(),
+            methods,
+            static_members: Vec::new(),
+            signals: Vec::new(),
+            type_params,
+            span
+        });
+
+        Ok(classes)
+    }
+
+    /// The type of a record or union as seen from inside of it, with its type parameters as the type arguments
+    fn value_type(
+        name: &Text<'convert>,
+        type_params: &[ir::TypeParameter<'convert>],
+        span: Span,
+    ) -> Type<'convert> {
+        if type_params.is_empty() {
+            Type::Object(name.clone(), span)
+        } else {
+            let type_args = type_params.iter()
+                .map(|param| Type::Object(param.name.clone(), param.span))
+                .collect();
+            Type::TypeArg(Box::new(Type::Object(name.clone(), span)), type_args, span)
+        }
+    }
+
+    /// Creates the `new` and `equals` methods that every record and variant gets.
+    /// `new` is left out when there are no fields since there is nothing to initialize.
+    /// The `equals` of a variant takes any value of its union and overrides the one the union has.
+    fn record_methods(
+        name: &Text<'convert>,
+        members: &[ir::Member<'convert>],
+        type_params: &[ir::TypeParameter<'convert>],
+        union: Option<&Text<'convert>>,
+        span: Span,
+    ) -> Vec<ir::Method<'convert>> {
+        let record_type = Self::value_type(name, type_params, span);
+        let field = |object: ir::Expression<'convert>, member: &ir::Member<'convert>| {
+            ir::Expression::MemberAccess {
+                object: Box::new(object),
+                field: PathName::new(vec![member.name.clone()], span),
+                span,
+                annotation: member.ty.clone(),
+            }
+        };
+
+        let mut methods = Vec::with_capacity(2);
+        if !members.is_empty() {
+            let mut parameters = vec![ir::Parameter::This(true, span)];
+            let mut body = Vec::with_capacity(members.len() + 1);
+            for member in members {
+                parameters.push(ir::Parameter::Pattern {
+                    name: ir::Pattern::Variable(member.name.clone(), false, span),
+                    ty: member.ty.clone(),
+                    span,
+                });
+                body.push(ir::Statement::Assignment {
+                    target: field(ir::Expression::This(span), member),
+                    operator: None,
+                    value: ir::Expression::Variable(member.name.clone(), member.ty.clone(), span),
+                    span,
+                });
+            }
+            body.push(ir::Statement::Expression(
+                ir::Expression::Return(Some(Box::new(ir::Expression::This(span))), span),
+                span,
+            ));
+
+            methods.push(ir::Method {
+                name: Text::Borrowed("new"),
+                is_native: false,
+                annotations: Vec::new(),
+                visibility: Visibility::Public,
+                type_params: Vec::new(),
+                parameters,
+                return_type: record_type.clone(),
+                body,
+                span,
+            });
+        }
+
+        // `other` is matched against the class of the record, which fails for null and for other variants
+        let other_field = |member: &ir::Member<'convert>| Text::Owned(format!("%other-{}", member.name));
+        let fields = members.iter()
+            .map(|member| (member.name.clone(), member.ty.clone(), ir::Pattern::Variable(other_field(member), false, span)))
+            .collect();
+        let equal = members.iter()
+            .map(|member| ir::Expression::BinaryOperation {
+                operator: BinaryOperator::Eq,
+                left: Box::new(field(ir::Expression::This(span), member)),
+                right: Box::new(ir::Expression::Variable(other_field(member), member.ty.clone(), span)),
+                span,
+            })
+            .reduce(|left, right| ir::Expression::BinaryOperation {
+                operator: BinaryOperator::And,
+                left: Box::new(left),
+                right: Box::new(right),
+                span,
+            })
+            .unwrap_or(ir::Expression::Literal(ir::Literal::Constant(ir::Constant::Bool(true, span))));
+        let other = Text::Borrowed("other");
+        let other_type = match union {
+            Some(union) => Self::value_type(union, type_params, span),
+            None => record_type.clone(),
+        };
+        let arms = vec![
+            ir::MatchArm::new(
+                ir::Pattern::Class { ty: record_type, fields, span },
+                Either::Left(equal),
+                span.start,
+                span.end,
+            ),
+            ir::MatchArm::new(
+                ir::Pattern::WildCard(span),
+                Either::Left(ir::Expression::Literal(ir::Literal::Constant(ir::Constant::Bool(false, span)))),
+                span.start,
+                span.end,
+            ),
+        ];
+        // The value is seen as a plain object so that its class is always tested, even for records
+        let value = ir::Expression::Variable(other.clone(), Type::Object(Text::Borrowed("Object"), span), span);
+        let equal = ir::MatchExpression::new(Box::new(value), arms, Type::Boolean, span.start, span.end);
+
+        let annotations = match union {
+            Some(_) => vec![Annotation::new(Text::Borrowed("Override"), Vec::new(), span)],
+            None => Vec::new(),
+        };
+        methods.push(ir::Method {
+            name: Text::Borrowed("equals"),
+            is_native: false,
+            annotations,
+            visibility: Visibility::Public,
+            type_params: Vec::new(),
+            parameters: vec![
+                ir::Parameter::This(false, span),
+                ir::Parameter::Pattern {
+                    name: ir::Pattern::Variable(other, false, span),
+                    ty: other_type,
+                    span,
+                },
+            ],
+            return_type: Type::Boolean,
+            body: vec![ir::Statement::Expression(
+                ir::Expression::Return(Some(Box::new(ir::Expression::MatchExpression(equal, span))), span),
+                span,
+            )],
+            span,
+        });
+
+        methods
+    }
+
     fn convert_parent_dec(&mut self, parent_dec: ast::ParentDec<'convert>) -> Result<ir::ParentDec<'convert>, ()> {
         let ast::ParentDec {
             name, type_args, type_params, span
@@ -189,6 +469,20 @@ impl<'convert> IRConverter {
         })
     }
 
+    fn convert_signal(&mut self, signal: ast::Signal<'convert>) -> Result<ir::Signal<'convert>, ()> {
+        let ast::Signal {
+            visibility, is_static, name, parameters, span
+        } = signal;
+
+        Ok(ir::Signal {
+            visibility,
+            is_static,
+            name,
+            parameters,
+            span,
+        })
+    }
+
     fn convert_type_param(&mut self, typ_param: ast::TypeParameter<'convert>) -> Result<ir::TypeParameter<'convert>, ()> {
         let ast::TypeParameter {
             name, constraints, span
@@ -279,6 +573,17 @@ impl<'convert> IRConverter {
             ast::Pattern::WildCard(span) => {
                 Ok(ir::Pattern::WildCard(span))
             }
+            ast::Pattern::Class { fields, ty, members, span, .. } => {
+                let fields = members.into_iter()
+                    .zip(fields)
+                    .map(|((name, ty), pattern)| Ok((name, ty, self.convert_pattern(pattern)?)))
+                    .collect::<Result<Vec<_>, ()>>()?;
+                Ok(ir::Pattern::Class {
+                    ty: ty.expect("TODO: handle missing type"),
+                    fields,
+                    span,
+                })
+            }
         }
     }
 
@@ -339,6 +644,7 @@ impl<'convert> IRConverter {
             }
             ast::Statement::Assignment {
                 target,
+                operator,
                 value,
                 span
             } => {
@@ -347,6 +653,7 @@ impl<'convert> IRConverter {
 
                 Ok(ir::Statement::Assignment {
                     target,
+                    operator,
                     value,
                     span,
                 })
@@ -369,7 +676,152 @@ impl<'convert> IRConverter {
                     span
                 })
             }
-            stmt => todo!("IR conversion statement: {:?}", stmt),
+            ast::Statement::For {
+                label,
+                bindings,
+                bindings_type,
+                iterable,
+                body,
+                span
+            } => {
+                let bindings = self.convert_pattern(bindings)?;
+                let iterable = self.convert_expression(iterable)?;
+                let body = body.into_iter()
+                    .map(|stmt| self.convert_statement(stmt))
+                    .collect::<Result<Vec<_>, ()>>()?;
+
+                Ok(ir::Statement::For {
+                    label,
+                    bindings,
+                    bindings_type,
+                    iterable,
+                    body,
+                    span
+                })
+            }
+            ast::Statement::With {
+                bindings,
+                bindings_type,
+                expr,
+                block,
+                span
+            } => {
+                let bindings = self.convert_pattern(bindings)?;
+                let expr = self.convert_expression(expr)?;
+                let block = block.into_iter()
+                    .map(|stmt| self.convert_statement(stmt))
+                    .collect::<Result<Vec<_>, ()>>()?;
+
+                Ok(ir::Statement::With {
+                    bindings,
+                    bindings_type,
+                    expr,
+                    block,
+                    span
+                })
+            }
+            ast::Statement::Emit {
+                object,
+                signal,
+                args,
+                span
+            } => {
+                let object = self.convert_expression(object)?;
+                let args = args.into_iter()
+                    .map(|arg| self.convert_expression(arg))
+                    .collect::<Result<Vec<_>, ()>>()?;
+
+                Ok(ir::Statement::Emit {
+                    object,
+                    signal,
+                    args,
+                    span
+                })
+            }
+            ast::Statement::EmitStatic {
+                signal,
+                args,
+                span
+            } => {
+                let args = args.into_iter()
+                    .map(|arg| self.convert_expression(arg))
+                    .collect::<Result<Vec<_>, ()>>()?;
+
+                Ok(ir::Statement::EmitStatic {
+                    signal,
+                    args,
+                    span
+                })
+            }
+            ast::Statement::Connect {
+                source,
+                signal,
+                target,
+                method,
+                span
+            } => {
+                let source = self.convert_expression(source)?;
+                let target = self.convert_expression(target)?;
+
+                Ok(ir::Statement::Connect {
+                    source,
+                    signal,
+                    target,
+                    method,
+                    span
+                })
+            }
+            ast::Statement::Disconnect {
+                source,
+                signal,
+                target,
+                method,
+                span
+            } => {
+                let source = self.convert_expression(source)?;
+                let target = self.convert_expression(target)?;
+
+                Ok(ir::Statement::Disconnect {
+                    source,
+                    signal,
+                    target,
+                    method,
+                    span
+                })
+            }
+            ast::Statement::Try {
+                body,
+                catches,
+                finally,
+                span
+            } => {
+                let body = body.into_iter()
+                    .map(|stmt| self.convert_statement(stmt))
+                    .collect::<Result<Vec<_>, ()>>()?;
+                let catches = catches.into_iter()
+                    .map(|catch| {
+                        let ast::CatchClause { binding, ty, body, span } = catch;
+                        let body = body.into_iter()
+                            .map(|stmt| self.convert_statement(stmt))
+                            .collect::<Result<Vec<_>, ()>>()?;
+                        Ok(ir::CatchClause::new(binding, ty, body, span))
+                    })
+                    .collect::<Result<Vec<_>, ()>>()?;
+                let finally = if let Some(finally) = finally {
+                    Some(finally.into_iter()
+                        .map(|stmt| self.convert_statement(stmt))
+                        .collect::<Result<Vec<_>, ()>>()?)
+                } else {
+                    None
+                };
+
+                Ok(ir::Statement::Try {
+                    body,
+                    catches,
+                    finally,
+                    span
+                })
+            }
         }
     }
 
@@ -473,6 +925,9 @@ impl<'convert> IRConverter {
             ast::Expression::IfExpression(if_expr, span) => {
                 Ok(ir::Expression::IfExpression(self.convert_if_expression(if_expr)?, span))
             }
+            ast::Expression::MatchExpression(match_expr, span) => {
+                Ok(ir::Expression::MatchExpression(self.convert_match_expression(match_expr)?, span))
+            }
             ast::Expression::UnaryOperation {
                 operator,
                 operand,
@@ -511,6 +966,10 @@ impl<'convert> IRConverter {
 
                 Ok(ir::Expression::Return(expr, span))
             }
+            ast::Expression::Throw(expr, span) => {
+                let expr = self.convert_expression(*expr)?;
+                Ok(ir::Expression::Throw(Box::new(expr), span))
+            }
             ast::Expression::New(ty, array_size, span) => {
                 let array_size = if let Some(array_size) = array_size {
                     Some(Box::new(self.convert_expression(*array_size)?))
@@ -538,7 +997,13 @@ impl<'convert> IRConverter {
 
                 Ok(ir::Literal::Array(body, annotation.expect("TODO: handle missing type"), span))
             }
-            _ => todo!("IR conversion literal tuple")
+            ast::Literal::Tuple(body, annotation, span) => {
+                let body = body.into_iter()
+                    .map(|expr| self.convert_expression(expr))
+                    .collect::<Result<Vec<_>, ()>>()?;
+
+                Ok(ir::Literal::Tuple(body, annotation.expect("TODO: handle missing type"), span))
+            }
         }
     }
 
@@ -589,4 +1054,34 @@ impl<'convert> IRConverter {
             end,
         })
     }
+
+    fn convert_match_expression(&mut self, match_expr: ast::MatchExpression<'convert>) -> Result<ir::MatchExpression<'convert>, ()> {
+        let ast::MatchExpression {
+            value,
+            arms,
+            annotation,
+            start,
+            end,
+        } = match_expr;
+        let value = Box::new(self.convert_expression(*value)?);
+
+        let arms = arms.into_iter()
+            .map(|arm| {
+                let ast::MatchArm { pattern, value, start, end } = arm;
+                let pattern = self.convert_pattern(pattern)?;
+                let value = match value {
+                    Either::Left(expr) => Either::Left(self.convert_expression(expr)?),
+                    Either::Right(body) => {
+                        let body = body.into_iter()
+                            .map(|stmt| self.convert_statement(stmt))
+                            .collect::<Result<Vec<_>, ()>>()?;
+                        Either::Right(body)
+                    }
+                };
+                Ok(ir::MatchArm::new(pattern, value, start, end))
+            })
+            .collect::<Result<Vec<_>, ()>>()?;
+
+        Ok(ir::MatchExpression::new(value, arms, annotation.unwrap_or(Type::Void), start, end))
+    }
 }
\ No newline at end of file
@@ -1,13 +1,21 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use either::Either;
 use itertools::Itertools;
-use crate::trees::ir::{Class, Expression, File, IfExpression, Import, Literal, Method, Parameter, ParentDec, Statement, TopLevelStatement, Trait, TraitImpl};
+use crate::trees::ir::{Class, Expression, File, IfExpression, Import, Literal, MatchExpression, Method, Parameter, ParentDec, Pattern, Statement, TopLevelStatement, Trait, TraitImpl};
 use crate::trees::{PathName, Span, Text, Type};
 
 pub struct SpecializeGenerics {
     imports_to_change: HashMap<String, Vec<String>>,
     current_type_argument: HashMap<String, Type<'static>>,
     functions_to_change: HashMap<Vec<String>, Vec<(String, Type<'static>)>>,
+    /// The type arguments of each specialized class that has generic methods, keyed by file path and class name
+    class_type_arguments: HashMap<(Vec<String>, String), HashMap<String, Type<'static>>>,
+    /// Instances of generic methods that still need to be generated, as the method name and its type arguments
+    pending_method_instances: Vec<(String, Vec<Type<'static>>)>,
+    /// The mangled names of every generic method instance that has been requested
+    method_instances: HashSet<String>,
+    /// The names of methods that have their own type parameters
+    generic_methods: HashSet<String>,
 }
 
 impl SpecializeGenerics {
@@ -16,6 +24,10 @@ impl SpecializeGenerics {
             imports_to_change: HashMap::new(),
             current_type_argument: HashMap::new(),
             functions_to_change: HashMap::new(),
+            class_type_arguments: HashMap::new(),
+            pending_method_instances: Vec::new(),
+            method_instances: HashSet::new(),
+            generic_methods: HashSet::new(),
         }
     }
 
@@ -33,9 +45,115 @@ impl SpecializeGenerics {
             output_files.push(result);
         }
 
+        self.specialize_generic_methods(&mut output_files);
+
         output_files
     }
 
+    /// Takes the generic methods out of their classes and generates an instance
+    /// of them for every set of type arguments they are called with
+    fn specialize_generic_methods<'special>(&mut self, files: &mut Vec<File<'special>>) {
+        let mut templates = Vec::new();
+        for (file_index, file) in files.iter_mut().enumerate() {
+            for (class_index, stmt) in file.content.iter_mut().enumerate() {
+                let TopLevelStatement::Class(class) = stmt else {
+                    continue
+                };
+                let (generic, methods) = std::mem::take(&mut class.methods)
+                    .into_iter()
+                    .partition::<Vec<_>, _>(|method| !method.type_params.is_empty());
+                class.methods = methods;
+                templates.extend(generic.into_iter().map(|method| (file_index, class_index, method)));
+            }
+        }
+
+        while let Some((method_name, type_args)) = self.pending_method_instances.pop() {
+            for (file_index, class_index, template) in &templates {
+                if template.name.as_str() != method_name || template.type_params.len() != type_args.len() {
+                    continue
+                }
+                let file_path = files[*file_index].path.segments.iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>();
+                let TopLevelStatement::Class(class) = &files[*file_index].content[*class_index] else {
+                    unreachable!("generic methods only come from classes")
+                };
+                self.current_type_argument = self.class_type_arguments
+                    .get(&(file_path.clone(), class.name.to_string()))
+                    .cloned()
+                    .unwrap_or_default();
+                for (param, ty) in template.type_params.iter().zip(type_args.iter()) {
+                    self.current_type_argument.insert(param.name.to_string(), ty.clone());
+                }
+
+                let mut method = template.clone();
+                method.name = Text::Owned(Self::mangle_method_name(&method_name, &type_args));
+                method.type_params = Vec::new();
+                let methods = self.specialize_method(&file_path, method);
+                self.clear_type_arguments();
+
+                let TopLevelStatement::Class(class) = &mut files[*file_index].content[*class_index] else {
+                    unreachable!("generic methods only come from classes")
+                };
+                class.methods.extend(methods);
+            }
+        }
+    }
+
+    fn mangle_method_name(name: &str, type_args: &[Type]) -> String {
+        let mut name = name.to_string();
+        for ty in type_args {
+            match ty {
+                Type::U8 => name.push_str("-u8"),
+                Type::U16 => name.push_str("-u16"),
+                Type::U32 => name.push_str("-u32"),
+                Type::U64 => name.push_str("-u64"),
+                Type::I8 => name.push_str("-i8"),
+                Type::I16 => name.push_str("-i16"),
+                Type::I32 => name.push_str("-i32"),
+                Type::I64 => name.push_str("-i64"),
+                Type::F32 => name.push_str("-f32"),
+                Type::F64 => name.push_str("-f64"),
+                Type::Char => name.push_str("-char"),
+                Type::Boolean => name.push_str("-bool"),
+                _ => name.push_str("-object"),
+            }
+        }
+        name
+    }
+
+    /// Resolves the type arguments of a call to a generic method, requests the matching instance
+    /// and returns the name of that instance
+    fn instantiate_generic_method(&mut self, name: &str, type_args: &mut Vec<Type>) -> String {
+        let type_args = std::mem::take(type_args)
+            .into_iter()
+            .map(|mut ty| {
+                self.specialize_type(&mut ty);
+                match ty {
+                    Type::U8 => Type::U8,
+                    Type::U16 => Type::U16,
+                    Type::U32 => Type::U32,
+                    Type::U64 => Type::U64,
+                    Type::I8 => Type::I8,
+                    Type::I16 => Type::I16,
+                    Type::I32 => Type::I32,
+                    Type::I64 => Type::I64,
+                    Type::F32 => Type::F32,
+                    Type::F64 => Type::F64,
+                    Type::Char => Type::Char,
+                    Type::Boolean => Type::Boolean,
+                    _ => Type::Object(Text::Borrowed(""), Span::new(0, 0)),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let mangled_name = Self::mangle_method_name(name, &type_args);
+        if self.method_instances.insert(mangled_name.clone()) {
+            self.pending_method_instances.push((name.to_string(), type_args));
+        }
+        mangled_name
+    }
+
     fn load_definitions<'special>(&mut self, files: &Vec<File<'special>>) {
         for file in files {
             let File {
@@ -69,9 +187,16 @@ impl SpecializeGenerics {
         let Class {
             name,
             type_params,
+            methods,
             ..
         } = class;
 
+        self.generic_methods.extend(
+            methods.iter()
+                .filter(|method| !method.type_params.is_empty())
+                .map(|method| method.name.to_string())
+        );
+
         if type_params.is_empty() {
             return
         }
@@ -280,10 +405,12 @@ impl SpecializeGenerics {
     fn specialize_class_inner<'special>(&mut self, path: &Vec<String>, class: &Class<'special>, name_mod: String) -> Class<'special> {
         let Class {
             name,
+            kind,
             parent,
             mut members,
             methods,
             mut static_members,
+            mut signals,
             span,
             ..
         } = class.clone();
@@ -375,6 +502,10 @@ impl SpecializeGenerics {
             }
         }
 
+        if methods.iter().any(|method| !method.type_params.is_empty()) {
+            self.class_type_arguments.insert((path.clone(), name.to_string()), self.current_type_argument.clone());
+        }
+
         let mut new_methods = Vec::new();
         for method in methods {
             let mut methods = self.specialize_method(path, method);
@@ -388,12 +519,20 @@ impl SpecializeGenerics {
             static_member.value.as_mut().map(|v| self.specialize_expression(path, v));
         }
 
+        for signal in &mut signals {
+            for ty in &mut signal.parameters {
+                self.specialize_type(ty);
+            }
+        }
+
         Class {
             name,
+            kind,
             parent,
             members,
             methods,
             static_members,
+            signals,
             type_params: Vec::new(),
             span
         }
@@ -647,7 +786,7 @@ impl SpecializeGenerics {
                         }
                         Type::Object(name, ..) => {
                             if let Some(ty) = self.current_type_argument.get(name.as_str()) {
-                                match arg {
+                                match ty {
                                     Type::I8 => {
                                         modifier_string.push_str("8");
                                     }
@@ -713,7 +852,7 @@ impl SpecializeGenerics {
                         }
                         Type::Object(name, ..) => {
                             if let Some(ty) = self.current_type_argument.get(name.as_str()) {
-                                match arg {
+                                match ty {
                                     Type::I8 => {
                                         modifier_string.push_str("8");
                                     }
@@ -767,8 +906,13 @@ impl SpecializeGenerics {
     }
 
 
-    /// TODO: add in expanding of methods if they have their own type parameters
+    /// Methods with their own type parameters are left as they are, their instances are generated
+    /// once every call site has been seen
     fn specialize_method<'special>(&mut self, path: &Vec<String>, method: Method<'special>) -> Vec<Method<'special>> {
+        if !method.type_params.is_empty() {
+            return vec![method];
+        }
+
         let Method {
             name,
             is_native,
@@ -781,14 +925,7 @@ impl SpecializeGenerics {
             span
         } = method;
 
-        match &return_type {
-            Type::Object(name, _) => {
-                if let Some(ty) = self.current_type_argument.get(name.as_str()) {
-                    return_type = ty.clone();
-                }
-            }
-            _ => {}
-        }
+        self.specialize_type(&mut return_type);
 
         for parameter in &mut parameters {
             match parameter {
@@ -862,7 +999,71 @@ impl SpecializeGenerics {
                 self.specialize_expression(path, test);
                 self.specialize_body(path, body);
             }
-            x => todo!("specialize statement: {:?}", x),
+            Statement::For {
+                bindings_type,
+                iterable,
+                body,
+                ..
+            } => {
+                self.specialize_type(bindings_type);
+                self.specialize_expression(path, iterable);
+                self.specialize_body(path, body);
+            }
+            Statement::With {
+                bindings_type,
+                expr,
+                block,
+                ..
+            } => {
+                self.specialize_type(bindings_type);
+                self.specialize_expression(path, expr);
+                self.specialize_body(path, block);
+            }
+            Statement::Emit {
+                object,
+                args,
+                ..
+            } => {
+                self.specialize_expression(path, object);
+                for arg in args {
+                    self.specialize_expression(path, arg);
+                }
+            }
+            Statement::EmitStatic {
+                args,
+                ..
+            } => {
+                for arg in args {
+                    self.specialize_expression(path, arg);
+                }
+            }
+            Statement::Connect {
+                source,
+                target,
+                ..
+            } | Statement::Disconnect {
+                source,
+                target,
+                ..
+            } => {
+                self.specialize_expression(path, source);
+                self.specialize_expression(path, target);
+            }
+            Statement::Try {
+                body,
+                catches,
+                finally,
+                ..
+            } => {
+                self.specialize_body(path, body);
+                for catch in catches {
+                    self.specialize_type(&mut catch.ty);
+                    self.specialize_body(path, &mut catch.body);
+                }
+                if let Some(finally) = finally {
+                    self.specialize_body(path, finally);
+                }
+            }
         }
     }
 
@@ -884,21 +1085,37 @@ impl SpecializeGenerics {
                 self.specialize_type(ty);
             }
             Expression::Call {
+                name,
+                type_args,
                 args,
                 annotation,
                 ..
             } => {
-                // TODO: look at type args and functions that have generic parameters
+                self.specialize_expression(path, name.as_mut());
+                if !type_args.is_empty() {
+                    if let Expression::MemberAccess { field, .. } = name.as_mut() {
+                        let method_name = field.segments.last_mut().unwrap();
+                        if self.generic_methods.contains(method_name.as_str()) {
+                                *method_name = Text::Owned(self.instantiate_generic_method(method_name.as_str(), type_args));
+                        }
+                    }
+                }
                 for arg in args {
                     self.specialize_expression(path, arg);
                 }
                 self.specialize_type(annotation);
             }
             Expression::StaticCall {
+                name,
+                type_args,
                 args,
                 annotation,
                 ..
             } => {
+                let method_name = name.segments.last_mut().unwrap();
+                if !type_args.is_empty() && self.generic_methods.contains(method_name.as_str()) {
+                    *method_name = Text::Owned(self.instantiate_generic_method(method_name.as_str(), type_args));
+                }
                 for arg in args {
                     self.specialize_expression(path, arg);
                 }
@@ -937,6 +1154,9 @@ impl SpecializeGenerics {
             Expression::IfExpression(if_expr, ..) => {
                 self.specialize_if(path, if_expr);
             }
+            Expression::MatchExpression(match_expr, ..) => {
+                self.specialize_match(path, match_expr);
+            }
             Expression::UnaryOperation {
                 operand,
                 ..
@@ -954,6 +1174,9 @@ impl SpecializeGenerics {
             Expression::Return(ret, _) => {
                 ret.as_mut().map(|expr| self.specialize_expression(path, expr.as_mut()));
             }
+            Expression::Throw(expr, _) => {
+                self.specialize_expression(path, expr.as_mut());
+            }
             Expression::New(ty, expr, _) => {
                 self.specialize_type(ty);
                 expr.as_mut().map(|expr| self.specialize_expression(path, expr.as_mut()));
@@ -968,6 +1191,7 @@ impl SpecializeGenerics {
             }
             Expression::Literal(_) => {}
             Expression::This(_) => {}
+            Expression::ClassAccess { .. } => {}
             x => todo!("complete specializing generics for: {x:?}"),
         }
     }
@@ -993,6 +1217,43 @@ impl SpecializeGenerics {
         }
     }
 
+    fn specialize_match<'special>(&mut self, path: &Vec<String>, expr: &mut MatchExpression<'special>) {
+        let MatchExpression {
+            value,
+            arms,
+            ty,
+            ..
+        } = expr;
+
+        self.specialize_expression(path, value);
+        self.specialize_type(ty);
+        for arm in arms {
+            self.specialize_pattern(&mut arm.pattern);
+            match &mut arm.value {
+                Either::Left(expr) => self.specialize_expression(path, expr),
+                Either::Right(body) => self.specialize_body(path, body),
+            }
+        }
+    }
+
+    fn specialize_pattern(&mut self, pattern: &mut Pattern) {
+        match pattern {
+            Pattern::Tuple(patterns, _) => {
+                for pattern in patterns {
+                    self.specialize_pattern(pattern);
+                }
+            }
+            Pattern::Class { ty, fields, .. } => {
+                self.specialize_type(ty);
+                for (_, ty, pattern) in fields {
+                    self.specialize_type(ty);
+                    self.specialize_pattern(pattern);
+                }
+            }
+            _ => {}
+        }
+    }
+
     fn specialize_type(&mut self, ty: &mut Type) {
         match ty {
             Type::Object(name, _) => {
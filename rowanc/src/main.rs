@@ -2,12 +2,14 @@ extern crate core;
 
 use std::cmp::Ordering;
 use std::collections::{HashMap, HashSet};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use ariadne::Source;
 use clap::Parser;
 use petgraph::graph::UnGraph;
 use crate::backend::pre_compilation;
-use crate::trees::ast::{File, TopLevelStatement};
+use rowan_shared::package::Package;
+use crate::backend::OutputFile;
+use crate::trees::ast::{File, Parameter, TopLevelStatement};
 
 pub mod backend;
 pub mod parser;
@@ -22,6 +24,18 @@ pub struct Args {
 
     #[arg(short, long)]
     pub stdlib_path: Option<String>,
+
+    /// The directory to write class files and native headers into
+    #[arg(short, long, default_value = "output")]
+    pub output: PathBuf,
+
+    /// Also bundle the class files and any native libraries next to them in the output directory into this package
+    #[arg(short, long)]
+    pub package: Option<PathBuf>,
+
+    /// The class whose main method starts the package, found by looking for a static main method if not given
+    #[arg(long)]
+    pub main_class: Option<String>,
 }
 
 fn explore_directories<P: AsRef<Path>>(path: P, files: &mut Vec<(String, Vec<String>, String, String)>) {
@@ -82,6 +96,71 @@ fn explore_directories_start<P: AsRef<Path>>(path: P, files: &mut Vec<(String, V
     }
 }
 
+/// Finds the class that declares a main method which doesn't take `self`
+fn find_main_class(files: &[File]) -> Option<String> {
+    files.iter().find_map(|file| {
+        file.content.iter().find_map(|statement| {
+            let TopLevelStatement::Class(class) = statement else {
+                return None;
+            };
+            let has_main = class.methods.iter().any(|method| {
+                method.name == "main" && !matches!(method.parameters.first(), Some(Parameter::This(..)))
+            });
+            if !has_main {
+                return None;
+            }
+            let mut path = file.path.segments.iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>();
+            path.push(class.name.to_string());
+            Some(path.join("::"))
+        })
+    })
+}
+
+fn write_output_directory(output: &Path, files: &[OutputFile]) {
+    for file in files {
+        let path = output.join(&file.path);
+        if let Some(parents) = path.parent() {
+            let _ = std::fs::create_dir_all(parents);
+        }
+        let _ = std::fs::remove_file(&path);
+        std::fs::write(&path, &file.bytes).unwrap();
+    }
+}
+
+/// Bundles the class files together with the native libraries that were built next to them in the output directory
+fn write_package(package_path: &Path, main_class: String, output: &Path, files: &[OutputFile]) {
+    let mut package = Package::new(main_class);
+    for file in files.iter().filter(|file| file.is_class_file()) {
+        package.add_entry(file.path.clone(), file.bytes.clone());
+
+        let library_path = file.path.replace(".class", native_library_extension());
+        if let Ok(library) = std::fs::read(output.join(&library_path)) {
+            package.add_entry(library_path, library);
+        }
+    }
+    // The files come out of a hash map, so they are sorted to get the same package from the same sources
+    package.entries.sort_by(|a, b| a.path.cmp(&b.path));
+    if let Some(parents) = package_path.parent() {
+        let _ = std::fs::create_dir_all(parents);
+    }
+    std::fs::write(package_path, package.as_binary()).unwrap();
+}
+
+#[cfg(target_family = "windows")]
+fn native_library_extension() -> &'static str {
+    ".dll"
+}
+#[cfg(target_os = "macos")]
+fn native_library_extension() -> &'static str {
+    ".dylib"
+}
+#[cfg(target_os = "linux")]
+fn native_library_extension() -> &'static str {
+    ".so"
+}
+
 fn main() {
 
     let args = Args::parse();
@@ -147,9 +226,18 @@ fn main() {
 
     let mut typechecker = typechecker::TypeChecker::new();
     let class_files = typechecker.check(class_files).unwrap();
+    let sources = class_files.iter()
+        .map(|(string_path, file, source)| {
+            let module = file.path.segments.iter()
+                .map(|segment| segment.to_string())
+                .collect::<Vec<_>>();
+            (module, format!("{string_path}.rowan"), *source)
+        })
+        .collect::<Vec<_>>();
     let class_files: Vec<File> = class_files.into_iter()
         .map(|(_, file, _)| file)
         .collect();
+    let main_class = args.main_class.clone().or_else(|| find_main_class(&class_files));
     
     let class_files = class_files.into_iter()
         .map(pre_compilation::ir_pass1)
@@ -161,6 +249,18 @@ fn main() {
         .collect::<Vec<_>>();
 
     let mut compiler = backend::Compiler::new();
-    compiler.compile_files(class_files).unwrap();
+    for (module, name, source) in sources {
+        compiler.add_source(module, name, source);
+    }
+    let outputs = compiler.compile_files(class_files).unwrap();
+    write_output_directory(&args.output, &outputs);
+
+    if let Some(package_path) = &args.package {
+        let Some(main_class) = main_class else {
+            eprintln!("Could not find a class with a main method, pass one with --main-class");
+            std::process::exit(1);
+        };
+        write_package(package_path, main_class, &args.output, &outputs);
+    }
 
 }
@@ -45,6 +45,17 @@ pub enum Token<'a> {
     To,
     This,
     Native,
+    // Signals
+    Signal,
+    Emit,
+    Connect,
+    Disconnect,
+    From,
+    // Exceptions
+    Try,
+    Catch,
+    Finally,
+    Throw,
     // Traits
     Trait,
     Impl,
@@ -99,6 +110,22 @@ pub enum Token<'a> {
     Assign,
     Concat,
     Scope,
+    Ampersand,
+    Caret,
+    Tilde,
+    ShiftLeft,
+    ShiftRight,
+    PlusAssign,
+    MinusAssign,
+    MultiplyAssign,
+    DivideAssign,
+    ModuloAssign,
+    ConcatAssign,
+    AmpersandAssign,
+    BarAssign,
+    CaretAssign,
+    ShiftLeftAssign,
+    ShiftRightAssign,
     // Symbols
     BraceOpen,
     BraceClose,
@@ -152,6 +179,15 @@ impl std::fmt::Display for Token<'_> {
             Token::To => write!(f, "to"),
             Token::This => write!(f, "self"),
             Token::Native => write!(f, "native"),
+            Token::Signal => write!(f, "signal"),
+            Token::Emit => write!(f, "emit"),
+            Token::Connect => write!(f, "connect"),
+            Token::Disconnect => write!(f, "disconnect"),
+            Token::From => write!(f, "from"),
+            Token::Try => write!(f, "try"),
+            Token::Catch => write!(f, "catch"),
+            Token::Finally => write!(f, "finally"),
+            Token::Throw => write!(f, "throw"),
             Token::Trait => write!(f, "trait"),
             Token::Impl => write!(f, "impl"),
             Token::Default => write!(f, "default"),
@@ -197,6 +233,22 @@ impl std::fmt::Display for Token<'_> {
             Token::Assign => write!(f, "="),
             Token::Concat => write!(f, "++"),
             Token::Scope => write!(f, "::"),
+            Token::Ampersand => write!(f, "&"),
+            Token::Caret => write!(f, "^"),
+            Token::Tilde => write!(f, "~"),
+            Token::ShiftLeft => write!(f, "<<"),
+            Token::ShiftRight => write!(f, ">>"),
+            Token::PlusAssign => write!(f, "+="),
+            Token::MinusAssign => write!(f, "-="),
+            Token::MultiplyAssign => write!(f, "*="),
+            Token::DivideAssign => write!(f, "/="),
+            Token::ModuloAssign => write!(f, "%="),
+            Token::ConcatAssign => write!(f, "++="),
+            Token::AmpersandAssign => write!(f, "&="),
+            Token::BarAssign => write!(f, "|="),
+            Token::CaretAssign => write!(f, "^="),
+            Token::ShiftLeftAssign => write!(f, "<<="),
+            Token::ShiftRightAssign => write!(f, ">>="),
             Token::BraceOpen => write!(f, "{{"),
             Token::BraceClose => write!(f, "}}"),
             Token::ParenOpen => write!(f, "("),
@@ -298,6 +350,18 @@ impl<'a> TokenLexer<'a> {
         }
     }
 
+    /// Lexes `token` or its compound assignment form if it is followed by `=`
+    fn with_assign(&mut self, token: Token<'a>, assign: Token<'a>, start: usize, end: usize) -> LexerResult<'a> {
+        if let Some((_, '=')) = self.chars.peek() {
+            let Some((end, _)) = self.chars.next() else {
+                panic!("Unexpected end of input");
+            };
+            Ok(SpannedToken::new(assign, start, end))
+        } else {
+            Ok(SpannedToken::new(token, start, end))
+        }
+    }
+
     fn next_token_inner(&mut self) -> LexerResult<'a> {
         let (start, c) = match self.chars.next() {
             Some((start, c)) => (start, c),
@@ -311,9 +375,9 @@ impl<'a> TokenLexer<'a> {
                     let Some((end, _)) = self.chars.next() else {
                         panic!("Unexpected end of input");
                     };
-                    Ok(SpannedToken::new(Token::Concat, start, end))
+                    self.with_assign(Token::Concat, Token::ConcatAssign, start, end)
                 } else {
-                    Ok(SpannedToken::new(Token::Plus, start, start + 1))
+                    self.with_assign(Token::Plus, Token::PlusAssign, start, start + 1)
                 }
             }
             '-' => {
@@ -323,10 +387,10 @@ impl<'a> TokenLexer<'a> {
                     };
                     Ok(SpannedToken::new(Token::Arrow, start, end))
                 } else {
-                    Ok(SpannedToken::new(Token::Minus, start, start + 1))
+                    self.with_assign(Token::Minus, Token::MinusAssign, start, start + 1)
                 }
             }
-            '*' => Ok(SpannedToken::new(Token::Multiply, start, start + 1)),
+            '*' => self.with_assign(Token::Multiply, Token::MultiplyAssign, start, start + 1),
             '/' => {
                 if let Some((_, '/')) = self.chars.peek() {
                     
@@ -356,10 +420,10 @@ impl<'a> TokenLexer<'a> {
                         Err(SpannedLexerError::new(LexerError::UnclosedComment, start, end))
                     }
                 } else {
-                    Ok(SpannedToken::new(Token::Divide, start, start + 1))
+                    self.with_assign(Token::Divide, Token::DivideAssign, start, start + 1)
                 }
             }
-            '%' => Ok(SpannedToken::new(Token::Modulo, start, start + 1)),
+            '%' => self.with_assign(Token::Modulo, Token::ModuloAssign, start, start + 1),
             '!' => {
                 if let Some((_, '=')) = self.chars.peek() {
                     let Some((end, _)) = self.chars.next() else {
@@ -377,7 +441,7 @@ impl<'a> TokenLexer<'a> {
                     };
                     Ok(SpannedToken::new(Token::Or, start, end))
                 } else {
-                    Ok(SpannedToken::new(Token::Bar, start, start + 1))
+                    self.with_assign(Token::Bar, Token::BarAssign, start, start + 1)
                 }
             }
             '&' => {
@@ -387,9 +451,11 @@ impl<'a> TokenLexer<'a> {
                     };
                     Ok(SpannedToken::new(Token::And, start, end))
                 } else {
-                    Err(SpannedLexerError::new(LexerError::UnexpectedCharacter('&'), start, start + 1))
+                    self.with_assign(Token::Ampersand, Token::AmpersandAssign, start, start + 1)
                 }
             }
+            '^' => self.with_assign(Token::Caret, Token::CaretAssign, start, start + 1),
+            '~' => Ok(SpannedToken::new(Token::Tilde, start, start + 1)),
             '=' => {
                 if let Some((_, '=')) = self.chars.peek() {
                     let Some((end, _)) = self.chars.next() else {
@@ -406,6 +472,11 @@ impl<'a> TokenLexer<'a> {
                         panic!("Unexpected end of input");
                     };
                     Ok(SpannedToken::new(Token::LessThanOrEqual, start, end))
+                } else if let Some((_, '<')) = self.chars.peek() {
+                    let Some((end, _)) = self.chars.next() else {
+                        panic!("Unexpected end of input");
+                    };
+                    self.with_assign(Token::ShiftLeft, Token::ShiftLeftAssign, start, end)
                 } else {
                     Ok(SpannedToken::new(Token::AngleOpen, start, start + 1))
                 }
@@ -416,6 +487,11 @@ impl<'a> TokenLexer<'a> {
                         panic!("Unexpected end of input");
                     };
                     Ok(SpannedToken::new(Token::GreaterThanOrEqual, start, end))
+                } else if let Some((_, '>')) = self.chars.peek() {
+                    let Some((end, _)) = self.chars.next() else {
+                        panic!("Unexpected end of input");
+                    };
+                    self.with_assign(Token::ShiftRight, Token::ShiftRightAssign, start, end)
                 } else {
                     Ok(SpannedToken::new(Token::AngleClose, start, start + 1))
                 }
@@ -491,6 +567,15 @@ impl<'a> TokenLexer<'a> {
                     "to" => Ok(SpannedToken::new(Token::To, start, end)),
                     "self" => Ok(SpannedToken::new(Token::This, start, end)),
                     "native" => Ok(SpannedToken::new(Token::Native, start, end)),
+                    "signal" => Ok(SpannedToken::new(Token::Signal, start, end)),
+                    "emit" => Ok(SpannedToken::new(Token::Emit, start, end)),
+                    "connect" => Ok(SpannedToken::new(Token::Connect, start, end)),
+                    "disconnect" => Ok(SpannedToken::new(Token::Disconnect, start, end)),
+                    "from" => Ok(SpannedToken::new(Token::From, start, end)),
+                    "try" => Ok(SpannedToken::new(Token::Try, start, end)),
+                    "catch" => Ok(SpannedToken::new(Token::Catch, start, end)),
+                    "finally" => Ok(SpannedToken::new(Token::Finally, start, end)),
+                    "throw" => Ok(SpannedToken::new(Token::Throw, start, end)),
                     "trait" => Ok(SpannedToken::new(Token::Trait, start, end)),
                     "impl" => Ok(SpannedToken::new(Token::Impl, start, end)),
                     "default" => Ok(SpannedToken::new(Token::Default, start, end)),
@@ -756,6 +841,54 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_signal_keywords() {
+        let input = "signal emit connect to disconnect from";
+        let mut lexer = TokenLexer::new(input);
+        let expected = vec![
+            Token::Signal,
+            Token::Emit,
+            Token::Connect,
+            Token::To,
+            Token::Disconnect,
+            Token::From,
+        ];
+        for token in expected {
+            let result = lexer.next_token().unwrap();
+            assert_eq!(result.token, token);
+        }
+    }
+
+    #[test]
+    fn test_exception_keywords() {
+        let input = "try catch finally throw";
+        let mut lexer = TokenLexer::new(input);
+        let expected = vec![
+            Token::Try,
+            Token::Catch,
+            Token::Finally,
+            Token::Throw,
+        ];
+        for token in expected {
+            let result = lexer.next_token().unwrap();
+            assert_eq!(result.token, token);
+        }
+    }
+
+    #[test]
+    fn test_with_keywords() {
+        let input = "with as";
+        let mut lexer = TokenLexer::new(input);
+        let expected = vec![
+            Token::With,
+            Token::As,
+        ];
+        for token in expected {
+            let result = lexer.next_token().unwrap();
+            assert_eq!(result.token, token);
+        }
+    }
+
     #[test]
     fn test_operators() {
         let input = "+ - * / % ! || && == != <= >= = ++ ::";
@@ -783,6 +916,56 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_bitwise_operators() {
+        let input = "& | ^ ~ << >> && || < >";
+        let mut lexer = TokenLexer::new(input);
+        let expected = vec![
+            Token::Ampersand,
+            Token::Bar,
+            Token::Caret,
+            Token::Tilde,
+            Token::ShiftLeft,
+            Token::ShiftRight,
+            Token::And,
+            Token::Or,
+            Token::AngleOpen,
+            Token::AngleClose,
+        ];
+        for token in expected {
+            let result = lexer.next_token().unwrap();
+            assert_eq!(result.token, token);
+        }
+    }
+
+    #[test]
+    fn test_compound_assignment_operators() {
+        let input = "+= -= *= /= %= ++= &= |= ^= <<= >>= == <= >= -> ++";
+        let mut lexer = TokenLexer::new(input);
+        let expected = vec![
+            Token::PlusAssign,
+            Token::MinusAssign,
+            Token::MultiplyAssign,
+            Token::DivideAssign,
+            Token::ModuloAssign,
+            Token::ConcatAssign,
+            Token::AmpersandAssign,
+            Token::BarAssign,
+            Token::CaretAssign,
+            Token::ShiftLeftAssign,
+            Token::ShiftRightAssign,
+            Token::Equals,
+            Token::LessThanOrEqual,
+            Token::GreaterThanOrEqual,
+            Token::Arrow,
+            Token::Concat,
+        ];
+        for token in expected {
+            let result = lexer.next_token().unwrap();
+            assert_eq!(result.token, token);
+        }
+    }
+
     #[test]
     fn test_symbols() {
         let input = "{ } ( ) [ ] < > , : ? | . ;";
@@ -302,14 +302,16 @@ impl<'a> Annotation<'a> {
 
 #[derive(Debug, Clone, PartialEq, Hash, PartialOrd)]
 pub enum Constraint<'a> {
+    /// `extends A + B`, where each bound is a class the type argument must inherit from
+    /// or a trait it must implement
     Extends(Vec<Type<'a>>, Span),
-    //TODO: add trait bounds
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum UnaryOperator {
     Neg,
     Not,
+    BitNot,
     Try,
 }
 
@@ -330,4 +332,9 @@ pub enum BinaryOperator {
     Ge,
     Concat,
     Index,
+    BitAnd,
+    BitOr,
+    BitXor,
+    Shl,
+    Shr,
 }
\ No newline at end of file
@@ -30,6 +30,8 @@ impl File<'_> {
 pub enum TopLevelStatement<'a> {
     Import(Import<'a>),
     Class(Class<'a>),
+    Record(Record<'a>),
+    Union(Union<'a>),
     Trait(Trait<'a>),
     TraitImpl(TraitImpl<'a>),
 }
@@ -53,6 +55,7 @@ pub struct Class<'a> {
     pub members: Vec<Member<'a>>,
     pub methods: Vec<Method<'a>>,
     pub static_members: Vec<StaticMember<'a>>,
+    pub signals: Vec<Signal<'a>>,
     pub type_params: Vec<TypeParameter<'a>>,
     pub span: Span,
 }
@@ -64,6 +67,7 @@ impl Class<'_> {
         members: Vec<Member<'a>>,
         methods: Vec<Method<'a>>,
         static_members: Vec<StaticMember<'a>>,
+        signals: Vec<Signal<'a>>,
         type_params: Vec<TypeParameter<'a>>,
         span: Span
     ) -> Class<'a> {
@@ -73,12 +77,89 @@ impl Class<'_> {
             members,
             methods,
             static_members,
+            signals,
             type_params,
             span
         }
     }
 }
 
+/// An immutable class that is compared by its fields.
+/// `new Name(a, b)` calls a constructor that takes the members in declaration order.
+#[derive(Debug, Clone, PartialEq, Hash, PartialOrd)]
+pub struct Record<'a> {
+    pub name: Text<'a>,
+    pub members: Vec<Member<'a>>,
+    pub methods: Vec<Method<'a>>,
+    pub type_params: Vec<TypeParameter<'a>>,
+    pub span: Span,
+}
+
+impl Record<'_> {
+    pub fn new<'a>(
+        name: Text<'a>,
+        members: Vec<Member<'a>>,
+        methods: Vec<Method<'a>>,
+        type_params: Vec<TypeParameter<'a>>,
+        span: Span
+    ) -> Record<'a> {
+        Record {
+            name,
+            members,
+            methods,
+            type_params,
+            span
+        }
+    }
+}
+
+/// A tagged sum type.
+/// Each variant becomes a record of its own that extends the union and shares its type parameters.
+#[derive(Debug, Clone, PartialEq, Hash, PartialOrd)]
+pub struct Union<'a> {
+    pub name: Text<'a>,
+    pub variants: Vec<Variant<'a>>,
+    pub methods: Vec<Method<'a>>,
+    pub type_params: Vec<TypeParameter<'a>>,
+    pub span: Span,
+}
+
+impl Union<'_> {
+    pub fn new<'a>(
+        name: Text<'a>,
+        variants: Vec<Variant<'a>>,
+        methods: Vec<Method<'a>>,
+        type_params: Vec<TypeParameter<'a>>,
+        span: Span
+    ) -> Union<'a> {
+        Union {
+            name,
+            variants,
+            methods,
+            type_params,
+            span
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Hash, PartialOrd)]
+pub struct Variant<'a> {
+    pub name: Text<'a>,
+    pub fields: Vec<Member<'a>>,
+    pub span: Span,
+}
+
+impl Variant<'_> {
+    pub fn new<'a>(name: Text<'a>, fields: Vec<Member<'a>>, span: Span) -> Variant<'a> {
+        Variant { name, fields, span }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Hash, PartialOrd)]
+pub enum UnionMember<'a> {
+    Variant(Variant<'a>),
+    Method(Method<'a>),
+}
 
 #[derive(Debug, Clone, PartialEq, Hash, PartialOrd)]
 pub struct ParentDec<'a> {
@@ -93,6 +174,7 @@ pub enum ClassMember<'a> {
     Member(Member<'a>),
     Method(Method<'a>),
     StaticMember(StaticMember<'a>),
+    Signal(Signal<'a>),
 }
 
 #[derive(Debug, Clone, PartialEq, Hash, PartialOrd)]
@@ -155,6 +237,16 @@ pub struct StaticMember<'a> {
 }
 
 
+#[derive(Debug, Clone, PartialEq, Hash, PartialOrd)]
+pub struct Signal<'a> {
+    pub visibility: Visibility,
+    pub is_static: bool,
+    pub name: Text<'a>,
+    pub parameters: Vec<Type<'a>>,
+    pub span: Span,
+}
+
+
 #[derive(Debug, Clone, PartialEq, Hash, PartialOrd)]
 pub struct TypeParameter<'a> {
     pub name: Text<'a>,
@@ -243,6 +335,8 @@ pub enum Statement<'a> {
     },
     Assignment {
         target: Expression<'a>,
+        /// The operator of a compound assignment like `+=`
+        operator: Option<BinaryOperator>,
         value: Expression<'a>,
         span: Span,
     },
@@ -257,6 +351,7 @@ pub enum Statement<'a> {
         bindings: Pattern<'a>,
         bindings_type: Type<'a>,
         iterable: Expression<'a>,
+        body: Vec<Statement<'a>>,
         span: Span,
     },
     With {
@@ -265,7 +360,38 @@ pub enum Statement<'a> {
         bindings_type: Type<'a>,
         block: Vec<Statement<'a>>,
         span: Span,
-    }
+    },
+    Emit {
+        object: Expression<'a>,
+        signal: PathName<'a>,
+        args: Vec<Expression<'a>>,
+        span: Span,
+    },
+    EmitStatic {
+        signal: PathName<'a>,
+        args: Vec<Expression<'a>>,
+        span: Span,
+    },
+    Connect {
+        source: Expression<'a>,
+        signal: PathName<'a>,
+        target: Expression<'a>,
+        method: PathName<'a>,
+        span: Span,
+    },
+    Disconnect {
+        source: Expression<'a>,
+        signal: PathName<'a>,
+        target: Expression<'a>,
+        method: PathName<'a>,
+        span: Span,
+    },
+    Try {
+        body: Vec<Statement<'a>>,
+        catches: Vec<CatchClause<'a>>,
+        finally: Option<Vec<Statement<'a>>>,
+        span: Span,
+    },
 }
 
 impl Statement<'_> {
@@ -290,6 +416,21 @@ impl Statement<'_> {
     pub fn new_assignment<'a>(target: Expression<'a>, value: Expression<'a>, span: Span) -> Statement<'a> {
         Statement::Assignment {
             target,
+            operator: None,
+            value,
+            span
+        }
+    }
+
+    pub fn new_compound_assignment<'a>(
+        target: Expression<'a>,
+        operator: BinaryOperator,
+        value: Expression<'a>,
+        span: Span,
+    ) -> Statement<'a> {
+        Statement::Assignment {
+            target,
+            operator: Some(operator),
             value,
             span
         }
@@ -314,6 +455,7 @@ impl Statement<'_> {
         bindings: Pattern<'a>,
         bindings_type: Type<'a>,
         iterable: Expression<'a>,
+        body: Vec<Statement<'a>>,
         span: Span,
     ) -> Statement<'a> {
         Statement::For {
@@ -321,6 +463,7 @@ impl Statement<'_> {
             bindings,
             bindings_type,
             iterable,
+            body,
             span
         }
     }
@@ -341,6 +484,93 @@ impl Statement<'_> {
         }
     }
 
+    pub fn new_emit<'a>(
+        object: Expression<'a>,
+        signal: PathName<'a>,
+        args: Vec<Expression<'a>>,
+        span: Span,
+    ) -> Statement<'a> {
+        Statement::Emit {
+            object,
+            signal,
+            args,
+            span
+        }
+    }
+
+    pub fn new_emit_static<'a>(
+        signal: PathName<'a>,
+        args: Vec<Expression<'a>>,
+        span: Span,
+    ) -> Statement<'a> {
+        Statement::EmitStatic {
+            signal,
+            args,
+            span
+        }
+    }
+
+    pub fn new_connect<'a>(
+        source: Expression<'a>,
+        signal: PathName<'a>,
+        target: Expression<'a>,
+        method: PathName<'a>,
+        span: Span,
+    ) -> Statement<'a> {
+        Statement::Connect {
+            source,
+            signal,
+            target,
+            method,
+            span
+        }
+    }
+
+    pub fn new_disconnect<'a>(
+        source: Expression<'a>,
+        signal: PathName<'a>,
+        target: Expression<'a>,
+        method: PathName<'a>,
+        span: Span,
+    ) -> Statement<'a> {
+        Statement::Disconnect {
+            source,
+            signal,
+            target,
+            method,
+            span
+        }
+    }
+
+    pub fn new_try<'a>(
+        body: Vec<Statement<'a>>,
+        catches: Vec<CatchClause<'a>>,
+        finally: Option<Vec<Statement<'a>>>,
+        span: Span,
+    ) -> Statement<'a> {
+        Statement::Try {
+            body,
+            catches,
+            finally,
+            span
+        }
+    }
+
+}
+
+/// A handler for exceptions of `ty` that binds the caught exception to `binding`
+#[derive(Debug, Clone, PartialEq, Hash, PartialOrd)]
+pub struct CatchClause<'a> {
+    pub binding: Text<'a>,
+    pub ty: Type<'a>,
+    pub body: Vec<Statement<'a>>,
+    pub span: Span,
+}
+
+impl CatchClause<'_> {
+    pub fn new<'a>(binding: Text<'a>, ty: Type<'a>, body: Vec<Statement<'a>>, span: Span) -> CatchClause<'a> {
+        CatchClause { binding, ty, body, span }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Hash, PartialOrd)]
@@ -349,6 +579,32 @@ pub enum Pattern<'a> {
     Tuple(Vec<Pattern<'a>>, Span),
     Constant(Constant<'a>),
     WildCard(Span),
+    /// Matches objects of a class, destructuring the fields of records and union variants
+    Class {
+        name: Text<'a>,
+        fields: Vec<Pattern<'a>>,
+        /// The type being tested for, filled in by the typechecker
+        ty: Option<Type<'a>>,
+        /// The names and types of the destructured fields in declaration order, filled in by the typechecker
+        members: Vec<(Text<'a>, Type<'a>)>,
+        span: Span,
+    },
+}
+
+impl Pattern<'_> {
+    pub fn new_class<'a>(name: Text<'a>, fields: Vec<Pattern<'a>>, span: Span) -> Pattern<'a> {
+        Pattern::Class { name, fields, ty: None, members: Vec::new(), span }
+    }
+
+    pub fn get_span(&self) -> Span {
+        match self {
+            Pattern::Variable(_, _, span) => *span,
+            Pattern::Tuple(_, span) => *span,
+            Pattern::Constant(constant) => constant.get_span(),
+            Pattern::WildCard(span) => *span,
+            Pattern::Class { span, .. } => *span,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Hash, PartialOrd)]
@@ -360,6 +616,18 @@ pub enum Constant<'a> {
     Bool(bool, Span),
 }
 
+impl Constant<'_> {
+    pub fn get_span(&self) -> Span {
+        match self {
+            Constant::Integer(_, _, span) => *span,
+            Constant::Float(_, _, span) => *span,
+            Constant::String(_, span) => *span,
+            Constant::Character(_, span) => *span,
+            Constant::Bool(_, span) => *span,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Hash, PartialOrd)]
 pub enum Expression<'a> {
     Variable(Text<'a>, Option<Type<'a>>, Span),
@@ -418,6 +686,7 @@ pub enum Expression<'a> {
     },
     Continue(Option<Text<'a>>, Span),
     Break(Option<Text<'a>>, Option<Box<Expression<'a>>>, Span),
+    Throw(Box<Expression<'a>>, Span),
     As {
         source: Box<Expression<'a>>,
         typ: Type<'a>,
@@ -461,6 +730,15 @@ impl Expression<'_> {
         }
     }
 
+    /// `new Type(args)` is a call to the `new` method of a fresh object.
+    /// Since `new` is a keyword, only records and union variants can have that method.
+    pub fn new_constructor_call<'a>(ty: Type<'a>, args: Vec<Expression<'a>>, span: Span) -> Expression<'a> {
+        let object = Expression::New(ty, None, span);
+        let field = PathName::new(vec![Text::Borrowed("new")], span);
+        let callee = Expression::new_member_access(Box::new(object), field, span);
+        Expression::new_call(Box::new(callee), Vec::new(), args, span)
+    }
+
     pub fn new_member_access<'a>(object: Box<Expression<'a>>, field: PathName<'a>, span: Span) -> Expression<'a> {
         Expression::MemberAccess {
             object,
@@ -581,9 +859,23 @@ impl Expression<'_> {
             Expression::BinaryOperation { operator: BinaryOperator::Sub, left,  .. } |
             Expression::BinaryOperation { operator: BinaryOperator::Mul, left,  .. } |
             Expression::BinaryOperation { operator: BinaryOperator::Div, left,  .. } |
-            Expression::BinaryOperation { operator: BinaryOperator::Mod, left,  .. } => {
+            Expression::BinaryOperation { operator: BinaryOperator::Mod, left,  .. } |
+            Expression::BinaryOperation { operator: BinaryOperator::BitAnd, left,  .. } |
+            Expression::BinaryOperation { operator: BinaryOperator::BitOr, left,  .. } |
+            Expression::BinaryOperation { operator: BinaryOperator::BitXor, left,  .. } |
+            Expression::BinaryOperation { operator: BinaryOperator::Shl, left,  .. } |
+            Expression::BinaryOperation { operator: BinaryOperator::Shr, left,  .. } => {
                 left.get_type()
             }
+            Expression::UnaryOperation { operator: UnaryOperator::BitNot, operand, .. } => {
+                operand.get_type()
+            }
+            Expression::BinaryOperation { operator: BinaryOperator::Index, left, .. } => {
+                match left.get_type()? {
+                    Either::Left(Type::Array(inner, _)) => Some(Either::Left(*inner)),
+                    x => todo!("Expression::get_type of indexing into {:?}", x),
+                }
+            }
             Expression::Call {annotation, ..} => {
                 annotation.clone().map(|t| Either::Left(t))
             }
@@ -597,6 +889,10 @@ impl Expression<'_> {
             Expression::StaticCall { annotation, ..} => {
                 annotation.clone().map(|t| Either::Left(t))
             }
+            Expression::New(ty, None, _) => Some(Either::Left(ty.clone())),
+            Expression::MatchExpression(match_expr, _) => {
+                match_expr.annotation.clone().map(|t| Either::Left(t))
+            }
             x => todo!("Expression::get_type {:?}", x),
         }
     }
@@ -709,6 +1005,8 @@ impl IfExpression<'_> {
 pub struct MatchExpression<'a> {
     pub value: Box<Expression<'a>>,
     pub arms: Vec<MatchArm<'a>>,
+    /// The type the match evaluates to, filled in by the typechecker
+    pub annotation: Option<Type<'a>>,
     pub start: usize,
     pub end: usize,
 }
@@ -720,7 +1018,7 @@ impl MatchExpression<'_> {
         start: usize,
         end: usize,
     ) -> MatchExpression<'a> {
-        MatchExpression { value, arms, start, end }
+        MatchExpression { value, arms, annotation: None, start, end }
     }
 }
 
@@ -1,6 +1,7 @@
 use either::Either;
 use crate::trees::{Annotation, BinaryOperator, Constraint, PathName, Text, Type, UnaryOperator};
 use crate::trees::{Span, Visibility};
+use rowan_shared::classfile::ClassKind;
 
 
 
@@ -49,10 +50,12 @@ impl Import<'_> {
 #[derive(Debug, Clone, PartialEq, Hash, PartialOrd)]
 pub struct Class<'a> {
     pub name: Text<'a>,
+    pub kind: ClassKind,
     pub parent: Option<ParentDec<'a>>,
     pub members: Vec<Member<'a>>,
     pub methods: Vec<Method<'a>>,
     pub static_members: Vec<StaticMember<'a>>,
+    pub signals: Vec<Signal<'a>>,
     pub type_params: Vec<TypeParameter<'a>>,
     pub span: Span,
 }
@@ -60,19 +63,23 @@ pub struct Class<'a> {
 impl Class<'_> {
     pub fn new<'a>(
         name: Text<'a>,
+        kind: ClassKind,
         parent: Option<ParentDec<'a>>,
         members: Vec<Member<'a>>,
         methods: Vec<Method<'a>>,
         static_members: Vec<StaticMember<'a>>,
+        signals: Vec<Signal<'a>>,
         type_params: Vec<TypeParameter<'a>>,
         span: Span
     ) -> Class<'a> {
         Class {
             name,
+            kind,
             parent,
             members,
             methods,
             static_members,
+            signals,
             type_params,
             span
         }
@@ -93,6 +100,7 @@ pub enum ClassMember<'a> {
     Member(Member<'a>),
     Method(Method<'a>),
     StaticMember(StaticMember<'a>),
+    Signal(Signal<'a>),
 }
 
 #[derive(Debug, Clone, PartialEq, Hash, PartialOrd)]
@@ -153,6 +161,16 @@ pub struct StaticMember<'a> {
 }
 
 
+#[derive(Debug, Clone, PartialEq, Hash, PartialOrd)]
+pub struct Signal<'a> {
+    pub visibility: Visibility,
+    pub is_static: bool,
+    pub name: Text<'a>,
+    pub parameters: Vec<Type<'a>>,
+    pub span: Span,
+}
+
+
 #[derive(Debug, Clone, PartialEq, Hash, PartialOrd)]
 pub struct TypeParameter<'a> {
     pub name: Text<'a>,
@@ -241,6 +259,8 @@ pub enum Statement<'a> {
     },
     Assignment {
         target: Expression<'a>,
+        /// The operator of a compound assignment like `+=`
+        operator: Option<BinaryOperator>,
         value: Expression<'a>,
         span: Span,
     },
@@ -255,6 +275,7 @@ pub enum Statement<'a> {
         bindings: Pattern<'a>,
         bindings_type: Type<'a>,
         iterable: Expression<'a>,
+        body: Vec<Statement<'a>>,
         span: Span,
     },
     With {
@@ -263,7 +284,38 @@ pub enum Statement<'a> {
         bindings_type: Type<'a>,
         block: Vec<Statement<'a>>,
         span: Span,
-    }
+    },
+    Emit {
+        object: Expression<'a>,
+        signal: PathName<'a>,
+        args: Vec<Expression<'a>>,
+        span: Span,
+    },
+    EmitStatic {
+        signal: PathName<'a>,
+        args: Vec<Expression<'a>>,
+        span: Span,
+    },
+    Connect {
+        source: Expression<'a>,
+        signal: PathName<'a>,
+        target: Expression<'a>,
+        method: PathName<'a>,
+        span: Span,
+    },
+    Disconnect {
+        source: Expression<'a>,
+        signal: PathName<'a>,
+        target: Expression<'a>,
+        method: PathName<'a>,
+        span: Span,
+    },
+    Try {
+        body: Vec<Statement<'a>>,
+        catches: Vec<CatchClause<'a>>,
+        finally: Option<Vec<Statement<'a>>>,
+        span: Span,
+    },
 }
 
 impl Statement<'_> {
@@ -288,6 +340,21 @@ impl Statement<'_> {
     pub fn new_assignment<'a>(target: Expression<'a>, value: Expression<'a>, span: Span) -> Statement<'a> {
         Statement::Assignment {
             target,
+            operator: None,
+            value,
+            span
+        }
+    }
+
+    pub fn new_compound_assignment<'a>(
+        target: Expression<'a>,
+        operator: BinaryOperator,
+        value: Expression<'a>,
+        span: Span,
+    ) -> Statement<'a> {
+        Statement::Assignment {
+            target,
+            operator: Some(operator),
             value,
             span
         }
@@ -312,6 +379,7 @@ impl Statement<'_> {
         bindings: Pattern<'a>,
         bindings_type: Type<'a>,
         iterable: Expression<'a>,
+        body: Vec<Statement<'a>>,
         span: Span,
     ) -> Statement<'a> {
         Statement::For {
@@ -319,6 +387,7 @@ impl Statement<'_> {
             bindings,
             bindings_type,
             iterable,
+            body,
             span
         }
     }
@@ -339,6 +408,110 @@ impl Statement<'_> {
         }
     }
 
+    pub fn new_emit<'a>(
+        object: Expression<'a>,
+        signal: PathName<'a>,
+        args: Vec<Expression<'a>>,
+        span: Span,
+    ) -> Statement<'a> {
+        Statement::Emit {
+            object,
+            signal,
+            args,
+            span
+        }
+    }
+
+    pub fn new_emit_static<'a>(
+        signal: PathName<'a>,
+        args: Vec<Expression<'a>>,
+        span: Span,
+    ) -> Statement<'a> {
+        Statement::EmitStatic {
+            signal,
+            args,
+            span
+        }
+    }
+
+    pub fn new_connect<'a>(
+        source: Expression<'a>,
+        signal: PathName<'a>,
+        target: Expression<'a>,
+        method: PathName<'a>,
+        span: Span,
+    ) -> Statement<'a> {
+        Statement::Connect {
+            source,
+            signal,
+            target,
+            method,
+            span
+        }
+    }
+
+    pub fn new_disconnect<'a>(
+        source: Expression<'a>,
+        signal: PathName<'a>,
+        target: Expression<'a>,
+        method: PathName<'a>,
+        span: Span,
+    ) -> Statement<'a> {
+        Statement::Disconnect {
+            source,
+            signal,
+            target,
+            method,
+            span
+        }
+    }
+
+    pub fn new_try<'a>(
+        body: Vec<Statement<'a>>,
+        catches: Vec<CatchClause<'a>>,
+        finally: Option<Vec<Statement<'a>>>,
+        span: Span,
+    ) -> Statement<'a> {
+        Statement::Try {
+            body,
+            catches,
+            finally,
+            span
+        }
+    }
+
+    pub fn get_span(&self) -> Span {
+        match self {
+            Statement::Expression(_, span) => *span,
+            Statement::Let { span, .. } => *span,
+            Statement::Const { span, .. } => *span,
+            Statement::Assignment { span, .. } => *span,
+            Statement::While { span, .. } => *span,
+            Statement::For { span, .. } => *span,
+            Statement::With { span, .. } => *span,
+            Statement::Emit { span, .. } => *span,
+            Statement::EmitStatic { span, .. } => *span,
+            Statement::Connect { span, .. } => *span,
+            Statement::Disconnect { span, .. } => *span,
+            Statement::Try { span, .. } => *span,
+        }
+    }
+
+}
+
+/// A handler for exceptions of `ty` that binds the caught exception to `binding`
+#[derive(Debug, Clone, PartialEq, Hash, PartialOrd)]
+pub struct CatchClause<'a> {
+    pub binding: Text<'a>,
+    pub ty: Type<'a>,
+    pub body: Vec<Statement<'a>>,
+    pub span: Span,
+}
+
+impl CatchClause<'_> {
+    pub fn new<'a>(binding: Text<'a>, ty: Type<'a>, body: Vec<Statement<'a>>, span: Span) -> CatchClause<'a> {
+        CatchClause { binding, ty, body, span }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Hash, PartialOrd)]
@@ -347,6 +520,12 @@ pub enum Pattern<'a> {
     Tuple(Vec<Pattern<'a>>, Span),
     Constant(Constant<'a>),
     WildCard(Span),
+    /// Matches objects of a class, the fields are the name and type of each destructured field with its pattern
+    Class {
+        ty: Type<'a>,
+        fields: Vec<(Text<'a>, Type<'a>, Pattern<'a>)>,
+        span: Span,
+    },
 }
 
 #[derive(Debug, Clone, PartialEq, Hash, PartialOrd)]
@@ -358,6 +537,18 @@ pub enum Constant<'a> {
     Bool(bool, Span),
 }
 
+impl Constant<'_> {
+    pub fn get_span(&self) -> Span {
+        match self {
+            Constant::Integer(_, _, span) => *span,
+            Constant::Float(_, _, span) => *span,
+            Constant::String(_, span) => *span,
+            Constant::Character(_, span) => *span,
+            Constant::Bool(_, span) => *span,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Hash, PartialOrd)]
 pub enum Expression<'a> {
     Variable(Text<'a>, Type<'a>, Span),
@@ -418,6 +609,7 @@ pub enum Expression<'a> {
     },
     Continue(Option<Text<'a>>, Span),
     Break(Option<Text<'a>>, Option<Box<Expression<'a>>>, Span),
+    Throw(Box<Expression<'a>>, Span),
     As {
         source: Box<Expression<'a>>,
         typ: Type<'a>,
@@ -554,8 +746,10 @@ impl Expression<'_> {
             span
         }
     }
+}
 
-    pub fn get_type(&self) -> Either<Type, ()> {
+impl<'a> Expression<'a> {
+    pub fn get_type(&self) -> Either<Type<'a>, ()> {
         match self {
             Expression::As {typ, ..} => Either::Left(typ.clone()),
             Expression::Into {typ, ..} => Either::Left(typ.clone()),
@@ -590,9 +784,33 @@ impl Expression<'_> {
             Expression::BinaryOperation { operator: BinaryOperator::Sub, left,  .. } |
             Expression::BinaryOperation { operator: BinaryOperator::Mul, left,  .. } |
             Expression::BinaryOperation { operator: BinaryOperator::Div, left,  .. } |
-            Expression::BinaryOperation { operator: BinaryOperator::Mod, left,  .. } => {
+            Expression::BinaryOperation { operator: BinaryOperator::Mod, left,  .. } |
+            Expression::BinaryOperation { operator: BinaryOperator::BitAnd, left,  .. } |
+            Expression::BinaryOperation { operator: BinaryOperator::BitOr, left,  .. } |
+            Expression::BinaryOperation { operator: BinaryOperator::BitXor, left,  .. } |
+            Expression::BinaryOperation { operator: BinaryOperator::Shl, left,  .. } |
+            Expression::BinaryOperation { operator: BinaryOperator::Shr, left,  .. } => {
                 left.get_type()
             }
+            Expression::UnaryOperation { operator: UnaryOperator::BitNot, operand, .. } => {
+                operand.get_type()
+            }
+            Expression::BinaryOperation { operator: BinaryOperator::Index, left, .. } => {
+                match left.get_type() {
+                    Either::Left(Type::Array(inner, _)) => Either::Left(*inner),
+                    x => todo!("Expression::get_type of indexing into {:?}", x),
+                }
+            }
+            Expression::BinaryOperation { operator: BinaryOperator::Eq, .. } |
+            Expression::BinaryOperation { operator: BinaryOperator::Ne, .. } |
+            Expression::BinaryOperation { operator: BinaryOperator::Lt, .. } |
+            Expression::BinaryOperation { operator: BinaryOperator::Le, .. } |
+            Expression::BinaryOperation { operator: BinaryOperator::Gt, .. } |
+            Expression::BinaryOperation { operator: BinaryOperator::Ge, .. } |
+            Expression::BinaryOperation { operator: BinaryOperator::And, .. } |
+            Expression::BinaryOperation { operator: BinaryOperator::Or, .. } => {
+                Either::Left(Type::U8)
+            }
             Expression::Call {annotation, ..} => {
                 Either::Left(annotation.clone())
             }
@@ -606,10 +824,14 @@ impl Expression<'_> {
             Expression::StaticCall { annotation, ..} => {
                 Either::Left(annotation.clone())
             }
+            Expression::New(ty, None, _) => Either::Left(ty.clone()),
+            Expression::MatchExpression(match_expr, _) => Either::Left(match_expr.ty.clone()),
             x => todo!("Expression::get_type {:?}", x),
         }
     }
-    
+}
+
+impl Expression<'_> {
     pub fn is_closure(&self) -> bool {
         match self {
             Expression::Closure { .. } => true,
@@ -666,6 +888,8 @@ impl IfExpression<'_> {
 pub struct MatchExpression<'a> {
     pub value: Box<Expression<'a>>,
     pub arms: Vec<MatchArm<'a>>,
+    /// The type the match evaluates to, void if the arms don't produce a value
+    pub ty: Type<'a>,
     pub start: usize,
     pub end: usize,
 }
@@ -674,10 +898,11 @@ impl MatchExpression<'_> {
     pub fn new<'a>(
         value: Box<Expression<'a>>,
         arms: Vec<MatchArm<'a>>,
+        ty: Type<'a>,
         start: usize,
         end: usize,
     ) -> MatchExpression<'a> {
-        MatchExpression { value, arms, start, end }
+        MatchExpression { value, arms, ty, start, end }
     }
 }
 
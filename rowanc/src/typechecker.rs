@@ -4,13 +4,23 @@ use std::collections::HashSet;
 use ariadne::{Label, Report, ReportKind, Source};
 use either::Either;
 use itertools::Itertools;
-use crate::trees::ast::{Class, ClosureParameter, Constant, Expression, File, IfExpression, Literal, Method, Parameter, ParentDec, Pattern, Statement, StaticMember, TopLevelStatement, Trait, TraitImpl};
-use crate::trees::{BinaryOperator, PathName, Span, Text, Type, UnaryOperator};
+use crate::trees::ast::{CatchClause, Class, ClosureParameter, Constant, Expression, File, IfExpression, Import, Literal, Member, Method, Parameter, ParentDec, Pattern, Record, Signal, Statement, StaticMember, TopLevelStatement, Trait, TraitImpl, TypeParameter, Union, Variant};
+use crate::trees::{Annotation, BinaryOperator, Constraint, PathName, Span, Text, Type, UnaryOperator};
+use crate::trees::ast::{MatchArm, MatchExpression};
+use exhaustiveness::{Constructor, Space};
+
+mod exhaustiveness;
 
 fn create_stdlib<'a>() -> HashMap<Vec<String>, (String, HashMap<String, ClassAttribute>)> {
     let mut info = HashMap::new();
     let mut object_attributes = HashMap::new();
     object_attributes.insert("downcast".to_string(), ClassAttribute::Method(TypeCheckerType::Function(vec![], Box::new(TypeCheckerType::Object(String::from("Object"))))));
+    object_attributes.insert("try-downcast".to_string(), ClassAttribute::Method(TypeCheckerType::Function(vec![], Box::new(TypeCheckerType::TypeArg(Box::new(TypeCheckerType::Object(String::from("Option"))), vec![TypeCheckerType::Object(String::from("Object"))])))));
+    object_attributes.insert("tick".to_string(), ClassAttribute::Method(TypeCheckerType::Function(vec![TypeCheckerType::F64], Box::new(TypeCheckerType::Void))));
+    object_attributes.insert("ready".to_string(), ClassAttribute::Method(TypeCheckerType::Function(vec![], Box::new(TypeCheckerType::Void))));
+    object_attributes.insert("add-child".to_string(), ClassAttribute::Method(TypeCheckerType::Function(vec![TypeCheckerType::Object(String::from("Object"))], Box::new(TypeCheckerType::Void))));
+    object_attributes.insert("remove-child".to_string(), ClassAttribute::Method(TypeCheckerType::Function(vec![TypeCheckerType::Object(String::from("Object"))], Box::new(TypeCheckerType::Void))));
+    object_attributes.insert("root".to_string(), ClassAttribute::Method(TypeCheckerType::Function(vec![], Box::new(TypeCheckerType::Object(String::from("Object"))))));
 
     info.insert(vec!["Object".to_string()], (String::new(), object_attributes));
 
@@ -51,6 +61,25 @@ fn create_stdlib<'a>() -> HashMap<Vec<String>, (String, HashMap<String, ClassAtt
 
     info.insert(vec!["InternedString".to_string()], (String::from("String"), interned_string_attributes));
 
+    let mut exception_attributes = HashMap::new();
+    exception_attributes.insert(String::from("message"), ClassAttribute::Member(TypeCheckerType::Object(String::from("String"))));
+    exception_attributes.insert(String::from("init"), ClassAttribute::Method(TypeCheckerType::Function(vec![TypeCheckerType::Object(String::from("String"))], Box::new(TypeCheckerType::Void))));
+    exception_attributes.insert(String::from("new"), ClassAttribute::Method(TypeCheckerType::Function(vec![TypeCheckerType::Object(String::from("String"))], Box::new(TypeCheckerType::Object(String::from("Exception"))))));
+
+    info.insert(vec!["Exception".to_string()], (String::from("Object"), exception_attributes));
+
+    let mut index_out_of_bounds_attributes = HashMap::new();
+    index_out_of_bounds_attributes.insert(String::from("init"), ClassAttribute::Method(TypeCheckerType::Function(vec![TypeCheckerType::U64, TypeCheckerType::U64], Box::new(TypeCheckerType::Void))));
+    index_out_of_bounds_attributes.insert(String::from("new"), ClassAttribute::Method(TypeCheckerType::Function(vec![TypeCheckerType::U64, TypeCheckerType::U64], Box::new(TypeCheckerType::Object(String::from("IndexOutOfBounds"))))));
+
+    info.insert(vec!["IndexOutOfBounds".to_string()], (String::from("Exception"), index_out_of_bounds_attributes));
+
+    let mut null_pointer_attributes = HashMap::new();
+    null_pointer_attributes.insert(String::from("init"), ClassAttribute::Method(TypeCheckerType::Function(vec![], Box::new(TypeCheckerType::Void))));
+    null_pointer_attributes.insert(String::from("new"), ClassAttribute::Method(TypeCheckerType::Function(vec![], Box::new(TypeCheckerType::Object(String::from("NullPointerException"))))));
+
+    info.insert(vec!["NullPointerException".to_string()], (String::from("Exception"), null_pointer_attributes));
+
 
     info
 }
@@ -135,7 +164,108 @@ pub enum TypeCheckerError {
         expected: String,
         found: String,
         location: Span,
-    }
+    },
+    UnknownSignal {
+        current_file: String,
+        class: String,
+        signal: String,
+        location: Span,
+    },
+    ImmutableField {
+        current_file: String,
+        record: String,
+        field: String,
+        location: Span,
+    },
+    WrongNumberOfFields {
+        current_file: String,
+        class: String,
+        expected: usize,
+        found: usize,
+        location: Span,
+    },
+    NonExhaustiveMatch {
+        current_file: String,
+        missing: String,
+        location: Span,
+    },
+    UnreachableMatchArm {
+        current_file: String,
+        location: Span,
+    },
+    /// A `let` or `with` whose pattern doesn't match every value of its type
+    RefutablePattern {
+        current_file: String,
+        missing: String,
+        location: Span,
+    },
+    StringPattern {
+        current_file: String,
+        location: Span,
+    },
+    /// Only variables, fields and array elements can be assigned to
+    InvalidAssignmentTarget {
+        current_file: String,
+        location: Span,
+    },
+    NotIterable {
+        current_file: String,
+        ty: String,
+        location: Span,
+    },
+    NotCloseable {
+        current_file: String,
+        ty: String,
+        location: Span,
+    },
+    TupleFieldOutOfRange {
+        current_file: String,
+        ty: String,
+        field: String,
+        location: Span,
+    },
+    NotTryable {
+        current_file: String,
+        ty: String,
+        location: Span,
+    },
+    MismatchedTryReturn {
+        current_file: String,
+        ty: String,
+        return_type: String,
+        location: Span,
+    },
+    MismatchedTypeArgumentCount {
+        current_file: String,
+        method: String,
+        expected: usize,
+        found: usize,
+        location: Span,
+    },
+    CannotInferTypeArgument {
+        current_file: String,
+        method: String,
+        type_param: String,
+        location: Span,
+    },
+    UnsatisfiedBound {
+        current_file: String,
+        ty: String,
+        type_param: String,
+        bound: String,
+        location: Span,
+    },
+    MissingBoundAttribute {
+        current_file: String,
+        type_param: String,
+        attribute: String,
+        location: Span,
+    },
+    MissingConstructor {
+        current_file: String,
+        class: String,
+        location: Span,
+    },
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -299,6 +429,8 @@ pub enum ClassAttribute {
     Member(TypeCheckerType),
     Method(TypeCheckerType),
     StaticMember(TypeCheckerType),
+    Signal(Vec<TypeCheckerType>),
+    StaticSignal(Vec<TypeCheckerType>),
 }
 
 pub struct Frame {
@@ -321,6 +453,14 @@ impl Frame {
     }
 }
 
+/// The fields of a record or union variant in declaration order, which is the order patterns destructure them in
+struct RecordInformation {
+    type_params: Vec<String>,
+    fields: Vec<(String, TypeCheckerType)>,
+    /// The path of the union if this is a variant
+    union: Option<Vec<String>>,
+}
+
 pub struct TypeChecker {
     /// A mapping of a path to a pair
     /// The pair is the parent of the class from the path, and a map of attribute name to attributes
@@ -335,6 +475,16 @@ pub struct TypeChecker {
     current_class: Vec<String>,
     active_paths: HashMap<String, Vec<String>>,
     active_module: Vec<String>,
+    /// Records and union variants, whose fields can't be assigned to
+    records: HashMap<Vec<String>, RecordInformation>,
+    /// A mapping of a union's path to the paths of its variants
+    unions: HashMap<Vec<String>, Vec<Vec<String>>>,
+    /// The type parameters of generic methods and their bounds, keyed by the path of the class followed by the method name
+    method_type_params: HashMap<Vec<String>, Vec<(String, Vec<TypeCheckerType>)>>,
+    /// The type parameters of generic classes and their bounds
+    class_type_params: HashMap<Vec<String>, Vec<(String, Vec<TypeCheckerType>)>>,
+    /// The bounds of the type parameters that are in scope
+    type_param_bounds: HashMap<String, Vec<TypeCheckerType>>,
     current_function_sig_span: Span,
     collected_errors: Vec<TypeCheckerError>,
     current_path: String,
@@ -349,6 +499,11 @@ impl TypeChecker {
             current_class: Vec::new(),
             active_paths: HashMap::new(),
             active_module: Vec::new(),
+            records: HashMap::new(),
+            unions: HashMap::new(),
+            method_type_params: HashMap::new(),
+            class_type_params: HashMap::new(),
+            type_param_bounds: HashMap::new(),
             trait_decl: HashMap::new(),
             trait_impls: HashMap::new(),
             current_function_sig_span: Span::new(0, 0),
@@ -403,7 +558,458 @@ impl TypeChecker {
         }))
     }
 
+    /// Looks up an attribute on a class, falling back to the attributes of its parents
+    fn get_inherited_attribute(&self, class: &[String], attribute: &str) -> Option<&ClassAttribute> {
+        let mut path = class.to_vec();
+        loop {
+            let (parent, attributes) = self.class_information.get(&path)?;
+            if let Some(attribute) = attributes.get(attribute) {
+                return Some(attribute);
+            }
+            let parent_path = self.attach_module_if_needed(parent.clone());
+            if parent_path == path {
+                return None;
+            }
+            path = parent_path;
+        }
+    }
+
+    /// Finds the type parameters of a generic method, looking through the parents of the class
+    fn get_method_type_params(&self, class: &[String], method: &str) -> Option<Vec<(String, Vec<TypeCheckerType>)>> {
+        let mut path = class.to_vec();
+        loop {
+            let mut key = path.clone();
+            key.push(method.to_string());
+            if let Some(type_params) = self.method_type_params.get(&key) {
+                return Some(type_params.clone());
+            }
+            let (parent, _) = self.class_information.get(&path)?;
+            let parent_path = self.attach_module_if_needed(parent.clone());
+            if parent_path == path {
+                return None;
+            }
+            path = parent_path;
+        }
+    }
+
+    /// Binds the type parameters of a generic method to the explicit type arguments of a call,
+    /// or infers them from the argument types when they are left out.
+    /// The resolved type arguments are written back into the call.
+    fn instantiate_method<'a>(
+        &self,
+        method_name: &str,
+        type_params: &[(String, Vec<TypeCheckerType>)],
+        type_args: &mut Vec<Type<'a>>,
+        args: &mut [Expression<'a>],
+        method: &TypeCheckerType,
+        span: Span,
+    ) -> Result<TypeCheckerType, TypeCheckerError> {
+        let TypeCheckerType::Function(param_types, _) = method else {
+            unreachable!("expected method to be a function type but got {:?}", method)
+        };
+        let names = type_params.iter().map(|(name, _)| name.clone()).collect::<Vec<_>>();
+        let mut bindings = HashMap::new();
+        if type_args.is_empty() {
+            for (param_ty, arg) in param_types.iter().zip(args.iter_mut()) {
+                if let Ok(arg_ty) = self.get_type(arg) {
+                    Self::infer_type_params(&names, param_ty, &TypeCheckerType::from(arg_ty), &mut bindings);
+                }
+            }
+            if let Some(type_param) = names.iter().find(|param| !bindings.contains_key(*param)) {
+                return Err(TypeCheckerError::CannotInferTypeArgument {
+                    current_file: self.current_path.clone(),
+                    method: method_name.to_string(),
+                    type_param: type_param.clone(),
+                    location: span,
+                });
+            }
+        } else if type_args.len() != type_params.len() {
+            return Err(TypeCheckerError::MismatchedTypeArgumentCount {
+                current_file: self.current_path.clone(),
+                method: method_name.to_string(),
+                expected: type_params.len(),
+                found: type_args.len(),
+                location: span,
+            });
+        } else {
+            for (param, arg) in names.iter().zip(type_args.iter()) {
+                bindings.insert(param.clone(), TypeCheckerType::from(arg.clone()));
+            }
+        }
+        self.check_bounds(type_params, &bindings, span)?;
+
+        *type_args = names.iter()
+            .map(|param| bindings[param].clone().into())
+            .collect();
+        Ok(Self::substitute_type_params(method, &bindings))
+    }
+
+    /// Checks that the type arguments bound to type parameters extend or implement all of their bounds
+    fn check_bounds(
+        &self,
+        type_params: &[(String, Vec<TypeCheckerType>)],
+        bindings: &HashMap<String, TypeCheckerType>,
+        location: Span,
+    ) -> Result<(), TypeCheckerError> {
+        for (type_param, bounds) in type_params {
+            let Some(ty) = bindings.get(type_param) else {
+                continue
+            };
+            for bound in bounds {
+                if !self.satisfies_bound(ty, bound) {
+                    return Err(TypeCheckerError::UnsatisfiedBound {
+                        current_file: self.current_path.clone(),
+                        ty: ty.to_string(),
+                        type_param: type_param.clone(),
+                        bound: bound.to_string(),
+                        location,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn satisfies_bound(&self, ty: &TypeCheckerType, bound: &TypeCheckerType) -> bool {
+        let Some(bound_name) = Self::bound_name(bound) else {
+            return false
+        };
+        // Type parameters satisfy the bounds they were given themselves
+        if let TypeCheckerType::Object(name) = ty {
+            if let Some(bounds) = self.type_param_bounds.get(name) {
+                return bounds.iter().any(|other| Self::bound_name(other) == Some(bound_name));
+            }
+        }
+        let Some(class) = self.get_object_class(ty) else {
+            return false
+        };
+        let bound_path = self.attach_module_if_needed(bound_name.to_string());
+        if self.trait_decl.contains_key(&bound_path) {
+            // Traits implemented by a parent are implemented by its children as well
+            let mut path = class;
+            loop {
+                let implemented = self.trait_impls.get(&path)
+                    .map(|impls| impls.iter().any(|(name, _)| name == bound_name))
+                    .unwrap_or(false);
+                if implemented {
+                    return true;
+                }
+                let Some((parent, _)) = self.class_information.get(&path) else {
+                    return false;
+                };
+                let parent_path = self.attach_module_if_needed(parent.clone());
+                if parent_path == path {
+                    return false;
+                }
+                path = parent_path;
+            }
+        }
+        self.is_subclass_of(&class, &bound_path)
+    }
+
+    fn bound_name(bound: &TypeCheckerType) -> Option<&str> {
+        match bound {
+            TypeCheckerType::Object(name) => Some(name),
+            TypeCheckerType::TypeArg(object, _) => Self::bound_name(object),
+            _ => None,
+        }
+    }
+
+    fn type_param_bounds(type_params: &[TypeParameter]) -> Vec<(String, Vec<TypeCheckerType>)> {
+        type_params.iter()
+            .map(|param| {
+                let bounds = param.constraints.iter()
+                    .flat_map(|constraint| match constraint {
+                        Constraint::Extends(bounds, _) => bounds.iter().map(TypeCheckerType::from),
+                    })
+                    .collect();
+                (param.name.to_string(), bounds)
+            })
+            .collect()
+    }
+
+    /// Looks up a method of a bounded type parameter in the classes and traits it is bounded by,
+    /// returning the bound that has the method along with the method's type
+    fn get_bound_method(&self, type_param: &str, method: &str, location: Span) -> Result<(TypeCheckerType, TypeCheckerType), TypeCheckerError> {
+        let bounds = self.type_param_bounds.get(type_param).into_iter().flatten();
+        for bound in bounds {
+            let Some(bound_name) = Self::bound_name(bound) else {
+                continue
+            };
+            let path = self.attach_module_if_needed(bound_name.to_string());
+            let attribute = match self.trait_decl.get(&path) {
+                Some((_, attributes)) => attributes.get(method).map(|(_, attribute)| attribute),
+                None => self.get_inherited_attribute(&path, method),
+            };
+            if let Some(ClassAttribute::Method(ty)) = attribute {
+                return Ok((TypeCheckerType::Object(bound_name.to_string()), ty.clone()));
+            }
+        }
+        Err(TypeCheckerError::MissingBoundAttribute {
+            current_file: self.current_path.clone(),
+            type_param: type_param.to_string(),
+            attribute: method.to_string(),
+            location,
+        })
+    }
+
+    /// Gives the object a bounded method is called on the type of the bound that has the method,
+    /// so it is called through that class or trait once the type parameter is erased
+    fn annotate_bound_receiver(object: &mut Expression, bound: &TypeCheckerType) {
+        match object {
+            Expression::Variable(_, ty, _) => *ty = Some(bound.clone().into()),
+            Expression::MemberAccess { annotation, .. } => *annotation = Some(bound.clone().into()),
+            _ => {}
+        }
+    }
+
+    /// Unifies a parameter type with the type of its argument, binding any type parameters it mentions
+    fn infer_type_params(
+        type_params: &[String],
+        expected: &TypeCheckerType,
+        found: &TypeCheckerType,
+        bindings: &mut HashMap<String, TypeCheckerType>,
+    ) {
+        match (expected, found) {
+            (TypeCheckerType::Object(name), found) if type_params.contains(name) => {
+                bindings.entry(name.clone()).or_insert_with(|| found.clone());
+            }
+            (TypeCheckerType::Array(expected), TypeCheckerType::Array(found)) => {
+                Self::infer_type_params(type_params, expected, found, bindings);
+            }
+            (TypeCheckerType::TypeArg(expected, expected_args), TypeCheckerType::TypeArg(found, found_args)) => {
+                Self::infer_type_params(type_params, expected, found, bindings);
+                for (expected, found) in expected_args.iter().zip(found_args.iter()) {
+                    Self::infer_type_params(type_params, expected, found, bindings);
+                }
+            }
+            (TypeCheckerType::Tuple(expected), TypeCheckerType::Tuple(found)) => {
+                for (expected, found) in expected.iter().zip(found.iter()) {
+                    Self::infer_type_params(type_params, expected, found, bindings);
+                }
+            }
+            (TypeCheckerType::Function(expected_args, expected_ret), TypeCheckerType::Function(found_args, found_ret)) => {
+                for (expected, found) in expected_args.iter().zip(found_args.iter()) {
+                    Self::infer_type_params(type_params, expected, found, bindings);
+                }
+                Self::infer_type_params(type_params, expected_ret, found_ret, bindings);
+            }
+            _ => {}
+        }
+    }
+
+    /// Gets the class path of the object a method is called on
+    fn get_object_class(&self, ty: &TypeCheckerType) -> Option<Vec<String>> {
+        match ty {
+            TypeCheckerType::Object(name) => Some(self.attach_module_if_needed(name.clone())),
+            TypeCheckerType::TypeArg(object, _) => self.get_object_class(object),
+            _ => None,
+        }
+    }
+
+    /// Checks if a class is the given class or inherits from it
+    fn is_subclass_of(&self, class: &[String], ancestor: &[String]) -> bool {
+        let mut path = class.to_vec();
+        loop {
+            if path == ancestor {
+                return true;
+            }
+            let Some((parent, _)) = self.class_information.get(&path) else {
+                return false;
+            };
+            let parent_path = self.attach_module_if_needed(parent.clone());
+            if parent_path == path {
+                return false;
+            }
+            path = parent_path;
+        }
+    }
+
+    /// Checks that a type can be thrown and caught
+    fn check_exception_type(&self, ty: &Type, location: Span) -> Result<(), TypeCheckerError> {
+        let is_exception = match ty {
+            Type::Object(name, _) => {
+                let path = self.attach_module_if_needed(name.to_string());
+                self.is_subclass_of(&path, &[String::from("Exception")])
+            }
+            _ => false,
+        };
+        if !is_exception {
+            return Err(
+                TypeCheckerError::MismatchedType {
+                    current_file: self.current_path.clone(),
+                    expected: String::from("Exception"),
+                    found: ty.to_string(),
+                    location,
+                }
+            );
+        }
+        Ok(())
+    }
+
+    /// Gets the path of the class of an object expression for use with signals
+    fn get_object_path<'a>(&self, object: &mut Expression<'a>) -> Result<Vec<String>, TypeCheckerError> {
+        let ty = self.get_type(object)?;
+        let name = match ty {
+            Type::Object(name, _) => name,
+            Type::TypeArg(obj, _, _) => {
+                let Type::Object(name, _) = obj.as_ref() else {
+                    unreachable!("type arg should always be an object")
+                };
+                name.clone()
+            }
+            ty => return Err(
+                TypeCheckerError::MismatchedType {
+                    current_file: self.current_path.clone(),
+                    expected: String::from("object"),
+                    found: ty.to_string(),
+                    location: object.get_span(),
+                }
+            ),
+        };
+        Ok(self.attach_module_if_needed(name.to_string()))
+    }
+
+    /// Gets the payload types of a signal on the class of an object
+    fn get_signal<'a>(&self, object: &mut Expression<'a>, signal: &PathName<'a>) -> Result<Vec<TypeCheckerType>, TypeCheckerError> {
+        let path = self.get_object_path(object)?;
+        let signal_name = signal.segments.last().unwrap();
+        match self.get_inherited_attribute(&path, signal_name.as_str()) {
+            Some(ClassAttribute::Signal(parameters)) => Ok(parameters.clone()),
+            _ => Err(
+                TypeCheckerError::UnknownSignal {
+                    current_file: self.current_path.clone(),
+                    class: path.join("::"),
+                    signal: signal_name.to_string(),
+                    location: signal.span,
+                }
+            ),
+        }
+    }
+
+    /// Gets the payload types of a static signal.
+    /// A signal without a class path refers to the current class.
+    fn get_static_signal(&self, class: Option<&str>, signal_name: &str, location: Span) -> Result<Vec<TypeCheckerType>, TypeCheckerError> {
+        let path = match class {
+            Some(class) => if let Some(path) = self.active_paths.get(class) {
+                path.clone()
+            } else {
+                self.attach_module_if_needed(class.to_string())
+            },
+            None => self.current_class.clone(),
+        };
+        match self.get_inherited_attribute(&path, signal_name) {
+            Some(ClassAttribute::StaticSignal(parameters)) => Ok(parameters.clone()),
+            _ => Err(
+                TypeCheckerError::UnknownSignal {
+                    current_file: self.current_path.clone(),
+                    class: path.join("::"),
+                    signal: signal_name.to_string(),
+                    location,
+                }
+            ),
+        }
+    }
+
+    /// Checks that a static method annotated with `@Listen` can receive the static signal
+    fn check_listener<'a>(&self, method: &Method<'a>, annotation: &Annotation<'a>) -> Result<(), TypeCheckerError> {
+        let (class, signal_name) = match annotation.parameters.as_slice() {
+            [signal] => (None, signal),
+            [class, signal] => (Some(class.as_str()), signal),
+            _ => return Err(
+                TypeCheckerError::UnknownSignal {
+                    current_file: self.current_path.clone(),
+                    class: self.current_class.join("::"),
+                    signal: String::new(),
+                    location: annotation.span,
+                }
+            ),
+        };
+        let parameters = self.get_static_signal(class, signal_name.as_str(), annotation.span)?;
+        let expected = TypeCheckerType::Function(parameters, Box::new(TypeCheckerType::Void));
+
+        let mut arguments = Vec::new();
+        let mut is_static = true;
+        for parameter in method.parameters.iter() {
+            match parameter {
+                Parameter::This(_, _) => is_static = false,
+                Parameter::Pattern { ty, .. } => arguments.push(TypeCheckerType::from(ty.clone())),
+            }
+        }
+        let found = TypeCheckerType::Function(arguments, Box::new(TypeCheckerType::from(method.return_type.clone())));
+        let matches = match (&expected, &found) {
+            (TypeCheckerType::Function(expected_args, _), TypeCheckerType::Function(args, ret)) => {
+                is_static
+                    && expected_args.len() == args.len()
+                    && self.compare_types(ret, &TypeCheckerType::Void)
+                    && self.compare_types(&expected, &found)
+            }
+            _ => false,
+        };
+        if !matches {
+            return Err(
+                TypeCheckerError::MismatchedType {
+                    current_file: self.current_path.clone(),
+                    expected: format!("static {}", expected),
+                    found: found.to_string(),
+                    location: method.signature_span,
+                }
+            );
+        }
+        Ok(())
+    }
+
+    /// Checks that a method on the target object can receive a signal from the source object
+    fn check_connection<'a>(
+        &mut self,
+        return_type: &TypeCheckerType,
+        source: &mut Expression<'a>,
+        signal: &PathName<'a>,
+        target: &mut Expression<'a>,
+        method: &PathName<'a>,
+    ) -> Result<(), TypeCheckerError> {
+        self.check_expr(return_type, source)?;
+        self.check_expr(return_type, target)?;
+        let parameters = self.get_signal(source, signal)?;
+        let path = self.get_object_path(target)?;
+        let method_name = method.segments.last().unwrap();
+        let expected = TypeCheckerType::Function(parameters, Box::new(TypeCheckerType::Void));
+        match self.get_inherited_attribute(&path, method_name.as_str()) {
+            Some(ClassAttribute::Method(method_ty)) => {
+                let matches = match (&expected, method_ty) {
+                    (TypeCheckerType::Function(expected_args, _), TypeCheckerType::Function(args, ret)) => {
+                        expected_args.len() == args.len()
+                            && self.compare_types(ret, &TypeCheckerType::Void)
+                            && self.compare_types(&expected, method_ty)
+                    }
+                    _ => false,
+                };
+                if !matches {
+                    return Err(
+                        TypeCheckerError::MismatchedType {
+                            current_file: self.current_path.clone(),
+                            expected: expected.to_string(),
+                            found: method_ty.to_string(),
+                            location: method.span,
+                        }
+                    );
+                }
+                Ok(())
+            }
+            _ => Err(
+                TypeCheckerError::AttributeTypeMismatch {
+                    current_file: self.current_path.clone(),
+                    attribute: method_name.to_string(),
+                    access_span: method.span,
+                }
+            ),
+        }
+    }
+
     fn attach_module_if_needed(&self, class: String) -> Vec<String> {
+        if class.contains("::") {
+            return class.split("::").map(ToString::to_string).collect();
+        }
         let path = self.active_paths.get(&class);
         if let Some(path) = path {
             let module = path.clone();
@@ -556,6 +1162,10 @@ impl TypeChecker {
             (TypeCheckerType::Array(ty1), TypeCheckerType::Array(ty2)) => {
                 self.compare_types(ty1, ty2)
             }
+            (TypeCheckerType::Tuple(tys1), TypeCheckerType::Tuple(tys2)) => {
+                tys1.len() == tys2.len() && tys1.iter().zip(tys2.iter())
+                    .all(|(ty1, ty2)| self.compare_types(ty1, ty2))
+            }
             (TypeCheckerType::Object(name1), TypeCheckerType::Object(name2)) => {
                 if name1 == name2 {
                     true
@@ -619,26 +1229,85 @@ impl TypeChecker {
     fn compare_object(&self, left: &str, right: &str) -> bool {
         if left == "object" || right == "object" {
             true
+        } else if left == "Object" || right == "Object" {
+            // Every class inherits from Object
+            true
         } else if left == right {
             true
         } else {
-            let right_path = self.active_paths.get(right).unwrap();
-            if self.compare_object(left, &self.class_information.get(right_path).unwrap().0) {
-                return true;
+            // Union variants are usually not imported, they live in the same module as their union
+            let right_path = self.attach_module_if_needed(right.to_string());
+            if let Some((parent, _)) = self.class_information.get(&right_path) {
+                if self.compare_object(left, parent) {
+                    return true;
+                }
             }
-            let left_path = self.active_paths.get(left).unwrap();
-            if self.compare_object(right, &self.class_information.get(left_path).unwrap().0) {
-                return true;
+            let left_path = self.attach_module_if_needed(left.to_string());
+            if let Some((parent, _)) = self.class_information.get(&left_path) {
+                if self.compare_object(right, parent) {
+                    return true;
+                }
             }
             false
         }
     }
 
+    /// Imports the types in `std::core` into every other file when the standard library is being compiled.
+    /// Names that a file declares itself or already imports are left alone.
+    fn add_prelude_imports<'a>(files: &mut Vec<(String, File<'a>, &'a String)>) {
+        const PRELUDE: [&str; 6] = ["Option", "Some", "None", "Result", "Ok", "Err"];
+        let is_core = |file: &File| file.path.segments.len() == 2
+            && file.path.segments[0].as_str() == "std"
+            && file.path.segments[1].as_str() == "core";
+        if !files.iter().any(|(_, file, _)| is_core(file)) {
+            return;
+        }
+
+        for (_, file, _) in files.iter_mut() {
+            if is_core(file) {
+                continue;
+            }
+            let mut declared = HashSet::new();
+            for statement in file.content.iter() {
+                match statement {
+                    TopLevelStatement::Import(import) => {
+                        declared.insert(import.path.segments.last().unwrap().to_string());
+                    }
+                    TopLevelStatement::Class(class) => {
+                        declared.insert(class.name.to_string());
+                    }
+                    TopLevelStatement::Record(record) => {
+                        declared.insert(record.name.to_string());
+                    }
+                    TopLevelStatement::Union(union) => {
+                        declared.insert(union.name.to_string());
+                        declared.extend(union.variants.iter().map(|variant| variant.name.to_string()));
+                    }
+                    TopLevelStatement::Trait(r#trait) => {
+                        declared.insert(r#trait.name.to_string());
+                    }
+                    TopLevelStatement::TraitImpl(_) => {}
+                }
+            }
+            for name in PRELUDE {
+                if declared.contains(name) {
+                    continue;
+                }
+                let segments = vec![Text::Borrowed("std"), Text::Borrowed("core"), Text::Borrowed(name)];
+                let import = Import::new(PathName::new(segments, Span::new(0, 0)), Span::new(0, 0));
+                file.content.insert(0, TopLevelStatement::Import(import));
+            }
+        }
+    }
+
     pub fn check<'a>(&mut self, files: Vec<(String, File<'a>, &'a String)>) -> Result<Vec<(String, File<'a>, &'a String)>, TypeCheckerError> {
         self.check_files(files)
     }
 
-    fn check_files<'a>(&mut self, mut files: Vec<(String, File<'a>, &'a String)>) -> Result<Vec<(String, File<'a>, &'a String)>, TypeCheckerError> {
+    /// Checks the files, returning the errors that were collected instead of reporting them
+    fn collect_errors<'a>(&mut self, files: &mut Vec<(String, File<'a>, &'a String)>) -> Result<Vec<TypeCheckerError>, TypeCheckerError> {
+        Self::add_prelude_imports(files);
+
         // Load all files into the typechecker
         for (_, file, _) in files.iter() {
             let module: Vec<String> = file.path.segments.iter().map(ToString::to_string).collect();
@@ -655,6 +1324,11 @@ impl TypeChecker {
             self.active_paths.clear();
             errors.append(&mut self.collected_errors);
         }
+        Ok(errors)
+    }
+
+    fn check_files<'a>(&mut self, mut files: Vec<(String, File<'a>, &'a String)>) -> Result<Vec<(String, File<'a>, &'a String)>, TypeCheckerError> {
+        let errors = self.collect_errors(&mut files)?;
 
         if errors.is_empty() {
             return Ok(files)
@@ -823,7 +1497,7 @@ impl TypeChecker {
                         }
                     }
                     Report::build(ReportKind::Error, (current_file.clone(), expression_span.start..expression_span.end))
-                        .with_message(format!("Extra function arguments (expected: {}, found: {})", expected, found))
+                        .with_message(format!("Wrong number of function arguments (expected: {}, found: {})", expected, found))
                         .with_label(Label::new((source_file.clone(), signature_span.start..signature_span.end))
                             .with_message("with signature here"))
                         .with_label(Label::new((source_file.clone(), signature_span.start..signature_span.end))
@@ -928,24 +1602,390 @@ impl TypeChecker {
                         .eprint((current_file.clone(), Source::from(current_content.unwrap())))
                         .unwrap();
                 }
-            }
-        }
-
-
-        std::process::exit(1);
-    }
-
-    fn check_file<'a>(&mut self, file: &mut File<'a>) -> Result<(), TypeCheckerError> {
-        let module: Vec<String> = file.path.segments.iter().map(ToString::to_string).collect();
-        file.content.sort_by(|a, b| {
-            match (a, b) {
-                (TopLevelStatement::Class(_), TopLevelStatement::Import(_)) => {
-                    Ordering::Greater
-                }
-                (TopLevelStatement::Import(_), TopLevelStatement::Class(_)) => {
-                    Ordering::Less
-                }
-                (TopLevelStatement::TraitImpl(_), TopLevelStatement::Import(_)) => {
+                TypeCheckerError::UnknownSignal {
+                    current_file,
+                    class,
+                    signal,
+                    location
+                } => {
+                    let mut current_content = None;
+                    for (path, _, content) in files.iter() {
+                        if current_file == *path {
+                            current_content = Some(*content);
+                            break;
+                        }
+                    }
+                    Report::build(ReportKind::Error, (current_file.clone(), location.start..location.end))
+                        .with_message(format!("{class} has no signal named {signal}"))
+                        .with_label(Label::new((current_file.clone(), location.start..location.end)))
+                        .finish()
+                        .eprint((current_file.clone(), Source::from(current_content.unwrap())))
+                        .unwrap();
+                }
+                TypeCheckerError::ImmutableField {
+                    current_file,
+                    record,
+                    field,
+                    location
+                } => {
+                    let mut current_content = None;
+                    for (path, _, content) in files.iter() {
+                        if current_file == *path {
+                            current_content = Some(*content);
+                            break;
+                        }
+                    }
+                    Report::build(ReportKind::Error, (current_file.clone(), location.start..location.end))
+                        .with_message(format!("Cannot assign to {field}, {record} is a record and its fields are immutable"))
+                        .with_label(Label::new((current_file.clone(), location.start..location.end)))
+                        .finish()
+                        .eprint((current_file.clone(), Source::from(current_content.unwrap())))
+                        .unwrap();
+                }
+                TypeCheckerError::WrongNumberOfFields {
+                    current_file,
+                    class,
+                    expected,
+                    found,
+                    location
+                } => {
+                    let mut current_content = None;
+                    for (path, _, content) in files.iter() {
+                        if current_file == *path {
+                            current_content = Some(*content);
+                            break;
+                        }
+                    }
+                    Report::build(ReportKind::Error, (current_file.clone(), location.start..location.end))
+                        .with_message(format!("Pattern for {class} has {found} fields, but {class} has {expected}"))
+                        .with_label(Label::new((current_file.clone(), location.start..location.end)))
+                        .finish()
+                        .eprint((current_file.clone(), Source::from(current_content.unwrap())))
+                        .unwrap();
+                }
+                TypeCheckerError::NonExhaustiveMatch {
+                    current_file,
+                    missing,
+                    location
+                } => {
+                    let mut current_content = None;
+                    for (path, _, content) in files.iter() {
+                        if current_file == *path {
+                            current_content = Some(*content);
+                            break;
+                        }
+                    }
+                    Report::build(ReportKind::Error, (current_file.clone(), location.start..location.end))
+                        .with_message(format!("Match is not exhaustive, {missing} is not covered"))
+                        .with_label(Label::new((current_file.clone(), location.start..location.end))
+                            .with_message(format!("Add an arm that matches {missing}")))
+                        .finish()
+                        .eprint((current_file.clone(), Source::from(current_content.unwrap())))
+                        .unwrap();
+                }
+                TypeCheckerError::UnreachableMatchArm {
+                    current_file,
+                    location
+                } => {
+                    let mut current_content = None;
+                    for (path, _, content) in files.iter() {
+                        if current_file == *path {
+                            current_content = Some(*content);
+                            break;
+                        }
+                    }
+                    Report::build(ReportKind::Error, (current_file.clone(), location.start..location.end))
+                        .with_message("Unreachable match arm")
+                        .with_label(Label::new((current_file.clone(), location.start..location.end))
+                            .with_message("The arms before this one already match everything it matches"))
+                        .finish()
+                        .eprint((current_file.clone(), Source::from(current_content.unwrap())))
+                        .unwrap();
+                }
+                TypeCheckerError::RefutablePattern {
+                    current_file,
+                    missing,
+                    location
+                } => {
+                    let mut current_content = None;
+                    for (path, _, content) in files.iter() {
+                        if current_file == *path {
+                            current_content = Some(*content);
+                            break;
+                        }
+                    }
+                    Report::build(ReportKind::Error, (current_file.clone(), location.start..location.end))
+                        .with_message(format!("Pattern is refutable, {missing} is not covered"))
+                        .with_label(Label::new((current_file.clone(), location.start..location.end))
+                            .with_message("Use a match to handle the values this pattern doesn't match"))
+                        .finish()
+                        .eprint((current_file.clone(), Source::from(current_content.unwrap())))
+                        .unwrap();
+                }
+                TypeCheckerError::InvalidAssignmentTarget {
+                    current_file,
+                    location
+                } => {
+                    let mut current_content = None;
+                    for (path, _, content) in files.iter() {
+                        if current_file == *path {
+                            current_content = Some(*content);
+                            break;
+                        }
+                    }
+                    Report::build(ReportKind::Error, (current_file.clone(), location.start..location.end))
+                        .with_message("Cannot assign to this expression")
+                        .with_label(Label::new((current_file.clone(), location.start..location.end))
+                            .with_message("Only variables, fields and array elements can be assigned to"))
+                        .finish()
+                        .eprint((current_file.clone(), Source::from(current_content.unwrap())))
+                        .unwrap();
+                }
+                TypeCheckerError::StringPattern {
+                    current_file,
+                    location
+                } => {
+                    let mut current_content = None;
+                    for (path, _, content) in files.iter() {
+                        if current_file == *path {
+                            current_content = Some(*content);
+                            break;
+                        }
+                    }
+                    Report::build(ReportKind::Error, (current_file.clone(), location.start..location.end))
+                        .with_message("Strings can't be matched against")
+                        .with_label(Label::new((current_file.clone(), location.start..location.end))
+                            .with_message("Compare the string in the body of an arm instead"))
+                        .finish()
+                        .eprint((current_file.clone(), Source::from(current_content.unwrap())))
+                        .unwrap();
+                }
+                TypeCheckerError::NotIterable {
+                    current_file,
+                    ty,
+                    location
+                } => {
+                    let mut current_content = None;
+                    for (path, _, content) in files.iter() {
+                        if current_file == *path {
+                            current_content = Some(*content);
+                            break;
+                        }
+                    }
+                    Report::build(ReportKind::Error, (current_file.clone(), location.start..location.end))
+                        .with_message(format!("Cannot iterate over {ty}"))
+                        .with_label(Label::new((current_file.clone(), location.start..location.end))
+                            .with_message("Only arrays and classes that implement std::iter::Iterator can be used in a for loop"))
+                        .finish()
+                        .eprint((current_file.clone(), Source::from(current_content.unwrap())))
+                        .unwrap();
+                }
+                TypeCheckerError::NotCloseable {
+                    current_file,
+                    ty,
+                    location
+                } => {
+                    let mut current_content = None;
+                    for (path, _, content) in files.iter() {
+                        if current_file == *path {
+                            current_content = Some(*content);
+                            break;
+                        }
+                    }
+                    Report::build(ReportKind::Error, (current_file.clone(), location.start..location.end))
+                        .with_message(format!("Cannot close {ty}"))
+                        .with_label(Label::new((current_file.clone(), location.start..location.end))
+                            .with_message("Only classes that implement std::Closeable can be used in a with statement"))
+                        .finish()
+                        .eprint((current_file.clone(), Source::from(current_content.unwrap())))
+                        .unwrap();
+                }
+                TypeCheckerError::TupleFieldOutOfRange {
+                    current_file,
+                    ty,
+                    field,
+                    location
+                } => {
+                    let mut current_content = None;
+                    for (path, _, content) in files.iter() {
+                        if current_file == *path {
+                            current_content = Some(*content);
+                            break;
+                        }
+                    }
+                    Report::build(ReportKind::Error, (current_file.clone(), location.start..location.end))
+                        .with_message(format!("{ty} has no field {field}"))
+                        .with_label(Label::new((current_file.clone(), location.start..location.end))
+                            .with_message("Tuple fields are numbered from 0"))
+                        .finish()
+                        .eprint((current_file.clone(), Source::from(current_content.unwrap())))
+                        .unwrap();
+                }
+                TypeCheckerError::NotTryable {
+                    current_file,
+                    ty,
+                    location
+                } => {
+                    let mut current_content = None;
+                    for (path, _, content) in files.iter() {
+                        if current_file == *path {
+                            current_content = Some(*content);
+                            break;
+                        }
+                    }
+                    Report::build(ReportKind::Error, (current_file.clone(), location.start..location.end))
+                        .with_message(format!("Cannot use ? on {ty}"))
+                        .with_label(Label::new((current_file.clone(), location.start..location.end))
+                            .with_message("Only Option and Result values can be used with ?"))
+                        .finish()
+                        .eprint((current_file.clone(), Source::from(current_content.unwrap())))
+                        .unwrap();
+                }
+                TypeCheckerError::MismatchedTryReturn {
+                    current_file,
+                    ty,
+                    return_type,
+                    location
+                } => {
+                    let mut current_content = None;
+                    for (path, _, content) in files.iter() {
+                        if current_file == *path {
+                            current_content = Some(*content);
+                            break;
+                        }
+                    }
+                    Report::build(ReportKind::Error, (current_file.clone(), location.start..location.end))
+                        .with_message(format!("Cannot use ? on {ty} in a method that returns {return_type}"))
+                        .with_label(Label::new((current_file.clone(), location.start..location.end))
+                            .with_message("? returns None from methods returning an Option and Err from methods returning a Result with the same error type"))
+                        .finish()
+                        .eprint((current_file.clone(), Source::from(current_content.unwrap())))
+                        .unwrap();
+                }
+                TypeCheckerError::MismatchedTypeArgumentCount {
+                    current_file,
+                    method,
+                    expected,
+                    found,
+                    location
+                } => {
+                    let mut current_content = None;
+                    for (path, _, content) in files.iter() {
+                        if current_file == *path {
+                            current_content = Some(*content);
+                            break;
+                        }
+                    }
+                    Report::build(ReportKind::Error, (current_file.clone(), location.start..location.end))
+                        .with_message(format!("Method {method} takes {expected} type arguments but {found} were given"))
+                        .with_label(Label::new((current_file.clone(), location.start..location.end))
+                            .with_message("Either give every type argument or leave them all out to have them inferred"))
+                        .finish()
+                        .eprint((current_file.clone(), Source::from(current_content.unwrap())))
+                        .unwrap();
+                }
+                TypeCheckerError::CannotInferTypeArgument {
+                    current_file,
+                    method,
+                    type_param,
+                    location
+                } => {
+                    let mut current_content = None;
+                    for (path, _, content) in files.iter() {
+                        if current_file == *path {
+                            current_content = Some(*content);
+                            break;
+                        }
+                    }
+                    Report::build(ReportKind::Error, (current_file.clone(), location.start..location.end))
+                        .with_message(format!("Unable to infer type parameter {type_param} of {method}"))
+                        .with_label(Label::new((current_file.clone(), location.start..location.end))
+                            .with_message("Give the type arguments explicitly with ::[...]"))
+                        .finish()
+                        .eprint((current_file.clone(), Source::from(current_content.unwrap())))
+                        .unwrap();
+                }
+                TypeCheckerError::UnsatisfiedBound {
+                    current_file,
+                    ty,
+                    type_param,
+                    bound,
+                    location
+                } => {
+                    let mut current_content = None;
+                    for (path, _, content) in files.iter() {
+                        if current_file == *path {
+                            current_content = Some(*content);
+                            break;
+                        }
+                    }
+                    Report::build(ReportKind::Error, (current_file.clone(), location.start..location.end))
+                        .with_message(format!("{ty} does not satisfy the bound {bound} of {type_param}"))
+                        .with_label(Label::new((current_file.clone(), location.start..location.end))
+                            .with_message(format!("{ty} needs to extend or implement {bound}")))
+                        .finish()
+                        .eprint((current_file.clone(), Source::from(current_content.unwrap())))
+                        .unwrap();
+                }
+                TypeCheckerError::MissingBoundAttribute {
+                    current_file,
+                    type_param,
+                    attribute,
+                    location
+                } => {
+                    let mut current_content = None;
+                    for (path, _, content) in files.iter() {
+                        if current_file == *path {
+                            current_content = Some(*content);
+                            break;
+                        }
+                    }
+                    Report::build(ReportKind::Error, (current_file.clone(), location.start..location.end))
+                        .with_message(format!("None of the bounds of {type_param} have a method {attribute}"))
+                        .with_label(Label::new((current_file.clone(), location.start..location.end))
+                            .with_message(format!("Add a bound to {type_param} with a class or trait that has {attribute}")))
+                        .finish()
+                        .eprint((current_file.clone(), Source::from(current_content.unwrap())))
+                        .unwrap();
+                }
+                TypeCheckerError::MissingConstructor {
+                    current_file,
+                    class,
+                    location
+                } => {
+                    let mut current_content = None;
+                    for (path, _, content) in files.iter() {
+                        if current_file == *path {
+                            current_content = Some(*content);
+                            break;
+                        }
+                    }
+                    Report::build(ReportKind::Error, (current_file.clone(), location.start..location.end))
+                        .with_message(format!("{class} has no constructor that takes arguments"))
+                        .with_label(Label::new((current_file.clone(), location.start..location.end))
+                            .with_message(format!("Add a method named new to {class} or create it with new {class}()")))
+                        .finish()
+                        .eprint((current_file.clone(), Source::from(current_content.unwrap())))
+                        .unwrap();
+                }
+            }
+        }
+
+
+        std::process::exit(1);
+    }
+
+    fn check_file<'a>(&mut self, file: &mut File<'a>) -> Result<(), TypeCheckerError> {
+        let module: Vec<String> = file.path.segments.iter().map(ToString::to_string).collect();
+        file.content.sort_by(|a, b| {
+            match (a, b) {
+                (TopLevelStatement::Class(_) | TopLevelStatement::Record(_) | TopLevelStatement::Union(_), TopLevelStatement::Import(_)) => {
+                    Ordering::Greater
+                }
+                (TopLevelStatement::Import(_), TopLevelStatement::Class(_) | TopLevelStatement::Record(_) | TopLevelStatement::Union(_)) => {
+                    Ordering::Less
+                }
+                (TopLevelStatement::TraitImpl(_), TopLevelStatement::Import(_)) => {
                     Ordering::Greater
                 }
                 (TopLevelStatement::Import(_), TopLevelStatement::TraitImpl(_)) => {
@@ -979,6 +2019,26 @@ impl TypeChecker {
                     self.check_class(class, &module)?;
                     self.active_paths.remove(class.name.as_str());
                 }
+                TopLevelStatement::Record(record) => {
+                    let mut new_module = module.clone();
+                    new_module.push(record.name.to_string());
+                    self.active_paths.insert(record.name.to_string(), new_module.clone());
+                    self.current_class = new_module;
+                    for method in record.methods.iter_mut() {
+                        self.check_method(method)?
+                    }
+                    self.active_paths.remove(record.name.as_str());
+                }
+                TopLevelStatement::Union(union) => {
+                    let mut new_module = module.clone();
+                    new_module.push(union.name.to_string());
+                    self.active_paths.insert(union.name.to_string(), new_module.clone());
+                    self.current_class = new_module;
+                    for method in union.methods.iter_mut() {
+                        self.check_method(method)?
+                    }
+                    self.active_paths.remove(union.name.as_str());
+                }
                 TopLevelStatement::Import(import) => {
                     let path_terminator = import.path.segments.last().unwrap().to_string();
                     let path = import.path.segments.iter().map(ToString::to_string).collect::<Vec<_>>();
@@ -1102,6 +2162,8 @@ impl TypeChecker {
                         methods,
                         parent,
                         static_members,
+                        signals,
+                        type_params,
                         ..
                     } = class;
                     let class_name = name;
@@ -1134,14 +2196,86 @@ impl TypeChecker {
                         class_attributes.insert(name.to_string(), ClassAttribute::StaticMember(ty));
                     }
 
+                    for signal in signals.iter() {
+                        let Signal { name, is_static, parameters, .. } = signal;
+                        let parameters = parameters.iter().map(TypeCheckerType::from).collect();
+                        let signal = if *is_static {
+                            ClassAttribute::StaticSignal(parameters)
+                        } else {
+                            ClassAttribute::Signal(parameters)
+                        };
+                        class_attributes.insert(name.to_string(), signal);
+                    }
+
                     let parent = parent.as_ref()
                         .map(|dec| dec.name.to_string())
                         .unwrap_or(String::from("Object"));
                     let mut module = module.clone();
                     module.push(class_name.to_string());
+                    self.load_method_type_params(&module, methods);
+                    if !type_params.is_empty() {
+                        self.class_type_params.insert(module.clone(), Self::type_param_bounds(type_params));
+                    }
 
                     self.class_information.insert(module.clone(), (parent, class_attributes));
                 }
+                TopLevelStatement::Record(record) => {
+                    let Record {
+                        name,
+                        members,
+                        methods,
+                        type_params,
+                        ..
+                    } = record;
+
+                    let mut attributes = Self::record_attributes(name, members, type_params, None);
+                    for method in methods.iter() {
+                        attributes.insert(method.name.to_string(), Self::method_attribute(method));
+                    }
+
+                    let mut module = module.clone();
+                    module.push(name.to_string());
+                    self.load_method_type_params(&module, methods);
+
+                    self.records.insert(module.clone(), Self::record_information(members, type_params, None));
+                    self.class_information.insert(module, (String::from("Object"), attributes));
+                }
+                TopLevelStatement::Union(union) => {
+                    let Union {
+                        name,
+                        variants,
+                        methods,
+                        type_params,
+                        ..
+                    } = union;
+
+                    let mut attributes = HashMap::new();
+                    let equals = TypeCheckerType::Function(vec![Self::value_type(name, type_params)], Box::new(TypeCheckerType::Boolean));
+                    attributes.insert(String::from("equals"), ClassAttribute::Method(equals));
+                    for method in methods.iter() {
+                        attributes.insert(method.name.to_string(), Self::method_attribute(method));
+                    }
+
+                    let mut union_module = module.clone();
+                    union_module.push(name.to_string());
+                    self.load_method_type_params(&union_module, methods);
+                    self.class_information.insert(union_module.clone(), (String::from("Object"), attributes));
+
+                    let mut variant_paths = Vec::new();
+                    for variant in variants.iter() {
+                        let Variant { name: variant_name, fields, .. } = variant;
+                        let attributes = Self::record_attributes(variant_name, fields, type_params, Some(name));
+
+                        let mut module = module.clone();
+                        module.push(variant_name.to_string());
+
+                        let information = Self::record_information(fields, type_params, Some(union_module.clone()));
+                        self.records.insert(module.clone(), information);
+                        self.class_information.insert(module.clone(), (name.to_string(), attributes));
+                        variant_paths.push(module);
+                    }
+                    self.unions.insert(union_module, variant_paths);
+                }
                 TopLevelStatement::Trait(r#trait) => {
                     let Trait {
                         name,
@@ -1258,14 +2392,104 @@ impl TypeChecker {
         Ok(())
     }
 
+    fn load_method_type_params(&mut self, class: &[String], methods: &[Method]) {
+        for method in methods.iter().filter(|method| !method.type_params.is_empty()) {
+            let mut path = class.to_vec();
+            path.push(method.name.to_string());
+            self.method_type_params.insert(path, Self::type_param_bounds(&method.type_params));
+        }
+    }
+
+    fn method_attribute(method: &Method) -> ClassAttribute {
+        let Method { parameters, return_type, .. } = method;
+        let argument_types = parameters.iter()
+            .filter_map(|parameter| match parameter {
+                Parameter::This(_, _) => None,
+                Parameter::Pattern { ty, .. } => Some(TypeCheckerType::from(ty.clone())),
+            })
+            .collect();
+        ClassAttribute::Method(TypeCheckerType::Function(argument_types, Box::new(TypeCheckerType::from(return_type.clone()))))
+    }
+
+    fn record_information(members: &[Member], type_params: &[TypeParameter], union: Option<Vec<String>>) -> RecordInformation {
+        RecordInformation {
+            type_params: type_params.iter().map(|param| param.name.to_string()).collect(),
+            fields: members.iter()
+                .map(|member| (member.name.to_string(), TypeCheckerType::from(member.ty.clone())))
+                .collect(),
+            union,
+        }
+    }
+
+    /// The type of a record or union as seen from inside of it, with its type parameters as the type arguments
+    fn value_type(name: &Text, type_params: &[TypeParameter]) -> TypeCheckerType {
+        if type_params.is_empty() {
+            TypeCheckerType::Object(name.to_string())
+        } else {
+            let type_args = type_params.iter()
+                .map(|param| TypeCheckerType::Object(param.name.to_string()))
+                .collect();
+            TypeCheckerType::TypeArg(Box::new(TypeCheckerType::Object(name.to_string())), type_args)
+        }
+    }
+
+    /// The members of a record along with the `new` and `equals` methods the compiler generates for it.
+    /// Variants can be compared with any value of their union.
+    fn record_attributes(name: &Text, members: &[Member], type_params: &[TypeParameter], union: Option<&Text>) -> HashMap<String, ClassAttribute> {
+        let record_type = Self::value_type(name, type_params);
+
+        let mut attributes = HashMap::new();
+        let mut field_types = Vec::new();
+        for member in members {
+            let ty = TypeCheckerType::from(member.ty.clone());
+            field_types.push(ty.clone());
+            attributes.insert(member.name.to_string(), ClassAttribute::Member(ty));
+        }
+
+        if !field_types.is_empty() {
+            let constructor = TypeCheckerType::Function(field_types, Box::new(record_type.clone()));
+            attributes.insert(String::from("new"), ClassAttribute::Method(constructor));
+        }
+        let other_type = match union {
+            Some(union) => Self::value_type(union, type_params),
+            None => record_type,
+        };
+        let equals = TypeCheckerType::Function(vec![other_type], Box::new(TypeCheckerType::Boolean));
+        attributes.insert(String::from("equals"), ClassAttribute::Method(equals));
+
+        attributes
+    }
+
+    /// Finds the record whose field is the target of an assignment, if there is one.
+    /// The object is found by its type so that chains like `a.b.x = 1` are caught as well.
+    fn assigned_record(&self, target: &mut Expression) -> Result<Option<Vec<String>>, TypeCheckerError> {
+        let Expression::MemberAccess { object, .. } = target else {
+            return Ok(None);
+        };
+        let path = match self.get_type(object.as_mut())? {
+            _ if matches!(object.as_ref(), Expression::This(_)) => self.current_class.clone(),
+            Type::Object(name, _) => self.attach_module_if_needed(name.to_string()),
+            Type::TypeArg(object, _, _) => {
+                let Type::Object(name, _) = object.as_ref() else {
+                    unreachable!("TypeArg should start with an object")
+                };
+                self.attach_module_if_needed(name.to_string())
+            }
+            _ => return Ok(None),
+        };
+        Ok(self.records.contains_key(&path).then_some(path))
+    }
+
     fn check_class<'a>(&mut self, class: &mut Class<'a>, module: &Vec<String>) -> Result<(), TypeCheckerError> {
         let Class {
             name,
             methods,
             static_members,
+            type_params,
             ..
         } = class;
         let class_name = name;
+        self.type_param_bounds = Self::type_param_bounds(type_params).into_iter().collect();
 
         for static_member in static_members.iter_mut() {
             let StaticMember { name, ty, value, .. } = static_member;
@@ -1281,12 +2505,25 @@ impl TypeChecker {
         for method in methods.iter_mut() {
             self.check_method(method)?
         }
+        self.type_param_bounds.clear();
 
         Ok(())
     }
 
     fn check_method<'a>(&mut self, method: &mut Method<'a>) -> Result<(), TypeCheckerError> {
-        let Method { parameters, return_type, body, signature_span, .. } = method;
+        for annotation in method.annotations.iter() {
+            if annotation.name == "Listen" {
+                if let Err(error) = self.check_listener(method, annotation) {
+                    self.collected_errors.push(error);
+                }
+            }
+        }
+
+        let Method { parameters, return_type, body, signature_span, type_params, .. } = method;
+
+        // The type parameters of the method are in scope along with those of its class
+        let class_bounds = self.type_param_bounds.clone();
+        self.type_param_bounds.extend(Self::type_param_bounds(type_params));
 
         self.current_function_sig_span = *signature_span;
         self.push_scope();
@@ -1309,6 +2546,7 @@ impl TypeChecker {
         }
         
         self.pop_scope();
+        self.type_param_bounds = class_bounds;
         Ok(())
     }
 
@@ -1328,6 +2566,257 @@ impl TypeChecker {
         }
     }
 
+    /// Arrays can be iterated over directly, anything else has to implement `std::iter::Iterator`
+    fn check_iterable(&self, ty: &TypeCheckerType, element: &Type, location: Span) -> Result<(), TypeCheckerError> {
+        let element = TypeCheckerType::from(element);
+        match ty {
+            TypeCheckerType::Array(inner) if self.compare_types(inner, &element) => Ok(()),
+            TypeCheckerType::Array(_) => {
+                Err(
+                    TypeCheckerError::MismatchedType {
+                        current_file: self.current_path.clone(),
+                        expected: TypeCheckerType::Array(Box::new(element)).to_string(),
+                        found: ty.to_string(),
+                        location,
+                    }
+                )
+            }
+            _ if self.implements_trait(ty, "Iterator") => Ok(()),
+            _ => {
+                Err(
+                    TypeCheckerError::NotIterable {
+                        current_file: self.current_path.clone(),
+                        ty: ty.to_string(),
+                        location,
+                    }
+                )
+            }
+        }
+    }
+
+    /// Turns `value?` into a match that unwraps `Some` or `Ok` and returns `None` or `Err` from the method
+    fn desugar_try<'a>(
+        &mut self,
+        return_type: &TypeCheckerType,
+        mut operand: Expression<'a>,
+        span: Span,
+    ) -> Result<Expression<'a>, TypeCheckerError> {
+        self.check_expr(return_type, &mut operand)?;
+        let ty = TypeCheckerType::from(self.get_type(&mut operand)?);
+
+        let (kind, type_args) = self.core_union(&ty).ok_or_else(|| TypeCheckerError::NotTryable {
+            current_file: self.current_path.clone(),
+            ty: ty.to_string(),
+            location: span,
+        })?;
+        let mismatch = || TypeCheckerError::MismatchedTryReturn {
+            current_file: self.current_path.clone(),
+            ty: ty.to_string(),
+            return_type: return_type.to_string(),
+            location: span,
+        };
+        let return_args = match self.core_union(return_type) {
+            Some((return_kind, return_args)) if return_kind == kind => return_args,
+            _ => return Err(mismatch()),
+        };
+        if kind == "Result" {
+            // Objects can be returned as one of their parents but primitives have to be the same type
+            let same_error = match (&type_args[1], &return_args[1]) {
+                (
+                    TypeCheckerType::Object(_) | TypeCheckerType::TypeArg(..),
+                    TypeCheckerType::Object(_) | TypeCheckerType::TypeArg(..),
+                ) => self.compare_types(&type_args[1], &return_args[1]),
+                (error, return_error) => error == return_error,
+            };
+            if !same_error {
+                return Err(mismatch());
+            }
+        }
+
+        let value_name = Text::Owned(format!("%try{}-value", span.start));
+        let value = Expression::Variable(value_name.clone(), None, span);
+        let (success, failure, failure_fields, failure_args) = if kind == "Option" {
+            ("Some", "None", Vec::new(), Vec::new())
+        } else {
+            let error_name = Text::Owned(format!("%try{}-error", span.start));
+            let error = Expression::Variable(error_name.clone(), None, span);
+            ("Ok", "Err", vec![Pattern::Variable(error_name, false, span)], vec![error])
+        };
+        let failure_type = Type::TypeArg(
+            Box::new(Type::Object(Text::Borrowed(failure), span)),
+            return_args.into_iter().map(Into::into).collect(),
+            span,
+        );
+        let failure_value = if failure_args.is_empty() {
+            Expression::New(failure_type, None, span)
+        } else {
+            Expression::new_constructor_call(failure_type, failure_args, span)
+        };
+
+        let arms = vec![
+            MatchArm::new(
+                Pattern::new_class(Text::Borrowed(success), vec![Pattern::Variable(value_name, false, span)], span),
+                Either::Left(value),
+                span.start,
+                span.end,
+            ),
+            MatchArm::new(
+                Pattern::new_class(Text::Borrowed(failure), failure_fields, span),
+                Either::Left(Expression::Return(Some(Box::new(failure_value)), span)),
+                span.start,
+                span.end,
+            ),
+        ];
+        let match_expr = MatchExpression::new(Box::new(operand), arms, span.start, span.end);
+        Ok(Expression::MatchExpression(match_expr, span))
+    }
+
+    /// Returns `Option` or `Result` and their type arguments when a type is one of the unions from `std::core`
+    fn core_union(&self, ty: &TypeCheckerType) -> Option<(&'static str, Vec<TypeCheckerType>)> {
+        let TypeCheckerType::TypeArg(object, type_args) = ty else {
+            return None;
+        };
+        let TypeCheckerType::Object(name) = object.as_ref() else {
+            return None;
+        };
+        let path = self.attach_module_if_needed(name.clone());
+        match path.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+            ["std", "core", "Option"] if type_args.len() == 1 => Some(("Option", type_args.clone())),
+            ["std", "core", "Result"] if type_args.len() == 2 => Some(("Result", type_args.clone())),
+            _ => None,
+        }
+    }
+
+    /// Bitwise operators work on integers of the same type, `&`, `|` and `^` also work on booleans.
+    /// Shifts keep the type of the left operand so the compiler can pick an arithmetic or logical shift
+    fn get_bitwise_type<'a>(
+        &self,
+        operator: &BinaryOperator,
+        left: &mut Expression<'a>,
+        right: &mut Expression<'a>,
+        span: Span,
+    ) -> Result<Type<'a>, TypeCheckerError> {
+        let lhs = self.get_type(left);
+        let rhs = self.get_type(right);
+
+        match (lhs, rhs) {
+            (Ok(ty), Err(_)) => {
+                self.annotate_expr(&ty, &mut *right)?;
+            }
+            (Err(_), Ok(ty)) => {
+                self.annotate_expr(&ty, &mut *left)?;
+            }
+            (Err(e), Err(_)) => {
+                return Err(e);
+            }
+            _ => {}
+        }
+
+        let lhs = self.get_type(left)?;
+        let rhs = self.get_type(right)?;
+
+        let (symbol, allows_booleans) = match operator {
+            BinaryOperator::BitAnd => ("&", true),
+            BinaryOperator::BitOr => ("|", true),
+            BinaryOperator::BitXor => ("^", true),
+            BinaryOperator::Shl => ("<<", false),
+            BinaryOperator::Shr => (">>", false),
+            _ => unreachable!("not a bitwise operator"),
+        };
+        for (ty, operand) in [(&lhs, &*left), (&rhs, &*right)] {
+            let is_boolean = matches!(ty, Type::Boolean | Type::U8) && allows_booleans;
+            if !ty.is_integer() && !is_boolean {
+                return Err(
+                    TypeCheckerError::MismatchedTypeForOperation {
+                        current_file: self.current_path.clone(),
+                        expected: "integer".to_string(),
+                        found: ty.to_string(),
+                        operator: symbol.to_string(),
+                        expression_span: span,
+                        error_location: operand.get_span(),
+                    }
+                )
+            }
+        }
+        if lhs != rhs {
+            return Err(
+                TypeCheckerError::UnequalTypes {
+                    current_file: self.current_path.clone(),
+                    left: lhs.to_string(),
+                    right: rhs.to_string(),
+                    expression_span: span,
+                    left_span: left.get_span(),
+                    right_span: right.get_span(),
+                }
+            )
+        }
+        Ok(lhs)
+    }
+
+    /// A value can be used in a with statement if it implements `std::Closeable`
+    fn check_closeable(&self, ty: &TypeCheckerType, binding: &Type, location: Span) -> Result<(), TypeCheckerError> {
+        let binding = TypeCheckerType::from(binding);
+        if !self.compare_types(ty, &binding) {
+            return Err(
+                TypeCheckerError::MismatchedType {
+                    current_file: self.current_path.clone(),
+                    expected: binding.to_string(),
+                    found: ty.to_string(),
+                    location,
+                }
+            );
+        }
+        if !self.implements_trait(ty, "Closeable") {
+            return Err(
+                TypeCheckerError::NotCloseable {
+                    current_file: self.current_path.clone(),
+                    ty: ty.to_string(),
+                    location,
+                }
+            );
+        }
+        Ok(())
+    }
+
+    /// Checks if a type has an `iter` method without parameters that for loops can get an iterator from
+    fn has_iter_method(&self, ty: &TypeCheckerType) -> bool {
+        let iter = self.get_object_class(ty)
+            .and_then(|class| self.class_information.get(&class))
+            .and_then(|(_, attributes)| attributes.get("iter"));
+        matches!(iter, Some(ClassAttribute::Method(TypeCheckerType::Function(params, _))) if params.is_empty())
+    }
+
+    /// Names a class by its full path when it comes from the module of another class and wasn't imported
+    fn qualify_from(&self, ty: TypeCheckerType, module: &[String]) -> TypeCheckerType {
+        match ty {
+            TypeCheckerType::Object(name) if !self.class_information.contains_key(&self.attach_module_if_needed(name.clone())) => {
+                let mut path = module.to_vec();
+                path.push(name);
+                TypeCheckerType::Object(path.join("::"))
+            }
+            TypeCheckerType::TypeArg(object, type_args) => {
+                TypeCheckerType::TypeArg(Box::new(self.qualify_from(*object, module)), type_args)
+            }
+            ty => ty,
+        }
+    }
+
+    fn implements_trait(&self, ty: &TypeCheckerType, trait_name: &str) -> bool {
+        let name = match ty {
+            TypeCheckerType::Object(name) => name,
+            TypeCheckerType::TypeArg(object, _) => match object.as_ref() {
+                TypeCheckerType::Object(name) => name,
+                _ => unreachable!("Only object types can have type parameters"),
+            },
+            _ => return false,
+        };
+
+        let path = self.attach_module_if_needed(name.to_string());
+        self.trait_impls.get(&path)
+            .map(|impls| impls.iter().any(|(name, _)| name == trait_name))
+            .unwrap_or(false)
+    }
+
     fn check_body<'a>(&mut self, return_type: &TypeCheckerType, body: &mut Vec<Statement<'a>>) -> Result<(), TypeCheckerError> {
 
         self.push_scope();
@@ -1346,26 +2835,237 @@ impl TypeChecker {
                 self.annotate_expr(ty, value)?;
                 self.bind_pattern(bindings, ty);
             }
-            Statement::Let { bindings, ty, value, .. } => {
-                self.check_expr(return_type, value)?;
-                self.annotate_expr(ty, value)?;
-                self.bind_pattern(bindings, ty);
+            Statement::Let { bindings, ty, value, .. } => {
+                self.check_expr(return_type, value)?;
+                self.annotate_expr(ty, value)?;
+                let space = self.check_pattern(bindings, &TypeCheckerType::from(&*ty))?;
+                if let Some(missing) = exhaustiveness::find_missing(&[vec![space]], 1) {
+                    return Err(
+                        TypeCheckerError::RefutablePattern {
+                            current_file: self.current_path.clone(),
+                            missing: missing[0].to_string(),
+                            location: bindings.get_span(),
+                        }
+                    );
+                }
+            }
+            Statement::Assignment { target, operator, value, span } => {
+                match target {
+                    Expression::Variable(..)
+                    | Expression::MemberAccess { .. }
+                    | Expression::BinaryOperation { operator: BinaryOperator::Index, .. } => {}
+                    _ => {
+                        return Err(
+                            TypeCheckerError::InvalidAssignmentTarget {
+                                current_file: self.current_path.clone(),
+                                location: target.get_span(),
+                            }
+                        );
+                    }
+                }
+                if let Expression::MemberAccess { object, .. } = target {
+                    // The object is an ordinary expression like `self.buffer[i]` in `self.buffer[i].count = 0`
+                    self.check_expr(return_type, object)?;
+                }
+                if let Some(record) = self.assigned_record(target)? {
+                    let Expression::MemberAccess { field, .. } = target else {
+                        unreachable!("only member accesses can assign to a record");
+                    };
+                    return Err(
+                        TypeCheckerError::ImmutableField {
+                            current_file: self.current_path.clone(),
+                            record: record.join("::"),
+                            field: field.to_string(),
+                            location: *span,
+                        }
+                    );
+                }
+                let lhs = self.get_type(target)?;
+                self.annotate_expr(&lhs, &mut *target)?;
+                if let Some(operator) = operator {
+                    // `a op= b` is checked as `a = a op b` and both sides are taken back out afterwards
+                    let lhs_expr = std::mem::replace(target, Expression::This(*span));
+                    let rhs_expr = std::mem::replace(value, Expression::This(*span));
+                    let mut combined = Expression::new_binary_operation(
+                        operator.clone(),
+                        Box::new(lhs_expr),
+                        Box::new(rhs_expr),
+                        *span,
+                    );
+                    self.check_expr(return_type, &mut combined)?;
+                    let ty = self.get_type(&mut combined)?;
+                    let Expression::BinaryOperation { left, right, .. } = combined else {
+                        unreachable!("compound assignments are binary operations");
+                    };
+                    *target = *left;
+                    *value = *right;
+                    if ty != lhs {
+                        return Err(
+                            TypeCheckerError::UnequalTypes {
+                                current_file: self.current_path.clone(),
+                                left: lhs.to_string(),
+                                right: ty.to_string(),
+                                expression_span: *span,
+                                left_span: target.get_span(),
+                                right_span: value.get_span(),
+                            }
+                        )
+                    }
+                } else {
+                    self.check_expr(return_type, value)?;
+                }
+                self.annotate_expr(&lhs, value)?;
+            }
+            Statement::Expression(expr, _) => {
+                self.check_expr(return_type, expr)?;
+                // Calls keep the type they return so that the compiler can pop a result that isn't used
+                let returned = match expr {
+                    Expression::Call { name, .. } => match self.get_type(name.as_mut())? {
+                        Type::Function(_, return_type, _) => Some(*return_type),
+                        _ => None,
+                    },
+                    Expression::StaticCall { .. } => Some(self.get_type(expr)?),
+                    _ => None,
+                };
+                self.annotate_expr(&Type::Void, &mut *expr)?;
+                if let (Some(ty), Expression::Call { annotation, .. } | Expression::StaticCall { annotation, .. }) = (returned, expr) {
+                    *annotation = Some(ty);
+                }
+            }
+            Statement::While { test, body, ..} => {
+                self.check_expr(return_type, test)?;
+                self.check_body(return_type, body)?;
+            }
+            Statement::With { expr, bindings, bindings_type, block, .. } => {
+                self.check_expr(return_type, expr)?;
+                let ty = TypeCheckerType::from(self.get_type(expr)?);
+                self.check_closeable(&ty, bindings_type, expr.get_span())?;
+                self.push_scope();
+                let space = self.check_pattern(bindings, &TypeCheckerType::from(&*bindings_type))?;
+                if let Some(missing) = exhaustiveness::find_missing(&[vec![space]], 1) {
+                    return Err(
+                        TypeCheckerError::RefutablePattern {
+                            current_file: self.current_path.clone(),
+                            missing: missing[0].to_string(),
+                            location: bindings.get_span(),
+                        }
+                    );
+                }
+                self.check_body(return_type, block)?;
+                self.pop_scope();
+            }
+            Statement::For { bindings, bindings_type, iterable, body, .. } => {
+                self.check_expr(return_type, iterable)?;
+                let mut iterable_type = TypeCheckerType::from(self.get_type(iterable)?);
+                if self.has_iter_method(&iterable_type) {
+                    // Each loop gets its own iterator, so loops over the same collection don't interfere
+                    let span = iterable.get_span();
+                    let collection = std::mem::replace(iterable, Expression::This(span));
+                    let iter = Expression::new_member_access(Box::new(collection), PathName::new(vec![Text::Borrowed("iter")], span), span);
+                    *iterable = Expression::new_call(Box::new(iter), Vec::new(), Vec::new(), span);
+                    self.check_expr(return_type, iterable)?;
+                    // The iterator class doesn't have to be imported where the loop is
+                    let mut module = self.get_object_class(&iterable_type).unwrap_or_default();
+                    module.pop();
+                    iterable_type = self.qualify_from(TypeCheckerType::from(self.get_type(iterable)?), &module);
+                    if let Expression::Call { annotation, .. } = iterable {
+                        *annotation = Some(iterable_type.clone().into());
+                    }
+                }
+                self.check_iterable(&iterable_type, bindings_type, iterable.get_span())?;
+                self.push_scope();
+                self.bind_pattern(bindings, bindings_type);
+                self.check_body(return_type, body)?;
+                self.pop_scope();
+            }
+            Statement::Emit { object, signal, args, span } => {
+                self.check_expr(return_type, object)?;
+                let parameters = self.get_signal(object, signal)?;
+                if args.len() != parameters.len() {
+                    return Err(
+                        TypeCheckerError::ExtraFunctionArgument {
+                            current_file: self.current_path.clone(),
+                            source_file: self.current_path.clone(),
+                            expected: parameters.len(),
+                            found: args.len(),
+                            signature_span: signal.span,
+                            expression_span: *span,
+                        }
+                    );
+                }
+                for (arg, expected_ty) in args.iter_mut().zip(parameters.iter()) {
+                    self.annotate_expr(&expected_ty.clone().into(), &mut *arg)?;
+                    self.check_expr(return_type, arg)?;
+                    let arg_ty = self.get_type(arg)?;
+                    if !self.compare_types(&TypeCheckerType::from(&arg_ty), expected_ty) {
+                        return Err(
+                            TypeCheckerError::MismatchedFunctionArgument {
+                                current_file: self.current_path.clone(),
+                                source_file: self.current_path.clone(),
+                                signature_span: signal.span,
+                                expression_span: arg.get_span(),
+                                expected: expected_ty.to_string(),
+                                found: arg_ty.to_string(),
+                            }
+                        );
+                    }
+                }
+            }
+            Statement::EmitStatic { signal, args, span } => {
+                let class = (signal.segments.len() > 1)
+                    .then(|| signal.segments[signal.segments.len() - 2].as_str());
+                let signal_name = signal.segments.last().unwrap();
+                let parameters = self.get_static_signal(class, signal_name.as_str(), signal.span)?;
+                if args.len() != parameters.len() {
+                    return Err(
+                        TypeCheckerError::ExtraFunctionArgument {
+                            current_file: self.current_path.clone(),
+                            source_file: self.current_path.clone(),
+                            expected: parameters.len(),
+                            found: args.len(),
+                            signature_span: signal.span,
+                            expression_span: *span,
+                        }
+                    );
+                }
+                for (arg, expected_ty) in args.iter_mut().zip(parameters.iter()) {
+                    self.annotate_expr(&expected_ty.clone().into(), &mut *arg)?;
+                    self.check_expr(return_type, arg)?;
+                    let arg_ty = self.get_type(arg)?;
+                    if !self.compare_types(&TypeCheckerType::from(&arg_ty), expected_ty) {
+                        return Err(
+                            TypeCheckerError::MismatchedFunctionArgument {
+                                current_file: self.current_path.clone(),
+                                source_file: self.current_path.clone(),
+                                signature_span: signal.span,
+                                expression_span: arg.get_span(),
+                                expected: expected_ty.to_string(),
+                                found: arg_ty.to_string(),
+                            }
+                        );
+                    }
+                }
             }
-            Statement::Assignment { target, value, .. } => {
-                let lhs = self.get_type(target)?;
-                self.annotate_expr(&lhs, target)?;
-                self.check_expr(return_type, value)?;
-                self.annotate_expr(&lhs, value)?;
+            Statement::Connect { source, signal, target, method, .. } => {
+                self.check_connection(return_type, source, signal, target, method)?;
             }
-            Statement::Expression(expr, _) => {
-                self.check_expr(return_type, expr)?;
-                self.annotate_expr(&Type::Void, expr)?;
+            Statement::Disconnect { source, signal, target, method, .. } => {
+                self.check_connection(return_type, source, signal, target, method)?;
             }
-            Statement::While { test, body, ..} => {
-                self.check_expr(return_type, test)?;
+            Statement::Try { body, catches, finally, .. } => {
                 self.check_body(return_type, body)?;
+                for catch in catches.iter_mut() {
+                    let CatchClause { binding, ty, body, span } = catch;
+                    self.check_exception_type(ty, *span)?;
+                    self.push_scope();
+                    self.insert_var(binding.as_str(), TypeCheckerType::from(ty.clone()));
+                    self.check_body(return_type, body)?;
+                    self.pop_scope();
+                }
+                if let Some(finally) = finally {
+                    self.check_body(return_type, finally)?;
+                }
             }
-            _ => {}
         }
 
         Ok(())
@@ -1378,11 +3078,19 @@ impl TypeChecker {
                 // TODO: check if if expression return values are the same
                 self.check_if_expr(return_type, expr)?;
             }
+            Expression::MatchExpression(expr, span) => {
+                self.check_match_expr(return_type, expr, *span)?;
+            }
+            Expression::Throw(value, span) => {
+                self.check_expr(return_type, value.as_mut())?;
+                let ty = self.get_type(value.as_mut())?;
+                self.check_exception_type(&ty, *span)?;
+            }
             Expression::Return(value, span) => {
                 let result = value.as_mut().map(|value| {
                     self.annotate_expr(&return_type.into(), value.as_mut())?;
                     let ty = self.get_type(value.as_mut())?;
-                    if <&TypeCheckerType as Into<Type>>::into(return_type) != ty {
+                    if !self.compare_types(return_type, &TypeCheckerType::from(&ty)) {
                         return Err(
                             TypeCheckerError::MismatchedReturnType {
                                 current_file: self.current_path.clone(),
@@ -1506,6 +3214,12 @@ impl TypeChecker {
                     _ => todo!("add support for non-array objects with indexing anything other than u64")
                 }
             }
+            Expression::UnaryOperation { operator: UnaryOperator::Try, operand, span } => {
+                let operand = std::mem::replace(operand.as_mut(), Expression::This(*span));
+                let mut desugared = self.desugar_try(return_type, operand, *span)?;
+                self.check_expr(return_type, &mut desugared)?;
+                *expr = desugared;
+            }
             Expression::UnaryOperation { operator: UnaryOperator::Neg, operand, .. } => {
                 let _ty = self.get_type(operand)?;
                 // TODO check if ty is a numeric type
@@ -1517,6 +3231,37 @@ impl TypeChecker {
                     todo!("report boolean operands aren't booleans")
                 }
             }
+            Expression::UnaryOperation { operator: UnaryOperator::BitNot, operand, span } => {
+                self.check_expr(return_type, operand)?;
+                let ty = self.get_type(operand)?;
+
+                if !ty.is_integer() {
+                    return Err(
+                        TypeCheckerError::MismatchedTypeForOperation {
+                            current_file: self.current_path.clone(),
+                            expected: "integer".to_string(),
+                            found: ty.to_string(),
+                            operator: "~".to_string(),
+                            expression_span: *span,
+                            error_location: operand.get_span(),
+                        }
+                    )
+                }
+            }
+            Expression::BinaryOperation {
+                operator: operator @ (BinaryOperator::BitAnd
+                    | BinaryOperator::BitOr
+                    | BinaryOperator::BitXor
+                    | BinaryOperator::Shl
+                    | BinaryOperator::Shr),
+                left,
+                right,
+                span,
+            } => {
+                self.check_expr(return_type, left)?;
+                self.check_expr(return_type, right)?;
+                self.get_bitwise_type(operator, left, right, *span)?;
+            }
             Expression::Variable(name, annotation, span) => {
                 if let Some(ty) = self.lookup_var(&name) {
                     // annotate the expression with the type
@@ -1540,14 +3285,50 @@ impl TypeChecker {
                     }
                 }
             }
-            Expression::Call { name, type_args: _, args, annotation, .. } => {
+            Expression::Call { name, type_args, args, annotation, span } => {
                 self.check_expr(return_type, name)?;
+                // The object is typed before the method, since typing the method gives receivers of bounded methods the type of their bound
+                let object_ty = match name.as_mut() {
+                    Expression::MemberAccess { object, .. } => self.get_type(object.as_mut()).ok(),
+                    _ => None,
+                };
                 let method = self.get_type(name)?;
+                // Methods of generic records take the type arguments of the object they are called on
+                let mut type_params = None;
+                let method = match (name.as_mut(), object_ty) {
+                    (Expression::MemberAccess { field, .. }, Some(object_ty)) => {
+                        let object_ty = TypeCheckerType::from(object_ty);
+                        type_params = self.get_object_class(&object_ty)
+                            .and_then(|class| self.get_method_type_params(&class, field.to_string().as_str()))
+                            .map(|params| (field.to_string(), params));
+                        self.substitute_record_type_args(&object_ty, TypeCheckerType::from(method)).into()
+                    }
+                    _ => method,
+                };
+
+                // check each argument in the call
+                for arg in args.iter_mut() {
+                    self.check_expr(return_type, arg)?;
+                }
+
+                // Generic methods are checked against their signature with the type arguments substituted in
+                let method: Type = match type_params {
+                    Some((method_name, type_params)) => {
+                        let method = self.instantiate_method(&method_name, &type_params, type_args, args, &TypeCheckerType::from(method), *span)?;
+                        if let Expression::MemberAccess { annotation, .. } = name.as_mut() {
+                            *annotation = Some(method.clone().into());
+                        }
+                        if let TypeCheckerType::Function(_, ret) = &method {
+                            *annotation = Some((**ret).clone().into());
+                        }
+                        method.into()
+                    }
+                    None => method,
+                };
+                let generic = !type_args.is_empty();
 
                 let args_len = args.len();
                 for (i, arg) in args.iter_mut().enumerate() {
-                    // check each argument in the call
-                    self.check_expr(return_type, arg)?;
                     let arg_ty = self.get_type(arg)?;
                     match &method {
                         Type::Function(arg_types, ..) => {
@@ -1567,7 +3348,6 @@ impl TypeChecker {
                                     );
                                 }
                                 self.annotate_expr(expected_ty, arg)?;
-                                *annotation = Some(return_type.clone().into());
                             } else {
                                 return Err(
                                     TypeCheckerError::ExtraFunctionArgument {
@@ -1585,8 +3365,25 @@ impl TypeChecker {
                         _ => unreachable!("expected method to be a function type but got {:?}", method),
                     }
                 }
+                if let Type::Function(arg_types, ..) = &method {
+                    if args_len < arg_types.len() {
+                        return Err(
+                            TypeCheckerError::ExtraFunctionArgument {
+                                current_file: self.current_path.clone(),
+                                source_file: self.current_path.clone(),
+                                expected: arg_types.len(),
+                                found: args_len,
+                                signature_span: Span::new(0,0),
+                                expression_span: *span,
+                            }
+                        );
+                    }
+                }
+                if let (false, Type::Function(_, method_return_type, _)) = (generic, &method) {
+                    *annotation = Some(method_return_type.as_ref().clone());
+                }
             }
-            Expression::StaticCall { name, type_args: _, args, annotation, span, .. } => {
+            Expression::StaticCall { name, type_args, args, annotation, span, .. } => {
                 let class_name = if self.active_paths.contains_key(name.segments[0].as_str()) {
                     let mut active_path = self.active_paths.get(name.segments[0].as_str()).unwrap().clone();
                     active_path.extend(
@@ -1623,11 +3420,26 @@ impl TypeChecker {
 
                 let method = method.clone();
 
+                // check each argument in the call
+                for arg in args.iter_mut() {
+                    self.check_expr(return_type, arg)?;
+                }
+
+                // Generic methods are checked against their signature with the type arguments substituted in
+                let method = match self.get_method_type_params(&class_name, method_name.as_str()) {
+                    Some(type_params) => {
+                        let method = self.instantiate_method(method_name.as_str(), &type_params, type_args, args, &method, *span)?;
+                        if let TypeCheckerType::Function(_, ret) = &method {
+                            *annotation = Some((**ret).clone().into());
+                        }
+                        method
+                    }
+                    None => method,
+                };
+
                 let args_len = args.len();
                 for (i, arg) in args.iter_mut().enumerate() {
                     //println!("i: {i} arg: {arg:?}");
-                    // check each argument in the call
-                    self.check_expr(return_type, arg)?;
                     let arg_ty = self.get_type(arg)?;
                     match &method {
                         TypeCheckerType::Function(arg_types, return_type) => {
@@ -1665,6 +3477,20 @@ impl TypeChecker {
                         _ => unreachable!("expected method to be a function type but got {:?}", method),
                     }
                 }
+                if let TypeCheckerType::Function(arg_types, _) = &method {
+                    if args_len < arg_types.len() {
+                        return Err(
+                            TypeCheckerError::ExtraFunctionArgument {
+                                current_file: self.current_path.clone(),
+                                source_file: self.current_path.clone(),
+                                expected: arg_types.len(),
+                                found: args_len,
+                                signature_span: Span::new(0,0),
+                                expression_span: *span,
+                            }
+                        );
+                    }
+                }
 
             }
             Expression::MemberAccess { object, field, annotation, span, .. } => {
@@ -1712,9 +3538,37 @@ impl TypeChecker {
                             _ => unreachable!("Existential can only be Object or TypeArg"),
                         }
                     }
+                    Type::Tuple(tys, _) => {
+                        // Tuple fields are accessed by their position
+                        let ty = field.to_string().parse::<usize>().ok()
+                            .and_then(|index| tys.get(index))
+                            .map(TypeCheckerType::from);
+                        let Some(ty) = ty else {
+                            return Err(
+                                TypeCheckerError::TupleFieldOutOfRange {
+                                    current_file: self.current_path.clone(),
+                                    ty: TypeCheckerType::from(&Type::Tuple(tys.clone(), *span)).to_string(),
+                                    field: field.to_string(),
+                                    location: *span,
+                                }
+                            )
+                        };
+                        *annotation = Some(ty.into());
+                        return Ok(())
+                    }
                     x => todo!("member access is incomplete {x:?}"),
                 };
                 let class_name = name;
+                let member_name = &field.segments[field.segments.len() - 1];
+
+                // Values of a type parameter can only use the methods of its bounds
+                if self.type_param_bounds.contains_key(class_name.as_str()) {
+                    let (bound, method) = self.get_bound_method(class_name.as_str(), member_name.as_str(), *span)?;
+                    Self::annotate_bound_receiver(object, &bound);
+                    *annotation = Some(method.into());
+                    return Ok(())
+                }
+
                 let path = self.attach_module_if_needed(class_name.to_string());
                 if path.len() == 0 {
                     return Err(
@@ -1726,15 +3580,32 @@ impl TypeChecker {
                     )
                 }
 
-                let member_name = &field.segments[field.segments.len() - 1];
+                // Receivers that have already been given the type of a trait bound
+                if let Some((_, attributes)) = self.trait_decl.get(&path) {
+                    if let Some((_, ClassAttribute::Method(method))) = attributes.get(member_name.as_str()) {
+                        *annotation = Some(method.into());
+                        return Ok(())
+                    }
+                }
 
                 let (_, attributes) = self.class_information.get(&path)
                     .expect(&format!("class missing or not loaded: {}",path.join("::")));
 
-                let member = match attributes.get(member_name.as_str()) {
+                let member = match attributes.get(member_name.as_str())
+                    .or_else(|| self.get_attribute(&[String::from("Object")], member_name.as_str())) {
                     Some(ClassAttribute::Method(method)) => method,
                     Some(ClassAttribute::Member(member)) => member,
                     Some(ClassAttribute::StaticMember(member)) => member,
+                    Some(ClassAttribute::Signal(_) | ClassAttribute::StaticSignal(_)) => {
+                        // Signals can only be used with emit, connect, and disconnect
+                        return Err(
+                            TypeCheckerError::AttributeTypeMismatch {
+                                current_file: self.current_path.clone(),
+                                attribute: member_name.to_string(),
+                                access_span: *span,
+                            }
+                        )
+                    }
                     None => &object.get_type().unwrap().unwrap_left().into(),
                 };
 
@@ -1760,10 +3631,34 @@ impl TypeChecker {
                     }
                 }
             }
-            Expression::New(_, arr_size, _) => {
+            Expression::Literal(Literal::Tuple(body, annotation, _)) => {
+                for body in body.iter_mut() {
+                    self.check_expr(return_type, body)?;
+                }
+                if annotation.is_none() {
+                    // Literals in the tuple get their types once the tuple is annotated
+                    let types = body.iter_mut()
+                        .map(|body| self.get_type(body).ok())
+                        .collect::<Option<Vec<_>>>();
+                    *annotation = types.map(|types| Type::Tuple(types, Span::new(0, 0)));
+                }
+            }
+            Expression::New(ty, arr_size, span) => {
                 if let Some(arr_size) = arr_size {
                     self.annotate_expr(&Type::U64, arr_size.as_mut())?;
                 }
+                // The type arguments of generic classes have to satisfy the bounds of their type parameters
+                if let TypeCheckerType::TypeArg(object, args) = TypeCheckerType::from(&*ty) {
+                    if let Some(class) = self.get_object_class(&object) {
+                        if let Some(type_params) = self.class_type_params.get(&class) {
+                            let bindings = type_params.iter()
+                                .map(|(name, _)| name.clone())
+                                .zip(args)
+                                .collect::<HashMap<_, _>>();
+                            self.check_bounds(type_params, &bindings, *span)?;
+                        }
+                    }
+                }
             }
             Expression::ClassAccess {
                 class_name,
@@ -1781,57 +3676,392 @@ impl TypeChecker {
                         .map(ToString::to_string)
                         .collect::<Vec<_>>()
                 };
-                if !self.class_information.contains_key(&class_name) {
-                    todo!("report missing class")
+                if !self.class_information.contains_key(&class_name) {
+                    todo!("report missing class")
+                }
+            }
+            Expression::BinaryOperation { operator: BinaryOperator::Lt, left, right, .. }
+            | Expression::BinaryOperation { operator: BinaryOperator::Le, left, right, .. }
+            | Expression::BinaryOperation { operator: BinaryOperator::Gt, left, right, .. }
+            | Expression::BinaryOperation { operator: BinaryOperator::Ge, left, right, .. }
+            | Expression::BinaryOperation { operator: BinaryOperator::Eq, left, right, .. }
+            | Expression::BinaryOperation { operator: BinaryOperator::Ne, left, right, .. } => {
+                // TODO: add conversion when traits are added
+                // TODO: make it so that types get upgraded if they are compatable
+                self.check_expr(return_type, left)?;
+                let ty = self.get_type(left.as_mut())?;
+                self.annotate_expr(&ty, left.as_mut())?;
+                self.annotate_expr(&ty, right.as_mut())?;
+                self.check_expr(return_type, right)?;
+                self.annotate_expr(&Type::U8, expr)?;
+            }
+            Expression::BinaryOperation { operator: BinaryOperator::Add, left, right, .. }
+            | Expression::BinaryOperation { operator: BinaryOperator::Sub, left, right, .. }
+            | Expression::BinaryOperation { operator: BinaryOperator::Mul, left, right, .. }
+            | Expression::BinaryOperation { operator: BinaryOperator::Div, left, right, .. }
+            | Expression::BinaryOperation { operator: BinaryOperator::Mod, left, right, .. } => {
+                // TODO: add conversion when traits are added
+                // TODO: make it so that types get upgraded if they are compatable
+                self.check_expr(return_type, left)?;
+                let ty = self.get_type(left.as_mut())?;
+                self.annotate_expr(&ty, left.as_mut())?;
+                self.annotate_expr(&ty, right.as_mut())?;
+                self.check_expr(return_type, right)?;
+            }
+            Expression::Closure { params, return_type, body, .. } => {
+                self.push_scope();
+                for param in params {
+                    match param {
+                        ClosureParameter::Typed(Parameter::Pattern { name, ty, ..}) => {
+                            self.bind_pattern(name, ty)
+                        }
+                        _ => todo!("handle type deduction of closures")
+                    }
+                }
+                let return_type: TypeCheckerType = return_type.as_ref().unwrap().into();
+                self.check_body(&return_type, body)?;
+                
+                self.pop_scope();
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn check_match_expr<'a>(&mut self, return_type: &TypeCheckerType, expr: &mut MatchExpression<'a>, span: Span) -> Result<(), TypeCheckerError> {
+        let MatchExpression { value, arms, annotation, .. } = expr;
+
+        self.check_expr(return_type, value.as_mut())?;
+        let ty = TypeCheckerType::from(self.get_type(value.as_mut())?);
+
+        let mut rows = Vec::new();
+        let mut arm_type = None;
+        let mut has_value = false;
+        for arm in arms.iter_mut() {
+            let MatchArm { pattern, value, .. } = arm;
+            self.push_scope();
+            let space = self.check_pattern(pattern, &ty)?;
+            match value {
+                Either::Left(value) => {
+                    self.check_expr(return_type, value)?;
+                    if !Self::diverges(value) {
+                        has_value = true;
+                        // Literals get their type from the other arms, so they might not have one yet
+                        if arm_type.is_none() {
+                            arm_type = self.get_type(value).ok();
+                        }
+                    }
+                }
+                Either::Right(body) => {
+                    self.check_body(return_type, body)?;
+                }
+            }
+            self.pop_scope();
+
+            if !exhaustiveness::is_useful(&rows, &[space.clone()]) {
+                return Err(
+                    TypeCheckerError::UnreachableMatchArm {
+                        current_file: self.current_path.clone(),
+                        location: pattern.get_span(),
+                    }
+                );
+            }
+            rows.push(vec![space]);
+        }
+
+        if let Some(missing) = exhaustiveness::find_missing(&rows, 1) {
+            return Err(
+                TypeCheckerError::NonExhaustiveMatch {
+                    current_file: self.current_path.clone(),
+                    missing: missing[0].to_string(),
+                    location: span,
+                }
+            );
+        }
+
+        if let Some(arm_type) = &arm_type {
+            for arm in arms.iter_mut() {
+                let MatchArm { pattern, value: Either::Left(value), .. } = arm else {
+                    continue;
+                };
+                if Self::diverges(value) {
+                    continue;
+                }
+                self.push_scope();
+                self.check_pattern(pattern, &ty)?;
+                self.annotate_expr(arm_type, &mut *value)?;
+                let found = self.get_type(value)?;
+                self.pop_scope();
+                if !self.compare_types(&TypeCheckerType::from(&found), &TypeCheckerType::from(arm_type)) {
+                    return Err(
+                        TypeCheckerError::MismatchedType {
+                            current_file: self.current_path.clone(),
+                            expected: arm_type.to_string(),
+                            found: found.to_string(),
+                            location: value.get_span(),
+                        }
+                    );
+                }
+            }
+        }
+
+        *annotation = match arm_type {
+            Some(ty) => Some(ty),
+            // The arms are all literals, so the type has to come from where the match is used
+            None if has_value => None,
+            None => Some(Type::Void),
+        };
+
+        Ok(())
+    }
+
+    /// Returns true for expressions that never produce a value because they leave the current block
+    fn diverges(expr: &Expression) -> bool {
+        matches!(expr, Expression::Return(..) | Expression::Throw(..) | Expression::Break(..) | Expression::Continue(..))
+    }
+
+    /// Checks a pattern against the type of the value it matches and binds the variables in it.
+    /// Returns the values the pattern covers for checking exhaustiveness.
+    fn check_pattern<'a>(&mut self, pattern: &mut Pattern<'a>, ty: &TypeCheckerType) -> Result<Space, TypeCheckerError> {
+        // A lone name is a class test when there is a class with that name
+        if let Pattern::Variable(name, _, span) = pattern {
+            let path = self.attach_module_if_needed(name.to_string());
+            if self.class_information.contains_key(&path) {
+                *pattern = Pattern::new_class(name.clone(), Vec::new(), *span);
+            }
+        }
+
+        match pattern {
+            Pattern::WildCard(_) => Ok(Space::Wild),
+            Pattern::Variable(name, _, _) if name.as_str() == "_" => Ok(Space::Wild),
+            Pattern::Variable(name, _, _) => {
+                self.insert_var(name, ty.clone());
+                Ok(Space::Wild)
+            }
+            Pattern::Constant(constant) => self.check_constant_pattern(constant, ty),
+            Pattern::Tuple(patterns, span) => {
+                let tys = match ty {
+                    TypeCheckerType::Tuple(tys) if tys.len() == patterns.len() => tys,
+                    _ => {
+                        let found = vec!["_"; patterns.len()].join(", ");
+                        return Err(
+                            TypeCheckerError::MismatchedType {
+                                current_file: self.current_path.clone(),
+                                expected: ty.to_string(),
+                                found: format!("({found})"),
+                                location: *span,
+                            }
+                        );
+                    }
+                };
+                let mut fields = Vec::new();
+                for (pattern, ty) in patterns.iter_mut().zip(tys.iter()) {
+                    fields.push(self.check_pattern(pattern, ty)?);
+                }
+                Ok(Space::Constructor(Constructor::Tuple(fields.len()), fields))
+            }
+            Pattern::Class { name, fields, ty: pattern_ty, members, span } => {
+                let path = self.attach_module_if_needed(name.to_string());
+                if !self.class_information.contains_key(&path) {
+                    return Err(
+                        TypeCheckerError::MissingImport {
+                            current_file: self.current_path.clone(),
+                            suggestion: name.to_string(),
+                            span: *span,
+                        }
+                    );
+                }
+
+                let (matched_name, type_args) = match ty {
+                    TypeCheckerType::Object(name) => (name.clone(), Vec::new()),
+                    TypeCheckerType::TypeArg(object, type_args) => {
+                        let TypeCheckerType::Object(name) = object.as_ref() else {
+                            unreachable!("TypeArg should start with an object")
+                        };
+                        (name.clone(), type_args.clone())
+                    }
+                    _ => (String::new(), Vec::new()),
+                };
+                let matched_path = self.attach_module_if_needed(matched_name);
+                let related = self.is_subclass_of(&path, &matched_path) || self.is_subclass_of(&matched_path, &path);
+                if !related {
+                    return Err(
+                        TypeCheckerError::MismatchedType {
+                            current_file: self.current_path.clone(),
+                            expected: ty.to_string(),
+                            found: name.to_string(),
+                            location: *span,
+                        }
+                    );
+                }
+
+                let Some(information) = self.records.get(&path) else {
+                    // Classes without records' fixed layout can only be tested for
+                    if !fields.is_empty() {
+                        return Err(
+                            TypeCheckerError::WrongNumberOfFields {
+                                current_file: self.current_path.clone(),
+                                class: name.to_string(),
+                                expected: 0,
+                                found: fields.len(),
+                                location: *span,
+                            }
+                        );
+                    }
+                    *pattern_ty = Some(Type::Object(name.clone(), *span));
+                    if self.is_subclass_of(&matched_path, &path) {
+                        return Ok(Space::Wild);
+                    }
+                    let constructor = Constructor::Class { path, fields: 0, siblings: None };
+                    return Ok(Space::Constructor(constructor, Vec::new()));
+                };
+
+                if !fields.is_empty() && fields.len() != information.fields.len() {
+                    return Err(
+                        TypeCheckerError::WrongNumberOfFields {
+                            current_file: self.current_path.clone(),
+                            class: name.to_string(),
+                            expected: information.fields.len(),
+                            found: fields.len(),
+                            location: *span,
+                        }
+                    );
+                }
+
+                // Fields of generic records use the type arguments of the value being matched
+                let substitute = !information.type_params.is_empty() && information.type_params.len() == type_args.len();
+                let substitutions = information.type_params.iter()
+                    .cloned()
+                    .zip(type_args.iter().cloned())
+                    .collect::<HashMap<_, _>>();
+                let field_types = information.fields.iter()
+                    .map(|(field, field_ty)| (field.clone(), Self::substitute_type_params(field_ty, &substitutions)))
+                    .collect::<Vec<_>>();
+
+                *pattern_ty = Some(if substitute {
+                    let type_args = type_args.iter().map(Into::into).collect();
+                    Type::TypeArg(Box::new(Type::Object(name.clone(), *span)), type_args, *span)
+                } else {
+                    Type::Object(name.clone(), *span)
+                });
+
+                // A variant can only be one of its union's variants when the value is the union
+                let siblings = match &information.union {
+                    Some(union) if *union == matched_path => {
+                        let variants = self.unions.get(union).expect("union should have been loaded");
+                        variants.iter()
+                            .map(|variant| (variant.clone(), self.records[variant].fields.len()))
+                            .collect()
+                    }
+                    _ => vec![(path.clone(), field_types.len())],
+                };
+
+                let mut spaces = Vec::new();
+                if fields.is_empty() {
+                    spaces = vec![Space::Wild; field_types.len()];
+                    members.clear();
+                } else {
+                    *members = field_types.iter()
+                        .map(|(field, ty)| (Text::Owned(field.clone()), ty.into()))
+                        .collect();
+                    for (pattern, (_, ty)) in fields.iter_mut().zip(field_types.iter()) {
+                        spaces.push(self.check_pattern(pattern, ty)?);
+                    }
                 }
+
+                let constructor = Constructor::Class { path, fields: field_types.len(), siblings: Some(siblings) };
+                Ok(Space::Constructor(constructor, spaces))
             }
-            Expression::BinaryOperation { operator: BinaryOperator::Lt, left, right, .. }
-            | Expression::BinaryOperation { operator: BinaryOperator::Le, left, right, .. }
-            | Expression::BinaryOperation { operator: BinaryOperator::Gt, left, right, .. }
-            | Expression::BinaryOperation { operator: BinaryOperator::Ge, left, right, .. }
-            | Expression::BinaryOperation { operator: BinaryOperator::Eq, left, right, .. }
-            | Expression::BinaryOperation { operator: BinaryOperator::Ne, left, right, .. } => {
-                // TODO: add conversion when traits are added
-                // TODO: make it so that types get upgraded if they are compatable
-                self.check_expr(return_type, left)?;
-                let ty = self.get_type(left.as_mut())?;
-                self.annotate_expr(&ty, left.as_mut())?;
-                self.annotate_expr(&ty, right.as_mut())?;
-                self.check_expr(return_type, right)?;
-                self.annotate_expr(&Type::U8, expr)?;
-            }
-            Expression::BinaryOperation { operator: BinaryOperator::Add, left, right, .. }
-            | Expression::BinaryOperation { operator: BinaryOperator::Sub, left, right, .. }
-            | Expression::BinaryOperation { operator: BinaryOperator::Mul, left, right, .. }
-            | Expression::BinaryOperation { operator: BinaryOperator::Div, left, right, .. }
-            | Expression::BinaryOperation { operator: BinaryOperator::Mod, left, right, .. } => {
-                // TODO: add conversion when traits are added
-                // TODO: make it so that types get upgraded if they are compatable
-                self.check_expr(return_type, left)?;
-                let ty = self.get_type(left.as_mut())?;
-                self.annotate_expr(&ty, left.as_mut())?;
-                self.annotate_expr(&ty, right.as_mut())?;
-                self.check_expr(return_type, right)?;
+        }
+    }
+
+    fn check_constant_pattern(&self, constant: &mut Constant, ty: &TypeCheckerType) -> Result<Space, TypeCheckerError> {
+        use TypeCheckerType::*;
+        let (matches, found) = match (&*constant, ty) {
+            (Constant::Integer(..), U8 | U16 | U32 | U64 | I8 | I16 | I32 | I64) => (true, "integer"),
+            (Constant::Integer(..), _) => (false, "integer"),
+            (Constant::Float(..), F32 | F64) => (true, "float"),
+            (Constant::Float(..), _) => (false, "float"),
+            (Constant::Bool(..), Boolean | U8) => (true, "bool"),
+            (Constant::Bool(..), _) => (false, "bool"),
+            (Constant::Character(..), Char) => (true, "char"),
+            (Constant::Character(..), _) => (false, "char"),
+            (Constant::String(..), Object(name)) => (name == "String" || name == "InternedString", "String"),
+            (Constant::String(..), _) => (false, "String"),
+        };
+        if !matches {
+            return Err(
+                TypeCheckerError::MismatchedType {
+                    current_file: self.current_path.clone(),
+                    expected: ty.to_string(),
+                    found: found.to_string(),
+                    location: constant.get_span(),
+                }
+            );
+        }
+
+        let constructor = match constant {
+            Constant::Integer(value, annotation, _) | Constant::Float(value, annotation, _) => {
+                *annotation = Some(ty.into());
+                Constructor::Constant(value.to_string())
             }
-            Expression::Closure { params, return_type, body, .. } => {
-                self.push_scope();
-                for param in params {
-                    match param {
-                        ClosureParameter::Typed(Parameter::Pattern { name, ty, ..}) => {
-                            self.bind_pattern(name, ty)
-                        }
-                        _ => todo!("handle type deduction of closures")
+            Constant::Bool(value, _) => Constructor::Bool(*value),
+            Constant::Character(value, _) => Constructor::Constant(format!("'{value}'")),
+            // Strings would have to be compared by their contents, which there isn't a bytecode for
+            Constant::String(_, span) => {
+                return Err(
+                    TypeCheckerError::StringPattern {
+                        current_file: self.current_path.clone(),
+                        location: *span,
                     }
-                }
-                let return_type: TypeCheckerType = return_type.as_ref().unwrap().into();
-                self.check_body(&return_type, body)?;
-                
-                self.pop_scope();
+                );
             }
-            _ => {}
+        };
+        Ok(Space::Constructor(constructor, Vec::new()))
+    }
+
+    /// Replaces type parameters with the type arguments they stand for
+    /// Replaces the type parameters of a generic class, record or variant in `ty` with the type arguments of `object_ty`
+    fn substitute_record_type_args(&self, object_ty: &TypeCheckerType, ty: TypeCheckerType) -> TypeCheckerType {
+        let TypeCheckerType::TypeArg(object, type_args) = object_ty else {
+            return ty;
+        };
+        let TypeCheckerType::Object(name) = object.as_ref() else {
+            return ty;
+        };
+        let path = self.attach_module_if_needed(name.clone());
+        let type_params = match (self.records.get(&path), self.class_type_params.get(&path)) {
+            (Some(information), _) => information.type_params.clone(),
+            (None, Some(type_params)) => type_params.iter().map(|(name, _)| name.clone()).collect(),
+            (None, None) => return ty,
+        };
+        if type_params.len() != type_args.len() {
+            return ty;
         }
+        let substitutions = type_params.into_iter()
+            .zip(type_args.iter().cloned())
+            .collect::<HashMap<_, _>>();
+        Self::substitute_type_params(&ty, &substitutions)
+    }
 
-        Ok(())
+    fn substitute_type_params(ty: &TypeCheckerType, substitutions: &HashMap<String, TypeCheckerType>) -> TypeCheckerType {
+        match ty {
+            TypeCheckerType::Object(name) => substitutions.get(name).cloned().unwrap_or_else(|| ty.clone()),
+            TypeCheckerType::TypeArg(object, args) => TypeCheckerType::TypeArg(
+                Box::new(Self::substitute_type_params(object, substitutions)),
+                args.iter().map(|arg| Self::substitute_type_params(arg, substitutions)).collect(),
+            ),
+            TypeCheckerType::Array(ty) => TypeCheckerType::Array(Box::new(Self::substitute_type_params(ty, substitutions))),
+            TypeCheckerType::Tuple(tys) => TypeCheckerType::Tuple(
+                tys.iter().map(|ty| Self::substitute_type_params(ty, substitutions)).collect()
+            ),
+            TypeCheckerType::Function(args, ret) => TypeCheckerType::Function(
+                args.iter().map(|arg| Self::substitute_type_params(arg, substitutions)).collect(),
+                Box::new(Self::substitute_type_params(ret, substitutions)),
+            ),
+            ty => ty.clone(),
+        }
     }
 
     fn check_if_expr<'a>(&mut self, return_type: &TypeCheckerType, expr: &mut IfExpression<'a>) -> Result<(), TypeCheckerError> {
@@ -1868,7 +4098,7 @@ impl TypeChecker {
         use crate::trees::ast::{Expression, Literal, Constant};
         //println!("Expression: {:#?}", expr);
         match expr {
-            Expression::Literal(Literal::Constant(Constant::Bool(_, _))) => Ok(Type::U8),
+            Expression::Literal(Literal::Constant(Constant::Bool(_, _))) => Ok(Type::Boolean),
             Expression::Literal(Literal::Constant(Constant::Float(_, annotation, span))) => match annotation {
                 Some(ty) => Ok(ty.clone()),
                 None => Err(TypeCheckerError::UnableToDeduceType { 
@@ -1884,6 +4114,9 @@ impl TypeChecker {
                 }),
             },
             Expression::Literal(Literal::Constant(Constant::Character(_, _))) => Ok(Type::Char),
+            Expression::Literal(Literal::Constant(Constant::String(_, _))) => {
+                Ok(TypeCheckerType::Object(String::from("String")).into())
+            }
             Expression::Variable(name, annotation, span) => {
                 if let Some(ty) = self.lookup_var(&name) {
                     *annotation = Some(ty.into());
@@ -1948,9 +4181,15 @@ impl TypeChecker {
                         );
                         active_path
                     } else {
-                        name.segments[..name.segments.len() - 1].iter()
+                        let class_name = name.segments[..name.segments.len() - 1].iter()
                             .map(ToString::to_string)
-                            .collect::<Vec<_>>()
+                            .collect::<Vec<_>>();
+
+                        if class_name.len() == 1 {
+                            self.attach_module_if_needed(class_name[0].clone())
+                        } else {
+                            class_name
+                        }
                     };
 
 
@@ -2035,6 +4274,28 @@ impl TypeChecker {
             | Expression::BinaryOperation { operator: BinaryOperator::Or, .. }=> {
                 Ok(Type::Boolean)
             }
+            Expression::BinaryOperation {
+                operator: operator @ (BinaryOperator::BitAnd
+                    | BinaryOperator::BitOr
+                    | BinaryOperator::BitXor
+                    | BinaryOperator::Shl
+                    | BinaryOperator::Shr),
+                left,
+                right,
+                span,
+            } => {
+                self.get_bitwise_type(operator, left, right, *span)
+            }
+            Expression::UnaryOperation { operator: UnaryOperator::BitNot, operand, .. } => {
+                self.get_type(operand)
+            }
+            Expression::Literal(Literal::Tuple(_, ty, span)) => match ty {
+                Some(ty) => Ok(ty.clone()),
+                None => Err(TypeCheckerError::UnableToDeduceType {
+                    current_file: self.current_path.clone(),
+                    start: span.start, end: span.end
+                }),
+            },
             Expression::Literal(Literal::Array(_, ty, _)) => {
                 if let Some(ty) = ty {
                     Ok(Type::Array(Box::new(ty.clone()), Span::new(0, 0)))
@@ -2079,6 +4340,14 @@ impl TypeChecker {
                 
                 Ok(Type::Function(arg_types, Box::new(return_type), Span::new(0, 0)))
             }
+            Expression::Throw(_, _) => Ok(Type::Void),
+            Expression::MatchExpression(match_expr, span) => match &match_expr.annotation {
+                Some(ty) => Ok(ty.clone()),
+                None => Err(TypeCheckerError::UnableToDeduceType {
+                    current_file: self.current_path.clone(),
+                    start: span.start, end: span.end
+                }),
+            },
             x => todo!("finish get_type: {:?}", x),
         }
     }
@@ -2086,6 +4355,10 @@ impl TypeChecker {
     fn get_type_member_access<'a>(&self, expr: &mut Expression<'a>) -> Result<Type<'a>, TypeCheckerError> {
         match expr {
             Expression::MemberAccess { object, field, annotation, .. } => {
+                // Tuple fields have already been annotated when the access was checked
+                if let (Some(ty), Ok(_)) = (annotation.as_ref(), field.to_string().parse::<usize>()) {
+                    return Ok(ty.clone());
+                }
                 match object.as_mut() {
                     Expression::Variable(name,ty, _) => {
                         let var_ty = self.lookup_var(name) // lookup the type of the variable
@@ -2094,6 +4367,12 @@ impl TypeChecker {
                             })?;
                         *ty = Some(var_ty.into()); // annotate the type of the variable
                         match var_ty {
+                            TypeCheckerType::Object(name) if self.type_param_bounds.contains_key(name) => {
+                                let (bound, method) = self.get_bound_method(name, field.to_string().as_str(), field.span)?;
+                                *ty = Some(bound.into());
+                                *annotation = Some(method.clone().into());
+                                Ok(method.into())
+                            }
                             TypeCheckerType::Object(name) => {
                                 let path = self.attach_module_if_needed(name.to_string());
 
@@ -2113,7 +4392,7 @@ impl TypeChecker {
                                         Ok(ty.clone().into())
                                     }
                                     _ => {
-                                        loop {
+                                        while parent.as_str() != "" {
                                             let path = self.attach_module_if_needed(parent.to_string());
                                             let (new_parent, attributes) = self.class_information.get(&path).unwrap();
 
@@ -2134,11 +4413,20 @@ impl TypeChecker {
                                                 }
                                                 _ => {}
                                             }
+                                        }
 
-                                            if parent.as_str() == "" {
-                                                break;
+                                        // Here we try to use attributes from traits
+                                        let impls = self.trait_impls.get(&path).into_iter().flatten();
+                                        for (_, attributes) in impls {
+                                            match attributes.get(&field.to_string()) {
+                                                Some(ClassAttribute::Method(ty)) => {
+                                                    *annotation = Some(ty.into());
+                                                    return Ok(ty.clone().into())
+                                                }
+                                                _ => {}
                                             }
                                         }
+
                                         eprintln!("Failed to find attribute {} in class {}", field.to_string(), name);
                                         todo!("report unknown member access")
                                     }
@@ -2166,7 +4454,7 @@ impl TypeChecker {
                                             }
                                             _ => {
                                                 // Here we try to use the parent's attributes to find the attribute
-                                                loop {
+                                                while parent.as_str() != "" {
                                                     let path = self.attach_module_if_needed(parent.to_string());
                                                     let (new_parent, attributes) = self.class_information.get(&path).unwrap();
 
@@ -2187,14 +4475,10 @@ impl TypeChecker {
                                                         }
                                                         _ => {}
                                                     }
-
-                                                    if parent.as_str() == "" {
-                                                        break;
-                                                    }
                                                 }
 
                                                 // Here we try to use attributes from traits
-                                                let attributes = self.trait_impls.get(&path).unwrap();
+                                                let attributes = self.trait_impls.get(&path).into_iter().flatten();
 
                                                 for (_, attributes) in attributes {
                                                     match attributes.get(&field.to_string()) {
@@ -2289,7 +4573,7 @@ impl TypeChecker {
                             }
                         }
                     }
-                    Expression::MemberAccess {..} => {
+                    Expression::MemberAccess {..} | Expression::BinaryOperation { operator: BinaryOperator::Index, .. } => {
                         let ty = self.get_type_member_access(object.as_mut())?;
                         let name = match ty {
                             Type::Object(name, _) => name,
@@ -2299,11 +4583,20 @@ impl TypeChecker {
                             _ => unreachable!("Only object types can have type parameters"),
                         };
 
+                        if self.type_param_bounds.contains_key(name.as_str()) {
+                            let (bound, method) = self.get_bound_method(name.as_str(), field.to_string().as_str(), field.span)?;
+                            Self::annotate_bound_receiver(object, &bound);
+                            *annotation = Some(method.clone().into());
+                            return Ok(method.into())
+                        }
+
                         let path = self.attach_module_if_needed(name.to_string());
 
                         let (_, attributes) = self.class_information.get(&path).unwrap();
 
-                        match attributes.get(field.to_string().as_str()) {
+                        // TODO: change this to look at base classes
+                        match attributes.get(field.to_string().as_str())
+                            .or_else(|| self.get_attribute(&[String::from("Object")], field.to_string())) {
                             Some(ClassAttribute::Member(ty)) => {
                                 *annotation = Some(ty.into());
                                 Ok(ty.clone().into())
@@ -2323,6 +4616,32 @@ impl TypeChecker {
                         }
 
                     },
+                    Expression::New(ty, None, _) => {
+                        let name = match ty {
+                            Type::Object(name, _) => name.to_string(),
+                            Type::TypeArg(object, _, _) => {
+                                let Type::Object(name, _) = object.as_ref() else {
+                                    unreachable!("TypeArg should only be object")
+                                };
+                                name.to_string()
+                            }
+                            _ => todo!("report constructor call on a non-object type"),
+                        };
+                        let path = self.attach_module_if_needed(name.clone());
+                        match self.get_inherited_attribute(&path, field.to_string().as_str()) {
+                            Some(ClassAttribute::Method(ty)) => {
+                                *annotation = Some(ty.into());
+                                Ok(ty.clone().into())
+                            }
+                            _ => {
+                                Err(TypeCheckerError::MissingConstructor {
+                                    current_file: self.current_path.clone(),
+                                    class: name,
+                                    location: field.span,
+                                })
+                            }
+                        }
+                    }
                     Expression::ClassAccess { class_name, span: _span } => {
 
 
@@ -2341,6 +4660,15 @@ impl TypeChecker {
                             Some(ClassAttribute::Method(method)) => method,
                             Some(ClassAttribute::Member(member)) => member,
                             Some(ClassAttribute::StaticMember(member)) => member,
+                            Some(ClassAttribute::Signal(_) | ClassAttribute::StaticSignal(_)) => {
+                                return Err(
+                                    TypeCheckerError::AttributeTypeMismatch {
+                                        current_file: self.current_path.clone(),
+                                        attribute: member_name.to_string(),
+                                        access_span: field.span,
+                                    }
+                                )
+                            }
                             None => &object.get_type().unwrap().unwrap_left().into(),
                         };
 
@@ -2444,6 +4772,26 @@ impl TypeChecker {
             (
                 ty, Expression::BinaryOperation {
                 operator: BinaryOperator::Mod, left, right, .. }
+            ) |
+            (
+                ty, Expression::BinaryOperation {
+                operator: BinaryOperator::BitAnd, left, right, .. }
+            ) |
+            (
+                ty, Expression::BinaryOperation {
+                operator: BinaryOperator::BitOr, left, right, .. }
+            ) |
+            (
+                ty, Expression::BinaryOperation {
+                operator: BinaryOperator::BitXor, left, right, .. }
+            ) |
+            (
+                ty, Expression::BinaryOperation {
+                operator: BinaryOperator::Shl, left, right, .. }
+            ) |
+            (
+                ty, Expression::BinaryOperation {
+                operator: BinaryOperator::Shr, left, right, .. }
             ) => {
                 self.annotate_expr(ty, left.as_mut())?;
                 self.annotate_expr(ty, right.as_mut())?;
@@ -2458,6 +4806,9 @@ impl TypeChecker {
             (ty, Expression::Parenthesized(expr, _)) => {
                 self.annotate_expr(ty, expr.as_mut())?;
             }
+            (ty, Expression::UnaryOperation { operator: UnaryOperator::BitNot, operand, .. }) => {
+                self.annotate_expr(ty, operand.as_mut())?;
+            }
             (ty, Expression::Call { name, annotation, span, ..}) => {
                 let access_ty = self.get_type(name.as_mut())?;
 
@@ -2477,7 +4828,12 @@ impl TypeChecker {
                     path.extend(name.segments[1..name.segments.len() - 1].iter().map(ToString::to_string));
                     path
                 } else {
-                    name.segments[..name.segments.len() - 1].iter().map(ToString::to_string).collect()
+                    let path = name.segments[..name.segments.len() - 1].iter().map(ToString::to_string).collect::<Vec<_>>();
+                    if path.len() == 1 {
+                        self.attach_module_if_needed(path[0].clone())
+                    } else {
+                        path
+                    }
                 };
 
                 let (_, attributes) = self.class_information.get(&path).expect(&format!("no information for class {}", path.join("::")));
@@ -2503,6 +4859,10 @@ impl TypeChecker {
             | (_, Expression::BinaryOperation { operator: BinaryOperator::Eq, left, right, .. })
             | (_, Expression::BinaryOperation { operator: BinaryOperator::Ne, left, right, .. }) => {
                 let lhs = self.get_type(left.as_mut())?;
+                if let Expression::MemberAccess { .. } = right.as_ref() {
+                    // The object of `holder.point` needs its type before the access is annotated
+                    self.get_type(right.as_mut())?;
+                }
                 self.annotate_expr(&lhs, right.as_mut())?;
             }
             (ty, Expression::MemberAccess { annotation, ..}) => {
@@ -2514,9 +4874,539 @@ impl TypeChecker {
                     //println!("arr_size: {:?}", arr_size);
                 }
             }
+            (ty, Expression::MatchExpression(match_expr, _)) if match_expr.annotation.is_none() => {
+                for arm in match_expr.arms.iter_mut() {
+                    if let Either::Left(value) = &mut arm.value {
+                        if !Self::diverges(value) {
+                            self.annotate_expr(ty, value)?;
+                        }
+                    }
+                }
+                match_expr.annotation = Some(ty.clone());
+            }
             _ => {}
         }
         Ok(())
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser;
+
+    const SIZED: &str = "module main;
+
+trait Sized {
+    fn size(self) -> u64
+}
+
+class Box {
+    pub v: u64;
+}
+
+impl Sized for Box {
+    fn size(self) -> u64 {
+        return self.v;
+    }
+}
+
+class Plain {
+    pub v: u64;
+}
+";
+
+    const POINT: &str = "module main;
+
+record Point {
+    pub x: u64
+    pub y: u64
+}
+
+union Shape {
+    Circle(radius: u64)
+    Empty
+}
+
+class Holder {
+    pub point: Point
+}
+";
+
+    /// The std modules that the traits and unions used by the language come from
+    const STD: [(&str, &str); 3] = [
+        ("std/core", include_str!("../../std/core.rowan")),
+        ("std/closeable", include_str!("../../std/closeable.rowan")),
+        ("std/iter", include_str!("../../std/iter.rowan")),
+    ];
+
+    /// Parses the source as the module `main` and type checks it with the small std modules, returning the first error
+    fn check(source: &str) -> Result<File<'static>, TypeCheckerError> {
+        let mut files = Vec::new();
+        for (path, source) in std::iter::once(("main", source)).chain(STD) {
+            let source: &'static String = Box::leak(Box::new(source.to_string()));
+            let file = parser::parse(path, path, source).expect("test source should parse");
+            files.push((path.to_string(), file, source));
+        }
+        if let Some(error) = TypeChecker::new().collect_errors(&mut files)?.into_iter().next() {
+            return Err(error);
+        }
+        Ok(files.remove(0).1)
+    }
+
+    fn get_method<'a>(file: &'a File<'static>, class_name: &str, method_name: &str) -> &'a Method<'static> {
+        file.content.iter()
+            .find_map(|statement| match statement {
+                TopLevelStatement::Class(class) if class.name.as_str() == class_name => {
+                    class.methods.iter().find(|method| method.name.as_str() == method_name)
+                }
+                _ => None,
+            })
+            .expect("test source should have the method")
+    }
+
+    #[test]
+    fn test_satisfied_bound_is_accepted() {
+        let source = format!("{SIZED}
+class Main {{
+    fn get[T: extends Sized](a: T) -> u64 {{
+        return a.size();
+    }}
+
+    fn main(args: [String]) {{
+        let bx: Box = new Box();
+        let size: u64 = Main::get(bx);
+    }}
+}}
+");
+        assert!(check(&source).is_ok());
+    }
+
+    #[test]
+    fn test_violated_bound_is_rejected() {
+        let source = format!("{SIZED}
+class Main {{
+    fn get[T: extends Sized](a: T) -> u64 {{
+        return a.size();
+    }}
+
+    fn main(args: [String]) {{
+        let plain: Plain = new Plain();
+        let size: u64 = Main::get(plain);
+    }}
+}}
+");
+        let error = check(&source).expect_err("Plain doesn't implement Sized");
+        assert!(matches!(error, TypeCheckerError::UnsatisfiedBound { ty, bound, .. } if ty == "Plain" && bound == "Sized"));
+    }
+
+    #[test]
+    fn test_bound_method_receiver_has_the_bound_type() {
+        let source = format!("{SIZED}
+class Main {{
+    fn get[T: extends Sized](a: T) -> u64 {{
+        let b: T = a;
+        return b.size();
+    }}
+}}
+");
+        let file = check(&source).unwrap();
+        let method = get_method(&file, "Main", "get");
+        let Statement::Expression(Expression::Return(Some(value), _), _) = &method.body[1] else {
+            panic!("the method should end with a return");
+        };
+        let Expression::Call { name, .. } = value.as_ref() else {
+            panic!("the method should return a call");
+        };
+        let Expression::MemberAccess { object, .. } = name.as_ref() else {
+            panic!("the call should be to a method");
+        };
+        // The compiler calls the method through the trait, so the receiver can't keep the type of the type parameter
+        assert!(matches!(object.as_ref(), Expression::Variable(_, Some(Type::Object(name, _)), _) if name.as_str() == "Sized"));
+    }
+
+    #[test]
+    fn test_union_variants_are_their_union() {
+        let source = format!("{POINT}
+class Main {{
+    fn main(args: [String]) {{
+        let one: u64 = 1;
+        let circle: Shape = new Circle(one);
+        let empty: Shape = new Empty();
+        let same: bool = circle == empty;
+    }}
+}}
+");
+        assert!(check(&source).is_ok());
+    }
+
+    #[test]
+    fn test_record_field_assignment_is_rejected() {
+        let source = format!("{POINT}
+class Main {{
+    fn main(args: [String]) {{
+        let one: u64 = 1;
+        let point: Point = new Point(one, one);
+        point.x = one;
+    }}
+}}
+");
+        let error = check(&source).expect_err("record fields are immutable");
+        assert!(matches!(error, TypeCheckerError::ImmutableField { field, .. } if field == "x"));
+    }
+
+    #[test]
+    fn test_chained_record_field_assignment_is_rejected() {
+        let source = format!("{POINT}
+class Main {{
+    fn main(args: [String]) {{
+        let one: u64 = 1;
+        let holder: Holder = new Holder();
+        holder.point.y = one;
+    }}
+}}
+");
+        let error = check(&source).expect_err("record fields are immutable through a class field too");
+        assert!(matches!(error, TypeCheckerError::ImmutableField { field, .. } if field == "y"));
+    }
+
+    #[test]
+    fn test_record_with_missing_field_is_rejected() {
+        let source = format!("{POINT}
+class Main {{
+    fn main(args: [String]) {{
+        let one: u64 = 1;
+        let point: Point = new Point(one);
+    }}
+}}
+");
+        let error = check(&source).expect_err("records are created with every field");
+        assert!(matches!(error, TypeCheckerError::ExtraFunctionArgument { expected: 2, found: 1, .. }));
+    }
+
+    const COUNTDOWN: &str = "module main;
+
+import std::iter::Iterator;
+
+class Countdown {
+    pub left: u64;
+}
+
+impl Iterator[u64] for Countdown {
+    fn has-next(self) -> bool {
+        return self.left > 0;
+    }
+
+    fn next(self) -> u64 {
+        self.left -= 1;
+        return self.left;
+    }
+}
+
+class Numbers {
+    pub count: u64;
+
+    pub fn iter(self) -> Countdown {
+        let countdown: Countdown = new Countdown();
+        countdown.left = self.count;
+        return countdown;
+    }
+}
+
+class Plain {
+    pub v: u64;
+}
+";
+
+    #[test]
+    fn test_for_over_array_is_accepted() {
+        let source = "module main;
+
+class Main {
+    fn main(args: [String]) {
+        let total: u64 = 0;
+        let numbers: [u64] = [1, 2, 3];
+        for number: u64 in numbers {
+            total += number;
+        }
+    }
+}
+";
+        assert!(check(source).is_ok());
+    }
+
+    #[test]
+    fn test_for_with_wrong_element_type_is_rejected() {
+        let source = "module main;
+
+class Main {
+    fn main(args: [String]) {
+        let numbers: [u64] = [1, 2, 3];
+        for number: f64 in numbers {
+        }
+    }
+}
+";
+        let error = check(source).expect_err("the elements are u64");
+        assert!(matches!(error, TypeCheckerError::MismatchedType { .. }));
+    }
+
+    #[test]
+    fn test_for_over_iterator_is_accepted() {
+        let source = format!("{COUNTDOWN}
+class Main {{
+    fn main(args: [String]) {{
+        let total: u64 = 0;
+        let countdown: Countdown = new Countdown();
+        for left: u64 in countdown {{
+            total += left;
+        }}
+    }}
+}}
+");
+        assert!(check(&source).is_ok());
+    }
+
+    #[test]
+    fn test_for_over_collection_iterates_a_new_iterator() {
+        let source = format!("{COUNTDOWN}
+class Main {{
+    fn sum(numbers: Numbers) -> u64 {{
+        let total: u64 = 0;
+        for left: u64 in numbers {{
+            total += left;
+        }}
+        return total;
+    }}
+}}
+");
+        let file = check(&source).unwrap();
+        let method = get_method(&file, "Main", "sum");
+        let Statement::For { iterable, .. } = &method.body[1] else {
+            panic!("the method should loop");
+        };
+        let Expression::Call { name, .. } = iterable else {
+            panic!("the loop should call iter");
+        };
+        assert!(matches!(name.as_ref(), Expression::MemberAccess { field, .. } if field.segments[0].as_str() == "iter"));
+    }
+
+    #[test]
+    fn test_for_over_non_iterable_is_rejected() {
+        let source = format!("{COUNTDOWN}
+class Main {{
+    fn main(args: [String]) {{
+        let plain: Plain = new Plain();
+        for v: u64 in plain {{
+        }}
+    }}
+}}
+");
+        let error = check(&source).expect_err("Plain doesn't implement Iterator");
+        assert!(matches!(error, TypeCheckerError::NotIterable { ty, .. } if ty == "Plain"));
+    }
+
+    const HANDLE: &str = "module main;
+
+import std::Closeable;
+
+record Handle {
+    pub id: u64
+}
+
+impl Closeable for Handle {
+    fn close(self) {
+    }
+}
+
+class Plain {
+    pub v: u64;
+}
+";
+
+    #[test]
+    fn test_with_closeable_is_accepted() {
+        let source = format!("{HANDLE}
+class Main {{
+    fn main(args: [String]) {{
+        let one: u64 = 1;
+        with new Handle(one) as handle: Handle {{
+            let id: u64 = handle.id;
+        }}
+    }}
+}}
+");
+        assert!(check(&source).is_ok());
+    }
+
+    #[test]
+    fn test_with_without_closeable_is_rejected() {
+        let source = format!("{HANDLE}
+class Main {{
+    fn main(args: [String]) {{
+        with new Plain() as plain: Plain {{
+        }}
+    }}
+}}
+");
+        let error = check(&source).expect_err("Plain doesn't implement Closeable");
+        assert!(matches!(error, TypeCheckerError::NotCloseable { ty, .. } if ty == "Plain"));
+    }
+
+    #[test]
+    fn test_refutable_with_binding_is_rejected() {
+        let source = format!("{HANDLE}
+class Main {{
+    fn main(args: [String]) {{
+        let one: u64 = 1;
+        with new Handle(one) as Handle(1): Handle {{
+        }}
+    }}
+}}
+");
+        let error = check(&source).expect_err("the pattern only matches one handle");
+        assert!(matches!(error, TypeCheckerError::RefutablePattern { .. }));
+    }
+
+    #[test]
+    fn test_try_on_option_is_accepted() {
+        let source = "module main;
+
+class Main {
+    fn first(o: Option[u64]) -> Option[u64] {
+        let v: u64 = o?;
+        return new Some[u64](v);
+    }
+}
+";
+        assert!(check(source).is_ok());
+    }
+
+    #[test]
+    fn test_try_in_method_without_option_return_is_rejected() {
+        let source = "module main;
+
+class Main {
+    fn first(o: Option[u64]) -> u64 {
+        let v: u64 = o?;
+        return v;
+    }
+}
+";
+        let error = check(source).expect_err("None can't be returned as a u64");
+        assert!(matches!(error, TypeCheckerError::MismatchedTryReturn { return_type, .. } if return_type == "u64"));
+    }
+
+    #[test]
+    fn test_try_with_different_error_type_is_rejected() {
+        let source = "module main;
+
+class Main {
+    fn first(r: Result[u64, u64]) -> Result[u64, f64] {
+        let v: u64 = r?;
+        return new Ok[u64, f64](v);
+    }
+}
+";
+        let error = check(source).expect_err("a u64 error can't be returned as an f64 error");
+        assert!(matches!(error, TypeCheckerError::MismatchedTryReturn { .. }));
+    }
+
+    #[test]
+    fn test_try_on_non_option_is_rejected() {
+        let source = "module main;
+
+class Main {
+    fn first(v: u64) -> Option[u64] {
+        let w: u64 = v?;
+        return new Some[u64](w);
+    }
+}
+";
+        let error = check(source).expect_err("? only works on Option and Result");
+        assert!(matches!(error, TypeCheckerError::NotTryable { ty, .. } if ty == "u64"));
+    }
+
+    const GENERIC: &str = "module main;
+
+class Wrapper {
+    pub fn pair[T](self, a: T, b: T) -> [T] {
+        return [a, b];
+    }
+}
+
+class Main {
+    fn ident[T](x: T) -> T {
+        return x;
+    }
+
+    fn make[T]() -> [T] {
+        return new [T; 0];
+    }
+";
+
+    #[test]
+    fn test_inferred_type_arguments_are_accepted() {
+        let source = format!("{GENERIC}
+    fn main(args: [String]) {{
+        let one: u64 = 1;
+        let two: f64 = 2.0;
+        let same: u64 = Main::ident(one);
+        let wrapper: Wrapper = new Wrapper();
+        let pair: [f64] = wrapper.pair(two, two);
+    }}
+}}
+");
+        assert!(check(&source).is_ok());
+    }
+
+    #[test]
+    fn test_explicit_type_arguments_are_accepted() {
+        let source = format!("{GENERIC}
+    fn main(args: [String]) {{
+        let empty: [u64] = Main::make::[u64]();
+    }}
+}}
+");
+        assert!(check(&source).is_ok());
+    }
+
+    #[test]
+    fn test_uninferable_type_parameter_is_rejected() {
+        let source = format!("{GENERIC}
+    fn main(args: [String]) {{
+        let empty: [u64] = Main::make();
+    }}
+}}
+");
+        let error = check(&source).expect_err("T only appears in the return type");
+        assert!(matches!(error, TypeCheckerError::CannotInferTypeArgument { type_param, .. } if type_param == "T"));
+    }
+
+    #[test]
+    fn test_wrong_number_of_type_arguments_is_rejected() {
+        let source = format!("{GENERIC}
+    fn main(args: [String]) {{
+        let one: u64 = 1;
+        let same: u64 = Main::ident::[u64, u64](one);
+    }}
+}}
+");
+        let error = check(&source).expect_err("ident has one type parameter");
+        assert!(matches!(error, TypeCheckerError::MismatchedTypeArgumentCount { expected: 1, found: 2, .. }));
+    }
+
+    #[test]
+    fn test_argument_of_explicit_type_argument_is_checked() {
+        let source = format!("{GENERIC}
+    fn main(args: [String]) {{
+        let one: u64 = 1;
+        let same: f64 = Main::ident::[f64](one);
+    }}
+}}
+");
+        let error = check(&source).expect_err("one isn't an f64");
+        assert!(matches!(error, TypeCheckerError::MismatchedFunctionArgument { .. }));
+    }
+}
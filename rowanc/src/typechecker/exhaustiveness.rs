@@ -0,0 +1,280 @@
+//! Exhaustiveness and reachability checking for match expressions.
+//! Patterns are reduced to spaces, which only keep what the values a pattern covers look like.
+//! The checks follow Maranget's usefulness algorithm from "Warnings for pattern matching",
+//! an arm is unreachable if it isn't useful after the arms before it,
+//! and a match is exhaustive if a wildcard isn't useful after all of its arms.
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Constructor {
+    Bool(bool),
+    Tuple(usize),
+    /// A class along with how many fields its patterns destructure.
+    /// The siblings are every class a value of the matched type could be, with their field counts,
+    /// or None if the class can be subclassed.
+    Class {
+        path: Vec<String>,
+        fields: usize,
+        siblings: Option<Vec<(Vec<String>, usize)>>,
+    },
+    /// Integers, floats, characters and strings as they are written in the source
+    Constant(String),
+}
+
+impl Constructor {
+    pub fn arity(&self) -> usize {
+        match self {
+            Constructor::Bool(_) => 0,
+            Constructor::Tuple(size) => *size,
+            Constructor::Class { fields, .. } => *fields,
+            Constructor::Constant(_) => 0,
+        }
+    }
+
+    /// Every constructor of the type this constructor belongs to.
+    /// Returns None when a type has too many constructors to list, like integers or classes that can be subclassed.
+    pub fn complete_signature(&self) -> Option<Vec<Constructor>> {
+        match self {
+            Constructor::Bool(_) => Some(vec![Constructor::Bool(true), Constructor::Bool(false)]),
+            Constructor::Tuple(_) => Some(vec![self.clone()]),
+            Constructor::Class { siblings: Some(siblings), .. } => {
+                let constructors = siblings.iter()
+                    .map(|(path, fields)| Constructor::Class {
+                        path: path.clone(),
+                        fields: *fields,
+                        siblings: Some(siblings.clone()),
+                    })
+                    .collect();
+                Some(constructors)
+            }
+            Constructor::Class { siblings: None, .. } => None,
+            Constructor::Constant(_) => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Space {
+    Wild,
+    Constructor(Constructor, Vec<Space>),
+}
+
+impl std::fmt::Display for Space {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Space::Wild => write!(f, "_"),
+            Space::Constructor(Constructor::Bool(true), _) => write!(f, "True"),
+            Space::Constructor(Constructor::Bool(false), _) => write!(f, "False"),
+            Space::Constructor(Constructor::Constant(constant), _) => write!(f, "{}", constant),
+            Space::Constructor(Constructor::Tuple(_), fields) => {
+                write!(f, "(")?;
+                for (i, field) in fields.iter().enumerate() {
+                    write!(f, "{}", field)?;
+                    if i + 1 != fields.len() {
+                        write!(f, ", ")?;
+                    }
+                }
+                write!(f, ")")
+            }
+            Space::Constructor(Constructor::Class { path, .. }, fields) => {
+                write!(f, "{}", path.last().map(String::as_str).unwrap_or(""))?;
+                if fields.is_empty() {
+                    return Ok(());
+                }
+                write!(f, "(")?;
+                for (i, field) in fields.iter().enumerate() {
+                    write!(f, "{}", field)?;
+                    if i + 1 != fields.len() {
+                        write!(f, ", ")?;
+                    }
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+/// Returns true if a value matched by the row is not matched by any of the rows before it
+pub fn is_useful(rows: &[Vec<Space>], row: &[Space]) -> bool {
+    let Some((head, rest)) = row.split_first() else {
+        return rows.is_empty();
+    };
+
+    match head {
+        Space::Constructor(constructor, fields) => {
+            let rows = specialize(rows, constructor);
+            let row = fields.iter().chain(rest.iter()).cloned().collect::<Vec<_>>();
+            is_useful(&rows, &row)
+        }
+        Space::Wild => {
+            let used = used_constructors(rows);
+            match complete_constructors(&used) {
+                Some(constructors) => constructors.iter().any(|constructor| {
+                    let rows = specialize(rows, constructor);
+                    let row = std::iter::repeat(Space::Wild)
+                        .take(constructor.arity())
+                        .chain(rest.iter().cloned())
+                        .collect::<Vec<_>>();
+                    is_useful(&rows, &row)
+                }),
+                None => is_useful(&default_rows(rows), rest),
+            }
+        }
+    }
+}
+
+/// Finds values of `width` columns that none of the rows match
+pub fn find_missing(rows: &[Vec<Space>], width: usize) -> Option<Vec<Space>> {
+    if width == 0 {
+        return rows.is_empty().then(Vec::new);
+    }
+
+    let used = used_constructors(rows);
+    if let Some(constructors) = complete_constructors(&used) {
+        for constructor in constructors {
+            let specialized = specialize(rows, &constructor);
+            let arity = constructor.arity();
+            if let Some(mut missing) = find_missing(&specialized, arity + width - 1) {
+                let rest = missing.split_off(arity);
+                let mut result = vec![Space::Constructor(constructor, missing)];
+                result.extend(rest);
+                return Some(result);
+            }
+        }
+        return None;
+    }
+
+    let mut missing = find_missing(&default_rows(rows), width - 1)?;
+    // Naming a constructor that no arm mentions gives a more useful message than a wildcard
+    let head = used.first()
+        .and_then(Constructor::complete_signature)
+        .and_then(|constructors| constructors.into_iter().find(|constructor| !used.contains(constructor)))
+        .map(|constructor| {
+            let fields = vec![Space::Wild; constructor.arity()];
+            Space::Constructor(constructor, fields)
+        })
+        .unwrap_or(Space::Wild);
+    missing.insert(0, head);
+    Some(missing)
+}
+
+fn used_constructors(rows: &[Vec<Space>]) -> Vec<Constructor> {
+    let mut used = Vec::new();
+    for row in rows {
+        if let Some(Space::Constructor(constructor, _)) = row.first() {
+            if !used.contains(constructor) {
+                used.push(constructor.clone());
+            }
+        }
+    }
+    used
+}
+
+/// The constructors of the first column if the rows use every one of them
+fn complete_constructors(used: &[Constructor]) -> Option<Vec<Constructor>> {
+    let constructors = used.first()?.complete_signature()?;
+    constructors.iter()
+        .all(|constructor| used.contains(constructor))
+        .then_some(constructors)
+}
+
+/// Keeps the rows that can match the constructor, replacing their first column with the constructor's fields
+fn specialize(rows: &[Vec<Space>], constructor: &Constructor) -> Vec<Vec<Space>> {
+    rows.iter()
+        .filter_map(|row| {
+            let (head, rest) = row.split_first()?;
+            match head {
+                Space::Constructor(other, fields) if other == constructor => {
+                    Some(fields.iter().chain(rest.iter()).cloned().collect())
+                }
+                Space::Constructor(..) => None,
+                Space::Wild => {
+                    Some(std::iter::repeat(Space::Wild)
+                        .take(constructor.arity())
+                        .chain(rest.iter().cloned())
+                        .collect())
+                }
+            }
+        })
+        .collect()
+}
+
+/// Keeps the rows whose first column is a wildcard, without that column
+fn default_rows(rows: &[Vec<Space>]) -> Vec<Vec<Space>> {
+    rows.iter()
+        .filter_map(|row| match row.split_first()? {
+            (Space::Wild, rest) => Some(rest.to_vec()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variant(name: &str, fields: usize, args: Vec<Space>) -> Space {
+        let siblings = vec![
+            (vec![String::from("Some")], 1),
+            (vec![String::from("None")], 0),
+        ];
+        let constructor = Constructor::Class {
+            path: vec![name.to_string()],
+            fields,
+            siblings: Some(siblings),
+        };
+        Space::Constructor(constructor, args)
+    }
+
+    fn boolean(value: bool) -> Space {
+        Space::Constructor(Constructor::Bool(value), Vec::new())
+    }
+
+    #[test]
+    fn test_both_booleans_are_exhaustive() {
+        let rows = vec![vec![boolean(true)], vec![boolean(false)]];
+        assert_eq!(find_missing(&rows, 1), None);
+        assert!(!is_useful(&rows, &[Space::Wild]));
+    }
+
+    #[test]
+    fn test_missing_variant_is_reported() {
+        let rows = vec![vec![variant("Some", 1, vec![Space::Wild])]];
+        let missing = find_missing(&rows, 1).expect("None should be missing");
+        assert_eq!(missing[0].to_string(), "None");
+    }
+
+    #[test]
+    fn test_nested_missing_value() {
+        let rows = vec![
+            vec![variant("Some", 1, vec![boolean(true)])],
+            vec![variant("None", 0, vec![])],
+        ];
+        let missing = find_missing(&rows, 1).expect("Some(False) should be missing");
+        assert_eq!(missing[0].to_string(), "Some(False)");
+    }
+
+    #[test]
+    fn test_arm_after_wildcard_is_unreachable() {
+        let rows = vec![vec![Space::Wild]];
+        assert!(!is_useful(&rows, &[boolean(true)]));
+    }
+
+    #[test]
+    fn test_constants_need_a_wildcard() {
+        let one = Space::Constructor(Constructor::Constant(String::from("1")), Vec::new());
+        let rows = vec![vec![one.clone()]];
+        assert!(!is_useful(&rows, &[one]));
+        assert_eq!(find_missing(&rows, 1), Some(vec![Space::Wild]));
+    }
+
+    #[test]
+    fn test_tuple_columns() {
+        let tuple = |left, right| Space::Constructor(Constructor::Tuple(2), vec![left, right]);
+        let rows = vec![
+            vec![tuple(boolean(true), Space::Wild)],
+            vec![tuple(Space::Wild, boolean(true))],
+        ];
+        let missing = find_missing(&rows, 1).expect("(False, False) should be missing");
+        assert_eq!(missing[0].to_string(), "(False, False)");
+    }
+}